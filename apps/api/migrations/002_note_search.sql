@@ -0,0 +1,23 @@
+-- Full-text search over note titles and content
+
+-- Plain text extracted from the Yjs document. NULL means the note has not been
+-- indexed yet (notes created before this migration are backfilled on startup).
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS content_text TEXT;
+
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+        setweight(to_tsvector('simple', coalesce(content_text, '')), 'B')
+    ) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_notes_search_vector ON notes USING GIN (search_vector);
+
+-- Indexing a note for the first time (content_text going from NULL to a value)
+-- must not bump updated_at, or the backfill would make every note look edited
+-- to syncing clients.
+DROP TRIGGER IF EXISTS update_notes_updated_at ON notes;
+CREATE TRIGGER update_notes_updated_at
+    BEFORE UPDATE ON notes
+    FOR EACH ROW
+    WHEN (OLD.content_text IS NOT NULL)
+    EXECUTE FUNCTION update_updated_at_column();
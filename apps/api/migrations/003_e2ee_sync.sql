@@ -0,0 +1,31 @@
+-- End-to-end encrypted sync (opt-in per account)
+
+-- When set, clients encrypt note content and titles before syncing and the
+-- server accepts no new plaintext note content
+ALTER TABLE users ADD COLUMN e2ee_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Encrypted notes keep `title` empty and `content_text` empty (not indexed);
+-- `content` and their sync updates are opaque client-encrypted blobs
+ALTER TABLE notes ADD COLUMN encrypted BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE notes ADD COLUMN encrypted_title BYTEA;
+
+-- The user's note key, wrapped with each device's public key. A device that
+-- registers without a wrapped key waits for another device to share it.
+CREATE TABLE IF NOT EXISTS device_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    device_id VARCHAR(100) NOT NULL,
+    name VARCHAR(255),
+    public_key BYTEA NOT NULL,
+    wrapped_key BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, device_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_device_keys_user_id ON device_keys(user_id);
+
+CREATE TRIGGER update_device_keys_updated_at
+    BEFORE UPDATE ON device_keys
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,19 @@
+-- Sharing notes with other users
+
+-- The owner of a note is `notes.user_id`; everyone else with access is a
+-- member. Members are invited by email: an invite for an address nobody
+-- has signed in with yet has no `user_id` until they do.
+CREATE TABLE IF NOT EXISTS note_members (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    note_id UUID NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    role VARCHAR(10) NOT NULL CHECK (role IN ('editor', 'viewer')),
+    invited_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (note_id, user_id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_note_members_note_email ON note_members(note_id, lower(email));
+CREATE INDEX IF NOT EXISTS idx_note_members_user_id ON note_members(user_id);
+CREATE INDEX IF NOT EXISTS idx_note_members_pending ON note_members(lower(email)) WHERE user_id IS NULL;
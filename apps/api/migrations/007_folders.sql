@@ -0,0 +1,31 @@
+-- Folders
+
+-- Folders nest through `parent_id`. Personal folders belong to `user_id`;
+-- folders in a workspace are shared by its members, like its notes. Ids
+-- come from the client so folders made offline keep theirs once synced.
+-- Deleting a folder only sets `deleted_at`, so that clients pulling
+-- changes learn about it.
+CREATE TABLE IF NOT EXISTS folders (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    workspace_id UUID REFERENCES workspaces(id) ON DELETE CASCADE,
+    parent_id UUID REFERENCES folders(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_folders_user_id ON folders(user_id) WHERE workspace_id IS NULL;
+CREATE INDEX IF NOT EXISTS idx_folders_workspace_id ON folders(workspace_id) WHERE workspace_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_folders_parent_id ON folders(parent_id);
+
+CREATE TRIGGER update_folders_updated_at
+    BEFORE UPDATE ON folders
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- A note is in at most one folder, in the same space as the note
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS folder_id UUID REFERENCES folders(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_notes_folder_id ON notes(folder_id) WHERE folder_id IS NOT NULL;
@@ -0,0 +1,20 @@
+-- Tasks
+
+-- Task items extracted from note content whenever it's saved, so tasks can
+-- be listed across notes. A task is addressed by its note and its path in
+-- the document: the child index of each element down to the task item.
+-- Encrypted notes have no tasks, since the server can't read them.
+CREATE TABLE IF NOT EXISTS tasks (
+    note_id UUID NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    depth INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    checked BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (note_id, path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tasks_open ON tasks(note_id) WHERE NOT checked;
+
+-- Notes saved before tasks were extracted get indexed at startup
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS tasks_indexed BOOLEAN NOT NULL DEFAULT FALSE;
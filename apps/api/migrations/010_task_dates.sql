@@ -0,0 +1,15 @@
+-- Task dates
+
+-- Due dates and reminder times of task items, and when checked tasks were
+-- checked, for the Today, Upcoming and Recently completed views. Completion
+-- times carry over when a note's tasks are re-extracted; tasks checked
+-- before this are left without one.
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS due_date DATE;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS remind_at BIGINT;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS completed_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_tasks_due_date ON tasks(due_date) WHERE due_date IS NOT NULL AND NOT checked;
+CREATE INDEX IF NOT EXISTS idx_tasks_completed_at ON tasks(completed_at) WHERE completed_at IS NOT NULL;
+
+-- Extract tasks again at startup to pick up their dates
+UPDATE notes SET tasks_indexed = FALSE WHERE NOT encrypted;
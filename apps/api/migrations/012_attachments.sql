@@ -0,0 +1,38 @@
+-- Attachments
+
+-- Files attached to notes are stored once per content in the blob store,
+-- named by the SHA-256 of the bytes uploaded. `blobs` records which are
+-- there. `used_at` is bumped whenever an attachment starts referring to a
+-- blob; garbage collection only removes blobs no attachment refers to that
+-- haven't been used for a while, so a blob uploaded just before its
+-- attachment is created isn't collected in between.
+CREATE TABLE IF NOT EXISTS blobs (
+    hash CHAR(64) PRIMARY KEY,
+    size BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    used_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- A blob attached to a note under a file name. Ids come from the client so
+-- files attached offline keep theirs once synced. Removing an attachment
+-- only sets `deleted_at`, so that clients pulling changes learn about it.
+CREATE TABLE IF NOT EXISTS note_attachments (
+    id UUID PRIMARY KEY,
+    note_id UUID NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    hash CHAR(64) NOT NULL,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_note_attachments_note_id ON note_attachments(note_id);
+CREATE INDEX IF NOT EXISTS idx_note_attachments_hash ON note_attachments(hash);
+
+CREATE TRIGGER update_note_attachments_updated_at
+    BEFORE UPDATE ON note_attachments
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,12 @@
+-- Task indexing doesn't touch updated_at
+
+-- Marking a note's tasks indexed (or due for indexing again) is bookkeeping,
+-- not an edit. Like indexing its text (see 002), it must not bump
+-- updated_at, or the task backfill would make every note look edited to
+-- syncing clients.
+DROP TRIGGER IF EXISTS update_notes_updated_at ON notes;
+CREATE TRIGGER update_notes_updated_at
+    BEFORE UPDATE ON notes
+    FOR EACH ROW
+    WHEN (OLD.content_text IS NOT NULL AND OLD.tasks_indexed = NEW.tasks_indexed)
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,21 @@
+-- Who uploaded each blob
+
+-- Blobs are shared by content, so knowing a hash mustn't be enough to use
+-- a blob: a user may attach, download or be told about a blob only if they
+-- uploaded it (proving they have the content) or it is attached to a note
+-- they can access. A row is added for every user who uploads the content,
+-- including when it was stored already.
+CREATE TABLE IF NOT EXISTS blob_uploads (
+    hash CHAR(64) NOT NULL REFERENCES blobs(hash) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (hash, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_blob_uploads_user_id ON blob_uploads(user_id);
+
+-- Whoever attached a blob before this table existed counts as its uploader
+INSERT INTO blob_uploads (hash, user_id)
+SELECT DISTINCT a.hash, a.user_id FROM note_attachments a
+WHERE EXISTS (SELECT 1 FROM blobs b WHERE b.hash = a.hash)
+ON CONFLICT DO NOTHING;
@@ -9,6 +9,8 @@ use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
+use crate::db::Database;
+use crate::models::{MemberWorkspace, WorkspaceRole};
 use crate::AppState;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -87,9 +89,15 @@ impl FromRequestParts<Arc<AppState>> for AuthUser {
             .strip_prefix("Bearer ")
             .ok_or((StatusCode::UNAUTHORIZED, "Invalid authorization format"))?;
 
+        AuthUser::from_token(&state.auth, token)
+    }
+}
+
+impl AuthUser {
+    /// The user an access token was issued to
+    pub fn from_token(auth: &AuthState, token: &str) -> Result<Self, (StatusCode, &'static str)> {
         // Verify JWT
-        let claims = state
-            .auth
+        let claims = auth
             .verify_token(token)
             .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token"))?;
 
@@ -100,3 +108,32 @@ impl FromRequestParts<Arc<AppState>> for AuthUser {
         Ok(AuthUser { user_id })
     }
 }
+
+impl AuthUser {
+    /// The workspace and the user's role in it. Workspaces the user isn't a
+    /// member of are reported as not found.
+    pub async fn workspace(&self, db: &Database, workspace_id: Uuid) -> Result<MemberWorkspace, (StatusCode, String)> {
+        db.get_member_workspace(workspace_id, self.user_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+            .ok_or((StatusCode::NOT_FOUND, "Workspace not found".to_string()))
+    }
+
+    /// The workspace, if the user may add notes to it
+    pub async fn workspace_editor(&self, db: &Database, workspace_id: Uuid) -> Result<MemberWorkspace, (StatusCode, String)> {
+        let workspace = self.workspace(db, workspace_id).await?;
+        if !workspace.role.can_edit() {
+            return Err((StatusCode::FORBIDDEN, "You can only read notes in this workspace".to_string()));
+        }
+        Ok(workspace)
+    }
+
+    /// The workspace, if the user administers it
+    pub async fn workspace_admin(&self, db: &Database, workspace_id: Uuid) -> Result<MemberWorkspace, (StatusCode, String)> {
+        let workspace = self.workspace(db, workspace_id).await?;
+        if workspace.role != WorkspaceRole::Admin {
+            return Err((StatusCode::FORBIDDEN, "Only workspace admins can do this".to_string()));
+        }
+        Ok(workspace)
+    }
+}
@@ -0,0 +1,56 @@
+// Blobs in a local directory (BLOB_DIR, `./data/blobs` by default). Each
+// blob is a file named by its hash, in a subdirectory named by the hash's
+// first two characters so no directory grows too large.
+use std::path::PathBuf;
+use axum::body::Bytes;
+use uuid::Uuid;
+
+use super::BlobError;
+
+pub struct LocalStore {
+    dir: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("BLOB_DIR").unwrap_or_else(|_| "./data/blobs".to_string()))
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        self.dir.join(&hash[..2]).join(hash)
+    }
+
+    pub async fn put(&self, hash: &str, data: &[u8]) -> Result<(), BlobError> {
+        let path = self.path(hash);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Written aside and renamed, so a blob is never seen half written
+        let temp = path.with_file_name(format!("{}.{}.tmp", hash, Uuid::now_v7()));
+        tokio::fs::write(&temp, data).await?;
+        if let Err(e) = tokio::fs::rename(&temp, &path).await {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    pub async fn get(&self, hash: &str) -> Result<Option<Bytes>, BlobError> {
+        match tokio::fs::read(self.path(hash)).await {
+            Ok(data) => Ok(Some(data.into())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn delete(&self, hash: &str) -> Result<(), BlobError> {
+        match tokio::fs::remove_file(self.path(hash)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
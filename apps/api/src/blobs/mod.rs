@@ -0,0 +1,84 @@
+// Storage for the content of attached files.
+//
+// Blobs are addressed by the SHA-256 of their bytes, hex encoded, so the
+// same file attached to many notes is stored once and a client can ask
+// which of its blobs the server is missing before uploading anything. The
+// server never looks inside a blob: files of encrypted notes are uploaded
+// encrypted, and hashed as such. Where blobs are kept is chosen with
+// BLOB_STORE: `local` (the default) keeps them in a directory, `s3` in a
+// bucket of any S3-compatible service.
+mod local;
+mod s3;
+
+use axum::body::Bytes;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+pub use local::LocalStore;
+pub use s3::S3Store;
+
+/// Largest blob the API accepts, in bytes
+pub const MAX_BLOB_SIZE: usize = 50 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum BlobError {
+    #[error("Blob store is not configured: {0}")]
+    Config(String),
+    #[error("Blob file error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Blob store request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Blob store responded with {0}")]
+    Status(reqwest::StatusCode),
+}
+
+pub enum BlobStore {
+    Local(LocalStore),
+    S3(S3Store),
+}
+
+impl BlobStore {
+    /// The store configured by BLOB_STORE and the variables of its kind
+    pub fn from_env() -> Result<Self, BlobError> {
+        match std::env::var("BLOB_STORE").unwrap_or_default().as_str() {
+            "" | "local" => Ok(BlobStore::Local(LocalStore::from_env())),
+            "s3" => Ok(BlobStore::S3(S3Store::from_env()?)),
+            other => Err(BlobError::Config(format!("Unknown BLOB_STORE {}", other))),
+        }
+    }
+
+    /// Store a blob under its hash, replacing any blob stored under it
+    pub async fn put(&self, hash: &str, data: Bytes) -> Result<(), BlobError> {
+        match self {
+            BlobStore::Local(store) => store.put(hash, &data).await,
+            BlobStore::S3(store) => store.put(hash, data).await,
+        }
+    }
+
+    /// A blob's content, or None if the store doesn't have it
+    pub async fn get(&self, hash: &str) -> Result<Option<Bytes>, BlobError> {
+        match self {
+            BlobStore::Local(store) => store.get(hash).await,
+            BlobStore::S3(store) => store.get(hash).await,
+        }
+    }
+
+    /// Remove a blob; removing one the store doesn't have is not an error
+    pub async fn delete(&self, hash: &str) -> Result<(), BlobError> {
+        match self {
+            BlobStore::Local(store) => store.delete(hash).await,
+            BlobStore::S3(store) => store.delete(hash).await,
+        }
+    }
+}
+
+/// The hash a blob with this content is stored under
+pub fn content_hash(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Whether `hash` looks like a hash from `content_hash`. Hashes end up in
+/// file paths and URLs, so anything else is refused before it gets there.
+pub fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
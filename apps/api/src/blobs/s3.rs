@@ -0,0 +1,140 @@
+// Blobs in a bucket of an S3-compatible service (AWS S3, MinIO, R2, ...).
+//
+// Requests are signed with AWS Signature Version 4 and address the bucket
+// by path (`{endpoint}/{bucket}/{key}`), which every such service accepts.
+// Configured with S3_BUCKET, S3_ACCESS_KEY_ID and S3_SECRET_ACCESS_KEY,
+// plus S3_REGION (`us-east-1` by default), S3_ENDPOINT (AWS's endpoint for
+// the region by default) and S3_PREFIX, prepended to every key.
+use axum::body::Bytes;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+
+use super::BlobError;
+
+/// SHA-256 of an empty payload, for requests without a body
+const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+pub struct S3Store {
+    client: reqwest::Client,
+    endpoint: String,
+    bucket: String,
+    region: String,
+    prefix: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+fn required_var(name: &str) -> Result<String, BlobError> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| BlobError::Config(format!("{} is not set", name)))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl S3Store {
+    pub fn from_env() -> Result<Self, BlobError> {
+        let region = std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        let endpoint = std::env::var("S3_ENDPOINT")
+            .unwrap_or_else(|_| format!("https://s3.{}.amazonaws.com", region));
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket: required_var("S3_BUCKET")?,
+            region,
+            prefix: std::env::var("S3_PREFIX").unwrap_or_default(),
+            access_key_id: required_var("S3_ACCESS_KEY_ID")?,
+            secret_access_key: required_var("S3_SECRET_ACCESS_KEY")?,
+        })
+    }
+
+    /// A signed request for the object holding a blob. `payload_hash` is
+    /// the hex SHA-256 of the request body.
+    fn request(&self, method: Method, hash: &str, payload_hash: &str) -> Result<reqwest::RequestBuilder, BlobError> {
+        let url = Url::parse(&format!("{}/{}/{}{}", self.endpoint, self.bucket, self.prefix, hash))
+            .map_err(|e| BlobError::Config(format!("Invalid S3_ENDPOINT: {}", e)))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(BlobError::Config("S3_ENDPOINT has no host".to_string())),
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+
+        // Keys are a prefix and a hex hash, neither of which needs encoding
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method,
+            url.path(),
+            host,
+            payload_hash,
+            amz_date,
+            signed_headers,
+            payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = hmac_sha256(format!("AWS4{}", self.secret_access_key).as_bytes(), &date);
+        let key = hmac_sha256(&key, &self.region);
+        let key = hmac_sha256(&key, "s3");
+        let key = hmac_sha256(&key, "aws4_request");
+        let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header(
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key_id, scope, signed_headers, signature
+                ),
+            ))
+    }
+
+    pub async fn put(&self, hash: &str, data: Bytes) -> Result<(), BlobError> {
+        // The blob's name is already the hash of its content
+        let response = self.request(Method::PUT, hash, hash)?.body(data).send().await?;
+        if !response.status().is_success() {
+            return Err(BlobError::Status(response.status()));
+        }
+        Ok(())
+    }
+
+    pub async fn get(&self, hash: &str) -> Result<Option<Bytes>, BlobError> {
+        let response = self.request(Method::GET, hash, EMPTY_PAYLOAD_HASH)?.send().await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.bytes().await?)),
+            status => Err(BlobError::Status(status)),
+        }
+    }
+
+    pub async fn delete(&self, hash: &str) -> Result<(), BlobError> {
+        let response = self.request(Method::DELETE, hash, EMPTY_PAYLOAD_HASH)?.send().await?;
+        // S3 answers 204 whether or not the object existed
+        if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
+            return Err(BlobError::Status(response.status()));
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,14 @@
 use sqlx::postgres::PgPoolOptions;
-use sqlx::{PgPool, Error};
+use sqlx::{PgConnection, PgPool, Error};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 
-use crate::models::{User, Note, RefreshToken};
+use crate::models::{
+    AccessibleNote, Attachment, DateField, DeviceKey, Folder, MemberWorkspace, NoteMember, NoteRole, NoteScope, User, Note,
+    NoteSearch, NoteSearchRow, RefreshToken, SavedSearch, ShareLink, Tag, TagSummary, Task, TaskCounts, Workspace,
+    WorkspaceMember, WorkspaceRole,
+};
+use crate::sync::document;
 
 pub struct Database {
     pool: PgPool,
@@ -110,52 +115,80 @@ impl Database {
         .await
     }
 
+    pub async fn is_e2ee_enabled(&self, user_id: Uuid) -> Result<bool, Error> {
+        let row: Option<(bool,)> = sqlx::query_as("SELECT e2ee_enabled FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|(enabled,)| enabled).unwrap_or(false))
+    }
+
+    pub async fn set_e2ee_enabled(&self, user_id: Uuid, enabled: bool) -> Result<User, Error> {
+        sqlx::query_as::<_, User>(
+            "UPDATE users SET e2ee_enabled = $2 WHERE id = $1 RETURNING *",
+        )
+        .bind(user_id)
+        .bind(enabled)
+        .fetch_one(&self.pool)
+        .await
+    }
+
     // Note queries
+
+    /// Notes the user owns, is a member of, or that are in one of their
+    /// workspaces, limited to `scope`. With `since`, only notes changed
+    /// since then, or shared with the user since then.
     pub async fn list_notes(
         &self,
         user_id: Uuid,
         include_deleted: bool,
         since: Option<DateTime<Utc>>,
-    ) -> Result<Vec<Note>, Error> {
-        let query = if include_deleted {
-            if let Some(since_time) = since {
-                sqlx::query_as::<_, Note>(
-                    "SELECT * FROM notes WHERE user_id = $1 AND updated_at > $2 ORDER BY updated_at DESC",
-                )
-                .bind(user_id)
-                .bind(since_time)
-                .fetch_all(&self.pool)
-                .await
-            } else {
-                sqlx::query_as::<_, Note>(
-                    "SELECT * FROM notes WHERE user_id = $1 ORDER BY updated_at DESC",
-                )
-                .bind(user_id)
-                .fetch_all(&self.pool)
-                .await
-            }
-        } else if let Some(since_time) = since {
-            sqlx::query_as::<_, Note>(
-                "SELECT * FROM notes WHERE user_id = $1 AND deleted_at IS NULL AND updated_at > $2 ORDER BY updated_at DESC",
-            )
-            .bind(user_id)
-            .bind(since_time)
-            .fetch_all(&self.pool)
-            .await
-        } else {
-            sqlx::query_as::<_, Note>(
-                "SELECT * FROM notes WHERE user_id = $1 AND deleted_at IS NULL ORDER BY updated_at DESC",
-            )
-            .bind(user_id)
-            .fetch_all(&self.pool)
-            .await
-        };
-        query
+        scope: NoteScope,
+    ) -> Result<Vec<AccessibleNote>, Error> {
+        let (scoped, workspace_id) = scope.filter();
+        sqlx::query_as::<_, AccessibleNote>(
+            r#"
+            SELECT n.*, CASE
+                WHEN n.workspace_id IS NULL AND n.user_id = $1 THEN 'owner'
+                WHEN w.role = 'admin' THEN 'owner'
+                WHEN w.role = 'editor' OR m.role = 'editor' THEN 'editor'
+                ELSE 'viewer'
+            END AS role
+            FROM notes n
+            LEFT JOIN note_members m ON m.note_id = n.id AND m.user_id = $1
+            LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $1
+            WHERE ((n.workspace_id IS NULL AND n.user_id = $1) OR m.user_id IS NOT NULL OR w.user_id IS NOT NULL)
+              AND ($2 OR n.deleted_at IS NULL)
+              AND ($3::timestamptz IS NULL OR n.updated_at > $3 OR m.created_at > $3 OR w.created_at > $3)
+              AND (NOT $4 OR n.workspace_id IS NOT DISTINCT FROM $5)
+            ORDER BY n.updated_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(include_deleted)
+        .bind(since)
+        .bind(scoped)
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await
     }
 
-    pub async fn get_note(&self, id: Uuid, user_id: Uuid) -> Result<Option<Note>, Error> {
-        sqlx::query_as::<_, Note>(
-            "SELECT * FROM notes WHERE id = $1 AND user_id = $2",
+    /// A note the user can access, with their role
+    pub async fn get_accessible_note(&self, id: Uuid, user_id: Uuid) -> Result<Option<AccessibleNote>, Error> {
+        sqlx::query_as::<_, AccessibleNote>(
+            r#"
+            SELECT n.*, CASE
+                WHEN n.workspace_id IS NULL AND n.user_id = $2 THEN 'owner'
+                WHEN w.role = 'admin' THEN 'owner'
+                WHEN w.role = 'editor' OR m.role = 'editor' THEN 'editor'
+                ELSE 'viewer'
+            END AS role
+            FROM notes n
+            LEFT JOIN note_members m ON m.note_id = n.id AND m.user_id = $2
+            LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $2
+            WHERE n.id = $1
+              AND ((n.workspace_id IS NULL AND n.user_id = $2) OR m.user_id IS NOT NULL OR w.user_id IS NOT NULL)
+            "#,
         )
         .bind(id)
         .bind(user_id)
@@ -163,36 +196,90 @@ impl Database {
         .await
     }
 
+    /// Of the given notes, those that exist but the user can no longer
+    /// access (their note or workspace membership was removed)
+    pub async fn inaccessible_note_ids(&self, ids: &[Uuid], user_id: Uuid) -> Result<Vec<Uuid>, Error> {
+        let rows: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            SELECT n.id FROM notes n
+            WHERE n.id = ANY($1)
+              AND NOT (n.workspace_id IS NULL AND n.user_id = $2)
+              AND NOT EXISTS (SELECT 1 FROM note_members m WHERE m.note_id = n.id AND m.user_id = $2)
+              AND NOT EXISTS (
+                  SELECT 1 FROM workspace_members w WHERE w.workspace_id = n.workspace_id AND w.user_id = $2
+              )
+            "#,
+        )
+        .bind(ids)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_note(
         &self,
         id: Uuid,
         user_id: Uuid,
+        workspace_id: Option<Uuid>,
         title: &str,
         content: &[u8],
+        content_text: &str,
         starred: bool,
     ) -> Result<Note, Error> {
         sqlx::query_as::<_, Note>(
             r#"
-            INSERT INTO notes (id, user_id, title, content, starred)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO notes (id, user_id, workspace_id, title, content, content_text, starred)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             RETURNING *
             "#,
         )
         .bind(id)
         .bind(user_id)
+        .bind(workspace_id)
         .bind(title)
         .bind(content)
+        .bind(content_text)
+        .bind(starred)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Create a note whose content and title were encrypted by the client.
+    /// Nothing about it is indexed for search.
+    pub async fn create_encrypted_note(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        content: &[u8],
+        encrypted_title: Option<&[u8]>,
+        starred: bool,
+    ) -> Result<Note, Error> {
+        sqlx::query_as::<_, Note>(
+            r#"
+            INSERT INTO notes (id, user_id, title, content, content_text, starred, encrypted, encrypted_title)
+            VALUES ($1, $2, '', $3, '', $4, TRUE, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(content)
         .bind(starred)
+        .bind(encrypted_title)
         .fetch_one(&self.pool)
         .await
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_note(
         &self,
         id: Uuid,
         user_id: Uuid,
         title: Option<&str>,
         content: Option<&[u8]>,
+        content_text: Option<&str>,
         state_vector: Option<&[u8]>,
         starred: Option<bool>,
     ) -> Result<Note, Error> {
@@ -202,8 +289,9 @@ impl Database {
             SET
                 title = COALESCE($3, title),
                 content = COALESCE($4, content),
-                state_vector = COALESCE($5, state_vector),
-                starred = COALESCE($6, starred),
+                content_text = COALESCE($5, content_text),
+                state_vector = COALESCE($6, state_vector),
+                starred = COALESCE($7, starred),
                 version = version + 1
             WHERE id = $1 AND user_id = $2
             RETURNING *
@@ -213,12 +301,290 @@ impl Database {
         .bind(user_id)
         .bind(title)
         .bind(content)
+        .bind(content_text)
         .bind(state_vector)
         .bind(starred)
         .fetch_one(&self.pool)
         .await
     }
 
+    /// Update an encrypted note. `content` is a client-encrypted snapshot;
+    /// with no arguments this only bumps the version (after a synced update).
+    pub async fn update_encrypted_note(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        content: Option<&[u8]>,
+        encrypted_title: Option<&[u8]>,
+        starred: Option<bool>,
+    ) -> Result<Note, Error> {
+        sqlx::query_as::<_, Note>(
+            r#"
+            UPDATE notes
+            SET
+                content = COALESCE($3, content),
+                encrypted_title = COALESCE($4, encrypted_title),
+                starred = COALESCE($5, starred),
+                version = version + 1
+            WHERE id = $1 AND user_id = $2 AND encrypted
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(content)
+        .bind(encrypted_title)
+        .bind(starred)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Merge a sync update into a plaintext note's content while holding its
+    /// row lock, so concurrent merges apply one after the other instead of
+    /// overwriting each other, and store the update for other clients along
+    /// with the new content. `merge` returns the new content and its search
+    /// text, or None to refuse the update. Returns None, storing nothing, if
+    /// the note isn't a plaintext note of `user_id` or the merge was refused.
+    pub async fn merge_sync_update<F>(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        update_data: &[u8],
+        merge: F,
+    ) -> Result<Option<Note>, Error>
+    where
+        F: FnOnce(&[u8]) -> Option<(Vec<u8>, String)>,
+    {
+        let mut tx = self.pool.begin().await?;
+
+        let content: Option<(Vec<u8>,)> = sqlx::query_as(
+            "SELECT content FROM notes WHERE id = $1 AND user_id = $2 AND NOT encrypted FOR UPDATE",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((new_content, content_text)) = content.and_then(|(content,)| merge(&content)) else {
+            return Ok(None);
+        };
+
+        let note = sqlx::query_as::<_, Note>(
+            r#"
+            UPDATE notes
+            SET content = $3, content_text = $4, version = version + 1
+            WHERE id = $1 AND user_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(new_content)
+        .bind(content_text)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("INSERT INTO sync_updates (note_id, update_data) VALUES ($1, $2)")
+            .bind(id)
+            .bind(update_data)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(Some(note))
+    }
+
+    /// Switch a note between plaintext and encrypted storage, replacing its
+    /// content. The sync history is dropped: it is in the old form, and for
+    /// a newly encrypted note it would keep the plaintext on the server.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn set_note_encryption(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        encrypted: bool,
+        title: &str,
+        content: &[u8],
+        content_text: &str,
+        encrypted_title: Option<&[u8]>,
+    ) -> Result<Note, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let note = sqlx::query_as::<_, Note>(
+            r#"
+            UPDATE notes
+            SET
+                encrypted = $3,
+                title = $4,
+                content = $5,
+                content_text = $6,
+                encrypted_title = $7,
+                state_vector = NULL,
+                version = version + 1
+            WHERE id = $1 AND user_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(encrypted)
+        .bind(title)
+        .bind(content)
+        .bind(content_text)
+        .bind(encrypted_title)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM sync_updates WHERE note_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(note)
+    }
+
+    /// Full-text search over a user's own notes and those of their
+    /// workspaces, best matches first.
+    /// The text is parsed with `websearch_to_tsquery`, so any user input is
+    /// safe; empty text matches every note. Notes must also pass the
+    /// search's filters: have all of its tags and none of its excluded tags
+    /// (compared without case), the starred flag, tasks and dates asked for.
+    pub async fn search_notes(
+        &self,
+        user_id: Uuid,
+        search: &NoteSearch,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<NoteSearchRow>, Error> {
+        let date_fields: Vec<&str> = search
+            .dates
+            .iter()
+            .map(|date| match date.field {
+                DateField::Created => "created",
+                DateField::Updated => "updated",
+            })
+            .collect();
+        let date_since: Vec<Option<DateTime<Utc>>> = search.dates.iter().map(|date| date.since).collect();
+        let date_until: Vec<Option<DateTime<Utc>>> = search.dates.iter().map(|date| date.until).collect();
+        let date_negated: Vec<bool> = search.dates.iter().map(|date| date.negated).collect();
+
+        // The content is HTML-escaped before ts_headline wraps matches in <mark>,
+        // so snippets can be rendered as HTML without trusting note text.
+        sqlx::query_as::<_, NoteSearchRow>(
+            r#"
+            SELECT
+                n.id, n.title, n.starred, n.created_at, n.updated_at, n.deleted_at, n.workspace_id, n.folder_id,
+                CASE
+                    WHEN n.workspace_id IS NULL THEN 'owner'
+                    WHEN w.role = 'admin' THEN 'owner'
+                    ELSE w.role
+                END AS role,
+                ts_rank(n.search_vector, q) AS rank,
+                ts_headline(
+                    'simple',
+                    replace(replace(replace(coalesce(n.content_text, ''), '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+                    q,
+                    'StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MinWords=5, MaxWords=20'
+                ) AS snippet,
+                COUNT(*) OVER () AS total
+            FROM notes n
+            CROSS JOIN websearch_to_tsquery('simple', $2) AS q
+            LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $1
+            WHERE ((n.workspace_id IS NULL AND n.user_id = $1) OR w.user_id IS NOT NULL)
+              AND n.deleted_at IS NULL AND NOT n.encrypted AND ($2 = '' OR n.search_vector @@ q)
+              AND NOT EXISTS (
+                  SELECT 1 FROM unnest($5::text[]) AS wanted(name)
+                  WHERE NOT EXISTS (
+                      SELECT 1 FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                      WHERE nt.note_id = n.id AND lower(t.name) = lower(wanted.name)
+                  )
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                  WHERE nt.note_id = n.id AND lower(t.name) = ANY($6)
+              )
+              AND ($7::boolean IS NULL OR n.starred = $7)
+              AND ($8::boolean IS NULL OR EXISTS (SELECT 1 FROM tasks t WHERE t.note_id = n.id) = $8)
+              AND ($9::boolean IS NULL OR EXISTS (SELECT 1 FROM tasks t WHERE t.note_id = n.id AND NOT t.checked) = $9)
+              AND NOT EXISTS (
+                  SELECT 1
+                  FROM unnest($10::text[], $11::timestamptz[], $12::timestamptz[], $13::boolean[])
+                      AS d(field, since, until, negated)
+                  CROSS JOIN LATERAL (
+                      SELECT CASE d.field WHEN 'created' THEN n.created_at ELSE n.updated_at END AS at
+                  ) AS date
+                  WHERE ((d.since IS NULL OR date.at >= d.since) AND (d.until IS NULL OR date.at < d.until)) = d.negated
+              )
+            ORDER BY rank DESC, n.updated_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(user_id)
+        .bind(&search.text)
+        .bind(limit)
+        .bind(offset)
+        .bind(&search.tags)
+        .bind(search.excluded_tags.iter().map(|tag| tag.to_lowercase()).collect::<Vec<_>>())
+        .bind(search.starred)
+        .bind(search.has_tasks)
+        .bind(search.has_open_tasks)
+        .bind(date_fields)
+        .bind(date_since)
+        .bind(date_until)
+        .bind(date_negated)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Fill in `content_text` for notes stored before search indexing existed.
+    /// Content that isn't a readable Yjs document is indexed as empty text
+    /// and logged, so such a note isn't picked up again on the next start.
+    /// Notes are visited in id order, each once. Returns the number of notes
+    /// indexed.
+    pub async fn backfill_content_text(&self) -> Result<u64, Error> {
+        let mut indexed = 0;
+        let mut after = Uuid::nil();
+        loop {
+            let batch: Vec<(Uuid, Vec<u8>)> = sqlx::query_as(
+                r#"
+                SELECT id, content FROM notes
+                WHERE content_text IS NULL AND NOT encrypted AND id > $1
+                ORDER BY id
+                LIMIT 100
+                "#,
+            )
+            .bind(after)
+            .fetch_all(&self.pool)
+            .await?;
+
+            let Some((last, _)) = batch.last() else {
+                return Ok(indexed);
+            };
+            after = *last;
+
+            for (id, content) in batch {
+                let text = document::extract_text(&content).unwrap_or_else(|e| {
+                    tracing::warn!("Note {} has unreadable content, indexing it as empty: {}", id, e);
+                    String::new()
+                });
+                sqlx::query("UPDATE notes SET content_text = $2 WHERE id = $1 AND content_text IS NULL")
+                    .bind(id)
+                    .bind(text)
+                    .execute(&self.pool)
+                    .await?;
+                indexed += 1;
+            }
+        }
+    }
+
+    /// Number of notes search can't find by their content: unencrypted
+    /// notes whose text was never indexed. Zero once the backfill has run.
+    pub async fn count_unindexed_notes(&self) -> Result<i64, Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM notes WHERE content_text IS NULL AND NOT encrypted")
+            .fetch_one(&self.pool)
+            .await
+    }
+
     pub async fn soft_delete_note(&self, id: Uuid, user_id: Uuid) -> Result<Note, Error> {
         sqlx::query_as::<_, Note>(
             "UPDATE notes SET deleted_at = NOW() WHERE id = $1 AND user_id = $2 RETURNING *",
@@ -240,11 +606,23 @@ impl Database {
     }
 
     pub async fn permanent_delete_note(&self, id: Uuid, user_id: Uuid) -> Result<u64, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let tag_ids: Vec<(Uuid,)> = sqlx::query_as("SELECT tag_id FROM note_tags WHERE note_id = $1")
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await?;
+
         let result = sqlx::query("DELETE FROM notes WHERE id = $1 AND user_id = $2")
             .bind(id)
             .bind(user_id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
+
+        let tag_ids: Vec<Uuid> = tag_ids.into_iter().map(|(id,)| id).collect();
+        remove_unused_tags(&mut tx, &tag_ids).await?;
+
+        tx.commit().await?;
         Ok(result.rows_affected())
     }
 
@@ -296,39 +674,1356 @@ impl Database {
         Ok(result.rows_affected())
     }
 
-    // Sync update queries
-    pub async fn store_sync_update(
+    // Device key queries
+    pub async fn list_device_keys(&self, user_id: Uuid) -> Result<Vec<DeviceKey>, Error> {
+        sqlx::query_as::<_, DeviceKey>(
+            "SELECT * FROM device_keys WHERE user_id = $1 ORDER BY created_at ASC",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Register a device or update its details. A wrapped key made for an
+    /// old public key is useless, so it is dropped when the key changes.
+    pub async fn upsert_device_key(
         &self,
-        note_id: Uuid,
-        update_data: &[u8],
-        client_id: Option<&str>,
-    ) -> Result<i64, Error> {
-        let row: (i64,) = sqlx::query_as(
+        user_id: Uuid,
+        device_id: &str,
+        name: Option<&str>,
+        public_key: &[u8],
+        wrapped_key: Option<&[u8]>,
+    ) -> Result<DeviceKey, Error> {
+        sqlx::query_as::<_, DeviceKey>(
             r#"
-            INSERT INTO sync_updates (note_id, update_data, client_id)
-            VALUES ($1, $2, $3)
-            RETURNING id
+            INSERT INTO device_keys (user_id, device_id, name, public_key, wrapped_key)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, device_id) DO UPDATE SET
+                name = EXCLUDED.name,
+                public_key = EXCLUDED.public_key,
+                wrapped_key = CASE
+                    WHEN EXCLUDED.wrapped_key IS NOT NULL THEN EXCLUDED.wrapped_key
+                    WHEN device_keys.public_key = EXCLUDED.public_key THEN device_keys.wrapped_key
+                    ELSE NULL
+                END
+            RETURNING *
             "#,
         )
-        .bind(note_id)
-        .bind(update_data)
-        .bind(client_id)
+        .bind(user_id)
+        .bind(device_id)
+        .bind(name)
+        .bind(public_key)
+        .bind(wrapped_key)
         .fetch_one(&self.pool)
-        .await?;
-        Ok(row.0)
+        .await
     }
 
-    pub async fn get_sync_updates_since(
+    pub async fn set_device_wrapped_key(
+        &self,
+        user_id: Uuid,
+        device_id: &str,
+        wrapped_key: &[u8],
+    ) -> Result<Option<DeviceKey>, Error> {
+        sqlx::query_as::<_, DeviceKey>(
+            "UPDATE device_keys SET wrapped_key = $3 WHERE user_id = $1 AND device_id = $2 RETURNING *",
+        )
+        .bind(user_id)
+        .bind(device_id)
+        .bind(wrapped_key)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn delete_device_key(&self, user_id: Uuid, device_id: &str) -> Result<u64, Error> {
+        let result = sqlx::query("DELETE FROM device_keys WHERE user_id = $1 AND device_id = $2")
+            .bind(user_id)
+            .bind(device_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    // Note member queries
+    pub async fn list_note_members(&self, note_id: Uuid) -> Result<Vec<NoteMember>, Error> {
+        sqlx::query_as::<_, NoteMember>(
+            "SELECT * FROM note_members WHERE note_id = $1 ORDER BY created_at ASC",
+        )
+        .bind(note_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Invite `email` to a note, or change the role of an existing member.
+    /// The invite is linked to the user with that email if there is one.
+    pub async fn upsert_note_member(
         &self,
         note_id: Uuid,
-        since: DateTime<Utc>,
-    ) -> Result<Vec<(i64, Vec<u8>)>, Error> {
-        sqlx::query_as(
-            "SELECT id, update_data FROM sync_updates WHERE note_id = $1 AND created_at > $2 ORDER BY id ASC",
+        email: &str,
+        role: NoteRole,
+        invited_by: Uuid,
+    ) -> Result<NoteMember, Error> {
+        sqlx::query_as::<_, NoteMember>(
+            r#"
+            INSERT INTO note_members (note_id, user_id, email, role, invited_by)
+            VALUES ($1, (SELECT id FROM users WHERE lower(email) = lower($2) LIMIT 1), $2, $3, $4)
+            ON CONFLICT (note_id, lower(email)) DO UPDATE SET role = EXCLUDED.role
+            RETURNING *
+            "#,
         )
         .bind(note_id)
-        .bind(since)
+        .bind(email)
+        .bind(role.as_str())
+        .bind(invited_by)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn delete_note_member(&self, id: Uuid, note_id: Uuid) -> Result<Option<NoteMember>, Error> {
+        sqlx::query_as::<_, NoteMember>("DELETE FROM note_members WHERE id = $1 AND note_id = $2 RETURNING *")
+            .bind(id)
+            .bind(note_id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Link invites sent to a user's email before they signed up
+    pub async fn claim_note_invites(&self, user_id: Uuid, email: &str) -> Result<u64, Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE note_members SET user_id = $1
+            WHERE user_id IS NULL AND lower(email) = lower($2)
+              AND note_id NOT IN (SELECT id FROM notes WHERE user_id = $1)
+            "#,
+        )
+        .bind(user_id)
+        .bind(email)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    // Workspace queries
+
+    /// The workspaces the user is a member of, with their role
+    pub async fn list_workspaces(&self, user_id: Uuid) -> Result<Vec<MemberWorkspace>, Error> {
+        sqlx::query_as::<_, MemberWorkspace>(
+            r#"
+            SELECT w.*, m.role FROM workspaces w
+            JOIN workspace_members m ON m.workspace_id = w.id
+            WHERE m.user_id = $1
+            ORDER BY lower(w.name) ASC
+            "#,
+        )
+        .bind(user_id)
         .fetch_all(&self.pool)
         .await
     }
+
+    /// A workspace the user is a member of, with their role
+    pub async fn get_member_workspace(&self, id: Uuid, user_id: Uuid) -> Result<Option<MemberWorkspace>, Error> {
+        sqlx::query_as::<_, MemberWorkspace>(
+            r#"
+            SELECT w.*, m.role FROM workspaces w
+            JOIN workspace_members m ON m.workspace_id = w.id
+            WHERE w.id = $1 AND m.user_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Create a workspace with its creator as the first admin
+    pub async fn create_workspace(&self, name: &str, user_id: Uuid, email: &str) -> Result<Workspace, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let workspace = sqlx::query_as::<_, Workspace>(
+            "INSERT INTO workspaces (name, created_by) VALUES ($1, $2) RETURNING *",
+        )
+        .bind(name)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO workspace_members (workspace_id, user_id, email, role, invited_by)
+            VALUES ($1, $2, $3, 'admin', $2)
+            "#,
+        )
+        .bind(workspace.id)
+        .bind(user_id)
+        .bind(email)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(workspace)
+    }
+
+    pub async fn rename_workspace(&self, id: Uuid, name: &str) -> Result<Workspace, Error> {
+        sqlx::query_as::<_, Workspace>("UPDATE workspaces SET name = $2 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(name)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Delete a workspace together with all of its notes
+    pub async fn delete_workspace(&self, id: Uuid) -> Result<u64, Error> {
+        let result = sqlx::query("DELETE FROM workspaces WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn workspace_note_ids(&self, workspace_id: Uuid) -> Result<Vec<Uuid>, Error> {
+        let rows: Vec<(Uuid,)> = sqlx::query_as("SELECT id FROM notes WHERE workspace_id = $1")
+            .bind(workspace_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    // Workspace member queries
+    pub async fn list_workspace_members(&self, workspace_id: Uuid) -> Result<Vec<WorkspaceMember>, Error> {
+        sqlx::query_as::<_, WorkspaceMember>(
+            "SELECT * FROM workspace_members WHERE workspace_id = $1 ORDER BY created_at ASC",
+        )
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Invite `email` to a workspace, or change the role of an existing
+    /// member. The invite is linked to the user with that email if there is one.
+    pub async fn upsert_workspace_member(
+        &self,
+        workspace_id: Uuid,
+        email: &str,
+        role: WorkspaceRole,
+        invited_by: Uuid,
+    ) -> Result<WorkspaceMember, Error> {
+        sqlx::query_as::<_, WorkspaceMember>(
+            r#"
+            INSERT INTO workspace_members (workspace_id, user_id, email, role, invited_by)
+            VALUES ($1, (SELECT id FROM users WHERE lower(email) = lower($2) LIMIT 1), $2, $3, $4)
+            ON CONFLICT (workspace_id, lower(email)) DO UPDATE SET role = EXCLUDED.role
+            RETURNING *
+            "#,
+        )
+        .bind(workspace_id)
+        .bind(email)
+        .bind(role.as_str())
+        .bind(invited_by)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn delete_workspace_member(
+        &self,
+        id: Uuid,
+        workspace_id: Uuid,
+    ) -> Result<Option<WorkspaceMember>, Error> {
+        sqlx::query_as::<_, WorkspaceMember>(
+            "DELETE FROM workspace_members WHERE id = $1 AND workspace_id = $2 RETURNING *",
+        )
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Link workspace invites sent to a user's email before they signed up
+    pub async fn claim_workspace_invites(&self, user_id: Uuid, email: &str) -> Result<u64, Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE workspace_members SET user_id = $1
+            WHERE user_id IS NULL AND lower(email) = lower($2)
+              AND workspace_id NOT IN (SELECT workspace_id FROM workspace_members WHERE user_id = $1)
+            "#,
+        )
+        .bind(user_id)
+        .bind(email)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    // Folder queries
+
+    /// Folders the user can see: their personal ones and those of their
+    /// workspaces, limited to `scope`. With `since`, only folders changed
+    /// since then, including deleted ones, or that the user has gained
+    /// access to since then.
+    pub async fn list_folders(
+        &self,
+        user_id: Uuid,
+        since: Option<DateTime<Utc>>,
+        scope: NoteScope,
+    ) -> Result<Vec<Folder>, Error> {
+        let (scoped, workspace_id) = scope.filter();
+        sqlx::query_as::<_, Folder>(
+            r#"
+            SELECT f.* FROM folders f
+            LEFT JOIN workspace_members w ON w.workspace_id = f.workspace_id AND w.user_id = $1
+            WHERE ((f.workspace_id IS NULL AND f.user_id = $1) OR w.user_id IS NOT NULL)
+              AND (($2::timestamptz IS NULL AND f.deleted_at IS NULL) OR f.updated_at > $2 OR w.created_at > $2)
+              AND (NOT $3 OR f.workspace_id IS NOT DISTINCT FROM $4)
+            ORDER BY lower(f.name) ASC
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .bind(scoped)
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// A folder the user can see that hasn't been deleted
+    pub async fn get_accessible_folder(&self, id: Uuid, user_id: Uuid) -> Result<Option<Folder>, Error> {
+        sqlx::query_as::<_, Folder>(
+            r#"
+            SELECT f.* FROM folders f
+            LEFT JOIN workspace_members w ON w.workspace_id = f.workspace_id AND w.user_id = $2
+            WHERE f.id = $1 AND f.deleted_at IS NULL
+              AND ((f.workspace_id IS NULL AND f.user_id = $2) OR w.user_id IS NOT NULL)
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn create_folder(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        workspace_id: Option<Uuid>,
+        parent_id: Option<Uuid>,
+        name: &str,
+    ) -> Result<Folder, Error> {
+        sqlx::query_as::<_, Folder>(
+            r#"
+            INSERT INTO folders (id, user_id, workspace_id, parent_id, name)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(workspace_id)
+        .bind(parent_id)
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Rename a folder and move it under `parent_id` (None for the top level)
+    pub async fn update_folder(&self, id: Uuid, name: &str, parent_id: Option<Uuid>) -> Result<Folder, Error> {
+        sqlx::query_as::<_, Folder>("UPDATE folders SET name = $2, parent_id = $3 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(name)
+            .bind(parent_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// The folder and all folders below it
+    pub async fn folder_subtree(&self, id: Uuid) -> Result<Vec<Uuid>, Error> {
+        let rows: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            WITH RECURSIVE subtree(id) AS (
+                SELECT $1::uuid
+                UNION
+                SELECT f.id FROM folders f JOIN subtree s ON f.parent_id = s.id
+                WHERE f.deleted_at IS NULL
+            )
+            SELECT id FROM subtree
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// Delete a folder and its subfolders, moving the notes in any of them
+    /// to the trash, outside any folder. Returns the ids of the notes that
+    /// were trashed.
+    pub async fn delete_folder(&self, id: Uuid) -> Result<Vec<Uuid>, Error> {
+        let folder_ids = self.folder_subtree(id).await?;
+        let mut tx = self.pool.begin().await?;
+
+        let trashed: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            UPDATE notes SET folder_id = NULL, deleted_at = NOW()
+            WHERE folder_id = ANY($1) AND deleted_at IS NULL
+            RETURNING id
+            "#,
+        )
+        .bind(&folder_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // Notes already in the trash just leave the folder
+        sqlx::query("UPDATE notes SET folder_id = NULL WHERE folder_id = ANY($1)")
+            .bind(&folder_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE folders SET deleted_at = NOW() WHERE id = ANY($1)")
+            .bind(&folder_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(trashed.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// File a note in a folder, or take it out of any folder
+    pub async fn set_note_folder(&self, note_id: Uuid, folder_id: Option<Uuid>) -> Result<Note, Error> {
+        sqlx::query_as::<_, Note>("UPDATE notes SET folder_id = $2 WHERE id = $1 RETURNING *")
+            .bind(note_id)
+            .bind(folder_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    // Tag queries
+
+    /// Tags the user can see: their personal ones and those of their
+    /// workspaces, limited to `scope`. With `since`, only tags changed since
+    /// then, including deleted ones, or that the user has gained access to
+    /// since then.
+    pub async fn list_tags(
+        &self,
+        user_id: Uuid,
+        since: Option<DateTime<Utc>>,
+        scope: NoteScope,
+    ) -> Result<Vec<TagSummary>, Error> {
+        let (scoped, workspace_id) = scope.filter();
+        sqlx::query_as::<_, TagSummary>(
+            r#"
+            SELECT t.*, (
+                SELECT COUNT(*) FROM note_tags nt JOIN notes n ON n.id = nt.note_id
+                WHERE nt.tag_id = t.id AND n.deleted_at IS NULL
+            ) AS note_count
+            FROM tags t
+            LEFT JOIN workspace_members w ON w.workspace_id = t.workspace_id AND w.user_id = $1
+            WHERE ((t.workspace_id IS NULL AND t.user_id = $1) OR w.user_id IS NOT NULL)
+              AND (($2::timestamptz IS NULL AND t.deleted_at IS NULL) OR t.updated_at > $2 OR w.created_at > $2)
+              AND (NOT $3 OR t.workspace_id IS NOT DISTINCT FROM $4)
+            ORDER BY lower(t.name) ASC
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .bind(scoped)
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// A tag the user can see that hasn't been deleted
+    pub async fn get_accessible_tag(&self, id: Uuid, user_id: Uuid) -> Result<Option<Tag>, Error> {
+        sqlx::query_as::<_, Tag>(
+            r#"
+            SELECT t.* FROM tags t
+            LEFT JOIN workspace_members w ON w.workspace_id = t.workspace_id AND w.user_id = $2
+            WHERE t.id = $1 AND t.deleted_at IS NULL
+              AND ((t.workspace_id IS NULL AND t.user_id = $2) OR w.user_id IS NOT NULL)
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn list_note_tags(&self, note_id: Uuid) -> Result<Vec<Tag>, Error> {
+        sqlx::query_as::<_, Tag>(
+            r#"
+            SELECT t.* FROM tags t JOIN note_tags nt ON nt.tag_id = t.id
+            WHERE nt.note_id = $1
+            ORDER BY lower(t.name) ASC
+            "#,
+        )
+        .bind(note_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// (note id, tag id) for every tag on the given notes
+    pub async fn note_tag_ids(&self, note_ids: &[Uuid]) -> Result<Vec<(Uuid, Uuid)>, Error> {
+        sqlx::query_as("SELECT note_id, tag_id FROM note_tags WHERE note_id = ANY($1)")
+            .bind(note_ids)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Tag a note by hand, creating the tag in the note's space if it's new.
+    /// `user_id` is who tags it; personal tags always belong to the owner.
+    pub async fn add_note_tag(&self, note: &Note, user_id: Uuid, name: &str) -> Result<Tag, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let creator = if note.workspace_id.is_some() { user_id } else { note.user_id };
+        let tag_id = tag_id(&mut tx, creator, note.workspace_id, name).await?;
+
+        // A hashtag that is also added by hand stays when the hashtag goes
+        sqlx::query(
+            r#"
+            INSERT INTO note_tags (note_id, tag_id, inline) VALUES ($1, $2, FALSE)
+            ON CONFLICT (note_id, tag_id) DO UPDATE SET inline = FALSE
+            "#,
+        )
+        .bind(note.id)
+        .bind(tag_id)
+        .execute(&mut *tx)
+        .await?;
+
+        // Clients pull the tags of changed notes
+        sqlx::query("UPDATE notes SET updated_at = NOW() WHERE id = $1")
+            .bind(note.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let tag = sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE id = $1")
+            .bind(tag_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(tag)
+    }
+
+    /// Take a tag off a note, deleting the tag if no note has it any more.
+    /// A hashtag still in the note's text adds it again on the next save.
+    pub async fn remove_note_tag(&self, note_id: Uuid, tag_id: Uuid) -> Result<u64, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query("DELETE FROM note_tags WHERE note_id = $1 AND tag_id = $2")
+            .bind(note_id)
+            .bind(tag_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() > 0 {
+            sqlx::query("UPDATE notes SET updated_at = NOW() WHERE id = $1")
+                .bind(note_id)
+                .execute(&mut *tx)
+                .await?;
+            remove_unused_tags(&mut tx, &[tag_id]).await?;
+        }
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Make the note's hashtag tags match `names`, the hashtags now in its
+    /// text. Tags added by hand are left alone. Called when the content is
+    /// saved, which already marks the note as changed.
+    pub async fn set_inline_tags(&self, note: &Note, names: &[String]) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let lowered: Vec<String> = names.iter().map(|name| name.to_lowercase()).collect();
+        let dropped: Vec<(Uuid,)> = sqlx::query_as(
+            r#"
+            DELETE FROM note_tags nt USING tags t
+            WHERE t.id = nt.tag_id AND nt.note_id = $1 AND nt.inline AND lower(t.name) <> ALL($2)
+            RETURNING nt.tag_id
+            "#,
+        )
+        .bind(note.id)
+        .bind(&lowered)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for name in names {
+            let tag_id = tag_id(&mut tx, note.user_id, note.workspace_id, name).await?;
+            sqlx::query(
+                "INSERT INTO note_tags (note_id, tag_id, inline) VALUES ($1, $2, TRUE) ON CONFLICT DO NOTHING",
+            )
+            .bind(note.id)
+            .bind(tag_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let dropped: Vec<Uuid> = dropped.into_iter().map(|(id,)| id).collect();
+        remove_unused_tags(&mut tx, &dropped).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Rename a tag, merging it into the tag of that name in the same space
+    /// if there already is one; returns the tag the notes now have. Hashtags
+    /// in note text keep the old name, so the renamed tag counts as added by
+    /// hand from now on.
+    pub async fn rename_tag(&self, tag: &Tag, name: &str) -> Result<Tag, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE note_tags SET inline = FALSE WHERE tag_id = $1")
+            .bind(tag.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let other: Option<(Uuid,)> = sqlx::query_as(
+            r#"
+            SELECT id FROM tags
+            WHERE lower(name) = lower($3) AND deleted_at IS NULL AND id <> $4
+              AND workspace_id IS NOT DISTINCT FROM $2 AND ($2::uuid IS NOT NULL OR user_id = $1)
+            "#,
+        )
+        .bind(tag.user_id)
+        .bind(tag.workspace_id)
+        .bind(name)
+        .bind(tag.id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let renamed = match other {
+            Some((other_id,)) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO note_tags (note_id, tag_id, inline)
+                    SELECT note_id, $2, FALSE FROM note_tags WHERE tag_id = $1
+                    ON CONFLICT (note_id, tag_id) DO UPDATE SET inline = FALSE
+                    "#,
+                )
+                .bind(tag.id)
+                .bind(other_id)
+                .execute(&mut *tx)
+                .await?;
+
+                // The merged notes' tags changed
+                sqlx::query("UPDATE notes SET updated_at = NOW() WHERE id IN (SELECT note_id FROM note_tags WHERE tag_id = $1)")
+                    .bind(tag.id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM note_tags WHERE tag_id = $1")
+                    .bind(tag.id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("UPDATE tags SET deleted_at = NOW() WHERE id = $1")
+                    .bind(tag.id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE id = $1")
+                    .bind(other_id)
+                    .fetch_one(&mut *tx)
+                    .await?
+            }
+            None => {
+                sqlx::query_as::<_, Tag>("UPDATE tags SET name = $2 WHERE id = $1 RETURNING *")
+                    .bind(tag.id)
+                    .bind(name)
+                    .fetch_one(&mut *tx)
+                    .await?
+            }
+        };
+
+        tx.commit().await?;
+        Ok(renamed)
+    }
+
+    /// Take a tag off every note and delete it. Hashtags in note text add
+    /// it again the next time those notes are saved.
+    pub async fn delete_tag(&self, id: Uuid) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE notes SET updated_at = NOW() WHERE id IN (SELECT note_id FROM note_tags WHERE tag_id = $1)")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM note_tags WHERE tag_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE tags SET deleted_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Saved search queries
+
+    /// The user's saved searches, by name. With `since`, only those changed
+    /// since then, including deleted ones.
+    pub async fn list_saved_searches(
+        &self,
+        user_id: Uuid,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<SavedSearch>, Error> {
+        sqlx::query_as::<_, SavedSearch>(
+            r#"
+            SELECT * FROM saved_searches
+            WHERE user_id = $1
+              AND (($2::timestamptz IS NULL AND deleted_at IS NULL) OR updated_at > $2)
+            ORDER BY lower(name) ASC
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// One of the user's saved searches that hasn't been deleted
+    pub async fn get_saved_search(&self, id: Uuid, user_id: Uuid) -> Result<Option<SavedSearch>, Error> {
+        sqlx::query_as::<_, SavedSearch>(
+            "SELECT * FROM saved_searches WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn create_saved_search(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        name: &str,
+        query: &str,
+    ) -> Result<SavedSearch, Error> {
+        sqlx::query_as::<_, SavedSearch>(
+            r#"
+            INSERT INTO saved_searches (id, user_id, name, query)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(name)
+        .bind(query)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn update_saved_search(&self, id: Uuid, name: &str, query: &str) -> Result<SavedSearch, Error> {
+        sqlx::query_as::<_, SavedSearch>("UPDATE saved_searches SET name = $2, query = $3 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(name)
+            .bind(query)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    pub async fn delete_saved_search(&self, id: Uuid) -> Result<(), Error> {
+        sqlx::query("UPDATE saved_searches SET deleted_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Attachment queries
+
+    /// Those of the given blob hashes the user can't read (see
+    /// `can_read_blob`), whether or not the store has them, so the answer
+    /// says nothing about other users' files
+    pub async fn missing_blobs(&self, hashes: &[String], user_id: Uuid) -> Result<Vec<String>, Error> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT h FROM UNNEST($1::text[]) AS h
+            WHERE NOT EXISTS (SELECT 1 FROM blob_uploads u WHERE u.hash = h AND u.user_id = $2)
+              AND NOT EXISTS (
+                SELECT 1 FROM note_attachments a
+                JOIN notes n ON n.id = a.note_id
+                LEFT JOIN note_members m ON m.note_id = n.id AND m.user_id = $2
+                LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $2
+                WHERE a.hash = h AND a.deleted_at IS NULL
+                  AND ((n.workspace_id IS NULL AND n.user_id = $2) OR m.user_id IS NOT NULL OR w.user_id IS NOT NULL)
+              )
+            "#,
+        )
+        .bind(hashes)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|(hash,)| hash).collect())
+    }
+
+    /// Record a blob that was put in the store, and that the user uploaded it
+    pub async fn insert_blob(&self, hash: &str, size: i64, user_id: Uuid) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO blobs (hash, size) VALUES ($1, $2)
+            ON CONFLICT (hash) DO UPDATE SET used_at = NOW()
+            "#,
+        )
+        .bind(hash)
+        .bind(size)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("INSERT INTO blob_uploads (hash, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(hash)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Whether the user uploaded the blob or it is attached to a note they
+    /// can access
+    pub async fn can_read_blob(&self, hash: &str, user_id: Uuid) -> Result<bool, Error> {
+        let row: (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM blob_uploads u WHERE u.hash = $1 AND u.user_id = $2)
+                OR EXISTS(
+                    SELECT 1 FROM note_attachments a
+                    JOIN notes n ON n.id = a.note_id
+                    LEFT JOIN note_members m ON m.note_id = n.id AND m.user_id = $2
+                    LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $2
+                    WHERE a.hash = $1 AND a.deleted_at IS NULL
+                      AND ((n.workspace_id IS NULL AND n.user_id = $2) OR m.user_id IS NOT NULL OR w.user_id IS NOT NULL)
+                )
+            "#,
+        )
+        .bind(hash)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.0)
+    }
+
+    /// Attachments of notes the user can access, limited to `scope`, that
+    /// changed since `since`, including removed ones, or whose note the
+    /// user has gained access to since then
+    pub async fn list_attachments(
+        &self,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+        scope: NoteScope,
+    ) -> Result<Vec<Attachment>, Error> {
+        let (scoped, workspace_id) = scope.filter();
+        sqlx::query_as::<_, Attachment>(
+            r#"
+            SELECT a.* FROM note_attachments a
+            JOIN notes n ON n.id = a.note_id
+            LEFT JOIN note_members m ON m.note_id = n.id AND m.user_id = $1
+            LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $1
+            WHERE ((n.workspace_id IS NULL AND n.user_id = $1) OR m.user_id IS NOT NULL OR w.user_id IS NOT NULL)
+              AND (a.updated_at > $2 OR m.created_at > $2 OR w.created_at > $2)
+              AND (NOT $3 OR n.workspace_id IS NOT DISTINCT FROM $4)
+            ORDER BY a.created_at ASC
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .bind(scoped)
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn list_note_attachments(&self, note_id: Uuid) -> Result<Vec<Attachment>, Error> {
+        sqlx::query_as::<_, Attachment>(
+            "SELECT * FROM note_attachments WHERE note_id = $1 AND deleted_at IS NULL ORDER BY created_at ASC",
+        )
+        .bind(note_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn get_attachment(&self, id: Uuid, note_id: Uuid) -> Result<Option<Attachment>, Error> {
+        sqlx::query_as::<_, Attachment>(
+            "SELECT * FROM note_attachments WHERE id = $1 AND note_id = $2 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(note_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Attach a stored blob to a note. None if the blob isn't stored (any
+    /// more) or the user can't read it (see `can_read_blob`); either way it
+    /// has to be uploaded first.
+    pub async fn create_attachment(
+        &self,
+        id: Uuid,
+        note_id: Uuid,
+        user_id: Uuid,
+        hash: &str,
+        file_name: &str,
+        content_type: &str,
+    ) -> Result<Option<Attachment>, Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // Knowing the hash isn't enough: attaching would let the user read
+        // the blob from then on
+        let readable = self.can_read_blob(hash, user_id).await?;
+        if !readable {
+            return Ok(None);
+        }
+
+        // Marking the blob used locks it against garbage collection until
+        // the attachment refers to it
+        let size: Option<(i64,)> = sqlx::query_as("UPDATE blobs SET used_at = NOW() WHERE hash = $1 RETURNING size")
+            .bind(hash)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some((size,)) = size else {
+            return Ok(None);
+        };
+
+        let attachment = sqlx::query_as::<_, Attachment>(
+            r#"
+            INSERT INTO note_attachments (id, note_id, user_id, hash, file_name, content_type, size)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(note_id)
+        .bind(user_id)
+        .bind(hash)
+        .bind(file_name)
+        .bind(content_type)
+        .bind(size)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(attachment))
+    }
+
+    pub async fn delete_attachment(&self, id: Uuid, note_id: Uuid) -> Result<u64, Error> {
+        let result = sqlx::query(
+            "UPDATE note_attachments SET deleted_at = NOW() WHERE id = $1 AND note_id = $2 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(note_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Forget the blobs no attachment refers to that haven't been used
+    /// since `unused_since`, returning their hashes for removal from the
+    /// store. Attachments of notes in the trash still count.
+    pub async fn remove_unused_blobs(&self, unused_since: DateTime<Utc>) -> Result<Vec<String>, Error> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            r#"
+            DELETE FROM blobs b
+            WHERE b.used_at < $1
+              AND NOT EXISTS (SELECT 1 FROM note_attachments a WHERE a.hash = b.hash AND a.deleted_at IS NULL)
+            RETURNING hash
+            "#,
+        )
+        .bind(unused_since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|(hash,)| hash).collect())
+    }
+
+    // Task queries
+    /// Replace the indexed tasks of a note with the task items now in its
+    /// content
+    pub async fn set_tasks(&self, note_id: Uuid, items: &[document::TaskItem]) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let previous: Vec<PreviousTask> =
+            sqlx::query_as("SELECT path, text, checked, completed_at FROM tasks WHERE note_id = $1")
+                .bind(note_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        sqlx::query("DELETE FROM tasks WHERE note_id = $1")
+            .bind(note_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let now = Utc::now();
+        for (position, item) in items.iter().enumerate() {
+            let path = document::task_path(&item.path);
+            let completed_at = if item.checked {
+                completed_at(&previous, &path, &item.text, now)
+            } else {
+                None
+            };
+            sqlx::query(
+                r#"
+                INSERT INTO tasks (note_id, path, position, depth, text, checked, due_date, remind_at, completed_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                "#,
+            )
+            .bind(note_id)
+            .bind(&path)
+            .bind(position as i32)
+            .bind(item.depth as i32)
+            .bind(&item.text)
+            .bind(item.checked)
+            .bind(item.due_date)
+            .bind(item.remind_at)
+            .bind(completed_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query("UPDATE notes SET tasks_indexed = TRUE WHERE id = $1")
+            .bind(note_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Unchecked tasks of the notes the user can access outside the trash,
+    /// most recently edited notes first and in document order within a note
+    pub async fn list_open_tasks(&self, user_id: Uuid, scope: NoteScope) -> Result<Vec<Task>, Error> {
+        let (scoped, workspace_id) = scope.filter();
+        sqlx::query_as::<_, Task>(
+            r#"
+            SELECT t.*, n.title AS note_title
+            FROM tasks t
+            JOIN notes n ON n.id = t.note_id
+            LEFT JOIN note_members m ON m.note_id = n.id AND m.user_id = $1
+            LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $1
+            WHERE ((n.workspace_id IS NULL AND n.user_id = $1) OR m.user_id IS NOT NULL OR w.user_id IS NOT NULL)
+              AND NOT t.checked AND n.deleted_at IS NULL
+              AND (NOT $2 OR n.workspace_id IS NOT DISTINCT FROM $3)
+            ORDER BY n.updated_at DESC, t.note_id, t.position
+            "#,
+        )
+        .bind(user_id)
+        .bind(scoped)
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// All tasks of a note in document order
+    pub async fn list_note_tasks(&self, note_id: Uuid) -> Result<Vec<Task>, Error> {
+        sqlx::query_as::<_, Task>(
+            r#"
+            SELECT t.*, n.title AS note_title
+            FROM tasks t JOIN notes n ON n.id = t.note_id
+            WHERE t.note_id = $1
+            ORDER BY t.position
+            "#,
+        )
+        .bind(note_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Open and completed task counts of the notes with tasks the user can
+    /// access outside the trash
+    pub async fn task_counts(&self, user_id: Uuid, scope: NoteScope) -> Result<Vec<TaskCounts>, Error> {
+        let (scoped, workspace_id) = scope.filter();
+        sqlx::query_as::<_, TaskCounts>(
+            r#"
+            SELECT t.note_id,
+                COUNT(*) FILTER (WHERE NOT t.checked) AS open,
+                COUNT(*) FILTER (WHERE t.checked) AS completed
+            FROM tasks t
+            JOIN notes n ON n.id = t.note_id
+            LEFT JOIN note_members m ON m.note_id = n.id AND m.user_id = $1
+            LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $1
+            WHERE ((n.workspace_id IS NULL AND n.user_id = $1) OR m.user_id IS NOT NULL OR w.user_id IS NOT NULL)
+              AND n.deleted_at IS NULL
+              AND (NOT $2 OR n.workspace_id IS NOT DISTINCT FROM $3)
+            GROUP BY t.note_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(scoped)
+        .bind(workspace_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Open tasks the user can access outside the trash that are due after
+    /// `after` (if set) and up to `until`, earliest due first
+    pub async fn list_due_tasks(
+        &self,
+        user_id: Uuid,
+        scope: NoteScope,
+        after: Option<NaiveDate>,
+        until: NaiveDate,
+    ) -> Result<Vec<Task>, Error> {
+        let (scoped, workspace_id) = scope.filter();
+        sqlx::query_as::<_, Task>(
+            r#"
+            SELECT t.*, n.title AS note_title
+            FROM tasks t
+            JOIN notes n ON n.id = t.note_id
+            LEFT JOIN note_members m ON m.note_id = n.id AND m.user_id = $1
+            LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $1
+            WHERE ((n.workspace_id IS NULL AND n.user_id = $1) OR m.user_id IS NOT NULL OR w.user_id IS NOT NULL)
+              AND NOT t.checked AND n.deleted_at IS NULL
+              AND (NOT $2 OR n.workspace_id IS NOT DISTINCT FROM $3)
+              AND ($4::date IS NULL OR t.due_date > $4) AND t.due_date <= $5
+            ORDER BY t.due_date, n.updated_at DESC, t.note_id, t.position
+            "#,
+        )
+        .bind(user_id)
+        .bind(scoped)
+        .bind(workspace_id)
+        .bind(after)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Tasks the user can access outside the trash that were checked since
+    /// `since`, most recently checked first
+    pub async fn list_completed_tasks(
+        &self,
+        user_id: Uuid,
+        scope: NoteScope,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Task>, Error> {
+        let (scoped, workspace_id) = scope.filter();
+        sqlx::query_as::<_, Task>(
+            r#"
+            SELECT t.*, n.title AS note_title
+            FROM tasks t
+            JOIN notes n ON n.id = t.note_id
+            LEFT JOIN note_members m ON m.note_id = n.id AND m.user_id = $1
+            LEFT JOIN workspace_members w ON w.workspace_id = n.workspace_id AND w.user_id = $1
+            WHERE ((n.workspace_id IS NULL AND n.user_id = $1) OR m.user_id IS NOT NULL OR w.user_id IS NOT NULL)
+              AND t.checked AND t.completed_at >= $4 AND n.deleted_at IS NULL
+              AND (NOT $2 OR n.workspace_id IS NOT DISTINCT FROM $3)
+            ORDER BY t.completed_at DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(user_id)
+        .bind(scoped)
+        .bind(workspace_id)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn get_task(&self, note_id: Uuid, path: &str) -> Result<Option<Task>, Error> {
+        sqlx::query_as::<_, Task>(
+            r#"
+            SELECT t.*, n.title AS note_title
+            FROM tasks t JOIN notes n ON n.id = t.note_id
+            WHERE t.note_id = $1 AND t.path = $2
+            "#,
+        )
+        .bind(note_id)
+        .bind(path)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Extract tasks of notes saved before tasks were indexed. Returns the
+    /// number of notes indexed.
+    pub async fn backfill_tasks(&self) -> Result<u64, Error> {
+        let mut indexed = 0;
+        loop {
+            let batch: Vec<(Uuid, Vec<u8>)> = sqlx::query_as(
+                "SELECT id, content FROM notes WHERE NOT tasks_indexed AND NOT encrypted LIMIT 100",
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            if batch.is_empty() {
+                return Ok(indexed);
+            }
+
+            for (id, content) in batch {
+                // Content that can't be decoded has no tasks
+                let items = document::tasks(&content).unwrap_or_default();
+                self.set_tasks(id, &items).await?;
+                indexed += 1;
+            }
+        }
+    }
+
+    // Share link queries
+    pub async fn create_share_link(
+        &self,
+        note_id: Uuid,
+        user_id: Uuid,
+        token: &str,
+        password_hash: Option<&str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<ShareLink, Error> {
+        sqlx::query_as::<_, ShareLink>(
+            r#"
+            INSERT INTO share_links (note_id, user_id, token, password_hash, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(note_id)
+        .bind(user_id)
+        .bind(token)
+        .bind(password_hash)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn list_share_links(&self, note_id: Uuid, user_id: Uuid) -> Result<Vec<ShareLink>, Error> {
+        sqlx::query_as::<_, ShareLink>(
+            "SELECT * FROM share_links WHERE note_id = $1 AND user_id = $2 ORDER BY created_at ASC",
+        )
+        .bind(note_id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn delete_share_link(&self, id: Uuid, note_id: Uuid, user_id: Uuid) -> Result<u64, Error> {
+        let result = sqlx::query("DELETE FROM share_links WHERE id = $1 AND note_id = $2 AND user_id = $3")
+            .bind(id)
+            .bind(note_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// The link and its note, unless the link has expired or the note is
+    /// in the trash
+    pub async fn get_shared_note(&self, token: &str) -> Result<Option<(ShareLink, Note)>, Error> {
+        let Some(link) = sqlx::query_as::<_, ShareLink>(
+            "SELECT * FROM share_links WHERE token = $1 AND (expires_at IS NULL OR expires_at > NOW())",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let note = sqlx::query_as::<_, Note>(
+            "SELECT * FROM notes WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL",
+        )
+        .bind(link.note_id)
+        .bind(link.user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(note.map(|note| (link, note)))
+    }
+
+    // Sync update queries
+    pub async fn store_sync_update(
+        &self,
+        note_id: Uuid,
+        update_data: &[u8],
+        client_id: Option<&str>,
+    ) -> Result<i64, Error> {
+        let row: (i64,) = sqlx::query_as(
+            r#"
+            INSERT INTO sync_updates (note_id, update_data, client_id)
+            VALUES ($1, $2, $3)
+            RETURNING id
+            "#,
+        )
+        .bind(note_id)
+        .bind(update_data)
+        .bind(client_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.0)
+    }
+
+    pub async fn get_sync_updates_since(
+        &self,
+        note_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(i64, Vec<u8>)>, Error> {
+        sqlx::query_as(
+            "SELECT id, update_data FROM sync_updates WHERE note_id = $1 AND created_at > $2 ORDER BY id ASC",
+        )
+        .bind(note_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
+
+/// The id of the tag named `name` in a space (the personal space of
+/// `user_id` when `workspace_id` is None), creating the tag if needed
+async fn tag_id(conn: &mut PgConnection, user_id: Uuid, workspace_id: Option<Uuid>, name: &str) -> Result<Uuid, Error> {
+    let existing: Option<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT id FROM tags
+        WHERE lower(name) = lower($3) AND deleted_at IS NULL
+          AND workspace_id IS NOT DISTINCT FROM $2 AND ($2::uuid IS NOT NULL OR user_id = $1)
+        "#,
+    )
+    .bind(user_id)
+    .bind(workspace_id)
+    .bind(name)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some((id,)) = existing {
+        return Ok(id);
+    }
+
+    let (id,): (Uuid,) = sqlx::query_as("INSERT INTO tags (user_id, workspace_id, name) VALUES ($1, $2, $3) RETURNING id")
+        .bind(user_id)
+        .bind(workspace_id)
+        .bind(name)
+        .fetch_one(&mut *conn)
+        .await?;
+    Ok(id)
+}
+
+/// Delete those of the given tags that no note has any more
+async fn remove_unused_tags(conn: &mut PgConnection, tag_ids: &[Uuid]) -> Result<(), Error> {
+    if tag_ids.is_empty() {
+        return Ok(());
+    }
+    sqlx::query(
+        r#"
+        UPDATE tags SET deleted_at = NOW()
+        WHERE id = ANY($1) AND deleted_at IS NULL
+          AND NOT EXISTS (SELECT 1 FROM note_tags nt WHERE nt.tag_id = tags.id)
+        "#,
+    )
+    .bind(tag_ids)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// A note's task as extracted before its content changed
+#[derive(sqlx::FromRow)]
+struct PreviousTask {
+    path: String,
+    text: String,
+    checked: bool,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+/// When a checked task was completed: kept from its previous row, found by
+/// path or else by text in case the task moved, or now if it was open
+/// there. A task that wasn't extracted before has no known completion time.
+fn completed_at(previous: &[PreviousTask], path: &str, text: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let task = previous
+        .iter()
+        .find(|task| task.path == path && task.text == text)
+        .or_else(|| previous.iter().find(|task| task.text == text))?;
+    if task.checked {
+        task.completed_at
+    } else {
+        Some(now)
+    }
 }
@@ -2,6 +2,7 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post, patch, delete, put},
     Router,
 };
@@ -15,13 +16,18 @@ mod models;
 mod db;
 mod sync;
 mod auth;
+mod blobs;
 
+use blobs::BlobStore;
 use db::Database;
 use auth::AuthState;
+use sync::live::LiveHub;
 
 pub struct AppState {
     pub db: Database,
     pub auth: AuthState,
+    pub live: LiveHub,
+    pub blobs: BlobStore,
 }
 
 #[tokio::main]
@@ -49,10 +55,52 @@ async fn main() {
     // Run migrations
     db.migrate().await.expect("Failed to run migrations");
 
+    // Index text of notes stored before full-text search existed
+    let indexed = db
+        .backfill_content_text()
+        .await
+        .expect("Failed to index note text");
+    if indexed > 0 {
+        tracing::info!("Indexed text of {} notes for search", indexed);
+    }
+    let unindexed = db
+        .count_unindexed_notes()
+        .await
+        .expect("Failed to check the search index");
+    if unindexed > 0 {
+        tracing::error!("{} notes are missing from search after indexing", unindexed);
+    }
+
+    // Extract tasks of notes stored before tasks were indexed
+    let indexed = db
+        .backfill_tasks()
+        .await
+        .expect("Failed to index note tasks");
+    if indexed > 0 {
+        tracing::info!("Indexed tasks of {} notes", indexed);
+    }
+
     // Initialize auth state
     let auth = AuthState::new();
 
-    let state = Arc::new(AppState { db, auth });
+    // Storage for attached files
+    let blobs = BlobStore::from_env().expect("Failed to configure blob store");
+
+    let state = Arc::new(AppState { db, auth, live: LiveHub::new(), blobs });
+
+    // Remove blobs no note refers to any more, hourly
+    let gc_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            match routes::attachments::collect_garbage(&gc_state).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!("Removed {} unused blobs", removed),
+                Err(e) => tracing::warn!("Blob garbage collection failed: {}", e),
+            }
+        }
+    });
 
     // Build router
     let app = Router::new()
@@ -65,11 +113,68 @@ async fn main() {
         // Note routes
         .route("/notes", get(routes::notes::list_notes))
         .route("/notes", post(routes::notes::create_note))
+        .route("/notes/search", get(routes::notes::search_notes))
         .route("/notes/:id", get(routes::notes::get_note))
         .route("/notes/:id", put(routes::notes::update_note))
         .route("/notes/:id", delete(routes::notes::delete_note))
         .route("/notes/:id/restore", post(routes::notes::restore_note))
         .route("/notes/:id/permanent", delete(routes::notes::permanent_delete))
+        .route("/notes/:id/folder", put(routes::folders::set_note_folder))
+        .route("/notes/:id/tags", get(routes::tags::list_note_tags))
+        .route("/notes/:id/tags", post(routes::tags::add_note_tag))
+        .route("/notes/:id/tags/:tag_id", delete(routes::tags::remove_note_tag))
+        .route("/notes/:id/tasks", get(routes::tasks::list_note_tasks))
+        .route("/notes/:id/attachments", get(routes::attachments::list_note_attachments))
+        .route("/notes/:id/attachments", post(routes::attachments::create_attachment))
+        .route("/notes/:id/attachments/:attachment_id", get(routes::attachments::download_attachment))
+        .route("/notes/:id/attachments/:attachment_id", delete(routes::attachments::delete_attachment))
+        .route("/notes/:id/export", get(routes::export::export_note))
+        .route("/notes/:id/members", get(routes::members::list_members))
+        .route("/notes/:id/members", post(routes::members::invite_member))
+        .route("/notes/:id/members/:member_id", delete(routes::members::remove_member))
+        .route("/notes/:id/share", get(routes::share::list_share_links))
+        .route("/notes/:id/share", post(routes::share::create_share_link))
+        .route("/notes/:id/share/:link_id", delete(routes::share::revoke_share_link))
+        // Folder routes
+        .route("/folders", get(routes::folders::list_folders))
+        .route("/folders", post(routes::folders::create_folder))
+        .route("/folders/:id", put(routes::folders::update_folder))
+        .route("/folders/:id", delete(routes::folders::delete_folder))
+        // Tag routes
+        .route("/tags", get(routes::tags::list_tags))
+        .route("/tags/:id", patch(routes::tags::rename_tag))
+        .route("/tags/:id", delete(routes::tags::delete_tag))
+        // Saved search routes
+        .route("/saved-searches", get(routes::saved_searches::list_saved_searches))
+        .route("/saved-searches", post(routes::saved_searches::create_saved_search))
+        .route("/saved-searches/:id", put(routes::saved_searches::update_saved_search))
+        .route("/saved-searches/:id", delete(routes::saved_searches::delete_saved_search))
+        .route("/saved-searches/:id/notes", get(routes::saved_searches::run_saved_search))
+        // Task routes
+        .route("/tasks", get(routes::tasks::list_open_tasks))
+        .route("/tasks/counts", get(routes::tasks::list_task_counts))
+        .route("/tasks/today", get(routes::tasks::list_today_tasks))
+        .route("/tasks/upcoming", get(routes::tasks::list_upcoming_tasks))
+        .route("/tasks/completed", get(routes::tasks::list_completed_tasks))
+        .route("/tasks/:id/toggle", post(routes::tasks::toggle_task))
+        // Blob routes
+        .route("/blobs/missing", post(routes::attachments::missing_blobs))
+        .route(
+            "/blobs/:hash",
+            put(routes::attachments::upload_blob).layer(DefaultBodyLimit::max(blobs::MAX_BLOB_SIZE)),
+        )
+        .route("/blobs/:hash", get(routes::attachments::download_blob))
+        // Workspace routes
+        .route("/workspaces", get(routes::workspaces::list_workspaces))
+        .route("/workspaces", post(routes::workspaces::create_workspace))
+        .route("/workspaces/:id", get(routes::workspaces::get_workspace))
+        .route("/workspaces/:id", patch(routes::workspaces::rename_workspace))
+        .route("/workspaces/:id", delete(routes::workspaces::delete_workspace))
+        .route("/workspaces/:id/members", post(routes::workspaces::add_workspace_member))
+        .route("/workspaces/:id/members/:member_id", delete(routes::workspaces::remove_workspace_member))
+        // Public share links (no auth)
+        .route("/s/:token", get(routes::share::view_shared_note))
+        .route("/s/:token", post(routes::share::unlock_shared_note))
         // Sync routes
         .route("/sync/push", post(routes::sync::push_updates))
         .route("/sync/pull", post(routes::sync::pull_updates))
@@ -77,6 +182,11 @@ async fn main() {
         // User routes
         .route("/user/me", get(routes::user::get_current_user))
         .route("/user/settings", patch(routes::user::update_settings))
+        .route("/user/encryption", get(routes::encryption::get_encryption))
+        .route("/user/encryption", put(routes::encryption::set_encryption))
+        .route("/user/devices", post(routes::encryption::register_device))
+        .route("/user/devices/:device_id", delete(routes::encryption::delete_device))
+        .route("/user/devices/:device_id/key", put(routes::encryption::share_device_key))
         // Middleware
         .layer(TraceLayer::new_for_http())
         .layer(
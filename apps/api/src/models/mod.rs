@@ -1,7 +1,8 @@
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
@@ -11,6 +12,7 @@ pub struct User {
     pub picture_url: Option<String>,
     pub google_id: Option<String>,
     pub settings: serde_json::Value,
+    pub e2ee_enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -27,6 +29,287 @@ pub struct Note {
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
     pub version: i32,
+    /// Content and title are encrypted by the client (end-to-end encryption)
+    pub encrypted: bool,
+    pub encrypted_title: Option<Vec<u8>>,
+    /// The workspace the note belongs to; None for the personal space
+    pub workspace_id: Option<Uuid>,
+    /// The folder the note is filed in, in the same space as the note
+    pub folder_id: Option<Uuid>,
+}
+
+/// What a user may do with a note. The owner of a personal note is
+/// `notes.user_id`; editors and viewers are listed in `note_members`.
+/// Workspace notes take the user's workspace role, admins being owners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteRole {
+    Owner,
+    Editor,
+    Viewer,
+}
+
+impl NoteRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NoteRole::Owner => "owner",
+            NoteRole::Editor => "editor",
+            NoteRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn can_edit(self) -> bool {
+        self != NoteRole::Viewer
+    }
+}
+
+impl TryFrom<String> for NoteRole {
+    type Error = String;
+
+    fn try_from(role: String) -> Result<Self, Self::Error> {
+        match role.as_str() {
+            "owner" => Ok(NoteRole::Owner),
+            "editor" => Ok(NoteRole::Editor),
+            "viewer" => Ok(NoteRole::Viewer),
+            _ => Err(format!("Unknown note role: {}", role)),
+        }
+    }
+}
+
+/// A note together with the requesting user's role on it
+#[derive(Debug, Clone, FromRow)]
+pub struct AccessibleNote {
+    #[sqlx(flatten)]
+    pub note: Note,
+    #[sqlx(try_from = "String")]
+    pub role: NoteRole,
+}
+
+impl AccessibleNote {
+    /// Whether the user sees the note's folder and tags. Folders and tags
+    /// in the personal space are the owner's own; people the note is shared
+    /// with don't see them.
+    pub fn sees_organization(&self) -> bool {
+        self.note.workspace_id.is_some() || self.role == NoteRole::Owner
+    }
+
+    /// The note's folder as the user sees it
+    pub fn folder_id(&self) -> Option<Uuid> {
+        if !self.sees_organization() {
+            return None;
+        }
+        self.note.folder_id
+    }
+}
+
+/// A user a note is shared with, or an invite waiting for them to sign in
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct NoteMember {
+    pub id: Uuid,
+    pub note_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub email: String,
+    #[sqlx(try_from = "String")]
+    pub role: NoteRole,
+    pub invited_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A user's role in a workspace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Admin,
+    Editor,
+    Viewer,
+}
+
+impl WorkspaceRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WorkspaceRole::Admin => "admin",
+            WorkspaceRole::Editor => "editor",
+            WorkspaceRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn can_edit(self) -> bool {
+        self != WorkspaceRole::Viewer
+    }
+}
+
+impl TryFrom<String> for WorkspaceRole {
+    type Error = String;
+
+    fn try_from(role: String) -> Result<Self, Self::Error> {
+        match role.as_str() {
+            "admin" => Ok(WorkspaceRole::Admin),
+            "editor" => Ok(WorkspaceRole::Editor),
+            "viewer" => Ok(WorkspaceRole::Viewer),
+            _ => Err(format!("Unknown workspace role: {}", role)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Workspace {
+    pub id: Uuid,
+    pub name: String,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A workspace together with the requesting user's role in it
+#[derive(Debug, Clone, FromRow)]
+pub struct MemberWorkspace {
+    #[sqlx(flatten)]
+    pub workspace: Workspace,
+    #[sqlx(try_from = "String")]
+    pub role: WorkspaceRole,
+}
+
+/// A member of a workspace, or an invite waiting for them to sign in
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WorkspaceMember {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub email: String,
+    #[sqlx(try_from = "String")]
+    pub role: WorkspaceRole,
+    pub invited_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A folder for organizing notes. Personal folders belong to `user_id`;
+/// workspace folders are shared by all members, `user_id` being who made
+/// them. Deleted folders are kept, with `deleted_at` set, so that clients
+/// pulling changes learn about the deletion.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Folder {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub workspace_id: Option<Uuid>,
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// A tag on notes. Like folders, tags live in a space: personal tags belong
+/// to `user_id`, workspace tags to the workspace. Tags no note has any more
+/// are deleted, keeping the row with `deleted_at` set for clients to pull.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Tag {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub workspace_id: Option<Uuid>,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// A tag with the number of notes outside the trash that have it
+#[derive(Debug, Clone, FromRow)]
+pub struct TagSummary {
+    #[sqlx(flatten)]
+    pub tag: Tag,
+    pub note_count: i64,
+}
+
+/// A search query saved under a name, as a smart folder. Saved searches are
+/// their user's own; deleted ones are kept, with `deleted_at` set, so that
+/// clients pulling changes learn about the deletion.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SavedSearch {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub query: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// A file attached to a note, its content being the blob `hash`. Removed
+/// attachments are kept, with `deleted_at` set, so that clients pulling
+/// changes learn about the removal.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub note_id: Uuid,
+    /// Who attached the file
+    pub user_id: Uuid,
+    pub hash: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// A task item in a note's content, with the title of its note. Tasks are
+/// re-extracted whenever the note's content is saved.
+#[derive(Debug, Clone, FromRow)]
+pub struct Task {
+    pub note_id: Uuid,
+    pub note_title: String,
+    /// Child indexes from the content fragment down to the task item
+    pub path: String,
+    pub position: i32,
+    pub depth: i32,
+    pub text: String,
+    pub checked: bool,
+    pub due_date: Option<NaiveDate>,
+    /// Reminder time in milliseconds since the epoch
+    pub remind_at: Option<i64>,
+    /// When the task was checked, if it is and that's known
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Open and completed task counts of a note
+#[derive(Debug, Clone, FromRow)]
+pub struct TaskCounts {
+    pub note_id: Uuid,
+    pub open: i64,
+    pub completed: i64,
+}
+
+/// Which notes a listing covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteScope {
+    /// Every note the user can access
+    All,
+    /// Notes outside any workspace: the user's own and those shared with them
+    Personal,
+    Workspace(Uuid),
+}
+
+impl NoteScope {
+    /// `personal`, a workspace id, or nothing for all notes
+    pub fn parse(scope: Option<&str>) -> Result<Self, String> {
+        match scope {
+            None | Some("") | Some("all") => Ok(NoteScope::All),
+            Some("personal") => Ok(NoteScope::Personal),
+            Some(id) => Uuid::parse_str(id)
+                .map(NoteScope::Workspace)
+                .map_err(|_| format!("Invalid workspace: {}", id)),
+        }
+    }
+
+    /// Whether the listing is scoped, and to which workspace (None for the
+    /// personal space)
+    pub fn filter(self) -> (bool, Option<Uuid>) {
+        match self {
+            NoteScope::All => (false, None),
+            NoteScope::Personal => (true, None),
+            NoteScope::Workspace(id) => (true, Some(id)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +323,25 @@ pub struct NoteMeta {
     pub updated_at: i64,
     #[serde(rename = "deletedAt")]
     pub deleted_at: Option<i64>,
+    pub encrypted: bool,
+    /// Base64 encoded; only set for encrypted notes, whose `title` is empty
+    #[serde(rename = "encryptedTitle", skip_serializing_if = "Option::is_none")]
+    pub encrypted_title: Option<String>,
+    pub role: NoteRole,
+    #[serde(rename = "workspaceId")]
+    pub workspace_id: Option<Uuid>,
+    #[serde(rename = "folderId")]
+    pub folder_id: Option<Uuid>,
+}
+
+impl From<AccessibleNote> for NoteMeta {
+    fn from(accessible: AccessibleNote) -> Self {
+        Self {
+            role: accessible.role,
+            folder_id: accessible.folder_id(),
+            ..accessible.note.into()
+        }
+    }
 }
 
 impl From<Note> for NoteMeta {
@@ -51,10 +353,78 @@ impl From<Note> for NoteMeta {
             created_at: note.created_at.timestamp_millis(),
             updated_at: note.updated_at.timestamp_millis(),
             deleted_at: note.deleted_at.map(|dt| dt.timestamp_millis()),
+            encrypted: note.encrypted,
+            encrypted_title: note
+                .encrypted_title
+                .map(|title| base64::engine::general_purpose::STANDARD.encode(title)),
+            role: NoteRole::Owner,
+            workspace_id: note.workspace_id,
+            folder_id: note.folder_id,
         }
     }
 }
 
+/// Which date of a note a search filter is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Created,
+    Updated,
+}
+
+/// Notes whose date falls in `since..until` (either end open), or outside
+/// it if `negated`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateFilter {
+    pub field: DateField,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub negated: bool,
+}
+
+/// A search query split into text for `websearch_to_tsquery` and filters
+#[derive(Debug, Clone, Default)]
+pub struct NoteSearch {
+    pub text: String,
+    pub tags: Vec<String>,
+    pub excluded_tags: Vec<String>,
+    pub starred: Option<bool>,
+    /// Whether notes must have any tasks, or any unchecked ones
+    pub has_tasks: Option<bool>,
+    pub has_open_tasks: Option<bool>,
+    pub dates: Vec<DateFilter>,
+}
+
+impl NoteSearch {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+            && self.tags.is_empty()
+            && self.excluded_tags.is_empty()
+            && self.starred.is_none()
+            && self.has_tasks.is_none()
+            && self.has_open_tasks.is_none()
+            && self.dates.is_empty()
+    }
+}
+
+/// A note matched by full-text search, with its rank and highlighted snippet
+#[derive(Debug, Clone, FromRow)]
+pub struct NoteSearchRow {
+    pub id: Uuid,
+    pub title: String,
+    pub starred: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub workspace_id: Option<Uuid>,
+    pub folder_id: Option<Uuid>,
+    #[sqlx(try_from = "String")]
+    pub role: NoteRole,
+    pub rank: f32,
+    pub snippet: String,
+    /// Total number of matches, ignoring LIMIT/OFFSET
+    pub total: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct SyncUpdate {
     pub id: i64,
@@ -64,6 +434,31 @@ pub struct SyncUpdate {
     pub created_at: DateTime<Utc>,
 }
 
+/// A user's device and the note key wrapped for it (end-to-end encryption)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub device_id: String,
+    pub name: Option<String>,
+    pub public_key: Vec<u8>,
+    pub wrapped_key: Option<Vec<u8>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A public read-only link to a note
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ShareLink {
+    pub id: Uuid,
+    pub note_id: Uuid,
+    pub user_id: Uuid,
+    pub token: String,
+    pub password_hash: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct RefreshToken {
     pub id: Uuid,
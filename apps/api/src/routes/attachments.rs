@@ -0,0 +1,318 @@
+// Files attached to notes.
+//
+// Attaching a file takes two steps: the client uploads its content with
+// PUT /blobs/:hash, then attaches the blob to a note under a file name.
+// Before uploading, a syncing client asks POST /blobs/missing which of its
+// blobs it can't use yet, so content it uploaded before, or that is
+// attached to a note it can access, is never sent again. Blobs are stored
+// once however many users upload them, but a user can only attach or
+// download a blob they uploaded themselves or can reach through a note they
+// have access to, so a hash alone gives nothing away. Attachments reach other clients through
+// GET /notes/:id/attachments and /sync/pull; blobs nothing refers to any
+// more are garbage collected (see `collect_garbage`).
+use std::sync::Arc;
+use std::time::Duration;
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use crate::blobs;
+use crate::models::{AccessibleNote, Attachment};
+use crate::routes::export::file_stem;
+use crate::AppState;
+
+/// How long a blob nothing refers to is kept, giving a client that just
+/// uploaded it time to attach it
+const BLOB_GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Longest file name, in characters (`note_attachments.file_name`)
+const MAX_FILE_NAME_LENGTH: usize = 255;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentResponse {
+    pub id: Uuid,
+    #[serde(rename = "noteId")]
+    pub note_id: Uuid,
+    pub hash: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    pub size: i64,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<i64>,
+}
+
+impl From<Attachment> for AttachmentResponse {
+    fn from(attachment: Attachment) -> Self {
+        Self {
+            id: attachment.id,
+            note_id: attachment.note_id,
+            hash: attachment.hash,
+            file_name: attachment.file_name,
+            content_type: attachment.content_type,
+            size: attachment.size,
+            created_at: attachment.created_at.timestamp_millis(),
+            updated_at: attachment.updated_at.timestamp_millis(),
+            deleted_at: attachment.deleted_at.map(|dt| dt.timestamp_millis()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttachmentsResponse {
+    pub attachments: Vec<AttachmentResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAttachmentRequest {
+    pub id: Uuid,
+    pub hash: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    #[serde(rename = "contentType")]
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MissingBlobsRequest {
+    pub hashes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MissingBlobsResponse {
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlobResponse {
+    pub hash: String,
+    pub size: i64,
+}
+
+fn blob_hash(hash: &str) -> Result<String, (StatusCode, String)> {
+    let hash = hash.to_ascii_lowercase();
+    if !blobs::is_valid_hash(&hash) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid blob hash".to_string()));
+    }
+    Ok(hash)
+}
+
+async fn accessible_note(state: &AppState, auth_user: &AuthUser, id: Uuid) -> Result<AccessibleNote, (StatusCode, String)> {
+    state
+        .db
+        .get_accessible_note(id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))
+}
+
+/// A note the user may attach files to or remove them from
+async fn editable_note(state: &AppState, auth_user: &AuthUser, id: Uuid) -> Result<AccessibleNote, (StatusCode, String)> {
+    let accessible = accessible_note(state, auth_user, id).await?;
+    if !accessible.role.can_edit() {
+        return Err((StatusCode::FORBIDDEN, "You can't change this note's attachments".to_string()));
+    }
+    Ok(accessible)
+}
+
+/// Which of the given blobs the user has to upload before attaching them
+pub async fn missing_blobs(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(payload): Json<MissingBlobsRequest>,
+) -> Result<Json<MissingBlobsResponse>, (StatusCode, String)> {
+    let hashes = payload
+        .hashes
+        .iter()
+        .map(|hash| blob_hash(hash))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let missing = state
+        .db
+        .missing_blobs(&hashes, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(MissingBlobsResponse { missing }))
+}
+
+/// Upload a blob; the body is its content, which must match the hash
+pub async fn upload_blob(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(hash): Path<String>,
+    body: Bytes,
+) -> Result<Json<BlobResponse>, (StatusCode, String)> {
+    let hash = blob_hash(&hash)?;
+    if blobs::content_hash(&body) != hash {
+        return Err((StatusCode::BAD_REQUEST, "Content doesn't match the hash".to_string()));
+    }
+
+    let size = body.len() as i64;
+    // In the store before it's recorded, so a recorded blob is always there
+    state
+        .blobs
+        .put(&hash, body)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store blob: {}", e)))?;
+    state
+        .db
+        .insert_blob(&hash, size, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(BlobResponse { hash, size }))
+}
+
+pub async fn download_blob(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(hash): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let hash = blob_hash(&hash)?;
+    let readable = state
+        .db
+        .can_read_blob(&hash, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    if !readable {
+        return Err((StatusCode::NOT_FOUND, "Blob not found".to_string()));
+    }
+
+    let data = read_blob(&state, &hash).await?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], data).into_response())
+}
+
+async fn read_blob(state: &AppState, hash: &str) -> Result<Bytes, (StatusCode, String)> {
+    state
+        .blobs
+        .get(hash)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read blob: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Blob not found".to_string()))
+}
+
+pub async fn list_note_attachments(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+) -> Result<Json<AttachmentsResponse>, (StatusCode, String)> {
+    accessible_note(&state, &auth_user, note_id).await?;
+
+    let attachments = state
+        .db
+        .list_note_attachments(note_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(AttachmentsResponse {
+        attachments: attachments.into_iter().map(AttachmentResponse::from).collect(),
+    }))
+}
+
+/// Attach an uploaded blob to a note
+pub async fn create_attachment(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+    Json(payload): Json<CreateAttachmentRequest>,
+) -> Result<Json<AttachmentResponse>, (StatusCode, String)> {
+    editable_note(&state, &auth_user, note_id).await?;
+    let hash = blob_hash(&payload.hash)?;
+
+    let file_name = payload.file_name.trim();
+    if file_name.is_empty() || file_name.chars().count() > MAX_FILE_NAME_LENGTH {
+        return Err((StatusCode::BAD_REQUEST, "Invalid file name".to_string()));
+    }
+    let content_type = payload
+        .content_type
+        .as_deref()
+        .map(str::trim)
+        .filter(|content_type| !content_type.is_empty() && content_type.len() <= 255)
+        .unwrap_or("application/octet-stream");
+
+    let attachment = state
+        .db
+        .create_attachment(payload.id, note_id, auth_user.user_id, &hash, file_name, content_type)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to attach file: {}", e)))?
+        .ok_or((StatusCode::CONFLICT, "Upload the blob before attaching it".to_string()))?;
+
+    Ok(Json(attachment.into()))
+}
+
+/// Download an attachment under its file name
+pub async fn download_attachment(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path((note_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, (StatusCode, String)> {
+    accessible_note(&state, &auth_user, note_id).await?;
+
+    let attachment = state
+        .db
+        .get_attachment(attachment_id, note_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Attachment not found".to_string()))?;
+
+    let data = read_blob(&state, &attachment.hash).await?;
+    let disposition = format!("attachment; filename=\"{}\"", file_stem(&attachment.file_name));
+    Ok((
+        [(header::CONTENT_TYPE, attachment.content_type), (header::CONTENT_DISPOSITION, disposition)],
+        data,
+    )
+        .into_response())
+}
+
+pub async fn delete_attachment(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path((note_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    editable_note(&state, &auth_user, note_id).await?;
+
+    let rows = state
+        .db
+        .delete_attachment(attachment_id, note_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to remove attachment: {}", e)))?;
+
+    if rows == 0 {
+        return Err((StatusCode::NOT_FOUND, "Attachment not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// Remove the blobs no note refers to any more. Runs periodically from
+/// main; returns the number of blobs removed.
+pub async fn collect_garbage(state: &AppState) -> Result<usize, String> {
+    let unused_since = Utc::now() - chrono::Duration::from_std(BLOB_GRACE_PERIOD).unwrap_or_default();
+    let hashes = state
+        .db
+        .remove_unused_blobs(unused_since)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    for hash in &hashes {
+        // Forgotten already; a blob left in the store is only wasted space
+        if let Err(e) = state.blobs.delete(hash).await {
+            tracing::warn!("Failed to remove blob {}: {}", hash, e);
+        }
+    }
+    Ok(hashes.len())
+}
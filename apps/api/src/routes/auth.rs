@@ -142,6 +142,14 @@ pub async fn google_auth(
         }
     };
 
+    // Notes and workspaces shared with this email before the user signed up
+    state.db.claim_note_invites(user.id, &user.email)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    state.db.claim_workspace_invites(user.id, &user.email)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
     // Generate tokens
     let access_token = state.auth.create_token(&user.id.to_string(), ACCESS_TOKEN_EXPIRY)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create token: {}", e)))?;
@@ -0,0 +1,213 @@
+// End-to-end encryption account setting and device keys.
+//
+// The server never sees the user's note key: each device uploads a public
+// key, and a device that has the note key wraps it for the others.
+use std::sync::Arc;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthUser;
+use crate::models::DeviceKey;
+use crate::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct DeviceKeyResponse {
+    #[serde(rename = "deviceId")]
+    pub device_id: String,
+    pub name: Option<String>,
+    #[serde(rename = "publicKey")]
+    pub public_key: String, // Base64 encoded
+    /// Base64 encoded; null while waiting for another device to share the key
+    #[serde(rename = "wrappedKey")]
+    pub wrapped_key: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+}
+
+impl From<DeviceKey> for DeviceKeyResponse {
+    fn from(device: DeviceKey) -> Self {
+        Self {
+            device_id: device.device_id,
+            name: device.name,
+            public_key: base64::engine::general_purpose::STANDARD.encode(&device.public_key),
+            wrapped_key: device
+                .wrapped_key
+                .map(|key| base64::engine::general_purpose::STANDARD.encode(key)),
+            created_at: device.created_at.timestamp_millis(),
+            updated_at: device.updated_at.timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EncryptionResponse {
+    pub enabled: bool,
+    pub devices: Vec<DeviceKeyResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetEncryptionRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterDeviceRequest {
+    #[serde(rename = "deviceId")]
+    pub device_id: String,
+    pub name: Option<String>,
+    #[serde(rename = "publicKey")]
+    pub public_key: String, // Base64 encoded
+    /// Set by the device that creates the note key, wrapped for itself
+    #[serde(rename = "wrappedKey")]
+    pub wrapped_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShareKeyRequest {
+    #[serde(rename = "wrappedKey")]
+    pub wrapped_key: String, // Base64 encoded
+}
+
+fn decode_base64(value: &str, field: &str) -> Result<Vec<u8>, (StatusCode, String)> {
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid base64 {}: {}", field, e)))
+}
+
+async fn encryption_response(
+    state: &AppState,
+    user_id: uuid::Uuid,
+    enabled: bool,
+) -> Result<Json<EncryptionResponse>, (StatusCode, String)> {
+    let devices = state
+        .db
+        .list_device_keys(user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(EncryptionResponse {
+        enabled,
+        devices: devices.into_iter().map(DeviceKeyResponse::from).collect(),
+    }))
+}
+
+pub async fn get_encryption(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<EncryptionResponse>, (StatusCode, String)> {
+    let enabled = state
+        .db
+        .is_e2ee_enabled(auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    encryption_response(&state, auth_user.user_id, enabled).await
+}
+
+/// Turn end-to-end encryption on or off for the account. Clients then
+/// convert existing notes with `PUT /notes/:id` (`encrypted`).
+pub async fn set_encryption(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(payload): Json<SetEncryptionRequest>,
+) -> Result<Json<EncryptionResponse>, (StatusCode, String)> {
+    if payload.enabled {
+        // Refuse unless some device holds the note key, or notes encrypted
+        // from now on could never be read again
+        let devices = state
+            .db
+            .list_device_keys(auth_user.user_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+        if !devices.iter().any(|device| device.wrapped_key.is_some()) {
+            return Err((
+                StatusCode::CONFLICT,
+                "Register a device with a wrapped key before enabling encryption".to_string(),
+            ));
+        }
+    }
+
+    let user = state
+        .db
+        .set_e2ee_enabled(auth_user.user_id, payload.enabled)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    encryption_response(&state, auth_user.user_id, user.e2ee_enabled).await
+}
+
+pub async fn register_device(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(payload): Json<RegisterDeviceRequest>,
+) -> Result<Json<DeviceKeyResponse>, (StatusCode, String)> {
+    if payload.device_id.is_empty() || payload.device_id.len() > 100 {
+        return Err((StatusCode::BAD_REQUEST, "Invalid device ID".to_string()));
+    }
+
+    let public_key = decode_base64(&payload.public_key, "publicKey")?;
+    let wrapped_key = payload
+        .wrapped_key
+        .as_deref()
+        .map(|key| decode_base64(key, "wrappedKey"))
+        .transpose()?;
+
+    let device = state
+        .db
+        .upsert_device_key(
+            auth_user.user_id,
+            &payload.device_id,
+            payload.name.as_deref(),
+            &public_key,
+            wrapped_key.as_deref(),
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to register device: {}", e)))?;
+
+    Ok(Json(device.into()))
+}
+
+/// Store the note key wrapped for `device_id` by one of the user's devices
+pub async fn share_device_key(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(device_id): Path<String>,
+    Json(payload): Json<ShareKeyRequest>,
+) -> Result<Json<DeviceKeyResponse>, (StatusCode, String)> {
+    let wrapped_key = decode_base64(&payload.wrapped_key, "wrappedKey")?;
+
+    let device = state
+        .db
+        .set_device_wrapped_key(auth_user.user_id, &device_id, &wrapped_key)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Device not found".to_string()))?;
+
+    Ok(Json(device.into()))
+}
+
+pub async fn delete_device(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(device_id): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let rows = state
+        .db
+        .delete_device_key(auth_user.user_id, &device_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    if rows == 0 {
+        return Err((StatusCode::NOT_FOUND, "Device not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use pdtodo_document::{self as document, html, markdown, pdf};
+use crate::AppState;
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    #[serde(alias = "md")]
+    Markdown,
+    Html,
+    Pdf,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Download a note as Markdown, HTML or PDF
+pub async fn export_note(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    // Anyone the note is shared with may download it
+    let note = state
+        .db
+        .get_accessible_note(id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))?
+        .note;
+
+    // The server can't read end-to-end encrypted notes; clients export those
+    if note.encrypted {
+        return Err((StatusCode::CONFLICT, "Encrypted notes can only be exported by a client".to_string()));
+    }
+
+    let blocks = document::from_yjs(&note.content)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read note: {}", e)))?;
+
+    let (content_type, extension, body) = match query.format {
+        ExportFormat::Markdown => {
+            let mut out = format!("# {}\n\n", note.title);
+            out.push_str(&markdown::to_markdown(&blocks));
+            ("text/markdown; charset=utf-8", "md", out.into_bytes())
+        }
+        ExportFormat::Html => ("text/html; charset=utf-8", "html", html::render(&note.title, &blocks).into_bytes()),
+        ExportFormat::Pdf => ("application/pdf", "pdf", pdf::render(&note.title, &blocks)),
+    };
+
+    let disposition = format!("attachment; filename=\"{}.{}\"", file_stem(&note.title), extension);
+    Ok((
+        [(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        body,
+    )
+        .into_response())
+}
+
+/// Note title (or file name) reduced to characters that are safe in a
+/// quoted header value
+pub fn file_stem(title: &str) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || " -_.()".contains(c) { c } else { '-' })
+        .take(100)
+        .collect();
+    let stem = stem.trim().trim_matches('.').trim();
+    if stem.is_empty() {
+        "Untitled".to_string()
+    } else {
+        stem.to_string()
+    }
+}
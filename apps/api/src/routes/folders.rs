@@ -0,0 +1,261 @@
+// Folders for organizing notes.
+//
+// Folders nest, and a note is in at most one folder. Both live in the same
+// space: a personal folder holds its owner's personal notes, a workspace
+// folder notes of that workspace, and editors there may change its folders.
+// Clients choose folder ids, like note ids, so folders made offline keep
+// theirs. Changes reach other clients through GET /folders and /sync/pull.
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use crate::models::{AccessibleNote, Folder, NoteRole};
+use crate::routes::notes::{note_scope, NoteResponse};
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ListFoldersQuery {
+    /// `personal` or a workspace id; all folders if unset
+    pub workspace: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderResponse {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<Uuid>,
+    #[serde(rename = "workspaceId")]
+    pub workspace_id: Option<Uuid>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<i64>,
+}
+
+impl From<Folder> for FolderResponse {
+    fn from(folder: Folder) -> Self {
+        Self {
+            id: folder.id,
+            name: folder.name,
+            parent_id: folder.parent_id,
+            workspace_id: folder.workspace_id,
+            created_at: folder.created_at.timestamp_millis(),
+            updated_at: folder.updated_at.timestamp_millis(),
+            deleted_at: folder.deleted_at.map(|dt| dt.timestamp_millis()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FoldersResponse {
+    pub folders: Vec<FolderResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFolderRequest {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<Uuid>,
+    /// Create the folder in a workspace instead of the personal space
+    #[serde(rename = "workspaceId")]
+    pub workspace_id: Option<Uuid>,
+}
+
+/// Renames and moves a folder; `parentId` null moves it to the top level
+#[derive(Debug, Deserialize)]
+pub struct UpdateFolderRequest {
+    pub name: String,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetNoteFolderRequest {
+    /// null takes the note out of its folder
+    #[serde(rename = "folderId")]
+    pub folder_id: Option<Uuid>,
+}
+
+fn folder_name(name: &str) -> Result<&str, (StatusCode, String)> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Folder name must not be empty".to_string()));
+    }
+    Ok(name)
+}
+
+/// A folder the user may change: their own personal folder, or a folder in
+/// a workspace they can edit
+pub async fn editable_folder(state: &AppState, auth_user: &AuthUser, id: Uuid) -> Result<Folder, (StatusCode, String)> {
+    let folder = state
+        .db
+        .get_accessible_folder(id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Folder not found".to_string()))?;
+
+    if let Some(workspace_id) = folder.workspace_id {
+        auth_user.workspace_editor(&state.db, workspace_id).await?;
+    }
+    Ok(folder)
+}
+
+/// An editable folder that notes and folders of `workspace_id` (None for
+/// the personal space) can be put in
+pub async fn folder_in_space(
+    state: &AppState,
+    auth_user: &AuthUser,
+    id: Uuid,
+    workspace_id: Option<Uuid>,
+) -> Result<Folder, (StatusCode, String)> {
+    let folder = editable_folder(state, auth_user, id).await?;
+    if folder.workspace_id != workspace_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The folder is in a different workspace".to_string(),
+        ));
+    }
+    Ok(folder)
+}
+
+pub async fn list_folders(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(query): Query<ListFoldersQuery>,
+) -> Result<Json<FoldersResponse>, (StatusCode, String)> {
+    let scope = note_scope(&state, &auth_user, query.workspace.as_deref()).await?;
+
+    let folders = state
+        .db
+        .list_folders(auth_user.user_id, None, scope)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(FoldersResponse {
+        folders: folders.into_iter().map(FolderResponse::from).collect(),
+    }))
+}
+
+pub async fn create_folder(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(payload): Json<CreateFolderRequest>,
+) -> Result<Json<FolderResponse>, (StatusCode, String)> {
+    let name = folder_name(&payload.name)?;
+
+    if let Some(workspace_id) = payload.workspace_id {
+        auth_user.workspace_editor(&state.db, workspace_id).await?;
+    }
+    if let Some(parent_id) = payload.parent_id {
+        folder_in_space(&state, &auth_user, parent_id, payload.workspace_id).await?;
+    }
+
+    let folder = state
+        .db
+        .create_folder(payload.id, auth_user.user_id, payload.workspace_id, payload.parent_id, name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create folder: {}", e)))?;
+
+    Ok(Json(folder.into()))
+}
+
+pub async fn update_folder(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateFolderRequest>,
+) -> Result<Json<FolderResponse>, (StatusCode, String)> {
+    let folder = editable_folder(&state, &auth_user, id).await?;
+    let name = folder_name(&payload.name)?;
+
+    if let Some(parent_id) = payload.parent_id.filter(|&parent_id| folder.parent_id != Some(parent_id)) {
+        folder_in_space(&state, &auth_user, parent_id, folder.workspace_id).await?;
+
+        let subtree = state
+            .db
+            .folder_subtree(id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        if subtree.contains(&parent_id) {
+            return Err((
+                StatusCode::CONFLICT,
+                "A folder can't be moved into itself or one of its subfolders".to_string(),
+            ));
+        }
+    }
+
+    let folder = state
+        .db
+        .update_folder(id, name, payload.parent_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update folder: {}", e)))?;
+
+    Ok(Json(folder.into()))
+}
+
+/// Delete a folder and its subfolders; the notes in them go to the trash.
+/// That takes owner rights on the notes, so in a workspace only admins
+/// delete folders.
+pub async fn delete_folder(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let folder = editable_folder(&state, &auth_user, id).await?;
+    if let Some(workspace_id) = folder.workspace_id {
+        auth_user.workspace_admin(&state.db, workspace_id).await?;
+    }
+
+    let trashed = state
+        .db
+        .delete_folder(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete folder: {}", e)))?;
+
+    Ok(Json(serde_json::json!({ "success": true, "trashedNotes": trashed })))
+}
+
+/// File a note in a folder, or take it out of its folder. Personal notes
+/// are filed by their owner; workspace notes by any editor.
+pub async fn set_note_folder(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+    Json(payload): Json<SetNoteFolderRequest>,
+) -> Result<Json<NoteResponse>, (StatusCode, String)> {
+    let AccessibleNote { note, role } = state
+        .db
+        .get_accessible_note(note_id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))?;
+
+    let allowed = match note.workspace_id {
+        Some(_) => role.can_edit(),
+        None => role == NoteRole::Owner,
+    };
+    if !allowed {
+        return Err((StatusCode::FORBIDDEN, "You can't move this note".to_string()));
+    }
+    if let Some(folder_id) = payload.folder_id {
+        folder_in_space(&state, &auth_user, folder_id, note.workspace_id).await?;
+    }
+
+    let note = state
+        .db
+        .set_note_folder(note_id, payload.folder_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to move note: {}", e)))?;
+
+    Ok(Json(AccessibleNote { note, role }.into()))
+}
@@ -0,0 +1,184 @@
+// Sharing notes with other users.
+//
+// The owner invites people by email as editors or viewers. Invites for an
+// address with no account yet take effect when that person first signs in.
+use std::sync::Arc;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use crate::models::{AccessibleNote, NoteMember, NoteRole};
+use crate::sync::live::LiveEvent;
+use crate::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct MemberResponse {
+    pub id: Uuid,
+    #[serde(rename = "userId")]
+    pub user_id: Option<Uuid>,
+    pub email: String,
+    pub role: NoteRole,
+    /// Invited, but hasn't signed in with this email yet
+    pub pending: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+impl From<NoteMember> for MemberResponse {
+    fn from(member: NoteMember) -> Self {
+        Self {
+            id: member.id,
+            pending: member.user_id.is_none(),
+            user_id: member.user_id,
+            email: member.email,
+            role: member.role,
+            created_at: member.created_at.timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OwnerResponse {
+    #[serde(rename = "userId")]
+    pub user_id: Uuid,
+    pub email: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MembersResponse {
+    pub owner: OwnerResponse,
+    pub members: Vec<MemberResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteMemberRequest {
+    pub email: String,
+    pub role: NoteRole,
+}
+
+async fn accessible_note(
+    state: &AppState,
+    note_id: Uuid,
+    user_id: Uuid,
+) -> Result<AccessibleNote, (StatusCode, String)> {
+    state
+        .db
+        .get_accessible_note(note_id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))
+}
+
+/// Everyone with access to the note; visible to all of them
+pub async fn list_members(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+) -> Result<Json<MembersResponse>, (StatusCode, String)> {
+    let AccessibleNote { note, .. } = accessible_note(&state, note_id, auth_user.user_id).await?;
+
+    let owner = state
+        .db
+        .get_user_by_id(note.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note owner not found".to_string()))?;
+    let members = state
+        .db
+        .list_note_members(note_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(MembersResponse {
+        owner: OwnerResponse {
+            user_id: owner.id,
+            email: owner.email,
+            name: owner.name,
+        },
+        members: members.into_iter().map(MemberResponse::from).collect(),
+    }))
+}
+
+/// Invite someone by email, or change the role of someone already invited
+pub async fn invite_member(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+    Json(payload): Json<InviteMemberRequest>,
+) -> Result<Json<MemberResponse>, (StatusCode, String)> {
+    let AccessibleNote { note, role } = accessible_note(&state, note_id, auth_user.user_id).await?;
+    if role != NoteRole::Owner {
+        return Err((StatusCode::FORBIDDEN, "Only the owner can share this note".to_string()));
+    }
+    // Collaborators wouldn't have the owner's note key
+    if note.encrypted {
+        return Err((StatusCode::CONFLICT, "Encrypted notes can't be shared".to_string()));
+    }
+    if payload.role == NoteRole::Owner {
+        return Err((StatusCode::BAD_REQUEST, "Role must be editor or viewer".to_string()));
+    }
+
+    let email = payload.email.trim();
+    if !email.contains('@') {
+        return Err((StatusCode::BAD_REQUEST, "Invalid email address".to_string()));
+    }
+    let owner = state
+        .db
+        .get_user_by_id(note.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    if owner.is_some_and(|owner| owner.email.eq_ignore_ascii_case(email)) {
+        return Err((StatusCode::BAD_REQUEST, "You already own this note".to_string()));
+    }
+
+    let member = state
+        .db
+        .upsert_note_member(note_id, email, payload.role, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to invite member: {}", e)))?;
+
+    Ok(Json(member.into()))
+}
+
+/// Remove someone's access. Members may also remove themselves.
+pub async fn remove_member(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path((note_id, member_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let AccessibleNote { role, .. } = accessible_note(&state, note_id, auth_user.user_id).await?;
+
+    if role != NoteRole::Owner {
+        let members = state
+            .db
+            .list_note_members(note_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+        let is_self = members
+            .iter()
+            .any(|member| member.id == member_id && member.user_id == Some(auth_user.user_id));
+        if !is_self {
+            return Err((StatusCode::FORBIDDEN, "Only the owner can remove other members".to_string()));
+        }
+    }
+
+    let member = state
+        .db
+        .delete_note_member(member_id, note_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to remove member: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    // End their live subscriptions to the note
+    if let Some(user_id) = member.user_id {
+        state.live.publish(note_id, LiveEvent::Revoked { user_id });
+    }
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
@@ -1,5 +1,15 @@
 pub mod health;
+pub mod attachments;
 pub mod auth;
+pub mod encryption;
+pub mod export;
+pub mod folders;
+pub mod members;
 pub mod notes;
+pub mod saved_searches;
+pub mod share;
 pub mod sync;
+pub mod tags;
+pub mod tasks;
 pub mod user;
+pub mod workspaces;
@@ -5,19 +5,28 @@ use axum::{
     Json,
 };
 use base64::Engine;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::auth::AuthUser;
-use crate::models::NoteMeta;
+use crate::models::{AccessibleNote, DateField, DateFilter, Note, NoteMeta, NoteRole, NoteScope, NoteSearch};
+use crate::routes::folders::folder_in_space;
+use crate::routes::tags::update_inline_tags;
+use crate::routes::tasks::update_tasks;
+use crate::sync::document;
 use crate::AppState;
 
+const SEARCH_DEFAULT_LIMIT: i64 = 20;
+const SEARCH_MAX_LIMIT: i64 = 100;
+
 #[derive(Debug, Deserialize)]
 pub struct ListNotesQuery {
     #[serde(rename = "includeDeleted", default)]
     pub include_deleted: bool,
     pub since: Option<i64>,
+    /// `personal` or a workspace id; all notes if unset
+    pub workspace: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -34,6 +43,17 @@ pub struct CreateNoteRequest {
     pub content: String, // Base64 encoded Yjs doc
     #[serde(default)]
     pub starred: bool,
+    /// Content is encrypted by the client; `title` is ignored
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(rename = "encryptedTitle")]
+    pub encrypted_title: Option<String>, // Base64 encoded
+    /// Create the note in a workspace instead of the personal space
+    #[serde(rename = "workspaceId")]
+    pub workspace_id: Option<Uuid>,
+    /// File the note in a folder of the same space
+    #[serde(rename = "folderId")]
+    pub folder_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize)]
@@ -55,6 +75,70 @@ pub struct NoteResponse {
     pub updated_at: i64,
     #[serde(rename = "stateVector")]
     pub state_vector: Option<String>,
+    pub encrypted: bool,
+    #[serde(rename = "encryptedTitle", skip_serializing_if = "Option::is_none")]
+    pub encrypted_title: Option<String>,
+    pub role: NoteRole,
+    #[serde(rename = "workspaceId")]
+    pub workspace_id: Option<Uuid>,
+    #[serde(rename = "folderId")]
+    pub folder_id: Option<Uuid>,
+}
+
+impl From<AccessibleNote> for NoteResponse {
+    fn from(accessible: AccessibleNote) -> Self {
+        Self {
+            role: accessible.role,
+            folder_id: accessible.folder_id(),
+            ..accessible.note.into()
+        }
+    }
+}
+
+impl From<Note> for NoteResponse {
+    fn from(note: Note) -> Self {
+        Self {
+            id: note.id,
+            title: note.title,
+            content: base64::engine::general_purpose::STANDARD.encode(&note.content),
+            starred: note.starred,
+            created_at: note.created_at.timestamp_millis(),
+            updated_at: note.updated_at.timestamp_millis(),
+            state_vector: note.state_vector.map(|sv| base64::engine::general_purpose::STANDARD.encode(&sv)),
+            encrypted: note.encrypted,
+            encrypted_title: note
+                .encrypted_title
+                .map(|title| base64::engine::general_purpose::STANDARD.encode(title)),
+            role: NoteRole::Owner,
+            workspace_id: note.workspace_id,
+            folder_id: note.folder_id,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchNotesQuery {
+    /// Search text with filters, see `parse_search`
+    pub q: String,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub note: NoteMeta,
+    /// Matching excerpt of the note text, HTML-escaped with matches in <mark>
+    pub snippet: String,
+    pub rank: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchNotesResponse {
+    pub results: Vec<SearchResult>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -64,6 +148,66 @@ pub struct UpdateNoteRequest {
     pub starred: Option<bool>,
     #[serde(rename = "stateVector")]
     pub state_vector: Option<String>,
+    /// Switch the note to or from end-to-end encryption; requires `content`
+    pub encrypted: Option<bool>,
+    #[serde(rename = "encryptedTitle")]
+    pub encrypted_title: Option<String>, // Base64 encoded
+}
+
+/// With end-to-end encryption on, the server must not receive new plaintext
+async fn ensure_plaintext_allowed(state: &AppState, user_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let e2ee_enabled = state
+        .db
+        .is_e2ee_enabled(user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    if e2ee_enabled {
+        return Err((
+            StatusCode::CONFLICT,
+            "End-to-end encryption is enabled; note content must be encrypted".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The scope of a listing; a workspace the user isn't in is not found
+pub async fn note_scope(
+    state: &AppState,
+    auth_user: &AuthUser,
+    scope: Option<&str>,
+) -> Result<NoteScope, (StatusCode, String)> {
+    let scope = NoteScope::parse(scope).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if let NoteScope::Workspace(workspace_id) = scope {
+        auth_user.workspace(&state.db, workspace_id).await?;
+    }
+    Ok(scope)
+}
+
+/// A note the user has owner rights on: their own personal note, or a note
+/// in a workspace they administer
+pub async fn owned_note(state: &AppState, id: Uuid, user_id: Uuid) -> Result<Note, (StatusCode, String)> {
+    let AccessibleNote { note, role } = state
+        .db
+        .get_accessible_note(id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))?;
+
+    if role != NoteRole::Owner {
+        return Err((StatusCode::FORBIDDEN, "Only the owner can do this".to_string()));
+    }
+    Ok(note)
+}
+
+fn decode_encrypted_title(title: Option<&str>) -> Result<Option<Vec<u8>>, (StatusCode, String)> {
+    title
+        .map(|t| {
+            base64::engine::general_purpose::STANDARD
+                .decode(t)
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid base64 encryptedTitle: {}", e)))
+        })
+        .transpose()
 }
 
 pub async fn list_notes(
@@ -75,9 +219,11 @@ pub async fn list_notes(
         DateTime::<Utc>::from_timestamp_millis(ts).unwrap_or_else(Utc::now)
     });
 
+    let scope = note_scope(&state, &auth_user, query.workspace.as_deref()).await?;
+
     let notes = state
         .db
-        .list_notes(auth_user.user_id, query.include_deleted, since)
+        .list_notes(auth_user.user_id, query.include_deleted, since, scope)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
 
@@ -89,6 +235,200 @@ pub async fn list_notes(
     }))
 }
 
+/// Longest relative date range, well within what chrono can represent
+const MAX_SEARCH_DAYS: i64 = 100 * 366;
+
+/// Split the filters out of a search query, with the same syntax as the
+/// desktop search box: `tag:name`, `starred:yes|no`, `has:tasks`,
+/// `has:open-tasks`, and `created:` / `updated:` with a day, a
+/// `from..until` range, a comparison like `>=2024-05-01` or the last days
+/// or weeks (`7d`, `2w`). Filters are negated with a leading `-`. Days are
+/// in UTC. Everything else is left as text.
+pub fn parse_search(q: &str) -> NoteSearch {
+    let mut search = NoteSearch::default();
+    let mut text = Vec::new();
+    let today = Utc::now().date_naive();
+
+    for word in q.split_whitespace() {
+        let (negated, filter) = match word.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, word),
+        };
+        let Some((field, value)) = filter.split_once(':') else {
+            text.push(word);
+            continue;
+        };
+        let value_lower = value.to_ascii_lowercase();
+
+        let applied = match field.to_ascii_lowercase().as_str() {
+            "tag" => {
+                let name = value.trim_start_matches('#');
+                if !name.is_empty() {
+                    let tags = if negated { &mut search.excluded_tags } else { &mut search.tags };
+                    tags.push(name.to_string());
+                }
+                !name.is_empty()
+            }
+            "starred" => {
+                let starred = match value_lower.as_str() {
+                    "" | "yes" | "true" | "1" => Some(true),
+                    "no" | "false" | "0" => Some(false),
+                    _ => None,
+                };
+                if let Some(starred) = starred {
+                    search.starred = Some(starred != negated);
+                }
+                starred.is_some()
+            }
+            "has" => {
+                let has = match value_lower.as_str() {
+                    "tasks" | "task" => Some(&mut search.has_tasks),
+                    "open-tasks" | "open-task" | "todo" => Some(&mut search.has_open_tasks),
+                    _ => None,
+                };
+                has.map(|has| *has = Some(!negated)).is_some()
+            }
+            "created" | "updated" => match date_range(&value_lower, today) {
+                Some((since, until)) => {
+                    let field = if field.eq_ignore_ascii_case("created") {
+                        DateField::Created
+                    } else {
+                        DateField::Updated
+                    };
+                    search.dates.push(DateFilter {
+                        field,
+                        since,
+                        until,
+                        negated,
+                    });
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        };
+
+        if !applied {
+            text.push(word);
+        }
+    }
+
+    search.text = text.join(" ");
+    search
+}
+
+/// Start and end of a range of times, either open if None
+type DateRange = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// Parse the value of a `created:` or `updated:` filter
+fn date_range(value: &str, today: NaiveDate) -> Option<DateRange> {
+    if let Some((since, until)) = value.split_once("..") {
+        let since = if since.is_empty() { None } else { Some(day_start(parse_day(since, today)?)) };
+        let until = if until.is_empty() { None } else { Some(day_start(parse_day(until, today)?.succ_opt()?)) };
+        return Some((since, until));
+    }
+
+    for (operator, after) in [(">=", false), ("<=", true), (">", true), ("<", false)] {
+        let Some(day) = value.strip_prefix(operator) else { continue };
+        let day = parse_day(day, today)?;
+        let start = day_start(if after { day.succ_opt()? } else { day });
+        return Some(if operator.starts_with('>') { (Some(start), None) } else { (None, Some(start)) });
+    }
+
+    // The last days or weeks, counting back from now
+    let days = match (value.strip_suffix('d'), value.strip_suffix('w')) {
+        (Some(count), _) => count.parse::<i64>().ok(),
+        (_, Some(count)) => count.parse::<i64>().ok().map(|weeks| weeks * 7),
+        _ => None,
+    };
+    if let Some(days) = days {
+        return Some((Some(Utc::now() - Duration::days(days.clamp(0, MAX_SEARCH_DAYS))), None));
+    }
+
+    let day = parse_day(value, today)?;
+    Some((Some(day_start(day)), Some(day_start(day.succ_opt()?))))
+}
+
+fn parse_day(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match value {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        _ => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok(),
+    }
+}
+
+fn day_start(day: NaiveDate) -> DateTime<Utc> {
+    day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+}
+
+/// Search the user's notes for `q`, as parsed by `parse_search`; a query
+/// without any text or filter finds nothing
+pub async fn run_search(
+    state: &AppState,
+    auth_user: &AuthUser,
+    q: &str,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<SearchNotesResponse, (StatusCode, String)> {
+    let limit = limit.unwrap_or(SEARCH_DEFAULT_LIMIT).clamp(1, SEARCH_MAX_LIMIT);
+    let offset = offset.unwrap_or(0).max(0);
+
+    let search = parse_search(q);
+    if search.is_empty() {
+        return Ok(SearchNotesResponse {
+            results: Vec::new(),
+            total: 0,
+            limit,
+            offset,
+        });
+    }
+
+    let rows = state
+        .db
+        .search_notes(auth_user.user_id, &search, limit, offset)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    let total = rows.first().map(|row| row.total).unwrap_or(0);
+    let results = rows
+        .into_iter()
+        .map(|row| SearchResult {
+            note: NoteMeta {
+                id: row.id,
+                title: row.title,
+                starred: row.starred,
+                created_at: row.created_at.timestamp_millis(),
+                updated_at: row.updated_at.timestamp_millis(),
+                deleted_at: row.deleted_at.map(|dt| dt.timestamp_millis()),
+                encrypted: false,
+                encrypted_title: None,
+                role: row.role,
+                workspace_id: row.workspace_id,
+                folder_id: row.folder_id,
+            },
+            snippet: row.snippet,
+            rank: row.rank,
+        })
+        .collect();
+
+    Ok(SearchNotesResponse {
+        results,
+        total,
+        limit,
+        offset,
+    })
+}
+
+pub async fn search_notes(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(query): Query<SearchNotesQuery>,
+) -> Result<Json<SearchNotesResponse>, (StatusCode, String)> {
+    run_search(&state, &auth_user, &query.q, query.limit, query.offset)
+        .await
+        .map(Json)
+}
+
 pub async fn create_note(
     State(state): State<Arc<AppState>>,
     auth_user: AuthUser,
@@ -98,11 +438,63 @@ pub async fn create_note(
         .decode(&payload.content)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid base64 content: {}", e)))?;
 
-    let note = state
-        .db
-        .create_note(payload.id, auth_user.user_id, &payload.title, &content, payload.starred)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create note: {}", e)))?;
+    if let Some(workspace_id) = payload.workspace_id {
+        auth_user.workspace_editor(&state.db, workspace_id).await?;
+        // Workspace members don't share a note key
+        if payload.encrypted {
+            return Err((StatusCode::CONFLICT, "Workspace notes can't be encrypted".to_string()));
+        }
+    }
+
+    if let Some(folder_id) = payload.folder_id {
+        folder_in_space(&state, &auth_user, folder_id, payload.workspace_id).await?;
+    }
+
+    let content_text = (!payload.encrypted).then(|| document::search_text(&content));
+    let note = if payload.encrypted {
+        let encrypted_title = decode_encrypted_title(payload.encrypted_title.as_deref())?;
+        state
+            .db
+            .create_encrypted_note(
+                payload.id,
+                auth_user.user_id,
+                &content,
+                encrypted_title.as_deref(),
+                payload.starred,
+            )
+            .await
+    } else {
+        // End-to-end encryption covers the personal space only
+        if payload.workspace_id.is_none() {
+            ensure_plaintext_allowed(&state, auth_user.user_id).await?;
+        }
+        state
+            .db
+            .create_note(
+                payload.id,
+                auth_user.user_id,
+                payload.workspace_id,
+                &payload.title,
+                &content,
+                content_text.as_deref().unwrap_or_default(),
+                payload.starred,
+            )
+            .await
+    }
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create note: {}", e)))?;
+
+    if let Some(content_text) = &content_text {
+        update_inline_tags(&state, &note, content_text).await?;
+        update_tasks(&state, &note).await?;
+    }
+
+    if payload.folder_id.is_some() {
+        state
+            .db
+            .set_note_folder(note.id, payload.folder_id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create note: {}", e)))?;
+    }
 
     Ok(Json(CreateNoteResponse {
         id: note.id,
@@ -117,20 +509,12 @@ pub async fn get_note(
 ) -> Result<Json<NoteResponse>, (StatusCode, String)> {
     let note = state
         .db
-        .get_note(id, auth_user.user_id)
+        .get_accessible_note(id, auth_user.user_id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
         .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))?;
 
-    Ok(Json(NoteResponse {
-        id: note.id,
-        title: note.title,
-        content: base64::engine::general_purpose::STANDARD.encode(&note.content),
-        starred: note.starred,
-        created_at: note.created_at.timestamp_millis(),
-        updated_at: note.updated_at.timestamp_millis(),
-        state_vector: note.state_vector.map(|sv| base64::engine::general_purpose::STANDARD.encode(&sv)),
-    }))
+    Ok(Json(note.into()))
 }
 
 pub async fn update_note(
@@ -159,28 +543,109 @@ pub async fn update_note(
         })
         .transpose()?;
 
-    let note = state
+    let encrypted_title = decode_encrypted_title(payload.encrypted_title.as_deref())?;
+
+    let AccessibleNote { note: existing, role } = state
         .db
-        .update_note(
-            id,
-            auth_user.user_id,
-            payload.title.as_deref(),
-            content.as_deref(),
-            state_vector.as_deref(),
-            payload.starred,
-        )
+        .get_accessible_note(id, auth_user.user_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update note: {}", e)))?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))?;
+    let encrypted = payload.encrypted.unwrap_or(existing.encrypted);
 
-    Ok(Json(NoteResponse {
-        id: note.id,
-        title: note.title,
-        content: base64::engine::general_purpose::STANDARD.encode(&note.content),
-        starred: note.starred,
-        created_at: note.created_at.timestamp_millis(),
-        updated_at: note.updated_at.timestamp_millis(),
-        state_vector: note.state_vector.map(|sv| base64::engine::general_purpose::STANDARD.encode(&sv)),
-    }))
+    // Editors may change the title and content; starring and encryption
+    // belong to the owner's library, or to the admins of a workspace
+    if !role.can_edit() {
+        return Err((StatusCode::FORBIDDEN, "This note is shared with you read-only".to_string()));
+    }
+    if role != NoteRole::Owner && (payload.starred.is_some() || encrypted != existing.encrypted) {
+        return Err((StatusCode::FORBIDDEN, "Only the owner can star or encrypt this note".to_string()));
+    }
+    // Collaborators write to the owner's note
+    let owner_id = existing.user_id;
+    let personal = existing.workspace_id.is_none();
+    // Plain text of new unencrypted content, for search and hashtags
+    let content_text = content.as_deref().filter(|_| !encrypted).map(document::search_text);
+
+    let note = if encrypted != existing.encrypted {
+        // Switching modes replaces the whole document
+        let content = content.as_deref().ok_or((
+            StatusCode::BAD_REQUEST,
+            "Changing encryption requires the full content".to_string(),
+        ))?;
+
+        if encrypted {
+            if !personal {
+                return Err((StatusCode::CONFLICT, "Workspace notes can't be encrypted".to_string()));
+            }
+            // Collaborators don't have the owner's note key
+            let members = state
+                .db
+                .list_note_members(id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+            if !members.is_empty() {
+                return Err((StatusCode::CONFLICT, "Stop sharing the note before encrypting it".to_string()));
+            }
+            state
+                .db
+                .set_note_encryption(id, owner_id, true, "", content, "", encrypted_title.as_deref())
+                .await
+        } else {
+            if personal {
+                ensure_plaintext_allowed(&state, owner_id).await?;
+            }
+            let title = payload.title.as_deref().unwrap_or_default();
+            let content_text = content_text.as_deref().unwrap_or_default();
+            state
+                .db
+                .set_note_encryption(id, owner_id, false, title, content, content_text, None)
+                .await
+        }
+    } else if encrypted {
+        if payload.title.is_some() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Encrypted notes take encryptedTitle instead of title".to_string(),
+            ));
+        }
+        state
+            .db
+            .update_encrypted_note(
+                id,
+                owner_id,
+                content.as_deref(),
+                encrypted_title.as_deref(),
+                payload.starred,
+            )
+            .await
+    } else {
+        if personal && (content.is_some() || payload.title.is_some()) {
+            ensure_plaintext_allowed(&state, owner_id).await?;
+        }
+        state
+            .db
+            .update_note(
+                id,
+                owner_id,
+                payload.title.as_deref(),
+                content.as_deref(),
+                content_text.as_deref(),
+                state_vector.as_deref(),
+                payload.starred,
+            )
+            .await
+    }
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update note: {}", e)))?;
+
+    // Encrypting a note drops its hashtag tags and tasks along with its
+    // plain text
+    if content_text.is_some() || encrypted != existing.encrypted {
+        update_inline_tags(&state, &note, content_text.as_deref().unwrap_or_default()).await?;
+        update_tasks(&state, &note).await?;
+    }
+
+    Ok(Json(AccessibleNote { note, role }.into()))
 }
 
 pub async fn delete_note(
@@ -188,9 +653,10 @@ pub async fn delete_note(
     auth_user: AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let owner_id = owned_note(&state, id, auth_user.user_id).await?.user_id;
     let note = state
         .db
-        .soft_delete_note(id, auth_user.user_id)
+        .soft_delete_note(id, owner_id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete note: {}", e)))?;
 
@@ -204,9 +670,10 @@ pub async fn restore_note(
     auth_user: AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let owner_id = owned_note(&state, id, auth_user.user_id).await?.user_id;
     let note = state
         .db
-        .restore_note(id, auth_user.user_id)
+        .restore_note(id, owner_id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to restore note: {}", e)))?;
 
@@ -220,9 +687,10 @@ pub async fn permanent_delete(
     auth_user: AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let owner_id = owned_note(&state, id, auth_user.user_id).await?.user_id;
     let rows = state
         .db
-        .permanent_delete_note(id, auth_user.user_id)
+        .permanent_delete_note(id, owner_id)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete note: {}", e)))?;
 
@@ -0,0 +1,172 @@
+// Saved searches, shown as smart folders.
+//
+// A saved search keeps the query, not its results: GET /saved-searches/:id/notes
+// runs it through the same search as GET /notes/search, so the notes it
+// finds follow every change. Saved searches are their user's own, in every
+// space. Clients choose the ids so searches saved offline keep theirs.
+// Changes reach other clients through GET /saved-searches and /sync/pull.
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use crate::models::SavedSearch;
+use crate::routes::notes::{run_search, SearchNotesResponse};
+use crate::AppState;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearchResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub query: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<i64>,
+}
+
+impl From<SavedSearch> for SavedSearchResponse {
+    fn from(search: SavedSearch) -> Self {
+        Self {
+            id: search.id,
+            name: search.name,
+            query: search.query,
+            created_at: search.created_at.timestamp_millis(),
+            updated_at: search.updated_at.timestamp_millis(),
+            deleted_at: search.deleted_at.map(|dt| dt.timestamp_millis()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedSearchesResponse {
+    #[serde(rename = "savedSearches")]
+    pub saved_searches: Vec<SavedSearchResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSavedSearchRequest {
+    pub id: Uuid,
+    pub name: String,
+    pub query: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSavedSearchRequest {
+    pub name: String,
+    pub query: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunSavedSearchQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Trimmed name and query, neither of which may be empty
+fn saved_search_fields<'a>(name: &'a str, query: &'a str) -> Result<(&'a str, &'a str), (StatusCode, String)> {
+    let (name, query) = (name.trim(), query.trim());
+    if name.is_empty() || query.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "A saved search needs a name and a query".to_string(),
+        ));
+    }
+    Ok((name, query))
+}
+
+async fn own_saved_search(state: &AppState, auth_user: &AuthUser, id: Uuid) -> Result<SavedSearch, (StatusCode, String)> {
+    state
+        .db
+        .get_saved_search(id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Saved search not found".to_string()))
+}
+
+pub async fn list_saved_searches(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<SavedSearchesResponse>, (StatusCode, String)> {
+    let searches = state
+        .db
+        .list_saved_searches(auth_user.user_id, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(SavedSearchesResponse {
+        saved_searches: searches.into_iter().map(SavedSearchResponse::from).collect(),
+    }))
+}
+
+pub async fn create_saved_search(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(payload): Json<CreateSavedSearchRequest>,
+) -> Result<Json<SavedSearchResponse>, (StatusCode, String)> {
+    let (name, query) = saved_search_fields(&payload.name, &payload.query)?;
+
+    let search = state
+        .db
+        .create_saved_search(payload.id, auth_user.user_id, name, query)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save search: {}", e)))?;
+
+    Ok(Json(search.into()))
+}
+
+/// Rename a saved search and change its query
+pub async fn update_saved_search(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateSavedSearchRequest>,
+) -> Result<Json<SavedSearchResponse>, (StatusCode, String)> {
+    own_saved_search(&state, &auth_user, id).await?;
+    let (name, query) = saved_search_fields(&payload.name, &payload.query)?;
+
+    let search = state
+        .db
+        .update_saved_search(id, name, query)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update saved search: {}", e)))?;
+
+    Ok(Json(search.into()))
+}
+
+pub async fn delete_saved_search(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    own_saved_search(&state, &auth_user, id).await?;
+
+    state
+        .db
+        .delete_saved_search(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete saved search: {}", e)))?;
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// The notes a saved search finds now
+pub async fn run_saved_search(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(query): Query<RunSavedSearchQuery>,
+) -> Result<Json<SearchNotesResponse>, (StatusCode, String)> {
+    let search = own_saved_search(&state, &auth_user, id).await?;
+
+    run_search(&state, &auth_user, &search.query, query.limit, query.offset)
+        .await
+        .map(Json)
+}
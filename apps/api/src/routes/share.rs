@@ -0,0 +1,303 @@
+// Public read-only share links.
+//
+// A link is an unguessable token; anyone who has it can read the note, no
+// account needed. Links may expire and may require a password.
+use std::sync::Arc;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Form, Json,
+};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use pdtodo_document::{self as document, html, markdown};
+use crate::models::{Note, ShareLink};
+use crate::routes::notes::owned_note;
+use crate::AppState;
+
+/// Header JSON clients send the password of a protected link in
+const PASSWORD_HEADER: &str = "x-share-password";
+
+#[derive(Debug, Deserialize)]
+pub struct CreateShareLinkRequest {
+    /// Unix millis; the link never expires if unset
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<i64>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShareLinkResponse {
+    pub id: Uuid,
+    pub token: String,
+    /// Path of the shared note, relative to the API server
+    pub path: String,
+    #[serde(rename = "hasPassword")]
+    pub has_password: bool,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<i64>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+impl From<ShareLink> for ShareLinkResponse {
+    fn from(link: ShareLink) -> Self {
+        Self {
+            id: link.id,
+            path: format!("/s/{}", link.token),
+            token: link.token,
+            has_password: link.password_hash.is_some(),
+            expires_at: link.expires_at.map(|t| t.timestamp_millis()),
+            created_at: link.created_at.timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShareLinksResponse {
+    pub links: Vec<ShareLinkResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedNoteResponse {
+    pub title: String,
+    pub content: String, // Base64 encoded Yjs doc
+    pub markdown: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SharedNoteQuery {
+    /// `json` for JSON; otherwise the Accept header decides
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordForm {
+    pub password: String,
+}
+
+fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn hash_password(password: &str) -> Result<String, (StatusCode, String)> {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to hash password: {}", e)))
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
+
+pub async fn create_share_link(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+    Json(payload): Json<CreateShareLinkRequest>,
+) -> Result<Json<ShareLinkResponse>, (StatusCode, String)> {
+    let note = owned_note(&state, note_id, auth_user.user_id).await?;
+
+    // The server can't read end-to-end encrypted notes, so it can't show them
+    if note.encrypted {
+        return Err((StatusCode::CONFLICT, "Encrypted notes can't be shared by link".to_string()));
+    }
+
+    let expires_at = payload
+        .expires_at
+        .map(|millis| {
+            DateTime::<Utc>::from_timestamp_millis(millis)
+                .filter(|time| *time > Utc::now())
+                .ok_or((StatusCode::BAD_REQUEST, "expiresAt must be in the future".to_string()))
+        })
+        .transpose()?;
+
+    let password_hash = match payload.password.as_deref() {
+        Some("") => return Err((StatusCode::BAD_REQUEST, "Password must not be empty".to_string())),
+        Some(password) => Some(hash_password(password)?),
+        None => None,
+    };
+
+    let link = state
+        .db
+        .create_share_link(note_id, note.user_id, &generate_token(), password_hash.as_deref(), expires_at)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create share link: {}", e)))?;
+
+    Ok(Json(link.into()))
+}
+
+pub async fn list_share_links(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+) -> Result<Json<ShareLinksResponse>, (StatusCode, String)> {
+    let owner_id = owned_note(&state, note_id, auth_user.user_id).await?.user_id;
+    let links = state
+        .db
+        .list_share_links(note_id, owner_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(ShareLinksResponse {
+        links: links.into_iter().map(ShareLinkResponse::from).collect(),
+    }))
+}
+
+pub async fn revoke_share_link(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path((note_id, link_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let owner_id = owned_note(&state, note_id, auth_user.user_id).await?.user_id;
+    let rows = state
+        .db
+        .delete_share_link(link_id, note_id, owner_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to revoke share link: {}", e)))?;
+
+    if rows == 0 {
+        return Err((StatusCode::NOT_FOUND, "Share link not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// View a shared note. Browsers get a page; JSON clients send a password
+/// in the `X-Share-Password` header.
+pub async fn view_shared_note(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Query(query): Query<SharedNoteQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let password = headers
+        .get(PASSWORD_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    shared_note(&state, &token, password.as_deref(), wants_json(&query, &headers)).await
+}
+
+/// The password form of a protected link's page
+pub async fn unlock_shared_note(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Form(form): Form<PasswordForm>,
+) -> Response {
+    shared_note(&state, &token, Some(&form.password), false).await
+}
+
+fn wants_json(query: &SharedNoteQuery, headers: &HeaderMap) -> bool {
+    match query.format.as_deref() {
+        Some(format) => format == "json",
+        None => headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|accept| accept.contains("application/json") && !accept.contains("text/html")),
+    }
+}
+
+async fn shared_note(state: &AppState, token: &str, password: Option<&str>, json: bool) -> Response {
+    let shared = match state.db.get_shared_note(token).await {
+        Ok(shared) => shared,
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)).into_response();
+        }
+    };
+    // Expired, revoked and trashed notes all look the same from outside
+    let Some((link, note)) = shared.filter(|(_, note)| !note.encrypted) else {
+        return (StatusCode::NOT_FOUND, "Share link not found".to_string()).into_response();
+    };
+
+    if let Some(hash) = &link.password_hash {
+        if !password.is_some_and(|password| verify_password(password, hash)) {
+            return if json {
+                (StatusCode::UNAUTHORIZED, "Password required".to_string()).into_response()
+            } else {
+                page(StatusCode::UNAUTHORIZED, password_form(password.is_some()))
+            };
+        }
+    }
+
+    let blocks = match document::from_yjs(&note.content) {
+        Ok(blocks) => blocks,
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read note: {}", e)).into_response();
+        }
+    };
+
+    if json {
+        return Json(shared_note_response(note, &blocks)).into_response();
+    }
+    page(StatusCode::OK, html::render(&note.title, &blocks))
+}
+
+fn shared_note_response(note: Note, blocks: &[document::Block]) -> SharedNoteResponse {
+    SharedNoteResponse {
+        content: base64::engine::general_purpose::STANDARD.encode(&note.content),
+        markdown: markdown::to_markdown(blocks),
+        updated_at: note.updated_at.timestamp_millis(),
+        title: note.title,
+    }
+}
+
+/// A shared page. The token is in the URL, so pages aren't cached, indexed
+/// or leaked through the referrer, and note content can't run scripts.
+fn page(status: StatusCode, body: String) -> Response {
+    (
+        status,
+        [
+            (header::CACHE_CONTROL, "no-store"),
+            (header::REFERRER_POLICY, "no-referrer"),
+            (
+                header::CONTENT_SECURITY_POLICY,
+                "default-src 'none'; style-src 'unsafe-inline'; img-src data:; form-action 'self'",
+            ),
+            (header::HeaderName::from_static("x-robots-tag"), "noindex"),
+        ],
+        Html(body),
+    )
+        .into_response()
+}
+
+fn password_form(incorrect: bool) -> String {
+    let message = if incorrect { "Incorrect password." } else { "This note is protected by a password." };
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Shared note</title>
+<style>
+body {{ margin: 0; font: 16px/1.6 -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, Helvetica, Arial, sans-serif; color: #1f2937; }}
+form {{ max-width: 320px; margin: 20vh auto 0; padding: 0 24px; display: flex; flex-direction: column; gap: 12px; }}
+input, button {{ font: inherit; padding: 8px 12px; border-radius: 6px; border: 1px solid #d1d5db; }}
+button {{ background: #1f2937; color: #fff; border: none; cursor: pointer; }}
+</style>
+</head>
+<body>
+<form method="post">
+<p>{message}</p>
+<input type="password" name="password" placeholder="Password" autofocus required>
+<button type="submit">View note</button>
+</form>
+</body>
+</html>
+"#
+    )
+}
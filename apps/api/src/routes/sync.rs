@@ -1,8 +1,9 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Query, State,
     },
     http::StatusCode,
     response::Response,
@@ -12,9 +13,19 @@ use base64::Engine;
 use chrono::{DateTime, Utc};
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, oneshot};
 use uuid::Uuid;
 
 use crate::auth::AuthUser;
+use crate::models::{AccessibleNote, NoteRole};
+use crate::routes::attachments::AttachmentResponse;
+use crate::routes::folders::FolderResponse;
+use crate::routes::notes::note_scope;
+use crate::routes::saved_searches::SavedSearchResponse;
+use crate::routes::tags::{update_inline_tags, TagResponse};
+use crate::routes::tasks::update_tasks;
+use crate::sync::document;
+use crate::sync::live::LiveEvent;
 use crate::AppState;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +39,11 @@ pub struct UpdateItem {
     pub note_id: String,
     pub update: String, // Base64 encoded
     pub timestamp: i64,
+    /// The update is encrypted by the client and is relayed without merging
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(rename = "encryptedTitle")]
+    pub encrypted_title: Option<String>, // Base64 encoded
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +59,9 @@ pub struct SyncPullRequest {
     #[serde(rename = "stateVectors")]
     pub state_vectors: std::collections::HashMap<String, String>,
     pub since: i64,
+    /// `personal` or a workspace id; all notes if unset
+    #[serde(default)]
+    pub workspace: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +71,28 @@ pub struct SyncPullResponse {
     pub new_notes: Vec<NewNote>,
     #[serde(rename = "deletedNotes")]
     pub deleted_notes: Vec<String>,
+    /// Shared notes the user has lost access to; clients remove them
+    #[serde(rename = "revokedNotes")]
+    pub revoked_notes: Vec<String>,
+    /// Folders changed since `since`; deleted ones have `deletedAt` set
+    pub folders: Vec<FolderResponse>,
+    /// The folder of every changed note the client already has, null for
+    /// notes outside any folder
+    #[serde(rename = "noteFolders")]
+    pub note_folders: std::collections::HashMap<String, Option<String>>,
+    /// Tags changed since `since`; deleted ones have `deletedAt` set
+    pub tags: Vec<TagResponse>,
+    /// The tag ids of every changed note the client already has
+    #[serde(rename = "noteTags")]
+    pub note_tags: std::collections::HashMap<String, Vec<String>>,
+    /// The user's saved searches changed since `since`, in every space;
+    /// deleted ones have `deletedAt` set
+    #[serde(rename = "savedSearches")]
+    pub saved_searches: Vec<SavedSearchResponse>,
+    /// Attachments changed since `since`, including those of notes the
+    /// user has gained access to; removed ones have `deletedAt` set.
+    /// Clients download the blobs they don't have with GET /blobs/:hash.
+    pub attachments: Vec<AttachmentResponse>,
     #[serde(rename = "serverTime")]
     pub server_time: i64,
 }
@@ -64,6 +105,102 @@ pub struct NewNote {
     pub starred: bool,
     #[serde(rename = "createdAt")]
     pub created_at: i64,
+    pub encrypted: bool,
+    #[serde(rename = "encryptedTitle", skip_serializing_if = "Option::is_none")]
+    pub encrypted_title: Option<String>,
+    pub role: NoteRole,
+    #[serde(rename = "workspaceId")]
+    pub workspace_id: Option<String>,
+    #[serde(rename = "folderId")]
+    pub folder_id: Option<String>,
+    #[serde(rename = "tagIds")]
+    pub tag_ids: Vec<String>,
+}
+
+/// Merge an update into a note's document, store it and pass it on to live
+/// subscribers. Returns false, changing nothing, when the user can't edit the
+/// note, the update doesn't match how the note is stored or it doesn't merge.
+async fn apply_update(
+    state: &AppState,
+    user_id: Uuid,
+    note_id: Uuid,
+    update_data: &[u8],
+    encrypted: bool,
+    encrypted_title: Option<&[u8]>,
+    origin: Option<Uuid>,
+) -> Result<bool, (StatusCode, String)> {
+    let note = state
+        .db
+        .get_accessible_note(note_id, user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    let Some(AccessibleNote { note, role }) = note else {
+        return Ok(false);
+    };
+    if !role.can_edit() {
+        return Ok(false);
+    }
+
+    // The client must agree with the note's storage mode, and may not
+    // send plaintext to a personal note once the owner's account uses
+    // end-to-end encryption. It resolves the conflict by converting the
+    // note with PUT /notes/:id.
+    let e2ee_enabled = state
+        .db
+        .is_e2ee_enabled(note.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+    if note.encrypted != encrypted || (e2ee_enabled && !encrypted && note.workspace_id.is_none()) {
+        return Ok(false);
+    }
+
+    // Encrypted updates can't be merged; clients combine the stored
+    // snapshot with the relayed updates themselves
+    if note.encrypted {
+        // Store sync update for other clients
+        state
+            .db
+            .store_sync_update(note_id, update_data, None)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store update: {}", e)))?;
+        state
+            .db
+            .update_encrypted_note(note_id, note.user_id, None, encrypted_title, None)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update note: {}", e)))?;
+    } else {
+        // Merge the Yjs update into the canonical document, reading it under
+        // the row lock so concurrent pushes don't drop each other's changes.
+        // The update is only stored for other clients if it merges.
+        // Collaborators write to the owner's note.
+        let mut content_text = String::new();
+        let merged = state
+            .db
+            .merge_sync_update(note_id, note.user_id, update_data, |content| {
+                match document::merge_update(content, update_data) {
+                    Ok(new_content) => {
+                        content_text = document::search_text(&new_content);
+                        Some((new_content, content_text.clone()))
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to merge update for note {}: {}", note_id, e);
+                        None
+                    }
+                }
+            })
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update note: {}", e)))?;
+        let Some(note) = merged else {
+            return Ok(false);
+        };
+        update_inline_tags(state, &note, &content_text).await?;
+        update_tasks(state, &note).await?;
+    }
+
+    let update = base64::engine::general_purpose::STANDARD.encode(update_data);
+    state.live.publish(note_id, LiveEvent::Update { update: Arc::new(update), origin });
+    Ok(true)
 }
 
 pub async fn push_updates(
@@ -78,50 +215,36 @@ pub async fn push_updates(
         let note_id = Uuid::parse_str(&update_item.note_id)
             .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid note ID".to_string()))?;
 
-        // Verify user owns this note
-        let note = state
-            .db
-            .get_note(note_id, auth_user.user_id)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
-
-        if note.is_none() {
-            conflicts.push(update_item.note_id.clone());
-            continue;
-        }
-
         // Decode and store update
         let update_data = base64::engine::general_purpose::STANDARD
             .decode(&update_item.update)
             .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid base64 update: {}", e)))?;
+        let encrypted_title = update_item
+            .encrypted_title
+            .as_ref()
+            .map(|title| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(title)
+                    .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid base64 encryptedTitle: {}", e)))
+            })
+            .transpose()?;
 
-        // Store sync update for other clients
-        state
-            .db
-            .store_sync_update(note_id, &update_data, None)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store update: {}", e)))?;
-
-        // Apply update to note content (merge Yjs update)
-        // For now, we just update the content directly - full CRDT merge would be more complex
-        let note = note.unwrap();
-        let mut new_content = note.content.clone();
-        new_content.extend_from_slice(&update_data);
+        let applied = apply_update(
+            &state,
+            auth_user.user_id,
+            note_id,
+            &update_data,
+            update_item.encrypted,
+            encrypted_title.as_deref(),
+            None,
+        )
+        .await?;
 
-        state
-            .db
-            .update_note(
-                note_id,
-                auth_user.user_id,
-                None,
-                Some(&new_content),
-                None,
-                None,
-            )
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update note: {}", e)))?;
-
-        processed.push(update_item.note_id);
+        if applied {
+            processed.push(update_item.note_id);
+        } else {
+            conflicts.push(update_item.note_id);
+        }
     }
 
     Ok(Json(SyncPushResponse {
@@ -139,18 +262,94 @@ pub async fn pull_updates(
     let since = DateTime::<Utc>::from_timestamp_millis(payload.since)
         .unwrap_or_else(|| DateTime::<Utc>::from_timestamp(0, 0).unwrap());
 
+    let scope = note_scope(&state, &auth_user, payload.workspace.as_deref()).await?;
+
     // Get all notes updated since timestamp
     let notes = state
         .db
-        .list_notes(auth_user.user_id, true, Some(since))
+        .list_notes(auth_user.user_id, true, Some(since), scope)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
 
+    let folders = state
+        .db
+        .list_folders(auth_user.user_id, Some(since), scope)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .into_iter()
+        .map(FolderResponse::from)
+        .collect();
+
+    let tags = state
+        .db
+        .list_tags(auth_user.user_id, Some(since), scope)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .into_iter()
+        .map(TagResponse::from)
+        .collect();
+
+    let saved_searches = state
+        .db
+        .list_saved_searches(auth_user.user_id, Some(since))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .into_iter()
+        .map(SavedSearchResponse::from)
+        .collect();
+
+    let attachments = state
+        .db
+        .list_attachments(auth_user.user_id, since, scope)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .into_iter()
+        .map(AttachmentResponse::from)
+        .collect();
+
+    // Tags of the changed notes; like folders, the tags of a personal note
+    // are only the owner's to see
+    let tagged_ids: Vec<Uuid> = notes
+        .iter()
+        .filter(|accessible| accessible.sees_organization() && accessible.note.deleted_at.is_none())
+        .map(|accessible| accessible.note.id)
+        .collect();
+    let mut tag_ids: std::collections::HashMap<Uuid, Vec<String>> =
+        tagged_ids.iter().map(|&id| (id, Vec::new())).collect();
+    for (note_id, tag_id) in state
+        .db
+        .note_tag_ids(&tagged_ids)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+    {
+        tag_ids.entry(note_id).or_default().push(tag_id.to_string());
+    }
+
+    // Notes the client has that were shared with it and no longer are
+    let known_ids: Vec<Uuid> = payload
+        .state_vectors
+        .keys()
+        .filter_map(|id| Uuid::parse_str(id).ok())
+        .collect();
+    let revoked_notes = state
+        .db
+        .inaccessible_note_ids(&known_ids, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .into_iter()
+        .map(|id| id.to_string())
+        .collect();
+
     let mut updates: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
     let mut new_notes = Vec::new();
     let mut deleted_notes = Vec::new();
+    let mut note_folders = std::collections::HashMap::new();
+    let mut note_tags = std::collections::HashMap::new();
 
-    for note in notes {
+    for accessible in notes {
+        let folder_id = accessible.folder_id().map(|id| id.to_string());
+        let AccessibleNote { note, role } = accessible;
+        let note_tag_ids = tag_ids.remove(&note.id).unwrap_or_default();
         let note_id = note.id.to_string();
 
         if note.deleted_at.is_some() {
@@ -160,6 +359,25 @@ pub async fn pull_updates(
 
         // Check if client has this note
         if !payload.state_vectors.contains_key(&note_id) {
+            // The content of an encrypted note is only the last snapshot a
+            // client uploaded, so send every update as well; applying one
+            // the snapshot already contains is harmless
+            if note.encrypted {
+                let note_updates = state
+                    .db
+                    .get_sync_updates_since(note.id, DateTime::<Utc>::UNIX_EPOCH)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+                if !note_updates.is_empty() {
+                    let encoded_updates: Vec<String> = note_updates
+                        .into_iter()
+                        .map(|(_, data)| base64::engine::general_purpose::STANDARD.encode(&data))
+                        .collect();
+                    updates.insert(note_id.clone(), encoded_updates);
+                }
+            }
+
             // Client doesn't have this note - send full content
             new_notes.push(NewNote {
                 id: note_id,
@@ -167,8 +385,19 @@ pub async fn pull_updates(
                 content: base64::engine::general_purpose::STANDARD.encode(&note.content),
                 starred: note.starred,
                 created_at: note.created_at.timestamp_millis(),
+                encrypted: note.encrypted,
+                encrypted_title: note
+                    .encrypted_title
+                    .map(|title| base64::engine::general_purpose::STANDARD.encode(title)),
+                role,
+                workspace_id: note.workspace_id.map(|id| id.to_string()),
+                folder_id,
+                tag_ids: note_tag_ids,
             });
         } else {
+            note_folders.insert(note_id.clone(), folder_id);
+            note_tags.insert(note_id.clone(), note_tag_ids);
+
             // Client has this note - send updates since their last sync
             let note_updates = state
                 .db
@@ -190,15 +419,38 @@ pub async fn pull_updates(
         updates,
         new_notes,
         deleted_notes,
+        revoked_notes,
+        folders,
+        note_folders,
+        tags,
+        note_tags,
+        saved_searches,
+        attachments,
         server_time: Utc::now().timestamp_millis(),
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct LiveQuery {
+    /// Access token, for browsers, which can't set headers on WebSockets
+    pub token: Option<String>,
+}
+
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
-) -> Response {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+    auth_user: Option<AuthUser>,
+    Query(query): Query<LiveQuery>,
+) -> Result<Response, (StatusCode, &'static str)> {
+    let auth_user = match auth_user {
+        Some(auth_user) => auth_user,
+        None => {
+            let token = query.token.ok_or((StatusCode::UNAUTHORIZED, "Missing authorization"))?;
+            AuthUser::from_token(&state.auth, &token)?
+        }
+    };
+
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, state, auth_user.user_id)))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -209,51 +461,172 @@ struct WsMessage {
     data: serde_json::Value,
 }
 
-async fn handle_socket(socket: WebSocket, _state: Arc<AppState>) {
+#[derive(Debug, Deserialize)]
+struct LiveUpdate {
+    #[serde(rename = "noteId")]
+    note_id: Uuid,
+    update: String, // Base64 encoded
+    #[serde(default)]
+    encrypted: bool,
+}
+
+/// A live connection. Clients subscribe to notes they can read, send
+/// updates to notes they can edit, and receive everyone else's updates.
+async fn handle_socket(socket: WebSocket, state: Arc<AppState>, user_id: Uuid) {
     let (mut sender, mut receiver) = socket.split();
+    let connection_id = Uuid::now_v7();
+
+    // Replies and subscribed updates are all written by one task
+    let (outbox, mut outgoing) = mpsc::unbounded_channel::<serde_json::Value>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = outgoing.recv().await {
+            if sender.send(Message::Text(message.to_string())).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Dropping a note's sender ends its subscription
+    let mut subscriptions: HashMap<Uuid, oneshot::Sender<()>> = HashMap::new();
 
-    // Send welcome message
-    let welcome = serde_json::json!({
+    let _ = outbox.send(serde_json::json!({
         "type": "connected",
         "serverTime": Utc::now().timestamp_millis()
-    });
-    if sender.send(Message::Text(welcome.to_string().into())).await.is_err() {
-        return;
-    }
+    }));
 
     // Handle incoming messages
     while let Some(msg) = receiver.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                // Parse and handle message
-                if let Ok(ws_msg) = serde_json::from_str::<WsMessage>(&text) {
-                    match ws_msg.msg_type.as_str() {
-                        "ping" => {
-                            let pong = serde_json::json!({
-                                "type": "pong",
-                                "serverTime": Utc::now().timestamp_millis()
-                            });
-                            if sender.send(Message::Text(pong.to_string().into())).await.is_err() {
-                                break;
-                            }
-                        }
-                        "subscribe" => {
-                            // Client wants to subscribe to note updates
-                            let ack = serde_json::json!({
-                                "type": "subscribed",
-                                "noteId": ws_msg.data.get("noteId")
-                            });
-                            if sender.send(Message::Text(ack.to_string().into())).await.is_err() {
-                                break;
-                            }
-                        }
-                        _ => {}
+        let text = match msg {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(_) => break,
+            _ => continue,
+        };
+        let Ok(ws_msg) = serde_json::from_str::<WsMessage>(&text) else {
+            continue;
+        };
+        let note_id = ws_msg
+            .data
+            .get("noteId")
+            .and_then(|id| id.as_str())
+            .and_then(|id| Uuid::parse_str(id).ok());
+
+        let reply = match (ws_msg.msg_type.as_str(), note_id) {
+            ("ping", _) => serde_json::json!({
+                "type": "pong",
+                "serverTime": Utc::now().timestamp_millis()
+            }),
+            ("subscribe", Some(note_id)) => {
+                match state.db.get_accessible_note(note_id, user_id).await {
+                    Ok(Some(accessible)) => {
+                        let (cancel, cancelled) = oneshot::channel();
+                        subscriptions.insert(note_id, cancel);
+                        tokio::spawn(forward_events(
+                            state.clone(),
+                            note_id,
+                            user_id,
+                            connection_id,
+                            outbox.clone(),
+                            cancelled,
+                        ));
+                        serde_json::json!({ "type": "subscribed", "noteId": note_id, "role": accessible.role })
                     }
+                    Ok(None) => serde_json::json!({ "type": "error", "noteId": note_id, "message": "Note not found" }),
+                    Err(e) => serde_json::json!({ "type": "error", "noteId": note_id, "message": format!("Database error: {}", e) }),
+                }
+            }
+            ("unsubscribe", Some(note_id)) => {
+                subscriptions.remove(&note_id);
+                serde_json::json!({ "type": "unsubscribed", "noteId": note_id })
+            }
+            ("update", Some(note_id)) => live_update(&state, user_id, connection_id, ws_msg.data).await
+                .unwrap_or_else(|message| serde_json::json!({ "type": "error", "noteId": note_id, "message": message })),
+            _ => continue,
+        };
+
+        if outbox.send(reply).is_err() {
+            break;
+        }
+    }
+
+    drop(subscriptions);
+    drop(outbox);
+    let _ = writer.await;
+}
+
+/// Apply an update sent over the socket; the reply acknowledges it
+async fn live_update(
+    state: &AppState,
+    user_id: Uuid,
+    connection_id: Uuid,
+    data: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let update: LiveUpdate = serde_json::from_value(data).map_err(|e| format!("Invalid update: {}", e))?;
+    let update_data = base64::engine::general_purpose::STANDARD
+        .decode(&update.update)
+        .map_err(|e| format!("Invalid base64 update: {}", e))?;
+
+    let applied = apply_update(
+        state,
+        user_id,
+        update.note_id,
+        &update_data,
+        update.encrypted,
+        None,
+        Some(connection_id),
+    )
+    .await
+    .map_err(|(_, message)| message)?;
+
+    if applied {
+        Ok(serde_json::json!({ "type": "ack", "noteId": update.note_id }))
+    } else {
+        Err("Update rejected; pull the note to resolve".to_string())
+    }
+}
+
+/// Pass a note's live events on to one connection until it unsubscribes
+async fn forward_events(
+    state: Arc<AppState>,
+    note_id: Uuid,
+    user_id: Uuid,
+    connection_id: Uuid,
+    outbox: mpsc::UnboundedSender<serde_json::Value>,
+    mut cancelled: oneshot::Receiver<()>,
+) {
+    let mut events = state.live.subscribe(note_id);
+
+    loop {
+        let event = tokio::select! {
+            _ = &mut cancelled => break,
+            event = events.recv() => event,
+        };
+
+        let message = match event {
+            Ok(LiveEvent::Update { update, origin }) => {
+                if origin == Some(connection_id) {
+                    continue;
+                }
+                serde_json::json!({ "type": "update", "noteId": note_id, "update": update.as_str() })
+            }
+            Ok(LiveEvent::Revoked { user_id: revoked }) => {
+                if revoked != user_id {
+                    continue;
                 }
+                let _ = outbox.send(serde_json::json!({ "type": "revoked", "noteId": note_id }));
+                break;
             }
-            Ok(Message::Close(_)) => break,
-            Err(_) => break,
-            _ => {}
+            // Too far behind to catch up live; the client pulls instead
+            Err(broadcast::error::RecvError::Lagged(_)) => {
+                serde_json::json!({ "type": "resync", "noteId": note_id })
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        if outbox.send(message).is_err() {
+            break;
         }
     }
+
+    drop(events);
+    state.live.release(note_id);
 }
@@ -0,0 +1,260 @@
+// Tags on notes.
+//
+// A tag lives in the space of the notes it's on, like a folder: personal
+// tags are their owner's, workspace tags are shared by its members and
+// editors there may change them. Tags come from two places: added by hand
+// through these endpoints, or picked up from #hashtags whenever a note's
+// content is saved. Changes reach other clients through GET /tags and
+// /sync/pull.
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use crate::models::{AccessibleNote, Note, Tag, TagSummary};
+use crate::routes::notes::note_scope;
+use crate::sync::document;
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ListTagsQuery {
+    /// `personal` or a workspace id; all tags if unset
+    pub workspace: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagResponse {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "workspaceId")]
+    pub workspace_id: Option<Uuid>,
+    /// Notes with the tag, not counting those in the trash (listings only)
+    #[serde(rename = "noteCount", skip_serializing_if = "Option::is_none")]
+    pub note_count: Option<i64>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: Option<i64>,
+}
+
+impl From<Tag> for TagResponse {
+    fn from(tag: Tag) -> Self {
+        Self {
+            id: tag.id,
+            name: tag.name,
+            workspace_id: tag.workspace_id,
+            note_count: None,
+            created_at: tag.created_at.timestamp_millis(),
+            updated_at: tag.updated_at.timestamp_millis(),
+            deleted_at: tag.deleted_at.map(|dt| dt.timestamp_millis()),
+        }
+    }
+}
+
+impl From<TagSummary> for TagResponse {
+    fn from(summary: TagSummary) -> Self {
+        Self {
+            note_count: Some(summary.note_count),
+            ..summary.tag.into()
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagsResponse {
+    pub tags: Vec<TagResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TagNameRequest {
+    pub name: String,
+}
+
+/// Trimmed, without a leading `#`. Tags can't contain whitespace so they
+/// can be typed in `tag:` search filters.
+fn tag_name(name: &str) -> Result<&str, (StatusCode, String)> {
+    let name = name.trim();
+    let name = name.strip_prefix('#').unwrap_or(name);
+    if name.is_empty() || name.chars().any(char::is_whitespace) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Tag names must not be empty or contain spaces".to_string(),
+        ));
+    }
+    if name.chars().count() > document::MAX_TAG_LENGTH {
+        return Err((StatusCode::BAD_REQUEST, "Tag name is too long".to_string()));
+    }
+    Ok(name)
+}
+
+/// A tag the user may change: their own personal tag, or a tag in a
+/// workspace they can edit
+async fn editable_tag(state: &AppState, auth_user: &AuthUser, id: Uuid) -> Result<Tag, (StatusCode, String)> {
+    let tag = state
+        .db
+        .get_accessible_tag(id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Tag not found".to_string()))?;
+
+    if let Some(workspace_id) = tag.workspace_id {
+        auth_user.workspace_editor(&state.db, workspace_id).await?;
+    }
+    Ok(tag)
+}
+
+/// A note the user may tag: personal notes are tagged by their owner,
+/// workspace notes by any editor
+async fn taggable_note(state: &AppState, auth_user: &AuthUser, id: Uuid) -> Result<Note, (StatusCode, String)> {
+    let accessible = state
+        .db
+        .get_accessible_note(id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))?;
+
+    if !accessible.sees_organization() || !accessible.role.can_edit() {
+        return Err((StatusCode::FORBIDDEN, "You can't tag this note".to_string()));
+    }
+    Ok(accessible.note)
+}
+
+/// Bring a note's hashtag tags up to date after its content was saved.
+/// `text` is the note's plain text; encrypted notes have none, so they
+/// keep no hashtag tags.
+pub async fn update_inline_tags(state: &AppState, note: &Note, text: &str) -> Result<(), (StatusCode, String)> {
+    let names = if note.encrypted { Vec::new() } else { document::hashtags(text) };
+    state
+        .db
+        .set_inline_tags(note, &names)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update tags: {}", e)))
+}
+
+pub async fn list_tags(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(query): Query<ListTagsQuery>,
+) -> Result<Json<TagsResponse>, (StatusCode, String)> {
+    let scope = note_scope(&state, &auth_user, query.workspace.as_deref()).await?;
+
+    let tags = state
+        .db
+        .list_tags(auth_user.user_id, None, scope)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(TagsResponse {
+        tags: tags.into_iter().map(TagResponse::from).collect(),
+    }))
+}
+
+pub async fn list_note_tags(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+) -> Result<Json<TagsResponse>, (StatusCode, String)> {
+    let accessible: AccessibleNote = state
+        .db
+        .get_accessible_note(note_id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))?;
+
+    if !accessible.sees_organization() {
+        return Ok(Json(TagsResponse { tags: Vec::new() }));
+    }
+
+    let tags = state
+        .db
+        .list_note_tags(note_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(TagsResponse {
+        tags: tags.into_iter().map(TagResponse::from).collect(),
+    }))
+}
+
+/// Tag a note, creating the tag if the note's space doesn't have it yet
+pub async fn add_note_tag(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+    Json(payload): Json<TagNameRequest>,
+) -> Result<Json<TagResponse>, (StatusCode, String)> {
+    let name = tag_name(&payload.name)?;
+    let note = taggable_note(&state, &auth_user, note_id).await?;
+
+    let tag = state
+        .db
+        .add_note_tag(&note, auth_user.user_id, name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to add tag: {}", e)))?;
+
+    Ok(Json(tag.into()))
+}
+
+pub async fn remove_note_tag(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path((note_id, tag_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    taggable_note(&state, &auth_user, note_id).await?;
+
+    let removed = state
+        .db
+        .remove_note_tag(note_id, tag_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to remove tag: {}", e)))?;
+
+    if removed == 0 {
+        return Err((StatusCode::NOT_FOUND, "The note doesn't have this tag".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// Rename a tag on every note that has it. Renaming to the name of another
+/// tag merges the two; the response is the tag the notes have now.
+pub async fn rename_tag(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<TagNameRequest>,
+) -> Result<Json<TagResponse>, (StatusCode, String)> {
+    let tag = editable_tag(&state, &auth_user, id).await?;
+    let name = tag_name(&payload.name)?;
+
+    let tag = state
+        .db
+        .rename_tag(&tag, name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to rename tag: {}", e)))?;
+
+    Ok(Json(tag.into()))
+}
+
+/// Take a tag off every note that has it
+pub async fn delete_tag(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    editable_tag(&state, &auth_user, id).await?;
+
+    state
+        .db
+        .delete_tag(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete tag: {}", e)))?;
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
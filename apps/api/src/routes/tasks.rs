@@ -0,0 +1,349 @@
+// Task items of notes.
+//
+// Tasks are extracted from a note's Yjs document whenever its content is
+// saved, so they can be listed across notes. A task's id is its note id and
+// its path in the document, `<note id>:<path>` as in the desktop app.
+// Toggling a task changes the document like an editor would: the change is
+// stored as a sync update and relayed to open editors. Checked repeating
+// tasks are moved to their next occurrence the same way whenever a note's
+// tasks are re-extracted, so it doesn't matter which client checked them.
+//
+// The Today, Upcoming and Recently completed views list tasks across notes
+// by due date and completion time. Clients pass their local date as
+// `today`, since the server doesn't know their time zone.
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use base64::Engine;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use crate::models::{Note, Task, TaskCounts};
+use crate::routes::notes::note_scope;
+use crate::sync::document;
+use crate::sync::live::LiveEvent;
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ListTasksQuery {
+    /// `personal` or a workspace id; all notes if unset
+    pub workspace: Option<String>,
+}
+
+/// Days after today the Upcoming view covers
+const UPCOMING_DAYS: i64 = 7;
+
+/// Days back the Recently completed view covers, and its longest list
+const RECENTLY_COMPLETED_DAYS: i64 = 7;
+const RECENTLY_COMPLETED_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct TaskViewQuery {
+    /// `personal` or a workspace id; all notes if unset
+    pub workspace: Option<String>,
+    /// The client's local date; the UTC date if unset
+    pub today: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskResponse {
+    pub id: String,
+    #[serde(rename = "noteId")]
+    pub note_id: Uuid,
+    #[serde(rename = "noteTitle")]
+    pub note_title: String,
+    /// Order of the task within its note
+    pub position: i32,
+    /// Child indexes from the top of the document, e.g. `2.0.1`
+    pub path: String,
+    /// Number of task items the task is nested in
+    pub depth: i32,
+    pub text: String,
+    pub checked: bool,
+    #[serde(rename = "dueDate")]
+    pub due_date: Option<NaiveDate>,
+    /// Reminder time in milliseconds since the epoch
+    #[serde(rename = "remindAt")]
+    pub remind_at: Option<i64>,
+    /// When the task was checked, if it is and that's known
+    #[serde(rename = "completedAt")]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<Task> for TaskResponse {
+    fn from(task: Task) -> Self {
+        Self {
+            id: format!("{}:{}", task.note_id, task.path),
+            note_id: task.note_id,
+            note_title: task.note_title,
+            position: task.position,
+            path: task.path,
+            depth: task.depth,
+            text: task.text,
+            checked: task.checked,
+            due_date: task.due_date,
+            remind_at: task.remind_at,
+            completed_at: task.completed_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TasksResponse {
+    pub tasks: Vec<TaskResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskCountsResponse {
+    #[serde(rename = "noteId")]
+    pub note_id: Uuid,
+    pub open: i64,
+    pub completed: i64,
+}
+
+impl From<TaskCounts> for TaskCountsResponse {
+    fn from(counts: TaskCounts) -> Self {
+        Self {
+            note_id: counts.note_id,
+            open: counts.open,
+            completed: counts.completed,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskCountsListResponse {
+    pub counts: Vec<TaskCountsResponse>,
+}
+
+/// Re-extract a note's tasks after its content was saved, first moving
+/// checked repeating tasks to their next occurrence. Encrypted notes can't
+/// be read, so they have no tasks.
+pub async fn update_tasks(state: &AppState, note: &Note) -> Result<(), (StatusCode, String)> {
+    let items = if note.encrypted {
+        Vec::new()
+    } else {
+        let advanced = advance_recurring(state, note).await?;
+        let content = advanced.as_ref().map_or(&note.content, |note| &note.content);
+        match document::tasks(content) {
+            Ok(items) => items,
+            Err(e) => {
+                tracing::warn!("Failed to extract tasks of note {}: {}", note.id, e);
+                return Ok(());
+            }
+        }
+    };
+    state
+        .db
+        .set_tasks(note.id, &items)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update tasks: {}", e)))
+}
+
+/// Move a note's checked repeating tasks to their next occurrence. Returns
+/// the updated note if any task was moved. The server doesn't know the
+/// user's time zone, so "today" is the UTC date.
+async fn advance_recurring(state: &AppState, note: &Note) -> Result<Option<Note>, (StatusCode, String)> {
+    let today = Utc::now().date_naive();
+    let edit = match document::advance_recurring(&note.content, today) {
+        Ok(Some(edit)) => edit,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            tracing::warn!("Failed to advance repeating tasks of note {}: {}", note.id, e);
+            return Ok(None);
+        }
+    };
+    store_edit(state, note, &edit).await.map(Some)
+}
+
+/// Store a change the server made to a note's document and relay it to
+/// open editors. Task changes leave the note's text as it was.
+async fn store_edit(state: &AppState, note: &Note, edit: &document::Edit) -> Result<Note, (StatusCode, String)> {
+    state
+        .db
+        .store_sync_update(note.id, &edit.update, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store update: {}", e)))?;
+    let updated = state
+        .db
+        .update_note(note.id, note.user_id, None, Some(&edit.content), None, None, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update note: {}", e)))?;
+
+    let update = base64::engine::general_purpose::STANDARD.encode(&edit.update);
+    state.live.publish(note.id, LiveEvent::Update { update: Arc::new(update), origin: None });
+    Ok(updated)
+}
+
+/// Unchecked tasks across the notes the user can access
+pub async fn list_open_tasks(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(query): Query<ListTasksQuery>,
+) -> Result<Json<TasksResponse>, (StatusCode, String)> {
+    let scope = note_scope(&state, &auth_user, query.workspace.as_deref()).await?;
+
+    let tasks = state
+        .db
+        .list_open_tasks(auth_user.user_id, scope)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(TasksResponse {
+        tasks: tasks.into_iter().map(TaskResponse::from).collect(),
+    }))
+}
+
+/// Open tasks due today or overdue
+pub async fn list_today_tasks(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(query): Query<TaskViewQuery>,
+) -> Result<Json<TasksResponse>, (StatusCode, String)> {
+    let scope = note_scope(&state, &auth_user, query.workspace.as_deref()).await?;
+    let today = query.today.unwrap_or_else(|| Utc::now().date_naive());
+
+    let tasks = state
+        .db
+        .list_due_tasks(auth_user.user_id, scope, None, today)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(TasksResponse {
+        tasks: tasks.into_iter().map(TaskResponse::from).collect(),
+    }))
+}
+
+/// Open tasks due in the week after today
+pub async fn list_upcoming_tasks(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(query): Query<TaskViewQuery>,
+) -> Result<Json<TasksResponse>, (StatusCode, String)> {
+    let scope = note_scope(&state, &auth_user, query.workspace.as_deref()).await?;
+    let today = query.today.unwrap_or_else(|| Utc::now().date_naive());
+
+    let tasks = state
+        .db
+        .list_due_tasks(auth_user.user_id, scope, Some(today), today + Duration::days(UPCOMING_DAYS))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(TasksResponse {
+        tasks: tasks.into_iter().map(TaskResponse::from).collect(),
+    }))
+}
+
+/// Tasks checked in the last week, most recently checked first
+pub async fn list_completed_tasks(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(query): Query<ListTasksQuery>,
+) -> Result<Json<TasksResponse>, (StatusCode, String)> {
+    let scope = note_scope(&state, &auth_user, query.workspace.as_deref()).await?;
+    let since = Utc::now() - Duration::days(RECENTLY_COMPLETED_DAYS);
+
+    let tasks = state
+        .db
+        .list_completed_tasks(auth_user.user_id, scope, since, RECENTLY_COMPLETED_LIMIT)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(TasksResponse {
+        tasks: tasks.into_iter().map(TaskResponse::from).collect(),
+    }))
+}
+
+/// Open and completed task counts of each note with tasks
+pub async fn list_task_counts(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Query(query): Query<ListTasksQuery>,
+) -> Result<Json<TaskCountsListResponse>, (StatusCode, String)> {
+    let scope = note_scope(&state, &auth_user, query.workspace.as_deref()).await?;
+
+    let counts = state
+        .db
+        .task_counts(auth_user.user_id, scope)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(TaskCountsListResponse {
+        counts: counts.into_iter().map(TaskCountsResponse::from).collect(),
+    }))
+}
+
+pub async fn list_note_tasks(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(note_id): Path<Uuid>,
+) -> Result<Json<TasksResponse>, (StatusCode, String)> {
+    state
+        .db
+        .get_accessible_note(note_id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Note not found".to_string()))?;
+
+    let tasks = state
+        .db
+        .list_note_tasks(note_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(TasksResponse {
+        tasks: tasks.into_iter().map(TaskResponse::from).collect(),
+    }))
+}
+
+/// Check an open task or uncheck a completed one. Fails with 404 if the
+/// note no longer has the task where it was indexed.
+pub async fn toggle_task(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<TaskResponse>, (StatusCode, String)> {
+    let not_found = || (StatusCode::NOT_FOUND, "Task not found".to_string());
+    let (note_id, path) = id.split_once(':').ok_or_else(not_found)?;
+    let note_id = Uuid::parse_str(note_id).map_err(|_| not_found())?;
+    let indexes = document::parse_task_path(path).ok_or_else(not_found)?;
+
+    let accessible = state
+        .db
+        .get_accessible_note(note_id, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(not_found)?;
+    if !accessible.role.can_edit() {
+        return Err((StatusCode::FORBIDDEN, "You can't edit this note".to_string()));
+    }
+    let note = accessible.note;
+
+    let task = state
+        .db
+        .get_task(note_id, path)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(not_found)?;
+
+    let edit = document::set_task_checked(&note.content, &indexes, &task.text, !task.checked)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read note: {}", e)))?
+        .ok_or_else(not_found)?;
+
+    let note = store_edit(&state, &note, &edit).await?;
+    update_tasks(&state, &note).await?;
+
+    let task = state
+        .db
+        .get_task(note_id, path)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or_else(not_found)?;
+
+    Ok(Json(task.into()))
+}
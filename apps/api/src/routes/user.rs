@@ -14,6 +14,8 @@ pub struct UserResponse {
     #[serde(rename = "createdAt")]
     pub created_at: i64,
     pub settings: serde_json::Value,
+    #[serde(rename = "e2eeEnabled")]
+    pub e2ee_enabled: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +60,7 @@ pub async fn get_current_user(
         picture: user.picture_url,
         created_at: user.created_at.timestamp_millis(),
         settings: user.settings,
+        e2ee_enabled: user.e2ee_enabled,
     }))
 }
 
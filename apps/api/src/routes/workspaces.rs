@@ -0,0 +1,294 @@
+// Team workspaces.
+//
+// A workspace is a shared collection of notes. Every member sees all of its
+// notes: admins manage the workspace and its members, editors edit notes,
+// viewers read them. Notes are put in a workspace when they are created
+// (`workspaceId` on POST /notes) and listed with `?workspace=<id>`.
+use std::sync::Arc;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use crate::models::{MemberWorkspace, WorkspaceMember, WorkspaceRole};
+use crate::sync::live::LiveEvent;
+use crate::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct WorkspaceResponse {
+    pub id: Uuid,
+    pub name: String,
+    /// The requesting user's role
+    pub role: WorkspaceRole,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+}
+
+impl From<MemberWorkspace> for WorkspaceResponse {
+    fn from(member_workspace: MemberWorkspace) -> Self {
+        let MemberWorkspace { workspace, role } = member_workspace;
+        Self {
+            id: workspace.id,
+            name: workspace.name,
+            role,
+            created_at: workspace.created_at.timestamp_millis(),
+            updated_at: workspace.updated_at.timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkspacesResponse {
+    pub workspaces: Vec<WorkspaceResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkspaceMemberResponse {
+    pub id: Uuid,
+    #[serde(rename = "userId")]
+    pub user_id: Option<Uuid>,
+    pub email: String,
+    pub role: WorkspaceRole,
+    /// Invited, but hasn't signed in with this email yet
+    pub pending: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+impl From<WorkspaceMember> for WorkspaceMemberResponse {
+    fn from(member: WorkspaceMember) -> Self {
+        Self {
+            id: member.id,
+            pending: member.user_id.is_none(),
+            user_id: member.user_id,
+            email: member.email,
+            role: member.role,
+            created_at: member.created_at.timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkspaceDetailsResponse {
+    #[serde(flatten)]
+    pub workspace: WorkspaceResponse,
+    pub members: Vec<WorkspaceMemberResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddWorkspaceMemberRequest {
+    pub email: String,
+    pub role: WorkspaceRole,
+}
+
+fn workspace_name(name: &str) -> Result<&str, (StatusCode, String)> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Workspace name must not be empty".to_string()));
+    }
+    Ok(name)
+}
+
+async fn workspace_members(state: &AppState, workspace_id: Uuid) -> Result<Vec<WorkspaceMember>, (StatusCode, String)> {
+    state
+        .db
+        .list_workspace_members(workspace_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))
+}
+
+/// Whether `member` is the only admin who has signed in; a workspace must
+/// keep one
+fn is_last_admin(members: &[WorkspaceMember], member: &WorkspaceMember) -> bool {
+    member.role == WorkspaceRole::Admin
+        && member.user_id.is_some()
+        && !members
+            .iter()
+            .any(|other| other.id != member.id && other.role == WorkspaceRole::Admin && other.user_id.is_some())
+}
+
+/// End a user's live subscriptions to the notes of a workspace
+async fn revoke_live_access(state: &AppState, workspace_id: Uuid, user_ids: &[Uuid]) -> Result<(), (StatusCode, String)> {
+    let note_ids = state
+        .db
+        .workspace_note_ids(workspace_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    for note_id in note_ids {
+        for &user_id in user_ids {
+            state.live.publish(note_id, LiveEvent::Revoked { user_id });
+        }
+    }
+    Ok(())
+}
+
+pub async fn list_workspaces(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<WorkspacesResponse>, (StatusCode, String)> {
+    let workspaces = state
+        .db
+        .list_workspaces(auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+
+    Ok(Json(WorkspacesResponse {
+        workspaces: workspaces.into_iter().map(WorkspaceResponse::from).collect(),
+    }))
+}
+
+/// Create a workspace; its creator becomes its first admin
+pub async fn create_workspace(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Json(payload): Json<WorkspaceRequest>,
+) -> Result<Json<WorkspaceResponse>, (StatusCode, String)> {
+    let name = workspace_name(&payload.name)?;
+    let user = state
+        .db
+        .get_user_by_id(auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "User not found".to_string()))?;
+
+    let workspace = state
+        .db
+        .create_workspace(name, user.id, &user.email)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create workspace: {}", e)))?;
+
+    Ok(Json(MemberWorkspace { workspace, role: WorkspaceRole::Admin }.into()))
+}
+
+/// The workspace and its members; visible to all of them
+pub async fn get_workspace(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(workspace_id): Path<Uuid>,
+) -> Result<Json<WorkspaceDetailsResponse>, (StatusCode, String)> {
+    let workspace = auth_user.workspace(&state.db, workspace_id).await?;
+    let members = workspace_members(&state, workspace_id).await?;
+
+    Ok(Json(WorkspaceDetailsResponse {
+        workspace: workspace.into(),
+        members: members.into_iter().map(WorkspaceMemberResponse::from).collect(),
+    }))
+}
+
+pub async fn rename_workspace(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(workspace_id): Path<Uuid>,
+    Json(payload): Json<WorkspaceRequest>,
+) -> Result<Json<WorkspaceResponse>, (StatusCode, String)> {
+    let MemberWorkspace { role, .. } = auth_user.workspace_admin(&state.db, workspace_id).await?;
+    let name = workspace_name(&payload.name)?;
+
+    let workspace = state
+        .db
+        .rename_workspace(workspace_id, name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to rename workspace: {}", e)))?;
+
+    Ok(Json(MemberWorkspace { workspace, role }.into()))
+}
+
+/// Delete a workspace and all of its notes
+pub async fn delete_workspace(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(workspace_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    auth_user.workspace_admin(&state.db, workspace_id).await?;
+
+    let members = workspace_members(&state, workspace_id).await?;
+    let user_ids: Vec<Uuid> = members.iter().filter_map(|member| member.user_id).collect();
+    revoke_live_access(&state, workspace_id, &user_ids).await?;
+
+    state
+        .db
+        .delete_workspace(workspace_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete workspace: {}", e)))?;
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// Add someone by email, or change the role of an existing member
+pub async fn add_workspace_member(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path(workspace_id): Path<Uuid>,
+    Json(payload): Json<AddWorkspaceMemberRequest>,
+) -> Result<Json<WorkspaceMemberResponse>, (StatusCode, String)> {
+    auth_user.workspace_admin(&state.db, workspace_id).await?;
+
+    let email = payload.email.trim();
+    if !email.contains('@') {
+        return Err((StatusCode::BAD_REQUEST, "Invalid email address".to_string()));
+    }
+
+    let members = workspace_members(&state, workspace_id).await?;
+    let existing = members.iter().find(|member| member.email.eq_ignore_ascii_case(email));
+    if payload.role != WorkspaceRole::Admin && existing.is_some_and(|member| is_last_admin(&members, member)) {
+        return Err((StatusCode::CONFLICT, "A workspace needs at least one admin".to_string()));
+    }
+
+    let member = state
+        .db
+        .upsert_workspace_member(workspace_id, email, payload.role, auth_user.user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to add member: {}", e)))?;
+
+    Ok(Json(member.into()))
+}
+
+/// Remove someone from a workspace. Members may also leave on their own.
+pub async fn remove_workspace_member(
+    State(state): State<Arc<AppState>>,
+    auth_user: AuthUser,
+    Path((workspace_id, member_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let MemberWorkspace { role, .. } = auth_user.workspace(&state.db, workspace_id).await?;
+
+    let members = workspace_members(&state, workspace_id).await?;
+    let member = members
+        .iter()
+        .find(|member| member.id == member_id)
+        .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    if role != WorkspaceRole::Admin && member.user_id != Some(auth_user.user_id) {
+        return Err((StatusCode::FORBIDDEN, "Only workspace admins can remove other members".to_string()));
+    }
+    if is_last_admin(&members, member) {
+        return Err((
+            StatusCode::CONFLICT,
+            "A workspace needs at least one admin; delete the workspace instead".to_string(),
+        ));
+    }
+
+    let member = state
+        .db
+        .delete_workspace_member(member_id, workspace_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to remove member: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Member not found".to_string()))?;
+
+    if let Some(user_id) = member.user_id {
+        revoke_live_access(&state, workspace_id, &[user_id]).await?;
+    }
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
@@ -0,0 +1,339 @@
+// Server-side handling of the Yjs documents stored in `notes.content`
+use chrono::NaiveDate;
+use yrs::types::text::YChange;
+use yrs::updates::decoder::Decode;
+use yrs::{
+    Any, Doc, Out, ReadTxn, StateVector, Text, Transact, Update, Xml, XmlElementRef, XmlFragment, XmlFragmentRef, XmlOut,
+    XmlTextRef,
+};
+
+use crate::sync::recurrence::{self, Rule};
+
+// Stored documents are read the same way the note renderers read them
+pub use pdtodo_document::{load, DocumentError, CONTENT_FRAGMENT};
+
+/// Format of a task's `dueDate` attribute
+pub const DUE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Longest tag name, in characters (`tags.name`)
+pub const MAX_TAG_LENGTH: usize = 255;
+
+fn apply(doc: &Doc, update: &[u8]) -> Result<(), DocumentError> {
+    // Notes created without content store an empty byte string
+    if update.is_empty() {
+        return Ok(());
+    }
+    let update = Update::decode_v1(update)?;
+    doc.transact_mut().apply_update(update)?;
+    Ok(())
+}
+
+/// Merge a client update into the stored document state and return the new state
+pub fn merge_update(content: &[u8], update: &[u8]) -> Result<Vec<u8>, DocumentError> {
+    let doc = load(content)?;
+    apply(&doc, update)?;
+    let txn = doc.transact();
+    Ok(txn.encode_state_as_update_v1(&StateVector::default()))
+}
+
+/// Extract the plain text of a document, one line per block, for search indexing
+pub fn extract_text(content: &[u8]) -> Result<String, DocumentError> {
+    let doc = load(content)?;
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+    let txn = doc.transact();
+
+    let mut text = String::new();
+    collect_children(&txn, &fragment, &mut text);
+    Ok(text.trim_end().to_string())
+}
+
+/// Text to store in `notes.content_text`. Content that can't be decoded is
+/// indexed as empty rather than rejected, matching how it was stored before.
+pub fn search_text(content: &[u8]) -> String {
+    extract_text(content).unwrap_or_else(|e| {
+        tracing::warn!("Failed to extract note text: {}", e);
+        String::new()
+    })
+}
+
+/// The `#hashtags` in a note's plain text (see `extract_text`), each once,
+/// compared without case and keeping the first spelling. The rules match
+/// the desktop app: a hashtag starts at the beginning of a line or after
+/// whitespace or an opening bracket, runs over letters, digits, `_`, `-` and
+/// `/`, and must contain a letter. Longer names than a tag can have are
+/// skipped.
+pub fn hashtags(text: &str) -> Vec<String> {
+    let is_tag_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '/');
+    let mut tags: Vec<String> = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let at_boundary = !matches!(previous, Some(p) if !p.is_whitespace() && !matches!(p, '(' | '['));
+        previous = Some(c);
+        if c != '#' || !at_boundary {
+            continue;
+        }
+
+        let mut end = start + 1;
+        while let Some((index, c)) = chars.next_if(|&(_, c)| is_tag_char(c)) {
+            end = index + c.len_utf8();
+            previous = Some(c);
+        }
+        // Trailing separators are punctuation, not part of the tag
+        let tag = text[start + 1..end].trim_end_matches(['-', '/']);
+        if tag.chars().any(char::is_alphabetic)
+            && tag.chars().count() <= MAX_TAG_LENGTH
+            && !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase())
+        {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// A task item in a note's document. `path` holds the child index of each
+/// element from the content fragment down to the task item, which is how
+/// tasks are addressed (the desktop app uses the same scheme).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskItem {
+    pub path: Vec<u32>,
+    /// Number of task items the task is nested in
+    pub depth: u32,
+    pub text: String,
+    pub checked: bool,
+    /// `dueDate` attribute (`YYYY-MM-DD`)
+    pub due_date: Option<NaiveDate>,
+    /// `remindAt` attribute, milliseconds since the epoch
+    pub remind_at: Option<i64>,
+    /// `repeat` attribute, a recurrence rule (see `recurrence`)
+    pub repeat: Option<String>,
+}
+
+/// Format a task path as stored in `tasks.path`, e.g. `2.0.1`
+pub fn task_path(path: &[u32]) -> String {
+    path.iter().map(u32::to_string).collect::<Vec<_>>().join(".")
+}
+
+pub fn parse_task_path(path: &str) -> Option<Vec<u32>> {
+    path.split('.').map(|index| index.parse().ok()).collect()
+}
+
+/// The task items of a document in document order
+pub fn tasks(content: &[u8]) -> Result<Vec<TaskItem>, DocumentError> {
+    let doc = load(content)?;
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+    let txn = doc.transact();
+
+    let mut tasks = Vec::new();
+    collect_tasks(&txn, &fragment, &mut Vec::new(), 0, &mut tasks);
+    Ok(tasks)
+}
+
+/// A change made to a stored document on the server
+pub struct Edit {
+    /// The new document state
+    pub content: Vec<u8>,
+    /// The update that made the change, for clients with the document open
+    pub update: Vec<u8>,
+}
+
+/// Check or uncheck the task item at `path`. Returns `None` if there's no
+/// task item with the given text at the path (the note changed since it
+/// was indexed).
+pub fn set_task_checked(
+    content: &[u8],
+    path: &[u32],
+    text: &str,
+    checked: bool,
+) -> Result<Option<Edit>, DocumentError> {
+    let doc = load(content)?;
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+
+    let update = {
+        let mut txn = doc.transact_mut();
+        let Some(item) = element_at(&txn, &fragment, path) else {
+            return Ok(None);
+        };
+        if item.tag().as_ref() != "taskItem" || task_text(&txn, &item) != text {
+            return Ok(None);
+        }
+        item.insert_attribute(&mut txn, "checked", checked);
+        txn.encode_update_v1()
+    };
+
+    let content = doc.transact().encode_state_as_update_v1(&StateVector::default());
+    Ok(Some(Edit { content, update }))
+}
+
+/// Move the checked repeating tasks of a document to their next
+/// occurrence: each is unchecked and gets the next due date, and its
+/// reminder moves by as many days. A task without a due date repeats from
+/// `today`, and one whose rule has run out stays checked. Returns `None` if
+/// no task was moved.
+pub fn advance_recurring(content: &[u8], today: NaiveDate) -> Result<Option<Edit>, DocumentError> {
+    let doc = load(content)?;
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+
+    let update = {
+        let mut txn = doc.transact_mut();
+        let mut items = Vec::new();
+        collect_tasks(&txn, &fragment, &mut Vec::new(), 0, &mut items);
+
+        let mut advanced = false;
+        for item in items.iter().filter(|item| item.checked) {
+            let Some(rule) = item.repeat.as_deref().and_then(Rule::parse) else {
+                continue;
+            };
+            let due = item.due_date.unwrap_or(today);
+            let Some((next, next_rule)) = recurrence::next_occurrence(&rule, due, today) else {
+                continue;
+            };
+            let Some(element) = element_at(&txn, &fragment, &item.path) else {
+                continue;
+            };
+
+            element.insert_attribute(&mut txn, "checked", false);
+            element.insert_attribute(&mut txn, "dueDate", next.format(DUE_DATE_FORMAT).to_string());
+            if let Some(time) = item.remind_at {
+                let moved = time + (next - due).num_milliseconds();
+                element.insert_attribute(&mut txn, "remindAt", moved as f64);
+            }
+            // Only a counted rule changes between occurrences
+            if next_rule != rule {
+                element.insert_attribute(&mut txn, "repeat", next_rule.to_string());
+            }
+            advanced = true;
+        }
+        if !advanced {
+            return Ok(None);
+        }
+        txn.encode_update_v1()
+    };
+
+    let content = doc.transact().encode_state_as_update_v1(&StateVector::default());
+    Ok(Some(Edit { content, update }))
+}
+
+fn collect_tasks<T: ReadTxn, F: XmlFragment>(
+    txn: &T,
+    parent: &F,
+    path: &mut Vec<u32>,
+    depth: u32,
+    tasks: &mut Vec<TaskItem>,
+) {
+    for i in 0..parent.len(txn) {
+        let Some(XmlOut::Element(element)) = parent.get(txn, i) else {
+            continue;
+        };
+        path.push(i);
+        if element.tag().as_ref() == "taskItem" {
+            tasks.push(TaskItem {
+                path: path.clone(),
+                depth,
+                text: task_text(txn, &element),
+                checked: attr_bool(txn, &element, "checked"),
+                due_date: attr_string(txn, &element, "dueDate")
+                    .and_then(|value| NaiveDate::parse_from_str(&value, DUE_DATE_FORMAT).ok()),
+                remind_at: attr_number(txn, &element, "remindAt").map(|time| time as i64),
+                repeat: attr_string(txn, &element, "repeat"),
+            });
+            collect_tasks(txn, &element, path, depth + 1, tasks);
+        } else {
+            collect_tasks(txn, &element, path, depth, tasks);
+        }
+        path.pop();
+    }
+}
+
+fn element_at<T: ReadTxn>(txn: &T, fragment: &XmlFragmentRef, path: &[u32]) -> Option<XmlElementRef> {
+    let (first, rest) = path.split_first()?;
+    let Some(XmlOut::Element(mut element)) = fragment.get(txn, *first) else {
+        return None;
+    };
+    for index in rest {
+        let Some(XmlOut::Element(child)) = element.get(txn, *index) else {
+            return None;
+        };
+        element = child;
+    }
+    Some(element)
+}
+
+fn attr_bool<T: ReadTxn>(txn: &T, element: &XmlElementRef, name: &str) -> bool {
+    match element.get_attribute(txn, name) {
+        Some(Out::Any(Any::Bool(value))) => value,
+        Some(Out::Any(Any::String(value))) => value.as_ref() == "true",
+        _ => false,
+    }
+}
+
+fn attr_number<T: ReadTxn>(txn: &T, element: &XmlElementRef, name: &str) -> Option<f64> {
+    match element.get_attribute(txn, name) {
+        Some(Out::Any(Any::Number(value))) => Some(value),
+        Some(Out::Any(Any::BigInt(value))) => Some(value as f64),
+        Some(Out::Any(Any::String(value))) => value.parse().ok(),
+        _ => None,
+    }
+}
+
+fn attr_string<T: ReadTxn>(txn: &T, element: &XmlElementRef, name: &str) -> Option<String> {
+    match element.get_attribute(txn, name) {
+        Some(Out::Any(Any::String(value))) if !value.is_empty() => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Text of a task's own paragraphs, leaving out nested lists
+fn task_text<T: ReadTxn>(txn: &T, item: &XmlElementRef) -> String {
+    let mut parts = Vec::new();
+    for i in 0..item.len(txn) {
+        if let Some(XmlOut::Element(child)) = item.get(txn, i) {
+            if matches!(child.tag().as_ref(), "paragraph" | "heading") {
+                let mut text = String::new();
+                collect_inline_text(txn, &child, &mut text);
+                if !text.trim().is_empty() {
+                    parts.push(text.trim().to_string());
+                }
+            }
+        }
+    }
+    parts.join(" ")
+}
+
+fn collect_inline_text<T: ReadTxn>(txn: &T, element: &XmlElementRef, out: &mut String) {
+    for i in 0..element.len(txn) {
+        match element.get(txn, i) {
+            Some(XmlOut::Text(text)) => out.push_str(&text_content(txn, &text)),
+            Some(XmlOut::Element(child)) => collect_inline_text(txn, &child, out),
+            _ => {}
+        }
+    }
+}
+
+fn collect_children<T: ReadTxn, F: XmlFragment>(txn: &T, parent: &F, out: &mut String) {
+    for i in 0..parent.len(txn) {
+        match parent.get(txn, i) {
+            Some(XmlOut::Text(text)) => out.push_str(&text_content(txn, &text)),
+            Some(XmlOut::Element(element)) => {
+                collect_children(txn, &element, out);
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+            }
+            Some(XmlOut::Fragment(fragment)) => collect_children(txn, &fragment, out),
+            None => {}
+        }
+    }
+}
+
+/// Text of an XmlText node without its formatting marks
+fn text_content<T: ReadTxn>(txn: &T, text: &XmlTextRef) -> String {
+    text.diff(txn, YChange::identity)
+        .into_iter()
+        .filter_map(|chunk| match chunk.insert {
+            Out::Any(Any::String(s)) => Some(s.to_string()),
+            _ => None,
+        })
+        .collect()
+}
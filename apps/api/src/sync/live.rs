@@ -0,0 +1,59 @@
+// Live collaboration: fan-out of note updates to WebSocket subscribers.
+//
+// Each note with subscribers has a broadcast channel. Updates pushed over
+// HTTP or a socket are published to it once stored, so everyone editing
+// the note, owner or collaborator, receives them straight away.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Updates a slow subscriber may fall behind by before it misses some (it
+/// catches up with /sync/pull)
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone)]
+pub enum LiveEvent {
+    /// A Yjs update, base64 encoded. `origin` is the connection that sent
+    /// it, which doesn't need it back; HTTP pushes have none.
+    Update { update: Arc<String>, origin: Option<Uuid> },
+    /// The user's access to the note was removed
+    Revoked { user_id: Uuid },
+}
+
+#[derive(Default)]
+pub struct LiveHub {
+    channels: Mutex<HashMap<Uuid, broadcast::Sender<LiveEvent>>>,
+}
+
+impl LiveHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, note_id: Uuid) -> broadcast::Receiver<LiveEvent> {
+        let mut channels = self.channels.lock().unwrap();
+        channels
+            .entry(note_id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    pub fn publish(&self, note_id: Uuid, event: LiveEvent) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(&note_id) {
+            // Fails only when nobody is listening any more
+            if sender.send(event).is_err() {
+                channels.remove(&note_id);
+            }
+        }
+    }
+
+    /// Drop the channel of a note once its last subscriber has gone
+    pub fn release(&self, note_id: Uuid) {
+        let mut channels = self.channels.lock().unwrap();
+        if channels.get(&note_id).is_some_and(|sender| sender.receiver_count() == 0) {
+            channels.remove(&note_id);
+        }
+    }
+}
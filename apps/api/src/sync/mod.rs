@@ -1,4 +1,8 @@
 // Yjs sync handling module
+pub mod document;
+pub mod live;
+pub mod recurrence;
+
 // Re-export types from routes/sync for use by other modules if needed
 pub use crate::routes::sync::{
     SyncPushRequest, SyncPushResponse, SyncPullRequest, SyncPullResponse, NewNote, UpdateItem,
@@ -1,6 +1,12 @@
+use crate::export::{self, ExportFormat, ExportReport};
+use crate::import::{self, ImportReport};
 use crate::logging::{AppLogger, LogEntry};
-use crate::storage::{NoteMeta, Note, Storage};
+use crate::storage::{
+    Attachment, Folder, LinkPreview, NoteMeta, Note, SavedSearch, Storage, StorageLayout, Tag, Task, TaskCounts,
+};
+use crate::unfurl;
 use serde::Serialize;
+use std::path::{Path, PathBuf};
 use tauri::{Manager, State};
 
 #[tauri::command]
@@ -18,9 +24,14 @@ pub fn get_note(storage: State<Storage>, note_id: String) -> Result<Note, String
 }
 
 #[tauri::command]
-pub fn create_note(storage: State<Storage>, logger: State<AppLogger>, title: String) -> Result<String, String> {
+pub fn create_note(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    title: String,
+    folder_id: Option<String>,
+) -> Result<String, String> {
     let result = storage
-        .create_note(&title)
+        .create_note(&title, folder_id.as_deref())
         .map_err(|e| e.to_string());
 
     if let Ok(ref note_id) = result {
@@ -29,19 +40,24 @@ pub fn create_note(storage: State<Storage>, logger: State<AppLogger>, title: Str
     result
 }
 
+/// Rename a note. Returns the ids of the notes whose links to it were
+/// rewritten.
 #[tauri::command]
 pub fn update_note_title(
     storage: State<Storage>,
     logger: State<AppLogger>,
     note_id: String,
     title: String,
-) -> Result<(), String> {
+) -> Result<Vec<String>, String> {
     let result = storage
         .update_note_title(&note_id, &title)
         .map_err(|e| e.to_string());
 
-    if result.is_ok() {
+    if let Ok(renamed) = &result {
         logger.info("notes", &format!("Updated title: {} -> {}", note_id, title));
+        if !renamed.is_empty() {
+            logger.info("notes", &format!("Rewrote links to {} in {} notes", note_id, renamed.len()));
+        }
     }
     result
 }
@@ -64,13 +80,15 @@ pub fn update_note_starred(
     result
 }
 
+/// Returns whether repeating tasks were moved to their next occurrence, so
+/// the editor should merge the stored content
 #[tauri::command]
 pub fn update_note_content(
     storage: State<Storage>,
     logger: State<AppLogger>,
     note_id: String,
     content: Vec<u8>,
-) -> Result<(), String> {
+) -> Result<bool, String> {
     let result = storage
         .update_note_content(&note_id, &content)
         .map_err(|e| e.to_string());
@@ -129,6 +147,264 @@ pub fn duplicate_note(storage: State<Storage>, logger: State<AppLogger>, note_id
     result
 }
 
+// Folder commands
+
+#[tauri::command]
+pub fn get_folders(storage: State<Storage>) -> Result<Vec<Folder>, String> {
+    storage
+        .get_folders()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_folder(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    name: String,
+    parent_id: Option<String>,
+) -> Result<String, String> {
+    let result = storage
+        .create_folder(&name, parent_id.as_deref())
+        .map_err(|e| e.to_string());
+
+    if let Ok(ref folder_id) = result {
+        logger.info("folders", &format!("Created folder: {} ({})", name, folder_id));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn rename_folder(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    folder_id: String,
+    name: String,
+) -> Result<(), String> {
+    let result = storage
+        .rename_folder(&folder_id, &name)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("folders", &format!("Renamed folder: {} -> {}", folder_id, name));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn move_folder(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    folder_id: String,
+    parent_id: Option<String>,
+) -> Result<(), String> {
+    let result = storage
+        .move_folder(&folder_id, parent_id.as_deref())
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        let target = parent_id.as_deref().unwrap_or("top level");
+        logger.info("folders", &format!("Moved folder {} to {}", folder_id, target));
+    }
+    result
+}
+
+/// Delete a folder and its subfolders; their notes go to the trash
+#[tauri::command]
+pub fn delete_folder(storage: State<Storage>, logger: State<AppLogger>, folder_id: String) -> Result<usize, String> {
+    let result = storage
+        .delete_folder(&folder_id)
+        .map_err(|e| e.to_string());
+
+    if let Ok(trashed) = result {
+        logger.info("folders", &format!("Deleted folder {} ({} notes moved to trash)", folder_id, trashed));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn move_note(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    note_id: String,
+    folder_id: Option<String>,
+) -> Result<(), String> {
+    let result = storage
+        .move_note(&note_id, folder_id.as_deref())
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        let target = folder_id.as_deref().unwrap_or("no folder");
+        logger.info("folders", &format!("Moved note {} to {}", note_id, target));
+    }
+    result
+}
+
+// Tag commands
+
+#[tauri::command]
+pub fn get_tags(storage: State<Storage>) -> Result<Vec<Tag>, String> {
+    storage
+        .get_tags()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_note_tags(storage: State<Storage>, note_id: String) -> Result<Vec<String>, String> {
+    storage
+        .get_note_tags(&note_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn add_note_tag(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    note_id: String,
+    name: String,
+) -> Result<String, String> {
+    let result = storage
+        .add_note_tag(&note_id, &name)
+        .map_err(|e| e.to_string());
+
+    if let Ok(ref tag) = result {
+        logger.info("tags", &format!("Tagged {} with {}", note_id, tag));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn remove_note_tag(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    note_id: String,
+    name: String,
+) -> Result<(), String> {
+    let result = storage
+        .remove_note_tag(&note_id, &name)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("tags", &format!("Removed tag {} from {}", name, note_id));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn rename_tag(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    tag_id: String,
+    name: String,
+) -> Result<(), String> {
+    let result = storage
+        .rename_tag(&tag_id, &name)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("tags", &format!("Renamed tag: {} -> {}", tag_id, name));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn delete_tag(storage: State<Storage>, logger: State<AppLogger>, tag_id: String) -> Result<(), String> {
+    let result = storage
+        .delete_tag(&tag_id)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("tags", &format!("Deleted tag: {}", tag_id));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn get_open_tasks(storage: State<Storage>) -> Result<Vec<Task>, String> {
+    storage
+        .get_open_tasks()
+        .map_err(|e| e.to_string())
+}
+
+/// Open tasks due today or overdue
+#[tauri::command]
+pub fn get_today_tasks(storage: State<Storage>) -> Result<Vec<Task>, String> {
+    storage
+        .get_today_tasks()
+        .map_err(|e| e.to_string())
+}
+
+/// Open tasks due in the next week
+#[tauri::command]
+pub fn get_upcoming_tasks(storage: State<Storage>) -> Result<Vec<Task>, String> {
+    storage
+        .get_upcoming_tasks()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_recently_completed_tasks(storage: State<Storage>) -> Result<Vec<Task>, String> {
+    storage
+        .get_recently_completed_tasks()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_note_tasks(storage: State<Storage>, note_id: String) -> Result<Vec<Task>, String> {
+    storage
+        .get_note_tasks(&note_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_task_counts(storage: State<Storage>) -> Result<Vec<TaskCounts>, String> {
+    storage
+        .get_task_counts()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn toggle_task(storage: State<Storage>, logger: State<AppLogger>, task_id: String) -> Result<Task, String> {
+    let result = storage
+        .toggle_task(&task_id)
+        .map_err(|e| e.to_string());
+
+    if let Ok(ref task) = result {
+        let state = if task.checked { "Completed" } else { "Reopened" };
+        logger.info("tasks", &format!("{} task {}", state, task.id));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn complete_task(storage: State<Storage>, logger: State<AppLogger>, task_id: String) -> Result<Task, String> {
+    let result = storage
+        .complete_task(&task_id)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("tasks", &format!("Completed task {}", task_id));
+    }
+    result
+}
+
+/// Remind of a task again at `until` (milliseconds since the epoch)
+#[tauri::command]
+pub fn snooze_task(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    task_id: String,
+    until: i64,
+) -> Result<Task, String> {
+    let result = storage
+        .snooze_task(&task_id, until)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("tasks", &format!("Snoozed task {}", task_id));
+    }
+    result
+}
+
 #[tauri::command]
 pub fn search_notes(storage: State<Storage>, query: String) -> Result<Vec<NoteMeta>, String> {
     storage
@@ -136,6 +412,357 @@ pub fn search_notes(storage: State<Storage>, query: String) -> Result<Vec<NoteMe
         .map_err(|e| e.to_string())
 }
 
+// Attachment commands
+
+#[tauri::command]
+pub fn get_note_attachments(storage: State<Storage>, note_id: String) -> Result<Vec<Attachment>, String> {
+    storage
+        .get_note_attachments(&note_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Attach files from disk, e.g. picked in a file dialog
+#[tauri::command]
+pub fn add_attachment_files(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    note_id: String,
+    paths: Vec<String>,
+) -> Result<Vec<Attachment>, String> {
+    let mut attachments = Vec::new();
+    for path in &paths {
+        let attachment = storage
+            .add_attachment_file(&note_id, Path::new(path))
+            .map_err(|e| e.to_string())?;
+        logger.info("attachments", &format!("Attached {} to {}", path, note_id));
+        attachments.push(attachment);
+    }
+    Ok(attachments)
+}
+
+/// Attach data that isn't a file on disk, such as a pasted image
+#[tauri::command]
+pub fn add_attachment(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    note_id: String,
+    file_name: String,
+    content_type: Option<String>,
+    data: Vec<u8>,
+) -> Result<Attachment, String> {
+    let result = storage
+        .add_attachment(&note_id, &file_name, content_type.as_deref(), &data)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("attachments", &format!("Attached {} to {}", file_name, note_id));
+    }
+    result
+}
+
+/// Save a copy of an attachment to `path`
+#[tauri::command]
+pub fn save_attachment(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    attachment_id: String,
+    path: String,
+) -> Result<(), String> {
+    let (_, data) = storage
+        .read_attachment(&attachment_id)
+        .map_err(|e| e.to_string())?;
+    std::fs::write(&path, data).map_err(|e| e.to_string())?;
+
+    logger.info("attachments", &format!("Saved attachment {} to {}", attachment_id, path));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_attachment(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    attachment_id: String,
+) -> Result<(), String> {
+    let result = storage
+        .remove_attachment(&attachment_id)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("attachments", &format!("Removed attachment {}", attachment_id));
+    }
+    result
+}
+
+// Link commands
+
+/// Notes outside the trash that link to a note
+#[tauri::command]
+pub fn get_backlinks(storage: State<Storage>, note_id: String) -> Result<Vec<NoteMeta>, String> {
+    storage
+        .get_backlinks(&note_id)
+        .map_err(|e| e.to_string())
+}
+
+// Saved search commands
+
+#[tauri::command]
+pub fn get_saved_searches(storage: State<Storage>) -> Result<Vec<SavedSearch>, String> {
+    storage
+        .get_saved_searches()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_saved_search(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    name: String,
+    query: String,
+) -> Result<String, String> {
+    let result = storage
+        .create_saved_search(&name, &query)
+        .map_err(|e| e.to_string());
+
+    if let Ok(ref search_id) = result {
+        logger.info("search", &format!("Saved search: {} ({})", name, search_id));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn update_saved_search(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    search_id: String,
+    name: String,
+    query: String,
+) -> Result<(), String> {
+    let result = storage
+        .update_saved_search(&search_id, &name, &query)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("search", &format!("Updated saved search: {} -> {}", search_id, name));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn delete_saved_search(storage: State<Storage>, logger: State<AppLogger>, search_id: String) -> Result<(), String> {
+    let result = storage
+        .delete_saved_search(&search_id)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("search", &format!("Deleted saved search: {}", search_id));
+    }
+    result
+}
+
+/// The notes a saved search finds now, as `search_notes` would
+#[tauri::command]
+pub fn run_saved_search(storage: State<Storage>, search_id: String) -> Result<Vec<NoteMeta>, String> {
+    storage
+        .run_saved_search(&search_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_storage_layout(storage: State<Storage>) -> StorageLayout {
+    storage.storage_layout()
+}
+
+#[tauri::command]
+pub fn set_storage_layout(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    layout: StorageLayout,
+) -> Result<(), String> {
+    let result = storage
+        .set_storage_layout(layout)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("storage", &format!("Switched storage layout to {}", layout.as_str()));
+    }
+    result
+}
+
+#[tauri::command]
+pub fn get_offline_mode(storage: State<Storage>) -> Result<bool, String> {
+    storage.offline_mode().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_offline_mode(storage: State<Storage>, logger: State<AppLogger>, offline: bool) -> Result<(), String> {
+    let result = storage
+        .set_offline_mode(offline)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("settings", if offline { "Turned offline mode on" } else { "Turned offline mode off" });
+    }
+    result
+}
+
+// Library encryption commands
+
+#[derive(Debug, Serialize)]
+pub struct EncryptionStatus {
+    pub encrypted: bool,
+    pub unlocked: bool,
+}
+
+#[tauri::command]
+pub fn get_encryption_status(app_handle: tauri::AppHandle) -> Result<EncryptionStatus, String> {
+    let app_data_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+
+    Ok(EncryptionStatus {
+        encrypted: Storage::is_encrypted(&app_data_dir),
+        unlocked: app_handle.try_state::<Storage>().is_some(),
+    })
+}
+
+#[tauri::command]
+pub fn unlock_library(
+    app_handle: tauri::AppHandle,
+    logger: State<AppLogger>,
+    passphrase: String,
+) -> Result<(), String> {
+    if app_handle.try_state::<Storage>().is_some() {
+        return Ok(());
+    }
+
+    let app_data_dir = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+    let storage = Storage::unlock(&app_data_dir, &passphrase).map_err(|e| {
+        logger.warn("storage", &format!("Failed to unlock note library: {}", e));
+        e.to_string()
+    })?;
+    crate::prepare_storage(&storage, &logger).map_err(|e| e.to_string())?;
+
+    app_handle.manage(storage);
+    logger.info("storage", "Unlocked note library");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn enable_encryption(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    passphrase: String,
+) -> Result<(), String> {
+    let result = storage
+        .enable_encryption(&passphrase)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("storage", "Enabled note library encryption");
+    }
+    result
+}
+
+#[tauri::command]
+pub fn disable_encryption(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    passphrase: String,
+) -> Result<(), String> {
+    let result = storage
+        .disable_encryption(&passphrase)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("storage", "Disabled note library encryption");
+    }
+    result
+}
+
+#[tauri::command]
+pub fn change_passphrase(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    current_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), String> {
+    let result = storage
+        .change_passphrase(&current_passphrase, &new_passphrase)
+        .map_err(|e| e.to_string());
+
+    if result.is_ok() {
+        logger.info("storage", "Changed note library passphrase");
+    }
+    result
+}
+
+// Export commands
+
+/// Export one note to `path`, in the format its extension names
+#[tauri::command]
+pub fn export_note(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    note_id: String,
+    path: String,
+) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    let format = ExportFormat::from_path(&path)
+        .ok_or_else(|| format!("Unsupported export format: {}", path.display()))?;
+
+    let result = export::export_note(&storage, &note_id, &path, format).map_err(|e| e.to_string());
+    match &result {
+        Ok(()) => logger.info("export", &format!("Exported {} to {}", note_id, path.display())),
+        Err(e) => logger.error("export", &format!("Failed to export {}: {}", note_id, e)),
+    }
+    result
+}
+
+/// Export every note that isn't in the trash
+#[tauri::command]
+pub fn export_all(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    directory: String,
+    format: ExportFormat,
+) -> Result<ExportReport, String> {
+    let note_ids: Vec<String> = storage
+        .get_notes(false)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|note| note.id)
+        .collect();
+
+    let report = export::export_notes(&storage, &note_ids, Path::new(&directory), format)
+        .map_err(|e| e.to_string())?;
+
+    for failure in &report.failed {
+        logger.error("export", &format!("Failed to export {}: {}", failure.note_id, failure.error));
+    }
+    logger.info(
+        "export",
+        &format!("Exported {} notes to {}", report.exported.len(), directory),
+    );
+    Ok(report)
+}
+
+// Import commands
+
+/// Import Markdown and text files; `paths` may include folders
+#[tauri::command]
+pub fn import_notes(
+    storage: State<Storage>,
+    logger: State<AppLogger>,
+    paths: Vec<String>,
+) -> Result<ImportReport, String> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    let report = import::import_files(&storage, &paths).map_err(|e| e.to_string())?;
+
+    for failure in &report.failed {
+        logger.error("import", &format!("Failed to import {}: {}", failure.path, failure.error));
+    }
+    logger.info("import", &format!("Imported {} notes", report.imported.len()));
+    Ok(report)
+}
+
 // App info and logging commands
 
 #[derive(Debug, Serialize)]
@@ -184,47 +811,25 @@ pub fn add_log(logger: State<AppLogger>, level: String, category: String, messag
     logger.log(&level, &category, &message);
 }
 
-/// Fetch the title of a URL by making an HTTP request and extracting the <title> tag
+/// A preview of the web page at `url`: its title, description, site name
+/// and icon. Previews are cached for a while, so hovering a link again
+/// doesn't fetch the page again. In offline mode only cached previews are
+/// returned.
 #[tauri::command]
-pub async fn fetch_url_title(url: String) -> Result<String, String> {
-    // Make HTTP request to fetch the page
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .build()
-        .map_err(|e| e.to_string())?;
+pub async fn unfurl_url(storage: State<'_, Storage>, url: String) -> Result<LinkPreview, String> {
+    if let Some(preview) = storage
+        .get_link_preview(&url, unfurl::CACHE_TTL_MS)
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(preview);
+    }
+    if storage.offline_mode().map_err(|e| e.to_string())? {
+        return Err("Offline mode is on".to_string());
+    }
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (compatible; PDTodo/1.0)")
-        .send()
-        .await
+    let preview = unfurl::unfurl(&url).await.map_err(|e| e.to_string())?;
+    storage
+        .save_link_preview(&preview, unfurl::CACHE_TTL_MS)
         .map_err(|e| e.to_string())?;
-
-    let html = response.text().await.map_err(|e| e.to_string())?;
-
-    // Extract title from HTML using a simple regex-like approach
-    // Look for <title>...</title>
-    let title_start = html.to_lowercase().find("<title>");
-    let title_end = html.to_lowercase().find("</title>");
-
-    match (title_start, title_end) {
-        (Some(start), Some(end)) if end > start => {
-            let title_content = &html[start + 7..end];
-            // Clean up the title (decode HTML entities, trim whitespace)
-            let title = title_content
-                .replace("&amp;", "&")
-                .replace("&lt;", "<")
-                .replace("&gt;", ">")
-                .replace("&quot;", "\"")
-                .replace("&#39;", "'")
-                .replace("&nbsp;", " ")
-                .trim()
-                .to_string();
-            Ok(title)
-        }
-        _ => {
-            // No title found, return the URL as fallback
-            Ok(url)
-        }
-    }
+    Ok(preview)
 }
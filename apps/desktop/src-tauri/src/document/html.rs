@@ -0,0 +1,287 @@
+//! Parsing HTML notes (exports from other apps, Evernote's ENML) into
+//! blocks, alongside the shared renderer.
+
+use ego_tree::NodeRef;
+use scraper::{Html, Node};
+
+use super::{list_blocks, Block, Inline, ListItem, Marks};
+
+pub use pdtodo_document::html::render;
+
+/// Parse an HTML document or fragment, returning its `<title>` if it has one
+pub fn parse(html: &str) -> (Option<String>, Vec<Block>) {
+    let document = Html::parse_document(html);
+
+    let title = document
+        .tree
+        .root()
+        .descendants()
+        .find(|node| matches!(node.value(), Node::Element(element) if element.name() == "title"))
+        .map(|node| collapse_whitespace(&text_of(node)).trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    let mut writer = Writer::default();
+    writer.children(document.tree.root(), &Marks::default());
+    (title, writer.finish())
+}
+
+/// Collects blocks, gathering inline content into paragraphs
+#[derive(Default)]
+struct Writer {
+    blocks: Vec<Block>,
+    inlines: Vec<Inline>,
+    /// Checkbox seen in the current paragraph, which makes it a task
+    todo: Option<bool>,
+    /// Inside a list item the first checkbox marks the item itself
+    in_item: bool,
+    item_checked: Option<bool>,
+}
+
+impl Writer {
+    fn children(&mut self, node: NodeRef<Node>, marks: &Marks) {
+        for child in node.children() {
+            self.node(child, marks);
+        }
+    }
+
+    fn node(&mut self, node: NodeRef<Node>, marks: &Marks) {
+        let element = match node.value() {
+            Node::Text(text) => return self.text(&collapse_whitespace(text), marks),
+            Node::Element(element) => element,
+            _ => return self.children(node, marks),
+        };
+
+        let name = element.name();
+        let style = element.attr("style").unwrap_or_default().to_ascii_lowercase().replace(' ', "");
+        let mut marks = marks.clone();
+
+        match name {
+            "head" | "title" | "script" | "style" | "template" | "noscript" | "img" => {}
+            "br" => self.flush(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.flush();
+                let mut heading = Writer::default();
+                heading.children(node, &marks);
+                self.blocks.push(Block::Heading {
+                    level: (name.as_bytes()[1] - b'0').min(3),
+                    content: trim_inlines(heading.inlines),
+                });
+            }
+            "ul" | "ol" => {
+                self.flush();
+                self.list(node, &marks);
+            }
+            "pre" => {
+                self.flush();
+                for line in text_of(node).trim_end_matches('\n').split('\n') {
+                    self.text(line, &marks);
+                    self.flush();
+                }
+            }
+            "en-todo" => {
+                self.checkbox(element.attr("checked") == Some("true"));
+                // HTML parsing doesn't treat <en-todo/> as self-closing, so
+                // the text after it ends up inside
+                self.children(node, &marks);
+            }
+            "input" if element.attr("type") == Some("checkbox") => {
+                self.checkbox(element.attr("checked").is_some())
+            }
+            "b" | "strong" => {
+                marks.bold = true;
+                self.children(node, &marks);
+            }
+            "u" | "ins" => {
+                marks.underline = true;
+                self.children(node, &marks);
+            }
+            "a" => {
+                if let Some(href) = element.attr("href").filter(|href| !href.is_empty()) {
+                    marks.link = Some(href.to_string());
+                }
+                self.children(node, &marks);
+            }
+            _ if is_block(name) => {
+                self.flush();
+                self.children(node, &marks);
+                self.flush();
+            }
+            _ => {
+                // Editors often format with inline styles instead of tags
+                if style.contains("font-weight:bold") || style.contains("font-weight:700") {
+                    marks.bold = true;
+                }
+                if style.contains("text-decoration:underline") {
+                    marks.underline = true;
+                }
+                self.children(node, &marks);
+            }
+        }
+    }
+
+    fn list(&mut self, node: NodeRef<Node>, marks: &Marks) {
+        let Node::Element(list) = node.value() else {
+            return;
+        };
+        let is_task_list = is_task_list(list);
+
+        let mut items = Vec::new();
+        for child in node.children() {
+            let Node::Element(element) = child.value() else {
+                continue;
+            };
+            if element.name() != "li" {
+                // Nested lists written directly inside the list
+                if let Some(ListItem { blocks, .. }) = items.last_mut() {
+                    let mut nested = Writer::default();
+                    nested.node(child, marks);
+                    blocks.extend(nested.finish());
+                }
+                continue;
+            }
+
+            let mut item = Writer {
+                in_item: true,
+                ..Default::default()
+            };
+            item.children(child, marks);
+            let checked = item_checked(element).or(item.item_checked);
+            items.push(ListItem {
+                checked: if is_task_list { Some(checked.unwrap_or(false)) } else { checked },
+                blocks: item.finish(),
+            });
+        }
+
+        let start = (list.name() == "ol")
+            .then(|| list.attr("start").and_then(|start| start.parse().ok()).unwrap_or(1));
+        self.blocks.extend(list_blocks(start, items));
+    }
+
+    fn checkbox(&mut self, checked: bool) {
+        if self.in_item && self.item_checked.is_none() && self.blocks.is_empty() {
+            self.item_checked = Some(checked);
+        } else {
+            self.flush();
+            self.todo = Some(checked);
+        }
+    }
+
+    fn text(&mut self, text: &str, marks: &Marks) {
+        if text.is_empty() {
+            return;
+        }
+        match self.inlines.last_mut() {
+            Some(last) if last.marks == *marks => last.text.push_str(text),
+            _ => self.inlines.push(Inline {
+                text: text.to_string(),
+                marks: marks.clone(),
+            }),
+        }
+    }
+
+    /// End the current paragraph
+    fn flush(&mut self) {
+        let inlines = trim_inlines(std::mem::take(&mut self.inlines));
+        let todo = self.todo.take();
+        if inlines.is_empty() && todo.is_none() {
+            return;
+        }
+
+        let paragraph = Block::Paragraph(inlines);
+        match todo {
+            // Consecutive checkbox lines form one task list
+            Some(checked) => {
+                let item = ListItem {
+                    checked: Some(checked),
+                    blocks: vec![paragraph],
+                };
+                match self.blocks.last_mut() {
+                    Some(Block::TaskList(items)) => items.push(item),
+                    _ => self.blocks.push(Block::TaskList(vec![item])),
+                }
+            }
+            None => self.blocks.push(paragraph),
+        }
+    }
+
+    fn finish(mut self) -> Vec<Block> {
+        self.flush();
+        self.blocks
+    }
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div" | "section" | "article" | "main" | "header" | "footer" | "aside" | "nav"
+            | "blockquote" | "li" | "dl" | "dt" | "dd" | "table" | "tr" | "td" | "th" | "hr"
+            | "figure" | "figcaption" | "address" | "body" | "html" | "en-note"
+    )
+}
+
+/// Checklist markup used by TipTap, Evernote and Apple Notes exports
+fn is_task_list(list: &scraper::node::Element) -> bool {
+    let class = list.attr("class").unwrap_or_default();
+    let style = list.attr("style").unwrap_or_default().replace(' ', "");
+    list.attr("data-type") == Some("taskList")
+        || class.split_whitespace().any(|class| class == "checklist" || class == "contains-task-list")
+        || style.contains("--en-todo:true")
+}
+
+fn item_checked(item: &scraper::node::Element) -> Option<bool> {
+    if let Some(checked) = item.attr("data-checked") {
+        return Some(checked == "true");
+    }
+    let style = item.attr("style").unwrap_or_default().replace(' ', "");
+    if style.contains("--en-checked:true") {
+        return Some(true);
+    }
+    if style.contains("--en-checked:false") {
+        return Some(false);
+    }
+    let class = item.attr("class").unwrap_or_default();
+    if class.split_whitespace().any(|class| class == "checked" || class == "done") {
+        return Some(true);
+    }
+    None
+}
+
+/// All text below a node, as written
+fn text_of(node: NodeRef<Node>) -> String {
+    node.descendants()
+        .filter_map(|node| match node.value() {
+            Node::Text(text) => Some(&**text),
+            _ => None,
+        })
+        .collect()
+}
+
+/// HTML whitespace rules: any run of whitespace shows as one space
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() && c != '\u{a0}' {
+            if !last_space {
+                out.push(' ');
+            }
+            last_space = true;
+        } else {
+            out.push(c);
+            last_space = false;
+        }
+    }
+    out
+}
+
+/// Drop whitespace at the edges of a paragraph
+fn trim_inlines(mut inlines: Vec<Inline>) -> Vec<Inline> {
+    if let Some(first) = inlines.first_mut() {
+        first.text = first.text.trim_start().to_string();
+    }
+    if let Some(last) = inlines.last_mut() {
+        last.text = last.text.trim_end().to_string();
+    }
+    inlines.retain(|inline| !inline.text.is_empty());
+    inlines
+}
@@ -0,0 +1,141 @@
+//! Links between notes.
+//!
+//! A note links to another by its title in double brackets, e.g.
+//! `[[Deploy runbook]]`. Titles are matched without case, surrounding
+//! whitespace doesn't count, and a link can't span lines or contain
+//! brackets. Links are plain text in the document, so they survive any
+//! editor and export format; renaming a note rewrites the text of the links
+//! to it (see `rename`).
+
+use super::{load, plain_text, Block, DocumentError, CONTENT_FRAGMENT};
+use yrs::types::text::YChange;
+use yrs::types::Attrs;
+use yrs::{Any, Out, ReadTxn, StateVector, Text, Transact, XmlFragment, XmlOut, XmlTextRef};
+
+/// The titles the blocks link to, each once (compared without case,
+/// keeping the first spelling)
+pub fn targets(blocks: &[Block]) -> Vec<String> {
+    let mut titles = Vec::new();
+    collect(blocks, &mut titles);
+    titles
+}
+
+/// Whether a note with this title can be linked to
+pub fn is_linkable(title: &str) -> bool {
+    let title = title.trim();
+    !title.is_empty() && !title.contains(['[', ']', '\n', '\r'])
+}
+
+/// Rewrite the links to `old` in a note's content to link to `new`, keeping
+/// the formatting of the link text. Returns the note's new content, or
+/// `None` if it has no link to `old` (or `new` can't be linked to).
+pub fn rename(content: &[u8], old: &str, new: &str) -> Result<Option<Vec<u8>>, DocumentError> {
+    let old = old.trim().to_lowercase();
+    let new = new.trim();
+    if old.is_empty() || !is_linkable(new) {
+        return Ok(None);
+    }
+
+    let doc = load(content)?;
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+
+    let mut txn = doc.transact_mut();
+    let mut texts = Vec::new();
+    collect_texts(&txn, &fragment, &mut texts);
+
+    let mut renamed = false;
+    for text in texts {
+        let chunks: Vec<(String, Option<Box<Attrs>>)> = text
+            .diff(&txn, YChange::identity)
+            .into_iter()
+            .filter_map(|chunk| match chunk.insert {
+                Out::Any(Any::String(s)) => Some((s.to_string(), chunk.attributes)),
+                _ => None,
+            })
+            .collect();
+        let full: String = chunks.iter().map(|(s, _)| s.as_str()).collect();
+
+        let matches: Vec<_> = spans(&full)
+            .into_iter()
+            .filter(|&(start, end)| full[start..end].to_lowercase() == old)
+            .collect();
+        // Back to front, so earlier offsets stay valid
+        for &(start, end) in matches.iter().rev() {
+            let attrs = attrs_at(&chunks, start).unwrap_or_default();
+            text.remove_range(&mut txn, start as u32, (end - start) as u32);
+            text.insert_with_attributes(&mut txn, start as u32, new, attrs);
+            renamed = true;
+        }
+    }
+
+    Ok(renamed.then(|| txn.encode_state_as_update_v1(&StateVector::default())))
+}
+
+fn collect(blocks: &[Block], titles: &mut Vec<String>) {
+    for block in blocks {
+        match block {
+            Block::Paragraph(content) | Block::Heading { content, .. } => {
+                let text = plain_text(content);
+                for (start, end) in spans(&text) {
+                    let title = &text[start..end];
+                    if !titles.iter().any(|t| t.to_lowercase() == title.to_lowercase()) {
+                        titles.push(title.to_string());
+                    }
+                }
+            }
+            Block::BulletList(items) | Block::OrderedList { items, .. } | Block::TaskList(items) => {
+                for item in items {
+                    collect(&item.blocks, titles);
+                }
+            }
+        }
+    }
+}
+
+/// Byte ranges of the link titles in a text, without surrounding whitespace
+fn spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut rest = 0;
+    while let Some(open) = text[rest..].find("[[").map(|i| rest + i) {
+        let inner = open + 2;
+        let Some(len) = text[inner..].find(['[', ']', '\n', '\r']) else {
+            break;
+        };
+        let close = inner + len;
+        if !text[close..].starts_with("]]") {
+            rest = open + 1;
+            continue;
+        }
+        let title = &text[inner..close];
+        let start = inner + (title.len() - title.trim_start().len());
+        let end = close - (title.len() - title.trim_end().len());
+        if start < end {
+            spans.push((start, end));
+        }
+        rest = close + 2;
+    }
+    spans
+}
+
+/// Formatting of the text at byte `offset`
+fn attrs_at(chunks: &[(String, Option<Box<Attrs>>)], offset: usize) -> Option<Attrs> {
+    let mut start = 0;
+    for (text, attrs) in chunks {
+        if offset < start + text.len() {
+            return attrs.as_deref().cloned();
+        }
+        start += text.len();
+    }
+    None
+}
+
+fn collect_texts<T: ReadTxn, F: XmlFragment>(txn: &T, parent: &F, texts: &mut Vec<XmlTextRef>) {
+    for i in 0..parent.len(txn) {
+        match parent.get(txn, i) {
+            Some(XmlOut::Element(element)) => collect_texts(txn, &element, texts),
+            Some(XmlOut::Fragment(fragment)) => collect_texts(txn, &fragment, texts),
+            Some(XmlOut::Text(text)) => texts.push(text),
+            None => {}
+        }
+    }
+}
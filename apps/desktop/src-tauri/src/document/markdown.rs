@@ -0,0 +1,177 @@
+//! Markdown (CommonMark with GFM task lists) parsing of note blocks,
+//! alongside the shared renderer.
+
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+use super::{list_blocks, Block, Inline, ListItem, Marks};
+
+pub use pdtodo_document::markdown::to_markdown;
+
+/// Parse Markdown into blocks the editor can show. Formatting the editor
+/// has no equivalent for (italics, code, quotes, images) is kept as text.
+pub fn parse(text: &str) -> Vec<Block> {
+    let mut builder = Builder {
+        stack: vec![Frame::Blocks(Vec::new())],
+        ..Default::default()
+    };
+    for event in Parser::new_ext(text, Options::ENABLE_TASKLISTS) {
+        builder.event(event);
+    }
+    builder.finish()
+}
+
+enum Frame {
+    Blocks(Vec<Block>),
+    List { start: Option<u64>, items: Vec<ListItem> },
+    Item(ListItem),
+}
+
+#[derive(Default)]
+struct Builder {
+    /// Open containers; the first is the document itself
+    stack: Vec<Frame>,
+    /// Text of the open paragraph or heading
+    inlines: Option<Vec<Inline>>,
+    heading: Option<u8>,
+    in_code_block: bool,
+    bold: usize,
+    underline: usize,
+    links: Vec<String>,
+}
+
+impl Builder {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(Tag::Paragraph) => self.open_textblock(None),
+            Event::Start(Tag::Heading { level, .. }) => self.open_textblock(Some(heading_level(level))),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_)) => self.close_textblock(),
+            Event::Start(Tag::List(start)) => {
+                self.close_textblock();
+                self.stack.push(Frame::List { start, items: Vec::new() });
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.close_textblock();
+                if let Some(Frame::List { start, items }) = self.stack.pop() {
+                    for block in list_blocks(start, items) {
+                        self.push_block(block);
+                    }
+                }
+            }
+            Event::Start(Tag::Item) => {
+                self.close_textblock();
+                self.stack.push(Frame::Item(ListItem::default()));
+            }
+            Event::End(TagEnd::Item) => {
+                self.close_textblock();
+                if let Some(Frame::Item(item)) = self.stack.pop() {
+                    match self.stack.last_mut() {
+                        Some(Frame::List { items, .. }) => items.push(item),
+                        _ => item.blocks.into_iter().for_each(|block| self.push_block(block)),
+                    }
+                }
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(Frame::Item(item)) = self.stack.last_mut() {
+                    item.checked = Some(checked);
+                }
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                self.close_textblock();
+                self.in_code_block = true;
+            }
+            Event::End(TagEnd::CodeBlock) => self.in_code_block = false,
+            Event::Start(Tag::Strong) => self.bold += 1,
+            Event::End(TagEnd::Strong) => self.bold = self.bold.saturating_sub(1),
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) => {
+                self.links.push(dest_url.to_string())
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                self.links.pop();
+            }
+            Event::Text(text) if self.in_code_block => {
+                // Each line of code becomes its own paragraph
+                for line in text.trim_end_matches('\n').split('\n') {
+                    self.open_textblock(None);
+                    self.text(line);
+                    self.close_textblock();
+                }
+            }
+            Event::Text(text) | Event::Code(text) => self.text(&text),
+            Event::SoftBreak => self.text(" "),
+            Event::HardBreak => self.text("\n"),
+            Event::InlineHtml(html) => self.inline_html(&html),
+            Event::Rule => self.close_textblock(),
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> Vec<Block> {
+        self.close_textblock();
+        match self.stack.drain(..).next() {
+            Some(Frame::Blocks(blocks)) => blocks,
+            _ => Vec::new(),
+        }
+    }
+
+    fn open_textblock(&mut self, heading: Option<u8>) {
+        self.close_textblock();
+        self.inlines = Some(Vec::new());
+        self.heading = heading;
+    }
+
+    fn close_textblock(&mut self) {
+        let Some(content) = self.inlines.take() else {
+            return;
+        };
+        let block = match self.heading.take() {
+            Some(level) => Block::Heading { level, content },
+            None => Block::Paragraph(content),
+        };
+        self.push_block(block);
+    }
+
+    fn push_block(&mut self, block: Block) {
+        match self.stack.last_mut() {
+            Some(Frame::Blocks(blocks)) => blocks.push(block),
+            Some(Frame::Item(item)) => item.blocks.push(block),
+            Some(Frame::List { items, .. }) => items.push(ListItem {
+                checked: None,
+                blocks: vec![block],
+            }),
+            None => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let marks = Marks {
+            bold: self.bold > 0,
+            underline: self.underline > 0,
+            link: self.links.last().cloned(),
+        };
+        // Text directly inside a list item (a tight list) is a paragraph
+        let inlines = self.inlines.get_or_insert_with(Vec::new);
+
+        match inlines.last_mut() {
+            Some(last) if last.marks == marks => last.text.push_str(text),
+            _ => inlines.push(Inline {
+                text: text.to_string(),
+                marks,
+            }),
+        }
+    }
+
+    /// `<u>` is how underline is exported; line breaks are kept too
+    fn inline_html(&mut self, html: &str) {
+        match html.trim().to_ascii_lowercase().as_str() {
+            "<u>" => self.underline += 1,
+            "</u>" => self.underline = self.underline.saturating_sub(1),
+            "<br>" | "<br/>" | "<br />" => self.text("\n"),
+            _ => {}
+        }
+    }
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    // The editor has three heading levels
+    (level as u8).min(3)
+}
@@ -0,0 +1,222 @@
+//! Note content as a tree of blocks.
+//!
+//! Notes are stored as Yjs documents holding the TipTap editor's
+//! ProseMirror structure in the "content" XmlFragment. Reading and rendering
+//! notes is shared with the API (the `pdtodo-document` crate); this module
+//! adds writing blocks back and what the app does with them (imports,
+//! hashtags, links and tasks), so notes move to and from other formats
+//! without going through the editor.
+
+pub mod html;
+pub mod links;
+pub mod markdown;
+pub mod recurrence;
+pub mod tasks;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use yrs::types::Attrs;
+use yrs::{
+    Any, Doc, ReadTxn, StateVector, Text, Transact, TransactionMut, Xml, XmlElementPrelim, XmlElementRef, XmlFragment,
+    XmlTextPrelim,
+};
+
+pub use pdtodo_document::{from_yjs, pdf, Block, DocumentError, Inline, ListItem, Marks, CONTENT_FRAGMENT};
+use pdtodo_document::{attr_bool, attr_number, load, read_inlines};
+
+/// The text of a paragraph or heading without its marks
+pub fn plain_text(inlines: &[Inline]) -> String {
+    inlines.iter().map(|inline| inline.text.as_str()).collect()
+}
+
+/// The `#hashtags` in the text of the blocks, each once (compared without
+/// case, keeping the first spelling). A hashtag starts at the beginning of
+/// a line or after whitespace or an opening bracket, runs over letters,
+/// digits, `_`, `-` and `/`, and must contain a letter, so `#1` and the
+/// fragment in `page#top` are not tags. Linked text is skipped.
+pub fn hashtags(blocks: &[Block]) -> Vec<String> {
+    let mut tags = Vec::new();
+    collect_hashtags(blocks, &mut tags);
+    tags
+}
+
+fn collect_hashtags(blocks: &[Block], tags: &mut Vec<String>) {
+    for block in blocks {
+        match block {
+            Block::Paragraph(content) | Block::Heading { content, .. } => {
+                let text: String = content
+                    .iter()
+                    .map(|inline| if inline.marks.link.is_some() { " " } else { inline.text.as_str() })
+                    .collect();
+                hashtags_in(&text, tags);
+            }
+            Block::BulletList(items) | Block::OrderedList { items, .. } | Block::TaskList(items) => {
+                for item in items {
+                    collect_hashtags(&item.blocks, tags);
+                }
+            }
+        }
+    }
+}
+
+fn hashtags_in(text: &str, tags: &mut Vec<String>) {
+    let is_tag_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | '/');
+    let mut previous: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let at_boundary = !matches!(previous, Some(p) if !p.is_whitespace() && !matches!(p, '(' | '['));
+        previous = Some(c);
+        if c != '#' || !at_boundary {
+            continue;
+        }
+
+        let mut end = start + 1;
+        while let Some((index, c)) = chars.next_if(|&(_, c)| is_tag_char(c)) {
+            end = index + c.len_utf8();
+            previous = Some(c);
+        }
+        // Trailing separators are punctuation, not part of the tag
+        let tag = text[start + 1..end].trim_end_matches(['-', '/']);
+        if tag.chars().any(char::is_alphabetic) && !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+            tags.push(tag.to_string());
+        }
+    }
+}
+
+/// One paragraph per line of plain text
+pub fn from_plain_text(text: &str) -> Vec<Block> {
+    let text = text.trim_end();
+    text.lines()
+        .map(|line| {
+            Block::Paragraph(vec![Inline {
+                text: line.to_string(),
+                marks: Marks::default(),
+            }])
+        })
+        .collect()
+}
+
+/// Split a list whose items are only partly tasks into task lists and
+/// plain lists, since the editor can't mix the two
+pub(crate) fn list_blocks(start: Option<u64>, items: Vec<ListItem>) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut run: Vec<ListItem> = Vec::new();
+    let mut position = 0;
+
+    let mut flush = |run: &mut Vec<ListItem>, position: usize| {
+        if run.is_empty() {
+            return;
+        }
+        let items = std::mem::take(run);
+        blocks.push(if items[0].checked.is_some() {
+            Block::TaskList(items)
+        } else if let Some(start) = start {
+            Block::OrderedList {
+                start: (start as usize + position - items.len()) as u32,
+                items,
+            }
+        } else {
+            Block::BulletList(items)
+        });
+    };
+
+    for item in items {
+        if run.first().is_some_and(|first| first.checked.is_some() != item.checked.is_some()) {
+            flush(&mut run, position);
+        }
+        run.push(item);
+        position += 1;
+    }
+    flush(&mut run, position);
+
+    blocks
+}
+
+/// Encode blocks as a new note's content, structured the way y-prosemirror
+/// stores the editor's document
+pub fn to_yjs(blocks: &[Block]) -> Vec<u8> {
+    let doc = Doc::new();
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+
+    let mut txn = doc.transact_mut();
+    // The editor's schema needs at least one block
+    if blocks.is_empty() {
+        fragment.push_back(&mut txn, XmlElementPrelim::empty("paragraph"));
+    }
+    write_blocks(&mut txn, &fragment, blocks);
+
+    txn.encode_state_as_update_v1(&StateVector::default())
+}
+
+fn write_blocks<F: XmlFragment>(txn: &mut TransactionMut, parent: &F, blocks: &[Block]) {
+    for block in blocks {
+        match block {
+            Block::Paragraph(content) => {
+                let paragraph = parent.push_back(txn, XmlElementPrelim::empty("paragraph"));
+                write_inlines(txn, &paragraph, content);
+            }
+            Block::Heading { level, content } => {
+                let heading = parent.push_back(txn, XmlElementPrelim::empty("heading"));
+                heading.insert_attribute(txn, "level", *level as f64);
+                write_inlines(txn, &heading, content);
+            }
+            Block::BulletList(items) => {
+                let list = parent.push_back(txn, XmlElementPrelim::empty("bulletList"));
+                write_items(txn, &list, items, "listItem");
+            }
+            Block::OrderedList { start, items } => {
+                let list = parent.push_back(txn, XmlElementPrelim::empty("orderedList"));
+                list.insert_attribute(txn, "start", *start as f64);
+                write_items(txn, &list, items, "listItem");
+            }
+            Block::TaskList(items) => {
+                let list = parent.push_back(txn, XmlElementPrelim::empty("taskList"));
+                write_items(txn, &list, items, "taskItem");
+            }
+        }
+    }
+}
+
+fn write_items(txn: &mut TransactionMut, list: &XmlElementRef, items: &[ListItem], tag: &str) {
+    for item in items {
+        let element = list.push_back(txn, XmlElementPrelim::empty(tag));
+        if tag == "taskItem" {
+            element.insert_attribute(txn, "checked", item.checked.unwrap_or(false));
+        }
+        // List items must start with a paragraph
+        if !matches!(item.blocks.first(), Some(Block::Paragraph(_))) {
+            element.push_back(txn, XmlElementPrelim::empty("paragraph"));
+        }
+        write_blocks(txn, &element, &item.blocks);
+    }
+}
+
+fn write_inlines(txn: &mut TransactionMut, element: &XmlElementRef, inlines: &[Inline]) {
+    // Empty textblocks have no text node at all
+    if inlines.iter().all(|inline| inline.text.is_empty()) {
+        return;
+    }
+
+    let text = element.push_back(txn, XmlTextPrelim::new(""));
+    for inline in inlines.iter().filter(|inline| !inline.text.is_empty()) {
+        let index = text.len(txn);
+        text.insert_with_attributes(txn, index, &inline.text, write_marks(&inline.marks));
+    }
+}
+
+fn write_marks(marks: &Marks) -> Attrs {
+    let mut attrs = Attrs::new();
+    let empty = || Any::Map(Arc::new(HashMap::new()));
+    if marks.bold {
+        attrs.insert("bold".into(), empty());
+    }
+    if marks.underline {
+        attrs.insert("underline".into(), empty());
+    }
+    if let Some(href) = &marks.link {
+        let link = HashMap::from([("href".to_string(), Any::String(href.as_str().into()))]);
+        attrs.insert("link".into(), Any::Map(Arc::new(link)));
+    }
+    attrs
+}
@@ -0,0 +1,212 @@
+//! Recurrence rules of repeating tasks.
+//!
+//! A rule is a subset of the iCalendar RRULE (RFC 5545): `FREQ` (`DAILY`,
+//! `WEEKLY`, `MONTHLY` or `YEARLY`), `INTERVAL`, `BYDAY` with plain weekdays
+//! for weekly rules, `BYMONTHDAY` for monthly rules (negative days count from
+//! the end of the month), `COUNT` and `UNTIL`, e.g.
+//! `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH`. A leading `RRULE:` is allowed.
+//! Anything else makes the rule invalid, and the task doesn't repeat.
+//!
+//! Unlike RFC 5545, a month without the rule's day uses its last day rather
+//! than being skipped, so a task due on the 31st still comes up every month.
+
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub frequency: Frequency,
+    /// Number of days, weeks, months or years between occurrences
+    pub interval: u32,
+    /// Days of the week of a weekly rule; the due date's day if empty
+    pub weekdays: Vec<Weekday>,
+    /// Day of the month of a monthly rule; the due date's day if unset
+    pub month_day: Option<i32>,
+    /// Occurrences left, including the current one
+    pub count: Option<u32>,
+    /// Last date an occurrence may fall on
+    pub until: Option<NaiveDate>,
+}
+
+const UNTIL_FORMAT: &str = "%Y%m%d";
+
+impl Rule {
+    pub fn parse(rule: &str) -> Option<Rule> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut weekdays = Vec::new();
+        let mut month_day = None;
+        let mut count = None;
+        let mut until = None;
+
+        for part in rule.split(';').filter(|part| !part.trim().is_empty()) {
+            let (key, value) = part.split_once('=')?;
+            let value = value.trim().to_ascii_uppercase();
+            match key.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return None,
+                    })
+                }
+                "INTERVAL" => interval = value.parse().ok().filter(|&interval| interval > 0)?,
+                "BYDAY" => weekdays = value.split(',').map(parse_weekday).collect::<Option<_>>()?,
+                "BYMONTHDAY" => {
+                    month_day = Some(value.parse().ok().filter(|day: &i32| *day != 0 && day.abs() <= 31)?)
+                }
+                "COUNT" => count = Some(value.parse().ok().filter(|&count| count > 0)?),
+                // Only the date of a date-time matters for tasks
+                "UNTIL" => until = Some(NaiveDate::parse_from_str(value.get(..8)?, UNTIL_FORMAT).ok()?),
+                // Weeks start on Monday, the RRULE default
+                "WKST" if value == "MO" => {}
+                _ => return None,
+            }
+        }
+
+        let frequency = frequency?;
+        if !weekdays.is_empty() && frequency != Frequency::Weekly {
+            return None;
+        }
+        if month_day.is_some() && frequency != Frequency::Monthly {
+            return None;
+        }
+        Some(Rule {
+            frequency,
+            interval,
+            weekdays,
+            month_day,
+            count,
+            until,
+        })
+    }
+
+    /// The occurrence after the one on `date`
+    fn step(&self, date: NaiveDate) -> NaiveDate {
+        match self.frequency {
+            Frequency::Daily => date + Duration::days(self.interval.into()),
+            Frequency::Weekly if self.weekdays.is_empty() => date + Duration::weeks(self.interval.into()),
+            Frequency::Weekly => {
+                // A later day of the same week, else the first day of the
+                // week `interval` weeks on
+                let monday = date - Duration::days(date.weekday().num_days_from_monday().into());
+                let later = (date.weekday().num_days_from_monday() + 1..7)
+                    .map(|day| monday + Duration::days(day.into()))
+                    .find(|day| self.weekdays.contains(&day.weekday()));
+                later.unwrap_or_else(|| {
+                    let monday = monday + Duration::weeks(self.interval.into());
+                    (0..7)
+                        .map(|day| monday + Duration::days(day))
+                        .find(|day| self.weekdays.contains(&day.weekday()))
+                        .unwrap_or(monday)
+                })
+            }
+            Frequency::Monthly => {
+                let day = self.month_day.unwrap_or(date.day() as i32);
+                let first = date.with_day(1).unwrap_or(date) + Months::new(self.interval);
+                day_of_month(first, day)
+            }
+            // Adding months keeps the day, or uses the last day of February
+            Frequency::Yearly => date + Months::new(12 * self.interval),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<_> = self.weekdays.iter().map(|day| weekday_code(*day)).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(day) = self.month_day {
+            write!(f, ";BYMONTHDAY={}", day)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format(UNTIL_FORMAT))?;
+        }
+        Ok(())
+    }
+}
+
+/// The occurrence of a repeating task after the one due on `due`, skipping
+/// occurrences up to `today` so a task completed late comes up next in the
+/// future. Also returns the rule to keep, which counts down `COUNT`.
+/// Returns `None` once the rule has no more occurrences.
+pub fn next_occurrence(rule: &Rule, due: NaiveDate, today: NaiveDate) -> Option<(NaiveDate, Rule)> {
+    let mut rule = rule.clone();
+    let mut date = due;
+    loop {
+        if let Some(count) = rule.count {
+            if count <= 1 {
+                return None;
+            }
+            rule.count = Some(count - 1);
+        }
+        date = rule.step(date);
+        if rule.until.is_some_and(|until| date > until) {
+            return None;
+        }
+        if date > today {
+            return Some((date, rule));
+        }
+    }
+}
+
+/// `day` of the month starting on `first`, the last day if the month is
+/// shorter; negative days count from the end
+fn day_of_month(first: NaiveDate, day: i32) -> NaiveDate {
+    let last = (first + Months::new(1) - Duration::days(1)).day() as i32;
+    let day = if day > 0 { day.min(last) } else { (last + 1 + day).max(1) };
+    first.with_day(day as u32).unwrap_or(first)
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    match code.trim() {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
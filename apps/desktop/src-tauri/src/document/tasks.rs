@@ -0,0 +1,208 @@
+//! Task items of a note.
+//!
+//! A task is a `taskItem` element anywhere in the document, including task
+//! lists nested inside other lists. Tasks are addressed by their path: the
+//! child index of each element from the content fragment down to the task
+//! item, so the path also records how the task is nested.
+//!
+//! Task items may carry a due date (`dueDate`, a `YYYY-MM-DD` calendar
+//! date) and a reminder time (`remindAt`, milliseconds since the epoch),
+//! both attributes of the `taskItem` element set by the editor. A task with a
+//! `repeat` rule (see `recurrence`) comes back when it is checked: it is
+//! unchecked again and moved to its next occurrence.
+
+use super::recurrence::{self, Rule};
+use super::{attr_bool, attr_number, load, plain_text, read_inlines, DocumentError, CONTENT_FRAGMENT};
+use chrono::NaiveDate;
+use yrs::{Any, Out, ReadTxn, StateVector, Transact, Xml, XmlElementRef, XmlFragment, XmlFragmentRef, XmlOut};
+
+/// Format of `dueDate`
+pub const DUE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskItem {
+    /// Child indexes from the content fragment down to the task item
+    pub path: Vec<u32>,
+    /// Number of task items the task is nested in
+    pub depth: u32,
+    pub text: String,
+    pub checked: bool,
+    pub due_date: Option<NaiveDate>,
+    pub remind_at: Option<i64>,
+    /// Recurrence rule, as written in the document
+    pub repeat: Option<String>,
+}
+
+/// A change to a task item made outside the editor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskChange {
+    Check(bool),
+    /// Set or clear the reminder time
+    RemindAt(Option<i64>),
+}
+
+/// Format a path as stored in the index, e.g. `2.0.1`
+pub fn path_to_string(path: &[u32]) -> String {
+    path.iter().map(u32::to_string).collect::<Vec<_>>().join(".")
+}
+
+pub fn parse_path(path: &str) -> Option<Vec<u32>> {
+    path.split('.').map(|index| index.parse().ok()).collect()
+}
+
+/// The task items of a stored note in document order
+pub fn read(content: &[u8]) -> Result<Vec<TaskItem>, DocumentError> {
+    let doc = load(content)?;
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+
+    let txn = doc.transact();
+    let mut tasks = Vec::new();
+    collect(&txn, &fragment, &mut Vec::new(), 0, &mut tasks);
+    Ok(tasks)
+}
+
+/// Apply `change` to the task item at `path`, returning the note's new
+/// content. Returns `None` when there's no task item with the given text
+/// at the path, i.e. the note changed since the task was indexed.
+pub fn update(
+    content: &[u8],
+    path: &[u32],
+    text: &str,
+    change: TaskChange,
+) -> Result<Option<Vec<u8>>, DocumentError> {
+    let doc = load(content)?;
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+
+    let mut txn = doc.transact_mut();
+    let Some(item) = element_at(&txn, &fragment, path) else {
+        return Ok(None);
+    };
+    if item.tag().as_ref() != "taskItem" || item_text(&txn, &item) != text {
+        return Ok(None);
+    }
+
+    match change {
+        TaskChange::Check(checked) => {
+            item.insert_attribute(&mut txn, "checked", checked);
+        }
+        TaskChange::RemindAt(Some(time)) => {
+            item.insert_attribute(&mut txn, "remindAt", time as f64);
+        }
+        TaskChange::RemindAt(None) => item.remove_attribute(&mut txn, &"remindAt"),
+    }
+    Ok(Some(txn.encode_state_as_update_v1(&StateVector::default())))
+}
+
+/// Move the checked repeating tasks of a note to their next occurrence:
+/// each is unchecked and gets the next due date, and its reminder moves by
+/// as many days. A task without a due date repeats from `today`, and one
+/// whose rule has run out stays checked. Returns the note's new content, or
+/// `None` if no task was moved.
+pub fn advance_recurring(content: &[u8], today: NaiveDate) -> Result<Option<Vec<u8>>, DocumentError> {
+    let doc = load(content)?;
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+
+    let mut txn = doc.transact_mut();
+    let mut items = Vec::new();
+    collect(&txn, &fragment, &mut Vec::new(), 0, &mut items);
+
+    let mut advanced = false;
+    for item in items.iter().filter(|item| item.checked) {
+        let Some(rule) = item.repeat.as_deref().and_then(Rule::parse) else {
+            continue;
+        };
+        let due = item.due_date.unwrap_or(today);
+        let Some((next, next_rule)) = recurrence::next_occurrence(&rule, due, today) else {
+            continue;
+        };
+        let Some(element) = element_at(&txn, &fragment, &item.path) else {
+            continue;
+        };
+
+        element.insert_attribute(&mut txn, "checked", false);
+        element.insert_attribute(&mut txn, "dueDate", next.format(DUE_DATE_FORMAT).to_string());
+        if let Some(time) = item.remind_at {
+            let moved = time + (next - due).num_milliseconds();
+            element.insert_attribute(&mut txn, "remindAt", moved as f64);
+        }
+        // Only a counted rule changes between occurrences
+        if next_rule != rule {
+            element.insert_attribute(&mut txn, "repeat", next_rule.to_string());
+        }
+        advanced = true;
+    }
+
+    Ok(advanced.then(|| txn.encode_state_as_update_v1(&StateVector::default())))
+}
+
+fn collect<T: ReadTxn, F: XmlFragment>(
+    txn: &T,
+    parent: &F,
+    path: &mut Vec<u32>,
+    depth: u32,
+    tasks: &mut Vec<TaskItem>,
+) {
+    for i in 0..parent.len(txn) {
+        let Some(XmlOut::Element(element)) = parent.get(txn, i) else {
+            continue;
+        };
+        path.push(i);
+        if element.tag().as_ref() == "taskItem" {
+            tasks.push(TaskItem {
+                path: path.clone(),
+                depth,
+                text: item_text(txn, &element),
+                checked: attr_bool(txn, &element, "checked"),
+                due_date: due_date(txn, &element),
+                remind_at: attr_number(txn, &element, "remindAt").map(|time| time as i64),
+                repeat: attr_string(txn, &element, "repeat"),
+            });
+            collect(txn, &element, path, depth + 1, tasks);
+        } else {
+            collect(txn, &element, path, depth, tasks);
+        }
+        path.pop();
+    }
+}
+
+fn due_date<T: ReadTxn>(txn: &T, item: &XmlElementRef) -> Option<NaiveDate> {
+    let value = attr_string(txn, item, "dueDate")?;
+    NaiveDate::parse_from_str(&value, DUE_DATE_FORMAT).ok()
+}
+
+fn attr_string<T: ReadTxn>(txn: &T, element: &XmlElementRef, name: &str) -> Option<String> {
+    match element.get_attribute(txn, name) {
+        Some(Out::Any(Any::String(value))) if !value.is_empty() => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn element_at<T: ReadTxn>(txn: &T, fragment: &XmlFragmentRef, path: &[u32]) -> Option<XmlElementRef> {
+    let (first, rest) = path.split_first()?;
+    let Some(XmlOut::Element(mut element)) = fragment.get(txn, *first) else {
+        return None;
+    };
+    for index in rest {
+        let Some(XmlOut::Element(child)) = element.get(txn, *index) else {
+            return None;
+        };
+        element = child;
+    }
+    Some(element)
+}
+
+/// Text of the task's own paragraphs, leaving out nested lists
+fn item_text<T: ReadTxn>(txn: &T, item: &XmlElementRef) -> String {
+    let mut parts = Vec::new();
+    for i in 0..item.len(txn) {
+        if let Some(XmlOut::Element(child)) = item.get(txn, i) {
+            if matches!(child.tag().as_ref(), "paragraph" | "heading") {
+                let text = plain_text(&read_inlines(txn, &child));
+                if !text.trim().is_empty() {
+                    parts.push(text.trim().to_string());
+                }
+            }
+        }
+    }
+    parts.join(" ")
+}
@@ -0,0 +1,176 @@
+//! Exporting notes as files outside the library.
+
+use chrono::{DateTime, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::document::{self, html, markdown, pdf, DocumentError};
+use crate::storage::{Note, Storage, StorageError};
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error(transparent)]
+    Document(#[from] DocumentError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ExportError>;
+
+#[derive(Debug, Serialize)]
+pub struct ExportedNote {
+    #[serde(rename = "noteId")]
+    pub note_id: String,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportFailure {
+    #[serde(rename = "noteId")]
+    pub note_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ExportReport {
+    pub exported: Vec<ExportedNote>,
+    pub failed: Vec<ExportFailure>,
+}
+
+/// File formats notes can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+
+    /// The format a file name asks for, by its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "html" | "htm" => Some(ExportFormat::Html),
+            "pdf" => Some(ExportFormat::Pdf),
+            _ => None,
+        }
+    }
+}
+
+/// A note rendered in the given format
+pub fn render_note(note: &Note, format: ExportFormat) -> Result<Vec<u8>> {
+    Ok(match format {
+        ExportFormat::Markdown => note_to_markdown(note)?.into_bytes(),
+        ExportFormat::Html => html::render(&note.title, &document::from_yjs(&note.content)?).into_bytes(),
+        ExportFormat::Pdf => pdf::render(&note.title, &document::from_yjs(&note.content)?),
+    })
+}
+
+/// A note as Markdown, with its metadata as YAML front-matter
+pub fn note_to_markdown(note: &Note) -> Result<String> {
+    let blocks = document::from_yjs(&note.content)?;
+
+    // JSON strings are valid YAML scalars, so titles need no other escaping
+    let mut out = String::from("---\n");
+    out.push_str(&format!("title: {}\n", serde_json::Value::from(note.title.as_str())));
+    out.push_str(&format!("starred: {}\n", note.starred));
+    out.push_str(&format!("created: {}\n", format_timestamp(note.created_at)));
+    out.push_str(&format!("updated: {}\n", format_timestamp(note.updated_at)));
+    out.push_str("---\n\n");
+    out.push_str(&markdown::to_markdown(&blocks));
+    Ok(out)
+}
+
+/// Write one note to `path`
+pub fn export_note(storage: &Storage, note_id: &str, path: &Path, format: ExportFormat) -> Result<()> {
+    let note = storage.get_note(note_id)?;
+    std::fs::write(path, render_note(&note, format)?)?;
+    Ok(())
+}
+
+/// Write each note to `dir` as `<title>.<extension>`. Existing files of the
+/// same name are replaced, so exporting again to one folder updates it.
+pub fn export_notes(storage: &Storage, note_ids: &[String], dir: &Path, format: ExportFormat) -> Result<ExportReport> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut report = ExportReport::default();
+    let mut used_names = HashSet::new();
+
+    for note_id in note_ids {
+        let result = storage
+            .get_note(note_id)
+            .map_err(ExportError::from)
+            .and_then(|note| {
+                let path = unique_path(dir, &note.title, format.extension(), &mut used_names);
+                std::fs::write(&path, render_note(&note, format)?)?;
+                Ok(path)
+            });
+
+        match result {
+            Ok(path) => report.exported.push(ExportedNote {
+                note_id: note_id.clone(),
+                path: path.to_string_lossy().to_string(),
+            }),
+            Err(e) => report.failed.push(ExportFailure {
+                note_id: note_id.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    Ok(report)
+}
+
+fn format_timestamp(millis: i64) -> String {
+    DateTime::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// A file name for `title` that no other note in this export has taken
+fn unique_path(dir: &Path, title: &str, extension: &str, used_names: &mut HashSet<String>) -> PathBuf {
+    let base = file_stem(title);
+    let mut name = base.clone();
+    let mut n = 1;
+    // Compare case-insensitively for case-insensitive file systems
+    while !used_names.insert(name.to_lowercase()) {
+        n += 1;
+        name = format!("{} ({})", base, n);
+    }
+    dir.join(format!("{}.{}", name, extension))
+}
+
+/// Note title made safe to use as a file name on every platform
+fn file_stem(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .take(100)
+        .collect();
+    // Windows ignores trailing dots and spaces; leading dots hide files
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+
+    if cleaned.is_empty() {
+        "Untitled".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
@@ -0,0 +1,256 @@
+//! Fetching URLs that come from note content.
+//!
+//! Links in notes are written by whoever wrote the note, so fetching one
+//! must not reach anything a web page couldn't: only http and https, only
+//! public addresses (not loopback, link-local or private networks, where
+//! local services and cloud metadata endpoints listen), a bounded number of
+//! redirects, each checked like the first request, and a bounded download
+//! of an expected content type. Hosts are resolved and checked before
+//! connecting, and the connection is pinned to the checked addresses so a
+//! second DNS answer can't point it somewhere else.
+//!
+//! Nothing here depends on the rest of the app, so a server-side fetcher
+//! (the API unfurling links itself) can use this module as it is.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use reqwest::header::{CONTENT_TYPE, LOCATION, USER_AGENT};
+use reqwest::{redirect, Response, StatusCode, Url};
+use thiserror::Error;
+
+#[derive(Debug, Clone)]
+pub struct FetchPolicy {
+    /// Whether loopback, link-local and private addresses may be fetched
+    pub allow_private: bool,
+    pub max_redirects: usize,
+    /// How much of a body is downloaded, in bytes; the rest is cut off
+    pub max_size: usize,
+    /// Content types a response may have, without parameters such as
+    /// `charset`; empty allows any
+    pub content_types: &'static [&'static str],
+    pub timeout: Duration,
+    pub user_agent: &'static str,
+}
+
+impl Default for FetchPolicy {
+    fn default() -> Self {
+        Self {
+            allow_private: false,
+            max_redirects: 5,
+            max_size: 1024 * 1024,
+            content_types: &[],
+            timeout: Duration::from_secs(5),
+            user_agent: "Mozilla/5.0 (compatible; PDTodo/1.0)",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FetchError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("Only http and https URLs can be fetched, not {0}")]
+    Scheme(String),
+    #[error("Couldn't resolve {0}")]
+    Resolve(String),
+    #[error("{0} is not a public address")]
+    Blocked(IpAddr),
+    #[error("More than {0} redirects")]
+    TooManyRedirects(usize),
+    #[error("Unexpected content type {0:?}")]
+    ContentType(String),
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Server responded with {0}")]
+    Status(StatusCode),
+}
+
+/// A fetched response
+#[derive(Debug)]
+pub struct Fetched {
+    /// Where redirects ended up
+    pub url: Url,
+    pub content_type: Option<String>,
+    /// The body, cut off at the policy's `max_size`
+    pub body: Vec<u8>,
+    /// Whether the body was longer than `max_size`
+    pub truncated: bool,
+}
+
+/// GET `url` within `policy`, following redirects
+pub async fn fetch(url: &str, policy: &FetchPolicy) -> Result<Fetched, FetchError> {
+    let mut url = Url::parse(url).map_err(|e| FetchError::InvalidUrl(e.to_string()))?;
+    let mut redirects = 0;
+    let mut response = loop {
+        let response = request(&url, policy).await?;
+        match redirect_target(&url, &response) {
+            Some(_) if redirects == policy.max_redirects => {
+                return Err(FetchError::TooManyRedirects(policy.max_redirects));
+            }
+            Some(target) => {
+                redirects += 1;
+                url = target;
+            }
+            None => break response,
+        }
+    };
+    if !response.status().is_success() {
+        return Err(FetchError::Status(response.status()));
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    if !policy.content_types.is_empty() {
+        let essence = content_type.as_deref().map(mime_essence).unwrap_or_default();
+        if !policy.content_types.contains(&essence.as_str()) {
+            return Err(FetchError::ContentType(essence));
+        }
+    }
+
+    let mut body = Vec::new();
+    let mut truncated = false;
+    while let Some(chunk) = response.chunk().await? {
+        let room = policy.max_size - body.len();
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            truncated = true;
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(Fetched {
+        url,
+        content_type,
+        body,
+        truncated,
+    })
+}
+
+/// A Content-Type without its parameters, lowercased: `text/html` for
+/// `text/html; charset=UTF-8`
+pub fn mime_essence(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+/// Send a single request, without following redirects, after checking
+/// where it goes
+async fn request(url: &Url, policy: &FetchPolicy) -> Result<Response, FetchError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(FetchError::Scheme(url.scheme().to_string()));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| FetchError::InvalidUrl("URL has no host".to_string()))?;
+    // IPv6 hosts come bracketed
+    let literal = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().ok();
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let addrs: Vec<SocketAddr> = match literal {
+        Some(ip) => vec![SocketAddr::new(ip, port)],
+        None => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| FetchError::Resolve(host.to_string()))?
+            .collect(),
+    };
+    if addrs.is_empty() {
+        return Err(FetchError::Resolve(host.to_string()));
+    }
+    if !policy.allow_private {
+        if let Some(addr) = addrs.iter().find(|addr| is_private(addr.ip())) {
+            return Err(FetchError::Blocked(addr.ip()));
+        }
+    }
+
+    // Proxies would resolve the host again themselves
+    let mut builder = reqwest::Client::builder()
+        .timeout(policy.timeout)
+        .redirect(redirect::Policy::none())
+        .no_proxy();
+    if literal.is_none() {
+        builder = builder.resolve_to_addrs(host, &addrs);
+    }
+    let response = builder
+        .build()?
+        .get(url.clone())
+        .header(USER_AGENT, policy.user_agent)
+        .send()
+        .await?;
+    Ok(response)
+}
+
+/// Where a redirect response points, or None if it isn't one. A redirect
+/// without a usable Location is left to fail as an unsuccessful status.
+fn redirect_target(url: &Url, response: &Response) -> Option<Url> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    url.join(location).ok()
+}
+
+/// Whether `ip` is anywhere but the public internet: loopback, link-local,
+/// a private network (RFC 1918, carrier-grade NAT, IPv6 unique and site
+/// local), reserved, unspecified, broadcast or multicast. IPv6 addresses
+/// that carry an IPv4 address (mapped, compatible, NAT64, 6to4) are judged
+/// by that address, since networks routing them reach the IPv4 host.
+pub fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => match embedded_ipv4(ip) {
+            Some(ip) => is_private_v4(ip),
+            None => is_private_v6(ip),
+        },
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_multicast()
+        // 0.0.0.0/8, which reaches this host
+        || a == 0
+        // 100.64.0.0/10, carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // 198.18.0.0/15, for benchmarking networks
+        || (a == 198 && (b == 18 || b == 19))
+        // 240.0.0.0/4, reserved, including the broadcast address
+        || a >= 240
+}
+
+fn is_private_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // fc00::/7, unique local
+        || (first & 0xfe00) == 0xfc00
+        // fe80::/10, link-local
+        || (first & 0xffc0) == 0xfe80
+        // fec0::/10, site-local (deprecated, but still routed on some networks)
+        || (first & 0xffc0) == 0xfec0
+}
+
+/// The IPv4 address inside an IPv4-mapped (`::ffff:0:0/96`), IPv4-compatible
+/// (`::/96`, deprecated), NAT64 (`64:ff9b::/96`, `64:ff9b:1::/48`) or 6to4
+/// (`2002::/16`) address. `::` and `::1` come out as 0.0.0.0 and 0.0.0.1,
+/// which are refused all the same.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    if let Some(mapped) = ip.to_ipv4_mapped() {
+        return Some(mapped);
+    }
+    let from = |high: u16, low: u16| Ipv4Addr::from((u32::from(high) << 16) | u32::from(low));
+    match ip.segments() {
+        [0, 0, 0, 0, 0, 0, high, low]
+        | [0x64, 0xff9b, 0, 0, 0, 0, high, low]
+        | [0x64, 0xff9b, 1, _, _, _, high, low] => Some(from(high, low)),
+        [0x2002, high, low, ..] => Some(from(high, low)),
+        _ => None,
+    }
+}
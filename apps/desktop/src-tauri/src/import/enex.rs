@@ -0,0 +1,108 @@
+//! Evernote exports (`.enex`), XML holding any number of notes whose
+//! content is ENML, a restricted XHTML.
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::path::Path;
+
+use super::{new_note, read_to_string, ImportError, Result};
+use crate::document::html;
+use crate::storage::NewNote;
+
+/// Fields of one `<note>`, as text
+#[derive(Debug, Default)]
+struct EnexNote {
+    title: String,
+    content: String,
+    created: String,
+    updated: String,
+    deleted: String,
+}
+
+pub(super) fn read(path: &Path) -> Result<Vec<NewNote>> {
+    let xml = read_to_string(path)?;
+    let invalid = |e: quick_xml::Error| ImportError::Invalid(format!("Not an Evernote export: {}", e));
+
+    let mut reader = Reader::from_str(&xml);
+    let mut notes = Vec::new();
+    let mut current: Option<EnexNote> = None;
+    // Elements open inside the current note
+    let mut open: Vec<Vec<u8>> = Vec::new();
+
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(start) => {
+                let name = start.name().as_ref().to_vec();
+                if name == b"note" && current.is_none() {
+                    current = Some(EnexNote::default());
+                } else if current.is_some() {
+                    open.push(name);
+                }
+            }
+            Event::End(end) => {
+                if end.name().as_ref() == b"note" && open.is_empty() {
+                    if let Some(note) = current.take() {
+                        notes.push(to_note(note, path)?);
+                    }
+                } else {
+                    open.pop();
+                }
+            }
+            Event::Text(text) => {
+                if let Some(field) = field(&mut current, &open) {
+                    field.push_str(&text.unescape().map_err(invalid)?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(field) = field(&mut current, &open) {
+                    field.push_str(&String::from_utf8_lossy(&data.into_inner()));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if notes.is_empty() {
+        return Err(ImportError::Invalid("No notes found in Evernote export".to_string()));
+    }
+    Ok(notes)
+}
+
+/// The field collecting text at this point, if it's one that's imported.
+/// Only direct children of `<note>` count; resources and attributes have
+/// their own nested elements.
+fn field<'a>(note: &'a mut Option<EnexNote>, open: &[Vec<u8>]) -> Option<&'a mut String> {
+    let note = note.as_mut()?;
+    let [name] = open else {
+        return None;
+    };
+    match name.as_slice() {
+        b"title" => Some(&mut note.title),
+        b"content" => Some(&mut note.content),
+        b"created" => Some(&mut note.created),
+        b"updated" => Some(&mut note.updated),
+        b"deleted" => Some(&mut note.deleted),
+        _ => None,
+    }
+}
+
+fn to_note(enex: EnexNote, path: &Path) -> Result<NewNote> {
+    let (_, blocks) = html::parse(&enex.content);
+    let mut note = new_note(Some(enex.title), blocks, path)?;
+
+    if let Some(updated) = parse_timestamp(&enex.updated) {
+        note.updated_at = updated;
+    }
+    note.created_at = parse_timestamp(&enex.created).unwrap_or(note.updated_at);
+    note.deleted_at = parse_timestamp(&enex.deleted);
+    Ok(note)
+}
+
+/// Evernote timestamps look like `20240131T093000Z`
+fn parse_timestamp(value: &str) -> Option<i64> {
+    NaiveDateTime::parse_from_str(value.trim(), "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|time| Utc.from_utc_datetime(&time).timestamp_millis())
+}
@@ -0,0 +1,12 @@
+//! HTML notes, such as Apple Notes or Keep exports.
+
+use std::path::Path;
+
+use super::{new_note, read_to_string, Result};
+use crate::document::html;
+use crate::storage::NewNote;
+
+pub(super) fn read(path: &Path) -> Result<NewNote> {
+    let (title, blocks) = html::parse(&read_to_string(path)?);
+    new_note(title, blocks, path)
+}
@@ -0,0 +1,132 @@
+//! Google Keep notes from a Takeout export (one JSON file per note).
+//!
+//! PDTodo has no archive, so archived notes go to the trash: out of the
+//! way, but still there to restore.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+use super::{new_note, read_to_string, ImportError, Result};
+use crate::document::{self, Block, Inline, ListItem, Marks};
+use crate::storage::NewNote;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepNote {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    text_content: String,
+    #[serde(default)]
+    list_content: Vec<KeepListItem>,
+    #[serde(default)]
+    annotations: Vec<KeepAnnotation>,
+    #[serde(default)]
+    is_pinned: bool,
+    #[serde(default)]
+    is_archived: bool,
+    #[serde(default)]
+    is_trashed: bool,
+    created_timestamp_usec: Option<i64>,
+    user_edited_timestamp_usec: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepListItem {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    is_checked: bool,
+}
+
+/// A link Keep attached to the note
+#[derive(Debug, Deserialize)]
+struct KeepAnnotation {
+    url: Option<String>,
+    title: Option<String>,
+}
+
+/// Whether `path` holds a Keep note rather than some other JSON
+pub(super) fn is_keep_note(path: &Path) -> bool {
+    read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+        .is_some_and(|value| has_keep_fields(&value))
+}
+
+/// Takeout writes the content and the edit time of every note
+fn has_keep_fields(value: &Value) -> bool {
+    (value.get("textContent").is_some() || value.get("listContent").is_some())
+        && value.get("userEditedTimestampUsec").is_some()
+}
+
+pub(super) fn read(path: &Path) -> Result<NewNote> {
+    let value: Value = serde_json::from_str(&read_to_string(path)?)
+        .map_err(|e| ImportError::Invalid(format!("Not a Google Keep note: {}", e)))?;
+    if !has_keep_fields(&value) {
+        return Err(ImportError::Invalid("Not a Google Keep note".to_string()));
+    }
+    let keep: KeepNote = serde_json::from_value(value)
+        .map_err(|e| ImportError::Invalid(format!("Not a Google Keep note: {}", e)))?;
+
+    let mut blocks = document::from_plain_text(&keep.text_content);
+    if !keep.list_content.is_empty() {
+        // Keep checklists become task lists
+        blocks.push(Block::TaskList(
+            keep.list_content
+                .iter()
+                .map(|item| ListItem {
+                    checked: Some(item.is_checked),
+                    blocks: vec![Block::Paragraph(vec![plain(&item.text)])],
+                })
+                .collect(),
+        ));
+    }
+    for annotation in &keep.annotations {
+        let Some(url) = &annotation.url else {
+            continue;
+        };
+        let text = annotation.title.clone().filter(|title| !title.is_empty()).unwrap_or_else(|| url.clone());
+        blocks.push(Block::Paragraph(vec![Inline {
+            text,
+            marks: Marks {
+                link: Some(url.clone()),
+                ..Default::default()
+            },
+        }]));
+    }
+
+    // Untitled Keep notes are common; their first line is the best title
+    let title = Some(keep.title.clone())
+        .filter(|title| !title.trim().is_empty())
+        .or_else(|| first_line(&keep));
+
+    let mut note = new_note(title, blocks, path)?;
+    note.starred = keep.is_pinned;
+    if let Some(usec) = keep.user_edited_timestamp_usec {
+        note.updated_at = usec / 1000;
+    }
+    note.created_at = keep.created_timestamp_usec.map_or(note.updated_at, |usec| usec / 1000);
+    if keep.is_trashed || keep.is_archived {
+        note.deleted_at = Some(note.updated_at);
+    }
+    Ok(note)
+}
+
+fn first_line(keep: &KeepNote) -> Option<String> {
+    keep.text_content
+        .lines()
+        .chain(keep.list_content.iter().map(|item| item.text.as_str()))
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(|line| line.chars().take(100).collect())
+}
+
+fn plain(text: &str) -> Inline {
+    Inline {
+        text: text.to_string(),
+        marks: Marks::default(),
+    }
+}
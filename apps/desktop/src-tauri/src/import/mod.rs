@@ -0,0 +1,220 @@
+//! Importing notes from files made outside the app.
+//!
+//! Each format is read into `NewNote`s with content generated from blocks
+//! (see `document`), so imported notes are the same Yjs documents the
+//! editor creates.
+
+mod enex;
+mod html;
+mod keep;
+mod text;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use thiserror::Error;
+
+use crate::document::{self, Block};
+use crate::storage::{NewNote, Storage, StorageError};
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Unsupported file type: {0}")]
+    Unsupported(String),
+    #[error("Invalid file: {0}")]
+    Invalid(String),
+}
+
+pub type Result<T> = std::result::Result<T, ImportError>;
+
+#[derive(Debug, Serialize)]
+pub struct ImportedNote {
+    pub path: String,
+    #[serde(rename = "noteId")]
+    pub note_id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: Vec<ImportedNote>,
+    pub failed: Vec<ImportFailure>,
+}
+
+impl ImportReport {
+    fn record(&mut self, path: &Path, result: Result<(String, String)>) {
+        let path = path.to_string_lossy().to_string();
+        match result {
+            Ok((note_id, title)) => self.imported.push(ImportedNote { path, note_id, title }),
+            Err(e) => self.failed.push(ImportFailure {
+                path,
+                error: e.to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Markdown,
+    Text,
+    /// Google Takeout Keep note
+    Keep,
+    /// Evernote export, which may hold many notes
+    Enex,
+    Html,
+}
+
+impl Format {
+    fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" | "mdown" | "mkd" => Some(Self::Markdown),
+            "txt" | "text" => Some(Self::Text),
+            "json" => Some(Self::Keep),
+            "enex" => Some(Self::Enex),
+            "html" | "htm" => Some(Self::Html),
+            _ => None,
+        }
+    }
+}
+
+/// Import notes from files. Each path may be a file or a folder, which is
+/// searched recursively; a file that fails to import is reported without
+/// stopping the others.
+pub fn import_files(storage: &Storage, paths: &[PathBuf]) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+
+    for path in paths {
+        if path.is_dir() {
+            for file in find_files(path)? {
+                import_file(storage, &file, &mut report);
+            }
+        } else {
+            import_file(storage, path, &mut report);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Supported files under `dir`, in a stable order; hidden entries are skipped
+fn find_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')) {
+            continue;
+        }
+        if path.is_dir() {
+            files.extend(find_files(&path)?);
+            continue;
+        }
+        match Format::of(&path) {
+            // Takeout writes every Keep note as both JSON and HTML; the JSON
+            // has the metadata
+            Some(Format::Html) if keep::is_keep_note(&path.with_extension("json")) => {}
+            // Other JSON found in a folder isn't something to import
+            Some(Format::Keep) if !keep::is_keep_note(&path) => {}
+            Some(_) => files.push(path),
+            None => {}
+        }
+    }
+    Ok(files)
+}
+
+fn import_file(storage: &Storage, path: &Path, report: &mut ImportReport) {
+    let notes = match read_notes(path) {
+        Ok(notes) => notes,
+        Err(e) => return report.record(path, Err(e)),
+    };
+
+    for note in notes {
+        let result = storage
+            .insert_note(&note)
+            .map(|id| (id, note.title.clone()))
+            .map_err(ImportError::from);
+        report.record(path, result);
+    }
+}
+
+fn read_notes(path: &Path) -> Result<Vec<NewNote>> {
+    match Format::of(path) {
+        Some(Format::Markdown) => Ok(vec![text::read_markdown(path)?]),
+        Some(Format::Text) => Ok(vec![text::read_text(path)?]),
+        Some(Format::Keep) => Ok(vec![keep::read(path)?]),
+        Some(Format::Enex) => enex::read(path),
+        Some(Format::Html) => Ok(vec![html::read(path)?]),
+        None => Err(ImportError::Unsupported(path.to_string_lossy().to_string())),
+    }
+}
+
+/// A file's text, tolerating invalid UTF-8 and a byte order mark
+fn read_to_string(path: &Path) -> Result<String> {
+    let bytes = std::fs::read(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+    Ok(text.strip_prefix('\u{feff}').unwrap_or(&text).to_string())
+}
+
+/// Build a note from its blocks. Without a title, a leading top-level
+/// heading becomes the title, or else the file name.
+fn new_note(title: Option<String>, mut blocks: Vec<Block>, path: &Path) -> Result<NewNote> {
+    let title = match title.filter(|title| !title.trim().is_empty()) {
+        Some(title) => title,
+        None => take_title(&mut blocks).unwrap_or_else(|| file_title(path)),
+    };
+    let (updated_at, created_at) = file_times(path)?;
+
+    Ok(NewNote {
+        title: title.trim().to_string(),
+        content: document::to_yjs(&blocks),
+        starred: false,
+        created_at,
+        updated_at,
+        deleted_at: None,
+    })
+}
+
+/// A leading top-level heading is the note's title, not part of its body
+fn take_title(blocks: &mut Vec<Block>) -> Option<String> {
+    match blocks.first() {
+        Some(Block::Heading { level: 1, content }) => {
+            let title = document::plain_text(content);
+            blocks.remove(0);
+            Some(title)
+        }
+        _ => None,
+    }
+}
+
+fn file_title(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+/// Modification and creation time in milliseconds; file systems that don't
+/// record creation time use the modification time for both
+fn file_times(path: &Path) -> Result<(i64, i64)> {
+    let metadata = std::fs::metadata(path)?;
+    let millis = |time: SystemTime| DateTime::<Utc>::from(time).timestamp_millis();
+
+    let modified = metadata.modified().map(millis).unwrap_or_else(|_| Utc::now().timestamp_millis());
+    let created = metadata.created().map(millis).unwrap_or(modified);
+    Ok((modified, created.min(modified)))
+}
@@ -0,0 +1,88 @@
+//! Markdown and plain-text files.
+
+use chrono::DateTime;
+use std::path::Path;
+
+use super::{new_note, read_to_string, Result};
+use crate::document::{self, markdown};
+use crate::storage::NewNote;
+
+pub(super) fn read_markdown(path: &Path) -> Result<NewNote> {
+    let text = read_to_string(path)?;
+    let (front_matter, body) = FrontMatter::split(&text);
+
+    let mut note = new_note(front_matter.title, markdown::parse(body), path)?;
+    note.starred = front_matter.starred;
+    note.created_at = front_matter.created.unwrap_or(note.created_at);
+    note.updated_at = front_matter.updated.unwrap_or(note.updated_at);
+    Ok(note)
+}
+
+/// One paragraph per line, titled after the file
+pub(super) fn read_text(path: &Path) -> Result<NewNote> {
+    let text = read_to_string(path)?;
+    new_note(None, document::from_plain_text(&text), path)
+}
+
+/// The fields of YAML front-matter that map onto a note, including those
+/// written by Markdown export
+#[derive(Debug, Default)]
+struct FrontMatter {
+    title: Option<String>,
+    starred: bool,
+    created: Option<i64>,
+    updated: Option<i64>,
+}
+
+impl FrontMatter {
+    /// Separate front-matter from the Markdown body. Only simple
+    /// `key: value` lines are understood; anything else is ignored.
+    fn split(text: &str) -> (Self, &str) {
+        let mut front_matter = Self::default();
+        let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+            return (front_matter, text);
+        };
+
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            offset += line.len();
+            let line = line.trim_end();
+            if line == "---" || line == "..." {
+                return (front_matter, &rest[offset..]);
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = unquote(value.trim());
+            match key.trim() {
+                "title" => front_matter.title = Some(value),
+                "starred" => front_matter.starred = value == "true",
+                "created" => front_matter.created = parse_timestamp(&value),
+                "updated" => front_matter.updated = parse_timestamp(&value),
+                _ => {}
+            }
+        }
+
+        // Never closed, so it wasn't front-matter after all
+        (Self::default(), text)
+    }
+}
+
+fn unquote(value: &str) -> String {
+    if value.starts_with('"') {
+        if let Ok(value) = serde_json::from_str::<String>(value) {
+            return value;
+        }
+    }
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].replace("''", "'");
+    }
+    value.to_string()
+}
+
+fn parse_timestamp(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.timestamp_millis())
+}
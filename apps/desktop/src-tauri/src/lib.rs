@@ -1,6 +1,12 @@
 mod commands;
+mod document;
+mod export;
+mod fetch_guard;
+mod import;
 mod logging;
+mod reminders;
 mod storage;
+mod unfurl;
 
 use tauri::Manager;
 
@@ -9,23 +15,28 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
             // Initialize storage
             let app_data_dir = app.path().app_data_dir().expect("Failed to get app data dir");
             std::fs::create_dir_all(&app_data_dir).expect("Failed to create app data dir");
 
-            let storage = storage::Storage::new(&app_data_dir)
-                .expect("Failed to initialize storage");
-
-            // Ensure the Scratch Pad note exists
-            storage.ensure_scratch_pad().expect("Failed to ensure scratch pad");
-
             // Initialize logger
             let logger = logging::AppLogger::new(1000);
             logger.info("system", "Application started");
 
-            app.manage(storage);
+            if storage::Storage::is_encrypted(&app_data_dir) {
+                // Storage is managed once the frontend unlocks it (see unlock_library)
+                logger.info("storage", "Note library is encrypted, waiting for passphrase");
+            } else {
+                let storage = storage::Storage::new(&app_data_dir)
+                    .expect("Failed to initialize storage");
+                prepare_storage(&storage, &logger).expect("Failed to ensure scratch pad");
+                app.manage(storage);
+            }
             app.manage(logger);
+            reminders::start(app.handle().clone());
 
             Ok(())
         })
@@ -40,8 +51,52 @@ pub fn run() {
             commands::restore_note,
             commands::permanently_delete_note,
             commands::duplicate_note,
+            commands::get_folders,
+            commands::create_folder,
+            commands::rename_folder,
+            commands::move_folder,
+            commands::delete_folder,
+            commands::move_note,
+            commands::get_tags,
+            commands::get_note_tags,
+            commands::add_note_tag,
+            commands::remove_note_tag,
+            commands::rename_tag,
+            commands::delete_tag,
+            commands::get_open_tasks,
+            commands::get_today_tasks,
+            commands::get_upcoming_tasks,
+            commands::get_recently_completed_tasks,
+            commands::get_note_tasks,
+            commands::get_task_counts,
+            commands::toggle_task,
+            commands::complete_task,
+            commands::snooze_task,
             commands::search_notes,
-            commands::fetch_url_title,
+            commands::get_saved_searches,
+            commands::create_saved_search,
+            commands::update_saved_search,
+            commands::delete_saved_search,
+            commands::run_saved_search,
+            commands::get_note_attachments,
+            commands::add_attachment_files,
+            commands::add_attachment,
+            commands::save_attachment,
+            commands::remove_attachment,
+            commands::get_backlinks,
+            commands::get_storage_layout,
+            commands::set_storage_layout,
+            commands::get_offline_mode,
+            commands::set_offline_mode,
+            commands::get_encryption_status,
+            commands::unlock_library,
+            commands::enable_encryption,
+            commands::disable_encryption,
+            commands::change_passphrase,
+            commands::export_note,
+            commands::export_all,
+            commands::import_notes,
+            commands::unfurl_url,
             commands::get_app_info,
             commands::get_logs,
             commands::get_logs_since,
@@ -50,3 +105,48 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Get a freshly opened library ready for use
+pub(crate) fn prepare_storage(storage: &storage::Storage, logger: &logging::AppLogger) -> storage::Result<()> {
+    // Ensure the Scratch Pad note exists
+    storage.ensure_scratch_pad()?;
+
+    // Repair or flag content files left inconsistent by a crash
+    match storage.check_consistency() {
+        Ok(report) if report.is_clean() => {}
+        Ok(report) => {
+            for id in &report.missing {
+                logger.error("storage", &format!("Content file missing for note {}", id));
+            }
+            for id in &report.orphaned {
+                logger.warn("storage", &format!("Moved aside orphaned content: {}", id));
+            }
+            for id in &report.repaired {
+                logger.warn("storage", &format!("Corrected content length for note {}", id));
+            }
+            if report.temp_files_removed > 0 {
+                logger.warn(
+                    "storage",
+                    &format!("Removed {} interrupted content writes", report.temp_files_removed),
+                );
+            }
+        }
+        Err(e) => logger.error("storage", &format!("Consistency check failed: {}", e)),
+    }
+
+    // Attachment blobs left behind by an interrupted removal
+    match storage.collect_garbage() {
+        Ok(0) => {}
+        Ok(count) => logger.info("attachments", &format!("Removed {} unused attachment files", count)),
+        Err(e) => logger.error("attachments", &format!("Attachment cleanup failed: {}", e)),
+    }
+
+    // Index tasks of notes saved before tasks were extracted
+    match storage.index_pending_notes() {
+        Ok(0) => {}
+        Ok(count) => logger.info("tasks", &format!("Indexed tasks of {} notes", count)),
+        Err(e) => logger.error("tasks", &format!("Task indexing failed: {}", e)),
+    }
+
+    Ok(())
+}
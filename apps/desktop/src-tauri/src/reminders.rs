@@ -0,0 +1,54 @@
+//! Task reminders.
+//!
+//! A background thread asks storage for reminders that came due and shows a
+//! native notification for each, whether or not the task's note is open.
+//! Desktop notifications can't carry actions, so the frontend also gets a
+//! `task-reminder` event and offers snooze and mark-done from there.
+
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::logging::AppLogger;
+use crate::storage::Storage;
+
+/// How often due reminders are looked for
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Event sent to the frontend with each task whose reminder is due
+pub const REMINDER_EVENT: &str = "task-reminder";
+
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        check(&app);
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+fn check(app: &AppHandle) {
+    // Nothing is due while the library is locked
+    let Some(storage) = app.try_state::<Storage>() else {
+        return;
+    };
+    let logger = app.state::<AppLogger>();
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let tasks = match storage.take_due_reminders(now) {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            logger.error("reminders", &format!("Failed to check reminders: {}", e));
+            return;
+        }
+    };
+
+    for task in tasks {
+        let title = if task.note_title.is_empty() { "Reminder" } else { task.note_title.as_str() };
+        if let Err(e) = app.notification().builder().title(title).body(&task.text).show() {
+            logger.warn("reminders", &format!("Failed to show notification: {}", e));
+        }
+        if let Err(e) = app.emit(REMINDER_EVENT, &task) {
+            logger.warn("reminders", &format!("Failed to send reminder to the window: {}", e));
+        }
+        logger.info("reminders", &format!("Reminder for task {}", task.id));
+    }
+}
@@ -0,0 +1,258 @@
+//! Files attached to notes.
+//!
+//! Attached files live in `blobs/`, each named by the SHA-256 of its
+//! content, so a file attached to several notes (or twice to one) is stored
+//! once. `note_attachments` records which note has which blob under what
+//! name. A blob is removed once no attachment refers to it any more;
+//! attachments of notes in the trash keep theirs until the note is deleted
+//! for good. Like content files, blobs are encrypted with the library key
+//! while the library is encrypted, and plaintext blobs are still read.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::crypto;
+use super::{write_atomic, Result, Storage, StorageError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    #[serde(rename = "noteId")]
+    pub note_id: String,
+    /// SHA-256 of the content, hex encoded; also the blob's file name
+    pub hash: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    pub size: i64,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+const ATTACHMENT_COLUMNS: &str = "id, note_id, hash, file_name, content_type, size, created_at";
+
+fn read_attachment(row: &rusqlite::Row) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        note_id: row.get(1)?,
+        hash: row.get(2)?,
+        file_name: row.get(3)?,
+        content_type: row.get(4)?,
+        size: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+/// The name a blob with this content is stored under
+pub fn content_hash(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Content type of a file going by its extension, for files added from
+/// disk; anything unknown is plain bytes
+pub fn content_type_for(file_name: &str) -> &'static str {
+    let extension = Path::new(file_name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+pub(super) fn remove_note(conn: &Connection, note_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM note_attachments WHERE note_id = ?", [note_id])?;
+    Ok(())
+}
+
+/// Give the note `to` the attachments of `from`, sharing their blobs
+pub(super) fn copy_note(conn: &Connection, from: &str, to: &str) -> Result<()> {
+    let attachments: Vec<Attachment> = conn
+        .prepare(&format!("SELECT {} FROM note_attachments WHERE note_id = ?", ATTACHMENT_COLUMNS))?
+        .query_map([from], read_attachment)?
+        .collect::<std::result::Result<_, _>>()?;
+    for attachment in attachments {
+        conn.execute(
+            "INSERT INTO note_attachments (id, note_id, hash, file_name, content_type, size, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                Uuid::now_v7().to_string(),
+                to,
+                attachment.hash,
+                attachment.file_name,
+                attachment.content_type,
+                attachment.size,
+                attachment.created_at
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Delete the blobs no attachment refers to, along with interrupted blob
+/// writes. Returns the number of files removed.
+pub(super) fn remove_unreferenced_blobs(conn: &Connection, dir: &Path) -> Result<usize> {
+    if !dir.is_dir() {
+        return Ok(0);
+    }
+    let referenced: HashSet<String> = conn
+        .prepare("SELECT DISTINCT hash FROM note_attachments")?
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<_, _>>()?;
+
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if path.is_file() && !referenced.contains(&file_name) {
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+impl Storage {
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.blobs_dir.join(hash)
+    }
+
+    /// Attach `data` to a note as `file_name`. The content type is guessed
+    /// from the name if not given.
+    pub fn add_attachment(
+        &self,
+        note_id: &str,
+        file_name: &str,
+        content_type: Option<&str>,
+        data: &[u8],
+    ) -> Result<Attachment> {
+        let conn = self.conn.lock().unwrap();
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM notes WHERE id = ?)",
+            [note_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(StorageError::NoteNotFound(note_id.to_string()));
+        }
+
+        // The blob is complete on disk before any attachment refers to it
+        let hash = content_hash(data);
+        let path = self.blob_path(&hash);
+        if !path.exists() {
+            std::fs::create_dir_all(&self.blobs_dir)?;
+            match self.key.lock().unwrap().as_deref() {
+                Some(key) => write_atomic(&path, &crypto::encrypt_content(key, &hash, data)?)?,
+                None => write_atomic(&path, data)?,
+            }
+        }
+
+        let attachment = Attachment {
+            id: Uuid::now_v7().to_string(),
+            note_id: note_id.to_string(),
+            hash,
+            file_name: file_name.trim().to_string(),
+            content_type: content_type
+                .filter(|content_type| !content_type.trim().is_empty())
+                .unwrap_or_else(|| content_type_for(file_name))
+                .to_string(),
+            size: data.len() as i64,
+            created_at: chrono::Utc::now().timestamp_millis(),
+        };
+        conn.execute(
+            "INSERT INTO note_attachments (id, note_id, hash, file_name, content_type, size, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                attachment.id,
+                attachment.note_id,
+                attachment.hash,
+                attachment.file_name,
+                attachment.content_type,
+                attachment.size,
+                attachment.created_at
+            ],
+        )?;
+
+        Ok(attachment)
+    }
+
+    /// Attach a file from disk under its own name
+    pub fn add_attachment_file(&self, note_id: &str, path: &Path) -> Result<Attachment> {
+        let data = std::fs::read(path)?;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        self.add_attachment(note_id, &file_name, None, &data)
+    }
+
+    /// Attachments of a note, oldest first
+    pub fn get_note_attachments(&self, note_id: &str) -> Result<Vec<Attachment>> {
+        let conn = self.conn.lock().unwrap();
+        let attachments = conn
+            .prepare(&format!(
+                "SELECT {} FROM note_attachments WHERE note_id = ? ORDER BY created_at, id",
+                ATTACHMENT_COLUMNS
+            ))?
+            .query_map([note_id], read_attachment)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(attachments)
+    }
+
+    /// An attachment and its content
+    pub fn read_attachment(&self, id: &str) -> Result<(Attachment, Vec<u8>)> {
+        let conn = self.conn.lock().unwrap();
+        let attachment = conn
+            .query_row(
+                &format!("SELECT {} FROM note_attachments WHERE id = ?", ATTACHMENT_COLUMNS),
+                [id],
+                read_attachment,
+            )
+            .optional()?
+            .ok_or_else(|| StorageError::AttachmentNotFound(id.to_string()))?;
+
+        let data = std::fs::read(self.blob_path(&attachment.hash))?;
+        if !crypto::is_encrypted_content(&data) {
+            return Ok((attachment, data));
+        }
+        match self.key.lock().unwrap().as_deref() {
+            Some(key) => {
+                let data = crypto::decrypt_content(key, &attachment.hash, &data)?;
+                Ok((attachment, data))
+            }
+            None => Err(StorageError::Locked),
+        }
+    }
+
+    /// Take an attachment off its note, deleting the blob if nothing else
+    /// refers to it
+    pub fn remove_attachment(&self, id: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let rows = conn.execute("DELETE FROM note_attachments WHERE id = ?", [id])?;
+        if rows == 0 {
+            return Err(StorageError::AttachmentNotFound(id.to_string()));
+        }
+        remove_unreferenced_blobs(&conn, &self.blobs_dir)?;
+
+        Ok(())
+    }
+
+    /// Delete blobs left without attachments, e.g. by an interrupted
+    /// removal. Returns the number of files removed.
+    pub fn collect_garbage(&self) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        remove_unreferenced_blobs(&conn, &self.blobs_dir)
+    }
+}
@@ -0,0 +1,106 @@
+//! Startup check that the `notes` table and the stored content agree.
+//!
+//! Content is only ever replaced atomically (renamed files, or BLOBs written
+//! in a transaction), so any stored document is complete and is treated as
+//! authoritative over the recorded `content_length`.
+
+use rusqlite::params;
+use serde::Serialize;
+
+use super::{Result, Storage, StorageLayout, TEMP_SUFFIX};
+
+#[derive(Debug, Default, Serialize)]
+pub struct ConsistencyReport {
+    /// Content with no matching note; files are moved to `notes/orphaned/`,
+    /// BLOBs to the `orphaned_content` table
+    pub orphaned: Vec<String>,
+    /// Notes that had content recorded but whose content is gone
+    pub missing: Vec<String>,
+    /// Notes whose recorded content length was corrected from the content
+    pub repaired: Vec<String>,
+    /// Interrupted file writes that were cleaned up
+    #[serde(rename = "tempFilesRemoved")]
+    pub temp_files_removed: usize,
+}
+
+impl ConsistencyReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphaned.is_empty()
+            && self.missing.is_empty()
+            && self.repaired.is_empty()
+            && self.temp_files_removed == 0
+    }
+}
+
+impl Storage {
+    pub fn check_consistency(&self) -> Result<ConsistencyReport> {
+        let mut report = ConsistencyReport::default();
+        let conn = self.conn.lock().unwrap();
+        let content = self.content.read().unwrap();
+
+        let mut stmt = conn.prepare("SELECT id, content_length FROM notes")?;
+        let notes = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        drop(stmt);
+
+        for (id, recorded) in &notes {
+            let actual = content.size(&conn, id)?.map(|size| size as i64);
+
+            match (recorded, actual) {
+                (Some(recorded), Some(actual)) if *recorded == actual => {}
+                // New notes have no content until their first save
+                (Some(0), None) => {}
+                (Some(_), None) => report.missing.push(id.clone()),
+                (recorded, actual) => {
+                    conn.execute(
+                        "UPDATE notes SET content_length = ? WHERE id = ?",
+                        params![actual.unwrap_or(0), id],
+                    )?;
+                    // Filling in the length for the first time isn't a repair
+                    if recorded.is_some() {
+                        report.repaired.push(id.clone());
+                    }
+                }
+            }
+        }
+
+        let known: std::collections::HashSet<&str> =
+            notes.iter().map(|(id, _)| id.as_str()).collect();
+        let orphaned_dir = self.notes_dir.join("orphaned");
+
+        for id in content.ids(&conn)? {
+            if known.contains(id.as_str()) {
+                continue;
+            }
+            match content.layout() {
+                StorageLayout::Files => {
+                    let file_name = format!("{}.yjs", id);
+                    std::fs::create_dir_all(&orphaned_dir)?;
+                    std::fs::rename(self.notes_dir.join(&file_name), orphaned_dir.join(&file_name))?;
+                }
+                StorageLayout::Database => {
+                    let tx = conn.unchecked_transaction()?;
+                    tx.execute(
+                        "INSERT OR REPLACE INTO orphaned_content (id, data, orphaned_at) SELECT id, data, ? FROM note_content WHERE id = ?",
+                        params![chrono::Utc::now().timestamp_millis(), id],
+                    )?;
+                    content.delete(&tx, &id)?;
+                    tx.commit()?;
+                }
+            }
+            report.orphaned.push(id);
+        }
+
+        // Leftovers of interrupted file writes, whichever layout is active now
+        for entry in std::fs::read_dir(&self.notes_dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.to_string_lossy().ends_with(TEMP_SUFFIX) {
+                std::fs::remove_file(&path)?;
+                report.temp_files_removed += 1;
+            }
+        }
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,191 @@
+//! Where note content (the Yjs document of each note) is kept.
+//!
+//! Metadata always lives in `pdtodo.db`. Content is either stored as loose
+//! `notes/{id}.yjs` files (the original layout) or as BLOBs in the
+//! `note_content` table, which makes the whole library a single file.
+//! When the library is encrypted, files are encrypted individually while
+//! BLOBs are covered by the database encryption.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::crypto::{self, DataKey};
+use super::{write_atomic, Result, StorageError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageLayout {
+    Files,
+    Database,
+}
+
+impl StorageLayout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageLayout::Files => "files",
+            StorageLayout::Database => "database",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "files" => Some(StorageLayout::Files),
+            "database" => Some(StorageLayout::Database),
+            _ => None,
+        }
+    }
+}
+
+/// Backend for note content. Methods receive the storage connection so the
+/// database backend can take part in the caller's transaction; writes must be
+/// durable once they return, before the caller commits note metadata.
+pub trait ContentStore: Send + Sync {
+    fn layout(&self) -> StorageLayout;
+    fn read(&self, conn: &Connection, id: &str) -> Result<Option<Vec<u8>>>;
+    fn write(&self, conn: &Connection, id: &str, data: &[u8]) -> Result<()>;
+    fn delete(&self, conn: &Connection, id: &str) -> Result<()>;
+    /// Size of the stored document, or None if there is none
+    fn size(&self, conn: &Connection, id: &str) -> Result<Option<u64>>;
+    /// Ids of all stored documents, including ones without a note
+    fn ids(&self, conn: &Connection) -> Result<Vec<String>>;
+}
+
+/// One `{id}.yjs` file per note in the notes directory. With a key, files
+/// are written encrypted; plaintext files are still read, so a library that
+/// is part way through being encrypted stays readable.
+pub struct FileStore {
+    dir: PathBuf,
+    key: Option<Arc<DataKey>>,
+}
+
+impl FileStore {
+    pub fn new(dir: &Path, key: Option<Arc<DataKey>>) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            key,
+        }
+    }
+
+    pub fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.yjs", id))
+    }
+}
+
+impl ContentStore for FileStore {
+    fn layout(&self) -> StorageLayout {
+        StorageLayout::Files
+    }
+
+    fn read(&self, _conn: &Connection, id: &str) -> Result<Option<Vec<u8>>> {
+        let data = match std::fs::read(self.path(id)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        if !crypto::is_encrypted_content(&data) {
+            return Ok(Some(data));
+        }
+        match &self.key {
+            Some(key) => Ok(Some(crypto::decrypt_content(key, id, &data)?)),
+            None => Err(StorageError::Locked),
+        }
+    }
+
+    fn write(&self, _conn: &Connection, id: &str, data: &[u8]) -> Result<()> {
+        match &self.key {
+            Some(key) => write_atomic(&self.path(id), &crypto::encrypt_content(key, id, data)?)?,
+            None => write_atomic(&self.path(id), data)?,
+        }
+        Ok(())
+    }
+
+    fn delete(&self, _conn: &Connection, id: &str) -> Result<()> {
+        match std::fs::remove_file(self.path(id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn size(&self, _conn: &Connection, id: &str) -> Result<Option<u64>> {
+        let path = self.path(id);
+        match std::fs::metadata(&path) {
+            // Report the plaintext size, which is what `content_length` records
+            Ok(meta) => Ok(Some(crypto::content_size(&path, meta.len())?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn ids(&self, _conn: &Connection) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            if let Some(id) = file_name.strip_suffix(".yjs") {
+                ids.push(id.to_string());
+            }
+        }
+        Ok(ids)
+    }
+}
+
+/// Content as BLOBs in the `note_content` table
+pub struct DatabaseStore;
+
+impl ContentStore for DatabaseStore {
+    fn layout(&self) -> StorageLayout {
+        StorageLayout::Database
+    }
+
+    fn read(&self, conn: &Connection, id: &str) -> Result<Option<Vec<u8>>> {
+        let data = conn
+            .query_row("SELECT data FROM note_content WHERE id = ?", [id], |row| row.get(0))
+            .optional()?;
+        Ok(data)
+    }
+
+    fn write(&self, conn: &Connection, id: &str, data: &[u8]) -> Result<()> {
+        conn.execute(
+            "INSERT INTO note_content (id, data) VALUES (?, ?) ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            params![id, data],
+        )?;
+        Ok(())
+    }
+
+    fn delete(&self, conn: &Connection, id: &str) -> Result<()> {
+        conn.execute("DELETE FROM note_content WHERE id = ?", [id])?;
+        Ok(())
+    }
+
+    fn size(&self, conn: &Connection, id: &str) -> Result<Option<u64>> {
+        let len: Option<i64> = conn
+            .query_row("SELECT length(data) FROM note_content WHERE id = ?", [id], |row| row.get(0))
+            .optional()?;
+        Ok(len.map(|len| len as u64))
+    }
+
+    fn ids(&self, conn: &Connection) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare("SELECT id FROM note_content")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+}
+
+pub fn open_store(
+    layout: StorageLayout,
+    notes_dir: &Path,
+    key: Option<Arc<DataKey>>,
+) -> Box<dyn ContentStore> {
+    match layout {
+        StorageLayout::Files => Box::new(FileStore::new(notes_dir, key)),
+        StorageLayout::Database => Box::new(DatabaseStore),
+    }
+}
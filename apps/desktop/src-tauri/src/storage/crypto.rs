@@ -0,0 +1,275 @@
+//! Passphrase encryption of the local note library.
+//!
+//! A random data key encrypts everything on disk: `pdtodo.db` through
+//! SQLCipher (so titles, the FTS index and settings are covered too) and
+//! content files with XChaCha20-Poly1305. The data key itself is kept in
+//! `pdtodo.key`, wrapped with a key derived from the passphrase by Argon2id,
+//! so changing the passphrase only rewrites that file.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use zeroize::Zeroize;
+
+use super::{write_atomic, Result, StorageError};
+
+/// Prefix of encrypted content files; anything else is read as plaintext
+const FILE_MAGIC: &[u8] = b"PDTE\x01";
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Every plaintext SQLite database starts with this; SQLCipher files don't
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+const KEY_FILE_VERSION: u32 = 1;
+
+/// Argon2id cost for newly wrapped keys (64 MiB, 3 passes)
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 1;
+
+/// The key all library data is encrypted with
+pub struct DataKey([u8; KEY_LEN]);
+
+impl DataKey {
+    pub fn generate() -> Self {
+        let mut key = [0u8; KEY_LEN];
+        OsRng.fill_bytes(&mut key);
+        Self(key)
+    }
+
+    /// Value for SQLCipher's `PRAGMA key`; a raw key skips its own KDF
+    pub fn sqlcipher_key(&self) -> String {
+        format!("x'{}'", hex::encode_upper(self.0))
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new((&self.0).into())
+    }
+}
+
+impl Drop for DataKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Progress of enabling or disabling encryption, so an interrupted switch
+/// is finished on the next unlock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyState {
+    Enabling,
+    Enabled,
+    Disabling,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+}
+
+/// Contents of `pdtodo.key`. Its presence means the library is encrypted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyFile {
+    version: u32,
+    pub state: KeyState,
+    kdf: KdfParams,
+    /// Nonce followed by the data key encrypted with the passphrase key
+    wrapped_key: String,
+}
+
+impl KeyFile {
+    /// Wrap `key` with a new salt derived from `passphrase`
+    pub fn new(passphrase: &str, key: &DataKey, state: KeyState) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(StorageError::Encryption("Passphrase must not be empty".to_string()));
+        }
+
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let kdf = KdfParams {
+            memory_kib: KDF_MEMORY_KIB,
+            iterations: KDF_ITERATIONS,
+            parallelism: KDF_PARALLELISM,
+            salt: hex::encode(salt),
+        };
+
+        let wrapping_key = derive_key(passphrase, &kdf)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let wrapped = wrapping_key
+            .cipher()
+            .encrypt(&nonce, key.0.as_slice())
+            .map_err(|_| StorageError::Encryption("Failed to wrap data key".to_string()))?;
+
+        let mut wrapped_key = nonce.to_vec();
+        wrapped_key.extend_from_slice(&wrapped);
+
+        Ok(Self {
+            version: KEY_FILE_VERSION,
+            state,
+            kdf,
+            wrapped_key: hex::encode(wrapped_key),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        let key_file: KeyFile = serde_json::from_slice(&data)
+            .map_err(|e| StorageError::Encryption(format!("Invalid key file: {}", e)))?;
+
+        if key_file.version > KEY_FILE_VERSION {
+            return Err(StorageError::Encryption(format!(
+                "Key file version {} is newer than this app supports",
+                key_file.version
+            )));
+        }
+        Ok(key_file)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| StorageError::Encryption(e.to_string()))?;
+        write_atomic(path, &data)?;
+        Ok(())
+    }
+
+    /// Recover the data key; fails with `WrongPassphrase` if it doesn't match
+    pub fn unwrap_key(&self, passphrase: &str) -> Result<DataKey> {
+        let wrapped = hex::decode(&self.wrapped_key)
+            .map_err(|e| StorageError::Encryption(format!("Invalid key file: {}", e)))?;
+        if wrapped.len() != NONCE_LEN + KEY_LEN + TAG_LEN {
+            return Err(StorageError::Encryption("Invalid key file: bad key length".to_string()));
+        }
+
+        let (nonce, ciphertext) = wrapped.split_at(NONCE_LEN);
+        let wrapping_key = derive_key(passphrase, &self.kdf)?;
+        let mut plain = wrapping_key
+            .cipher()
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| StorageError::WrongPassphrase)?;
+
+        let mut key = [0u8; KEY_LEN];
+        key.copy_from_slice(&plain);
+        plain.zeroize();
+        Ok(DataKey(key))
+    }
+}
+
+fn derive_key(passphrase: &str, kdf: &KdfParams) -> Result<DataKey> {
+    let salt = hex::decode(&kdf.salt)
+        .map_err(|e| StorageError::Encryption(format!("Invalid key file: {}", e)))?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(KEY_LEN))
+        .map_err(|e| StorageError::Encryption(e.to_string()))?;
+
+    let mut key = [0u8; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| StorageError::Encryption(e.to_string()))?;
+    Ok(DataKey(key))
+}
+
+pub fn is_encrypted_content(data: &[u8]) -> bool {
+    data.starts_with(FILE_MAGIC)
+}
+
+/// Encrypt note content. The note id is authenticated along with it, so
+/// files can't be swapped between notes unnoticed.
+pub fn encrypt_content(key: &DataKey, id: &str, plain: &[u8]) -> Result<Vec<u8>> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = key
+        .cipher()
+        .encrypt(&nonce, Payload { msg: plain, aad: id.as_bytes() })
+        .map_err(|_| StorageError::Encryption(format!("Failed to encrypt note {}", id)))?;
+
+    let mut data = Vec::with_capacity(FILE_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    data.extend_from_slice(FILE_MAGIC);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+pub fn decrypt_content(key: &DataKey, id: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let body = &data[FILE_MAGIC.len()..];
+    if body.len() < NONCE_LEN + TAG_LEN {
+        return Err(StorageError::Encryption(format!("Encrypted content of note {} is truncated", id)));
+    }
+
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    key.cipher()
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: id.as_bytes() })
+        .map_err(|_| StorageError::Encryption(format!("Failed to decrypt note {}", id)))
+}
+
+/// Plaintext size of a stored content file of `stored_len` bytes
+pub fn content_size(path: &Path, stored_len: u64) -> std::io::Result<u64> {
+    let mut prefix = [0u8; FILE_MAGIC.len()];
+    let mut file = std::fs::File::open(path)?;
+    let overhead = (FILE_MAGIC.len() + NONCE_LEN + TAG_LEN) as u64;
+
+    match file.read_exact(&mut prefix) {
+        Ok(()) if prefix == FILE_MAGIC => Ok(stored_len.saturating_sub(overhead)),
+        Ok(()) => Ok(stored_len),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(stored_len),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether the database file is encrypted. A missing or empty file isn't
+/// plaintext yet, so it is created with whichever key is given.
+pub fn is_encrypted_database(db_path: &Path) -> Result<bool> {
+    let mut header = [0u8; SQLITE_HEADER.len()];
+    let mut file = match std::fs::File::open(db_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    if file.metadata()?.len() == 0 {
+        return Ok(false);
+    }
+    file.read_exact(&mut header)?;
+    Ok(header != SQLITE_HEADER)
+}
+
+/// Open the database, keyed if `key` is given, and check the key works
+pub fn open_database(db_path: &Path, key: Option<&DataKey>) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key.sqlcipher_key())?;
+    }
+    // SQLCipher only reads the first page once the schema is queried
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))?;
+    Ok(conn)
+}
+
+/// Copy the whole database to `dest`, encrypted with `key` or as plaintext
+pub fn export_database(conn: &Connection, dest: &Path, key: Option<&DataKey>) -> Result<()> {
+    if dest.exists() {
+        std::fs::remove_file(dest)?;
+    }
+
+    let dest_key = key.map(DataKey::sqlcipher_key).unwrap_or_default();
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    conn.execute(
+        "ATTACH DATABASE ? AS export KEY ?",
+        [dest.to_string_lossy().as_ref(), dest_key.as_str()],
+    )?;
+    let exported = conn
+        .query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()))
+        // sqlcipher_export doesn't carry the schema version over
+        .and_then(|_| conn.execute_batch(&format!("PRAGMA export.user_version = {};", version)));
+    conn.execute("DETACH DATABASE export", [])?;
+    exported?;
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+//! Turning library encryption on and off (see `crypto.rs` for the scheme).
+//!
+//! The key file records whether a switch is in progress. Each step checks
+//! the actual state of the files it converts, so an interrupted switch is
+//! simply run again on the next unlock.
+
+use rusqlite::Connection;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::content::open_store;
+use super::crypto::{self, DataKey, KeyFile, KeyState};
+use super::{
+    enable_wal, write_atomic, Result, Storage, StorageError, StorageLayout, DB_FILE, KEY_FILE, TEMP_SUFFIX,
+};
+
+impl Storage {
+    /// Encrypt the library with a new key protected by `passphrase`
+    pub fn enable_encryption(&self, passphrase: &str) -> Result<()> {
+        let key_path = self.db_path.with_file_name(KEY_FILE);
+        if key_path.exists() {
+            return Err(StorageError::Encryption("Library is already encrypted".to_string()));
+        }
+
+        let key = DataKey::generate();
+        // The wrapped key must be on disk before anything is encrypted with it
+        KeyFile::new(passphrase, &key, KeyState::Enabling)?.save(&key_path)?;
+        self.encrypt_library_with(Arc::new(key))
+    }
+
+    /// Decrypt the library and forget the key; `passphrase` must match
+    pub fn disable_encryption(&self, passphrase: &str) -> Result<()> {
+        let key_path = self.db_path.with_file_name(KEY_FILE);
+        if !key_path.exists() {
+            return Err(StorageError::Encryption("Library is not encrypted".to_string()));
+        }
+
+        let mut key_file = KeyFile::load(&key_path)?;
+        key_file.unwrap_key(passphrase)?;
+        key_file.state = KeyState::Disabling;
+        key_file.save(&key_path)?;
+        self.decrypt_library()
+    }
+
+    /// Re-wrap the data key with a new passphrase. Notes are encrypted with
+    /// the data key, not the passphrase, so only the key file changes.
+    pub fn change_passphrase(&self, current: &str, new: &str) -> Result<()> {
+        let key_path = self.db_path.with_file_name(KEY_FILE);
+        if !key_path.exists() {
+            return Err(StorageError::Encryption("Library is not encrypted".to_string()));
+        }
+
+        // Hold the connection so this can't race with enabling or disabling
+        let _conn = self.conn.lock().unwrap();
+        let key_file = KeyFile::load(&key_path)?;
+        let key = key_file.unwrap_key(current)?;
+        KeyFile::new(new, &key, key_file.state)?.save(&key_path)
+    }
+
+    /// Finish encrypting the library with the unlocked key
+    pub(super) fn encrypt_library(&self) -> Result<()> {
+        let key = self.key.lock().unwrap().clone().ok_or(StorageError::Locked)?;
+        self.encrypt_library_with(key)
+    }
+
+    fn encrypt_library_with(&self, key: Arc<DataKey>) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let mut content = self.content.write().unwrap();
+
+        // From here on new content is written encrypted
+        *self.key.lock().unwrap() = Some(key.clone());
+        *content = open_store(content.layout(), &self.notes_dir, Some(key.clone()));
+
+        self.convert_content_files(&key, true)?;
+        if !crypto::is_encrypted_database(&self.db_path)? {
+            self.replace_database(&mut conn, Some(&key), content.layout())?;
+        }
+        self.remove_plaintext_backups()?;
+
+        let key_path = self.db_path.with_file_name(KEY_FILE);
+        let mut key_file = KeyFile::load(&key_path)?;
+        key_file.state = KeyState::Enabled;
+        key_file.save(&key_path)
+    }
+
+    /// Decrypt everything with the unlocked key, then remove the key file
+    pub(super) fn decrypt_library(&self) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let mut content = self.content.write().unwrap();
+        let key = self.key.lock().unwrap().clone().ok_or(StorageError::Locked)?;
+
+        self.convert_content_files(&key, false)?;
+        if crypto::is_encrypted_database(&self.db_path)? {
+            self.replace_database(&mut conn, None, content.layout())?;
+        }
+
+        std::fs::remove_file(self.db_path.with_file_name(KEY_FILE))?;
+        *self.key.lock().unwrap() = None;
+        *content = open_store(content.layout(), &self.notes_dir, None);
+
+        Ok(())
+    }
+
+    /// Encrypt or decrypt every content file, including orphaned ones, and
+    /// every attachment blob. Files already in the wanted form are left
+    /// alone. Content BLOBs, orphaned ones in `orphaned_content` included,
+    /// are converted with the rest of the database.
+    fn convert_content_files(&self, key: &DataKey, encrypt: bool) -> Result<()> {
+        for dir in [self.notes_dir.clone(), self.notes_dir.join("orphaned")] {
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let Some(id) = file_name.strip_suffix(".yjs") else {
+                    continue;
+                };
+                convert_file(&path, id, key, encrypt)?;
+            }
+        }
+
+        // Blobs are encrypted under their hash, which is their file name
+        if self.blobs_dir.is_dir() {
+            for entry in std::fs::read_dir(&self.blobs_dir)? {
+                let path = entry?.path();
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                if path.is_file() && !file_name.ends_with(TEMP_SUFFIX) {
+                    convert_file(&path, &file_name, key, encrypt)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Swap the database for a copy encrypted with `key`, or a plaintext
+    /// copy if `key` is None. The copy is complete before it replaces the
+    /// original, so an interruption leaves one or the other intact.
+    fn replace_database(
+        &self,
+        conn: &mut Connection,
+        key: Option<&DataKey>,
+        layout: StorageLayout,
+    ) -> Result<()> {
+        let export_path = self.db_path.with_extension("db.export");
+        crypto::export_database(conn, &export_path, key)?;
+
+        // Close the old connection first; an open file can't be replaced on Windows
+        let old = std::mem::replace(conn, Connection::open_in_memory()?);
+        old.close().map_err(|(_, e)| e)?;
+        std::fs::rename(&export_path, &self.db_path)?;
+
+        *conn = crypto::open_database(&self.db_path, key)?;
+        if layout == StorageLayout::Database {
+            enable_wal(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Migration backups taken before encryption was enabled would leave a
+    /// readable copy of the library behind
+    fn remove_plaintext_backups(&self) -> Result<()> {
+        let Some(dir) = self.db_path.parent() else {
+            return Ok(());
+        };
+        let prefix = format!("{}.v", DB_FILE);
+
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if file_name.starts_with(&prefix)
+                && file_name.ends_with(".bak")
+                && !crypto::is_encrypted_database(&path)?
+            {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn convert_file(path: &Path, id: &str, key: &DataKey, encrypt: bool) -> Result<()> {
+    let data = std::fs::read(path)?;
+    if crypto::is_encrypted_content(&data) == encrypt {
+        return Ok(());
+    }
+    let converted = if encrypt {
+        crypto::encrypt_content(key, id, &data)?
+    } else {
+        crypto::decrypt_content(key, id, &data)?
+    };
+    write_atomic(path, &converted)?;
+    Ok(())
+}
@@ -0,0 +1,176 @@
+//! Hierarchical folders for organizing notes.
+//!
+//! A folder has at most one parent and a note is in at most one folder.
+//! Deleting a folder deletes its subfolders too, and moves the notes in any
+//! of them to the trash, outside any folder, so they can still be restored.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{Result, Storage, StorageError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Folder {
+    pub id: String,
+    pub name: String,
+    /// None for top-level folders
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+}
+
+pub(super) fn ensure_folder_exists(conn: &Connection, id: &str) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM folders WHERE id = ?)",
+        [id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Err(StorageError::FolderNotFound(id.to_string()));
+    }
+    Ok(())
+}
+
+/// The folder and all folders below it
+fn subtree(conn: &Connection, id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        r#"
+        WITH RECURSIVE subtree(id) AS (
+            SELECT ?
+            UNION
+            SELECT f.id FROM folders f JOIN subtree s ON f.parent_id = s.id
+        )
+        SELECT id FROM subtree
+        "#,
+    )?;
+    let ids = stmt
+        .query_map([id], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(ids)
+}
+
+impl Storage {
+    /// All folders, by name; the frontend builds the tree from `parent_id`
+    pub fn get_folders(&self) -> Result<Vec<Folder>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, parent_id, created_at, updated_at FROM folders ORDER BY name COLLATE NOCASE",
+        )?;
+        let folders = stmt
+            .query_map([], |row| {
+                Ok(Folder {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    parent_id: row.get(2)?,
+                    created_at: row.get(3)?,
+                    updated_at: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(folders)
+    }
+
+    pub fn create_folder(&self, name: &str, parent_id: Option<&str>) -> Result<String> {
+        let id = Uuid::now_v7().to_string();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let conn = self.conn.lock().unwrap();
+        if let Some(parent_id) = parent_id {
+            ensure_folder_exists(&conn, parent_id)?;
+        }
+        conn.execute(
+            "INSERT INTO folders (id, parent_id, name, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
+            params![id, parent_id, name, now, now],
+        )?;
+
+        Ok(id)
+    }
+
+    pub fn rename_folder(&self, id: &str, name: &str) -> Result<()> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let conn = self.conn.lock().unwrap();
+        let rows = conn.execute(
+            "UPDATE folders SET name = ?, updated_at = ? WHERE id = ?",
+            params![name, now, id],
+        )?;
+
+        if rows == 0 {
+            return Err(StorageError::FolderNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Move a folder under `parent_id`, or to the top level
+    pub fn move_folder(&self, id: &str, parent_id: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        ensure_folder_exists(&conn, id)?;
+
+        if let Some(parent_id) = parent_id {
+            ensure_folder_exists(&conn, parent_id)?;
+            if subtree(&conn, id)?.iter().any(|folder| folder == parent_id) {
+                return Err(StorageError::FolderCycle);
+            }
+        }
+
+        let now = chrono::Utc::now().timestamp_millis();
+        conn.execute(
+            "UPDATE folders SET parent_id = ?, updated_at = ? WHERE id = ?",
+            params![parent_id, now, id],
+        )?;
+
+        Ok(())
+    }
+
+    /// Delete a folder and its subfolders. Returns the number of notes that
+    /// were moved to the trash.
+    pub fn delete_folder(&self, id: &str) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        ensure_folder_exists(&conn, id)?;
+
+        let folder_ids = subtree(&conn, id)?;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let tx = conn.unchecked_transaction()?;
+        let mut trashed = 0;
+        for folder_id in &folder_ids {
+            trashed += tx.execute(
+                "UPDATE notes SET folder_id = NULL, deleted_at = ?, updated_at = ? WHERE folder_id = ? AND deleted_at IS NULL",
+                params![now, now, folder_id],
+            )?;
+            // Notes already in the trash just leave the folder
+            tx.execute("UPDATE notes SET folder_id = NULL WHERE folder_id = ?", [folder_id])?;
+        }
+        // Children first, so no folder is left pointing at a deleted parent
+        for folder_id in folder_ids.iter().rev() {
+            tx.execute("DELETE FROM folders WHERE id = ?", [folder_id])?;
+        }
+        tx.commit()?;
+
+        Ok(trashed)
+    }
+
+    /// Move a note into `folder_id`, or out of any folder
+    pub fn move_note(&self, note_id: &str, folder_id: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        if let Some(folder_id) = folder_id {
+            ensure_folder_exists(&conn, folder_id)?;
+        }
+
+        let rows = conn.execute(
+            "UPDATE notes SET folder_id = ? WHERE id = ?",
+            params![folder_id, note_id],
+        )?;
+
+        if rows == 0 {
+            return Err(StorageError::NoteNotFound(note_id.to_string()));
+        }
+
+        Ok(())
+    }
+}
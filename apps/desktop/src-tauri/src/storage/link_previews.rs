@@ -0,0 +1,71 @@
+//! Cached link previews.
+//!
+//! Previews of web pages (see `unfurl`) are kept by URL so hovering a link
+//! again doesn't fetch the page again. A cached preview is used until it
+//! is older than the caller allows; expired previews are dropped whenever
+//! a new one is saved.
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::{Result, Storage};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "siteName")]
+    pub site_name: Option<String>,
+    #[serde(rename = "faviconUrl")]
+    pub favicon_url: Option<String>,
+    #[serde(rename = "fetchedAt")]
+    pub fetched_at: i64,
+}
+
+impl Storage {
+    /// The cached preview of `url`, unless it was fetched more than
+    /// `max_age_ms` ago
+    pub fn get_link_preview(&self, url: &str, max_age_ms: i64) -> Result<Option<LinkPreview>> {
+        let conn = self.conn.lock().unwrap();
+        let oldest = chrono::Utc::now().timestamp_millis() - max_age_ms;
+        let preview = conn
+            .query_row(
+                "SELECT url, title, description, site_name, favicon_url, fetched_at FROM link_previews WHERE url = ? AND fetched_at >= ?",
+                params![url, oldest],
+                |row| {
+                    Ok(LinkPreview {
+                        url: row.get(0)?,
+                        title: row.get(1)?,
+                        description: row.get(2)?,
+                        site_name: row.get(3)?,
+                        favicon_url: row.get(4)?,
+                        fetched_at: row.get(5)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(preview)
+    }
+
+    /// Cache a preview, dropping those fetched more than `max_age_ms` ago
+    pub fn save_link_preview(&self, preview: &LinkPreview, max_age_ms: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let oldest = chrono::Utc::now().timestamp_millis() - max_age_ms;
+        conn.execute("DELETE FROM link_previews WHERE fetched_at < ?", [oldest])?;
+        conn.execute(
+            "INSERT OR REPLACE INTO link_previews (url, title, description, site_name, favicon_url, fetched_at) VALUES (?, ?, ?, ?, ?, ?)",
+            params![
+                preview.url,
+                preview.title,
+                preview.description,
+                preview.site_name,
+                preview.favicon_url,
+                preview.fetched_at
+            ],
+        )?;
+
+        Ok(())
+    }
+}
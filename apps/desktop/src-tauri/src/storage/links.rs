@@ -0,0 +1,107 @@
+//! Links between notes.
+//!
+//! `note_links` holds the titles each note links to (see
+//! `document::links`), picked up when the note's content is saved. Links are
+//! kept by title rather than note id, so a link to a note that doesn't exist
+//! yet starts working once the note is created. Renaming a note rewrites the
+//! links to it in the other notes' content.
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{index_content, NoteMeta, Result, Storage, StorageError};
+use crate::document;
+
+pub(super) fn set_links(conn: &Connection, note_id: &str, titles: &[String]) -> Result<()> {
+    conn.execute("DELETE FROM note_links WHERE source_id = ?", [note_id])?;
+    let mut stmt = conn.prepare("INSERT OR IGNORE INTO note_links (source_id, target_title) VALUES (?, ?)")?;
+    for title in titles {
+        stmt.execute(params![note_id, title])?;
+    }
+    Ok(())
+}
+
+pub(super) fn remove_note(conn: &Connection, note_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM note_links WHERE source_id = ?", [note_id])?;
+    Ok(())
+}
+
+impl Storage {
+    /// Notes outside the trash that link to a note, most recently edited
+    /// first
+    pub fn get_backlinks(&self, note_id: &str) -> Result<Vec<NoteMeta>> {
+        let conn = self.conn.lock().unwrap();
+        let title: String = conn
+            .query_row("SELECT TRIM(title) FROM notes WHERE id = ?", [note_id], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| StorageError::NoteNotFound(note_id.to_string()))?;
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT n.id, n.title, n.starred, n.created_at, n.updated_at, n.deleted_at, n.folder_id
+            FROM notes n
+            JOIN note_links l ON l.source_id = n.id
+            WHERE l.target_title = ? AND n.id != ? AND n.deleted_at IS NULL
+            ORDER BY n.updated_at DESC
+            "#,
+        )?;
+        let notes = stmt
+            .query_map(params![title, note_id], |row| {
+                Ok(NoteMeta {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    starred: row.get::<_, i32>(2)? != 0,
+                    created_at: row.get(3)?,
+                    updated_at: row.get(4)?,
+                    deleted_at: row.get(5)?,
+                    folder_id: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(notes)
+    }
+
+    /// Point the links to note `id` at its new title after a rename.
+    /// Nothing changes when only the case changed, since links still match,
+    /// or when another note outside the trash still has the old title.
+    /// Returns the ids of the notes whose content was rewritten.
+    pub(super) fn rename_links(&self, conn: &Connection, id: &str, old: &str, new: &str) -> Result<Vec<String>> {
+        let (old, new) = (old.trim(), new.trim());
+        if old.is_empty() || old.to_lowercase() == new.to_lowercase() {
+            return Ok(Vec::new());
+        }
+        let taken: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM notes WHERE id != ? AND deleted_at IS NULL AND TRIM(title) = ? COLLATE NOCASE)",
+            params![id, old],
+            |row| row.get(0),
+        )?;
+        if taken {
+            return Ok(Vec::new());
+        }
+
+        let sources: Vec<String> = conn
+            .prepare("SELECT source_id FROM note_links WHERE target_title = ?")?
+            .query_map([old], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+
+        let store = self.content.read().unwrap();
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut renamed = Vec::new();
+        for source in sources {
+            let data = store.read(conn, &source)?.unwrap_or_default();
+            // Content that can't be read keeps its links
+            let Ok(Some(content)) = document::links::rename(&data, old, new) else {
+                continue;
+            };
+            store.write(conn, &source, &content)?;
+            conn.execute(
+                "UPDATE notes SET updated_at = ?, content_length = ? WHERE id = ?",
+                params![now, content.len() as i64, source],
+            )?;
+            index_content(conn, &source, &content)?;
+            renamed.push(source);
+        }
+
+        Ok(renamed)
+    }
+}
@@ -0,0 +1,369 @@
+//! Versioned schema migrations for `pdtodo.db`.
+//!
+//! The schema version lives in `PRAGMA user_version`. Each migration runs in
+//! its own transaction together with the version bump, so a failed upgrade
+//! leaves the database at the last good version. Before upgrading an existing
+//! database, a copy is written next to it with `VACUUM INTO`.
+//!
+//! Never edit a migration once released; append a new one instead.
+
+use rusqlite::{Connection, Transaction};
+use std::path::Path;
+
+use super::{Result, StorageError};
+
+type Migration = fn(&Transaction) -> rusqlite::Result<()>;
+
+/// Migrations in order; the database version is the number applied so far.
+const MIGRATIONS: &[Migration] = &[
+    initial_schema,
+    content_length,
+    content_in_database,
+    folders,
+    tags,
+    tasks,
+    task_dates,
+    task_completed_at,
+    saved_searches,
+    note_links,
+    attachments,
+    link_previews,
+    inline_favicons,
+    orphaned_content,
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.len() as i64
+}
+
+/// Bring the database up to the latest schema version.
+pub fn run(conn: &mut Connection, db_path: &Path) -> Result<()> {
+    let current: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(StorageError::SchemaTooNew(current, latest));
+    }
+    if current == latest {
+        return Ok(());
+    }
+
+    // Databases created before versioning existed report version 0 but
+    // already hold notes, so check for tables rather than the version.
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_tables {
+        backup(conn, db_path, current)?;
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = index as i64 + 1;
+        let tx = conn.transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Copy the database to `pdtodo.db.v{version}.bak` before it is upgraded.
+fn backup(conn: &Connection, db_path: &Path, version: i64) -> Result<()> {
+    let mut file_name = db_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".v{}.bak", version));
+    let backup_path = db_path.with_file_name(file_name);
+
+    // VACUUM INTO refuses to overwrite, so drop a leftover from a failed upgrade
+    if backup_path.exists() {
+        std::fs::remove_file(&backup_path)?;
+    }
+
+    conn.execute("VACUUM INTO ?", [backup_path.to_string_lossy()])?;
+    Ok(())
+}
+
+/// Version 1: the schema as it existed before versioning. Written to be safe
+/// on unversioned databases that already have some or all of it.
+fn initial_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS notes (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL DEFAULT '',
+            starred INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            deleted_at INTEGER
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_notes_updated_at ON notes(updated_at);
+        CREATE INDEX IF NOT EXISTS idx_notes_deleted_at ON notes(deleted_at);
+
+        DROP TRIGGER IF EXISTS notes_ai;
+        DROP TRIGGER IF EXISTS notes_au;
+        DROP TRIGGER IF EXISTS notes_ad;
+        "#,
+    )?;
+
+    // Early builds created notes_fts as a contentless table, which can't
+    // return the note id needed to join search results back to notes.
+    let contentless: bool = tx.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'notes_fts' AND sql LIKE '%content=''''%')",
+        [],
+        |row| row.get(0),
+    )?;
+    if contentless {
+        tx.execute_batch("DROP TABLE notes_fts;")?;
+    }
+
+    let fts_exists: bool = tx.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'notes_fts')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !fts_exists {
+        // FTS table for title search (content is stored in files, not DB)
+        tx.execute_batch(
+            r#"
+            CREATE VIRTUAL TABLE notes_fts USING fts5(
+                id UNINDEXED,
+                title
+            );
+
+            INSERT INTO notes_fts(id, title) SELECT id, title FROM notes;
+            "#,
+        )?;
+    }
+
+    // Triggers to keep FTS in sync
+    tx.execute_batch(
+        r#"
+        CREATE TRIGGER IF NOT EXISTS notes_fts_ai AFTER INSERT ON notes BEGIN
+            INSERT INTO notes_fts(id, title) VALUES (new.id, new.title);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS notes_fts_au AFTER UPDATE ON notes BEGIN
+            UPDATE notes_fts SET title = new.title WHERE id = old.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS notes_fts_ad AFTER DELETE ON notes BEGIN
+            DELETE FROM notes_fts WHERE id = old.id;
+        END;
+        "#,
+    )
+}
+
+/// Version 2: remember the size of each note's content file so the startup
+/// consistency check can spot missing or mismatched files. NULL means
+/// unknown; it is filled in by the first consistency check.
+fn content_length(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch("ALTER TABLE notes ADD COLUMN content_length INTEGER;")
+}
+
+/// Version 3: key/value settings and the table for keeping note content in
+/// the database instead of loose files (see `content.rs`).
+fn content_in_database(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE note_content (
+            id TEXT PRIMARY KEY,
+            data BLOB NOT NULL
+        );
+        "#,
+    )
+}
+
+/// Version 4: hierarchical folders. Notes outside any folder have a NULL
+/// `folder_id`; top-level folders have a NULL `parent_id`.
+fn folders(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE folders (
+            id TEXT PRIMARY KEY,
+            parent_id TEXT REFERENCES folders(id),
+            name TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX idx_folders_parent_id ON folders(parent_id);
+
+        ALTER TABLE notes ADD COLUMN folder_id TEXT REFERENCES folders(id);
+
+        CREATE INDEX idx_notes_folder_id ON notes(folder_id);
+        "#,
+    )
+}
+
+/// Version 5: free-form tags. `note_tags.inline` marks tags that come from
+/// a `#hashtag` in the note's text rather than being added by hand.
+fn tags(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE tags (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL COLLATE NOCASE UNIQUE,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE note_tags (
+            note_id TEXT NOT NULL REFERENCES notes(id),
+            tag_id TEXT NOT NULL REFERENCES tags(id),
+            inline INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (note_id, tag_id)
+        );
+
+        CREATE INDEX idx_note_tags_tag_id ON note_tags(tag_id);
+        "#,
+    )
+}
+
+/// Version 6: task items extracted from note content. `notes.tasks_indexed`
+/// is cleared for notes saved before tasks were indexed, which get indexed
+/// at startup since migrations can't read content.
+fn tasks(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE tasks (
+            id TEXT PRIMARY KEY,
+            note_id TEXT NOT NULL REFERENCES notes(id),
+            position INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            depth INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            checked INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE INDEX idx_tasks_note_id ON tasks(note_id, position);
+
+        ALTER TABLE notes ADD COLUMN tasks_indexed INTEGER NOT NULL DEFAULT 0;
+        "#,
+    )
+}
+
+/// Version 7: due dates and reminder times of tasks. Notes are indexed
+/// again at startup to pick them up.
+fn task_dates(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        ALTER TABLE tasks ADD COLUMN due_date TEXT;
+        ALTER TABLE tasks ADD COLUMN remind_at INTEGER;
+
+        CREATE INDEX idx_tasks_remind_at ON tasks(remind_at) WHERE remind_at IS NOT NULL AND checked = 0;
+
+        UPDATE notes SET tasks_indexed = 0;
+        "#,
+    )
+}
+
+/// Version 8: when tasks were checked, for the recently completed view.
+/// Tasks checked before this are left without a time.
+fn task_completed_at(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        ALTER TABLE tasks ADD COLUMN completed_at INTEGER;
+
+        CREATE INDEX idx_tasks_due_date ON tasks(due_date) WHERE due_date IS NOT NULL AND checked = 0;
+        CREATE INDEX idx_tasks_completed_at ON tasks(completed_at) WHERE completed_at IS NOT NULL;
+        "#,
+    )
+}
+
+/// Version 9: searches saved as smart folders in the sidebar
+fn saved_searches(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE saved_searches (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            query TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        "#,
+    )
+}
+
+/// Version 10: links between notes by title. `notes.links_indexed` starts
+/// cleared, so existing notes are indexed at startup to pick up their links.
+fn note_links(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE note_links (
+            source_id TEXT NOT NULL REFERENCES notes(id),
+            target_title TEXT NOT NULL COLLATE NOCASE,
+            PRIMARY KEY (source_id, target_title)
+        );
+
+        CREATE INDEX idx_note_links_target ON note_links(target_title);
+
+        ALTER TABLE notes ADD COLUMN links_indexed INTEGER NOT NULL DEFAULT 0;
+        "#,
+    )
+}
+
+/// Version 11: files attached to notes, stored once per content hash in
+/// `blobs/`
+fn attachments(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE note_attachments (
+            id TEXT PRIMARY KEY,
+            note_id TEXT NOT NULL REFERENCES notes(id),
+            hash TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX idx_note_attachments_note_id ON note_attachments(note_id, created_at);
+        CREATE INDEX idx_note_attachments_hash ON note_attachments(hash);
+        "#,
+    )
+}
+
+/// Version 12: cached previews of linked web pages
+fn link_previews(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE link_previews (
+            url TEXT PRIMARY KEY,
+            title TEXT,
+            description TEXT,
+            site_name TEXT,
+            favicon_url TEXT,
+            fetched_at INTEGER NOT NULL
+        );
+        "#,
+    )
+}
+
+/// Version 13: icons of link previews are stored inline as `data:` URLs.
+/// Previews cached before that point at remote icons, so they are dropped
+/// and fetched again.
+fn inline_favicons(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch("DELETE FROM link_previews;")
+}
+
+/// Version 14: content found without a note is kept aside in
+/// `orphaned_content` rather than deleted, like `notes/orphaned/` for files
+fn orphaned_content(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE orphaned_content (
+            id TEXT PRIMARY KEY,
+            data BLOB NOT NULL,
+            orphaned_at INTEGER NOT NULL
+        );
+        "#,
+    )
+}
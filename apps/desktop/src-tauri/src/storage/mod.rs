@@ -1,22 +1,83 @@
-use rusqlite::{Connection, params};
+mod attachments;
+mod consistency;
+mod content;
+mod crypto;
+mod encryption;
+mod folders;
+mod link_previews;
+mod links;
+mod migrations;
+mod saved_searches;
+mod search;
+mod tags;
+mod tasks;
+
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
-use std::sync::Mutex;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::document;
+use content::{open_store, ContentStore};
+use crypto::{DataKey, KeyFile, KeyState};
+use search::SearchQuery;
+
+pub use attachments::Attachment;
+pub use content::StorageLayout;
+pub use folders::Folder;
+pub use link_previews::LinkPreview;
+pub use saved_searches::SavedSearch;
+pub use tags::Tag;
+pub use tasks::{Task, TaskCounts};
+
 #[derive(Error, Debug)]
 pub enum StorageError {
     #[error("Database error: {0}")]
     Database(#[from] rusqlite::Error),
     #[error("Note not found: {0}")]
     NoteNotFound(String),
+    #[error("Folder not found: {0}")]
+    FolderNotFound(String),
+    #[error("A folder can't be moved into itself or one of its subfolders")]
+    FolderCycle,
+    #[error("Tag not found: {0}")]
+    TagNotFound(String),
+    #[error("Invalid tag name: {0:?}")]
+    InvalidTagName(String),
+    #[error("Saved search not found: {0}")]
+    SavedSearchNotFound(String),
+    #[error("A saved search needs a name and a query")]
+    InvalidSavedSearch,
+    #[error("Attachment not found: {0}")]
+    AttachmentNotFound(String),
+    #[error("Task not found: {0}")]
+    TaskNotFound(String),
+    #[error("Invalid note content: {0}")]
+    Document(#[from] document::DocumentError),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("Database schema version {0} is newer than this app supports ({1})")]
+    SchemaTooNew(i64, i64),
+    #[error("Note library is locked")]
+    Locked,
+    #[error("Incorrect passphrase")]
+    WrongPassphrase,
+    #[error("Encryption error: {0}")]
+    Encryption(String),
 }
 
 pub type Result<T> = std::result::Result<T, StorageError>;
 
+/// Suffix of in-progress content writes; leftovers are removed at startup
+const TEMP_SUFFIX: &str = ".tmp";
+
+const DB_FILE: &str = "pdtodo.db";
+/// Wrapped encryption key; only present while the library is encrypted
+const KEY_FILE: &str = "pdtodo.key";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NoteMeta {
     pub id: String,
@@ -28,6 +89,9 @@ pub struct NoteMeta {
     pub updated_at: i64,
     #[serde(rename = "deletedAt")]
     pub deleted_at: Option<i64>,
+    /// None for notes outside any folder
+    #[serde(rename = "folderId")]
+    pub folder_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,88 +106,174 @@ pub struct Note {
     pub updated_at: i64,
     #[serde(rename = "deletedAt")]
     pub deleted_at: Option<i64>,
+    #[serde(rename = "folderId")]
+    pub folder_id: Option<String>,
+}
+
+/// A note created from outside the app, keeping its own metadata
+#[derive(Debug, Clone)]
+pub struct NewNote {
+    pub title: String,
+    pub content: Vec<u8>,
+    pub starred: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub deleted_at: Option<i64>,
 }
 
 pub struct Storage {
     conn: Mutex<Connection>,
-    notes_dir: std::path::PathBuf,
+    db_path: PathBuf,
+    notes_dir: PathBuf,
+    /// Attached files, named by their content hash (see `attachments`)
+    blobs_dir: PathBuf,
+    /// Always lock `conn` first when both are needed
+    content: RwLock<Box<dyn ContentStore>>,
+    /// Data key while the library is encrypted; only changed with `conn` held
+    key: Mutex<Option<Arc<DataKey>>>,
 }
 
 impl Storage {
+    /// Open an unencrypted library. Fails with `Locked` if the library is
+    /// encrypted; use `unlock` for that.
     pub fn new(app_data_dir: &Path) -> Result<Self> {
-        let db_path = app_data_dir.join("pdtodo.db");
+        if Self::is_encrypted(app_data_dir) {
+            return Err(StorageError::Locked);
+        }
+        Self::open(app_data_dir, None)
+    }
+
+    /// Open an encrypted library with its passphrase
+    pub fn unlock(app_data_dir: &Path, passphrase: &str) -> Result<Self> {
+        let key_file = KeyFile::load(&app_data_dir.join(KEY_FILE))?;
+        let key = Arc::new(key_file.unwrap_key(passphrase)?);
+        let storage = Self::open(app_data_dir, Some(key))?;
+
+        // Finish enabling or disabling encryption if that was interrupted
+        match key_file.state {
+            KeyState::Enabling => storage.encrypt_library()?,
+            KeyState::Disabling => storage.decrypt_library()?,
+            KeyState::Enabled if !crypto::is_encrypted_database(&storage.db_path)? => {
+                storage.encrypt_library()?
+            }
+            KeyState::Enabled => {}
+        }
+
+        Ok(storage)
+    }
+
+    /// Whether the library in `app_data_dir` needs a passphrase to open
+    pub fn is_encrypted(app_data_dir: &Path) -> bool {
+        app_data_dir.join(KEY_FILE).exists()
+    }
+
+    fn open(app_data_dir: &Path, key: Option<Arc<DataKey>>) -> Result<Self> {
+        let db_path = app_data_dir.join(DB_FILE);
         let notes_dir = app_data_dir.join("notes");
         std::fs::create_dir_all(&notes_dir)?;
 
-        let conn = Connection::open(&db_path)?;
-
-        // Initialize database schema
-        conn.execute_batch(
-            r#"
-            CREATE TABLE IF NOT EXISTS notes (
-                id TEXT PRIMARY KEY,
-                title TEXT NOT NULL DEFAULT '',
-                starred INTEGER NOT NULL DEFAULT 0,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL,
-                deleted_at INTEGER
-            );
-
-            CREATE INDEX IF NOT EXISTS idx_notes_updated_at ON notes(updated_at);
-            CREATE INDEX IF NOT EXISTS idx_notes_deleted_at ON notes(deleted_at);
-            "#,
-        )?;
+        // An interrupted switch can leave the database still (or already) plaintext
+        let db_key = match &key {
+            Some(key) if crypto::is_encrypted_database(&db_path)? => Some(key.as_ref()),
+            _ => None,
+        };
+        let mut conn = crypto::open_database(&db_path, db_key)?;
+        migrations::run(&mut conn, &db_path)?;
 
-        // Create FTS table for title search (content is stored in files, not DB)
-        // Use contentless FTS5 since we manage the content ourselves
-        conn.execute_batch(
-            r#"
-            CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(
-                id UNINDEXED,
-                title,
-                content=''
-            );
-            "#,
-        )?;
+        let layout = conn
+            .query_row("SELECT value FROM settings WHERE key = 'storage_layout'", [], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()?
+            .and_then(|value| StorageLayout::parse(&value))
+            .unwrap_or(StorageLayout::Files);
 
-        // Drop old triggers if they exist (they may reference wrong schema)
-        let _ = conn.execute_batch(
-            r#"
-            DROP TRIGGER IF EXISTS notes_ai;
-            DROP TRIGGER IF EXISTS notes_au;
-            DROP TRIGGER IF EXISTS notes_ad;
-            "#,
-        );
-
-        // Create triggers to keep FTS in sync
-        conn.execute_batch(
-            r#"
-            CREATE TRIGGER IF NOT EXISTS notes_fts_ai AFTER INSERT ON notes BEGIN
-                INSERT INTO notes_fts(id, title) VALUES (new.id, new.title);
-            END;
-
-            CREATE TRIGGER IF NOT EXISTS notes_fts_au AFTER UPDATE ON notes BEGIN
-                UPDATE notes_fts SET title = new.title WHERE id = old.id;
-            END;
-
-            CREATE TRIGGER IF NOT EXISTS notes_fts_ad AFTER DELETE ON notes BEGIN
-                DELETE FROM notes_fts WHERE id = old.id;
-            END;
-            "#,
-        )?;
+        if layout == StorageLayout::Database {
+            enable_wal(&conn)?;
+        }
 
         Ok(Self {
             conn: Mutex::new(conn),
+            db_path,
+            content: RwLock::new(open_store(layout, &notes_dir, key.clone())),
             notes_dir,
+            blobs_dir: app_data_dir.join("blobs"),
+            key: Mutex::new(key),
         })
     }
 
+    pub fn storage_layout(&self) -> StorageLayout {
+        self.content.read().unwrap().layout()
+    }
+
+    /// Move all note content to `layout` and make it the active layout.
+    /// Content is copied and the switch committed before the old copies are
+    /// removed, so an interruption never loses data.
+    pub fn set_storage_layout(&self, layout: StorageLayout) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let mut content = self.content.write().unwrap();
+        if content.layout() == layout {
+            return Ok(());
+        }
+
+        let target = open_store(layout, &self.notes_dir, self.key.lock().unwrap().clone());
+        if layout == StorageLayout::Database {
+            enable_wal(&conn)?;
+        }
+
+        let mut stmt = conn.prepare("SELECT id FROM notes")?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        drop(stmt);
+
+        let tx = conn.unchecked_transaction()?;
+        for id in &ids {
+            if let Some(data) = content.read(&tx, id)? {
+                target.write(&tx, id, &data)?;
+            }
+        }
+        tx.execute(
+            "INSERT INTO settings (key, value) VALUES ('storage_layout', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            [layout.as_str()],
+        )?;
+        tx.commit()?;
+
+        for id in &ids {
+            content.delete(&conn, id)?;
+        }
+        *content = target;
+
+        Ok(())
+    }
+
+    /// Whether the app stays off the network. Links aren't unfurled while
+    /// it does; previews fetched before are still shown.
+    pub fn offline_mode(&self) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let value = conn
+            .query_row("SELECT value FROM settings WHERE key = 'offline_mode'", [], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()?;
+        Ok(value.as_deref() == Some("true"))
+    }
+
+    pub fn set_offline_mode(&self, offline: bool) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES ('offline_mode', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            [offline.to_string()],
+        )?;
+        Ok(())
+    }
+
     pub fn get_notes(&self, include_deleted: bool) -> Result<Vec<NoteMeta>> {
         let conn = self.conn.lock().unwrap();
         let query = if include_deleted {
-            "SELECT id, title, starred, created_at, updated_at, deleted_at FROM notes ORDER BY updated_at DESC"
+            "SELECT id, title, starred, created_at, updated_at, deleted_at, folder_id FROM notes ORDER BY updated_at DESC"
         } else {
-            "SELECT id, title, starred, created_at, updated_at, deleted_at FROM notes WHERE deleted_at IS NULL ORDER BY updated_at DESC"
+            "SELECT id, title, starred, created_at, updated_at, deleted_at, folder_id FROM notes WHERE deleted_at IS NULL ORDER BY updated_at DESC"
         };
 
         let mut stmt = conn.prepare(query)?;
@@ -136,6 +286,7 @@ impl Storage {
                     created_at: row.get(3)?,
                     updated_at: row.get(4)?,
                     deleted_at: row.get(5)?,
+                    folder_id: row.get(6)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -146,7 +297,7 @@ impl Storage {
     pub fn get_note(&self, id: &str) -> Result<Note> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, title, starred, created_at, updated_at, deleted_at FROM notes WHERE id = ?",
+            "SELECT id, title, starred, created_at, updated_at, deleted_at, folder_id FROM notes WHERE id = ?",
         )?;
 
         let meta = stmt.query_row([id], |row| {
@@ -157,16 +308,17 @@ impl Storage {
                 created_at: row.get(3)?,
                 updated_at: row.get(4)?,
                 deleted_at: row.get(5)?,
+                folder_id: row.get(6)?,
             })
         }).map_err(|_| StorageError::NoteNotFound(id.to_string()))?;
 
-        // Load content from file
-        let content_path = self.notes_dir.join(format!("{}.yjs", id));
-        let content = if content_path.exists() {
-            std::fs::read(&content_path)?
-        } else {
-            Vec::new()
-        };
+        // Load content from the active content store
+        let content = self
+            .content
+            .read()
+            .unwrap()
+            .read(&conn, id)?
+            .unwrap_or_default();
 
         Ok(Note {
             id: meta.id,
@@ -176,35 +328,46 @@ impl Storage {
             created_at: meta.created_at,
             updated_at: meta.updated_at,
             deleted_at: meta.deleted_at,
+            folder_id: meta.folder_id,
         })
     }
 
-    pub fn create_note(&self, title: &str) -> Result<String> {
+    /// Create an empty note, inside `folder_id` if given
+    pub fn create_note(&self, title: &str, folder_id: Option<&str>) -> Result<String> {
         let id = Uuid::now_v7().to_string();
         let now = chrono::Utc::now().timestamp_millis();
 
         let conn = self.conn.lock().unwrap();
+        if let Some(folder_id) = folder_id {
+            folders::ensure_folder_exists(&conn, folder_id)?;
+        }
         conn.execute(
-            "INSERT INTO notes (id, title, starred, created_at, updated_at) VALUES (?, ?, 0, ?, ?)",
-            params![id, title, now, now],
+            "INSERT INTO notes (id, title, starred, created_at, updated_at, content_length, folder_id) VALUES (?, ?, 0, ?, ?, 0, ?)",
+            params![id, title, now, now, folder_id],
         )?;
 
         Ok(id)
     }
 
-    pub fn update_note_title(&self, id: &str, title: &str) -> Result<()> {
+    /// Rename a note, rewriting the links to it in other notes (see
+    /// `rename_links`). Returns the ids of the notes whose content changed.
+    pub fn update_note_title(&self, id: &str, title: &str) -> Result<Vec<String>> {
         let now = chrono::Utc::now().timestamp_millis();
         let conn = self.conn.lock().unwrap();
-        let rows = conn.execute(
+        let old: String = conn
+            .query_row("SELECT title FROM notes WHERE id = ?", [id], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| StorageError::NoteNotFound(id.to_string()))?;
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
             "UPDATE notes SET title = ?, updated_at = ? WHERE id = ?",
             params![title, now, id],
         )?;
+        let renamed = self.rename_links(&tx, id, &old, title)?;
+        tx.commit()?;
 
-        if rows == 0 {
-            return Err(StorageError::NoteNotFound(id.to_string()));
-        }
-
-        Ok(())
+        Ok(renamed)
     }
 
     pub fn update_note_starred(&self, id: &str, starred: bool) -> Result<()> {
@@ -222,26 +385,43 @@ impl Storage {
         Ok(())
     }
 
-    pub fn update_note_content(&self, id: &str, content: &[u8]) -> Result<()> {
-        // Update timestamp in database
-        let now = chrono::Utc::now().timestamp_millis();
-        {
-            let conn = self.conn.lock().unwrap();
-            let rows = conn.execute(
-                "UPDATE notes SET updated_at = ? WHERE id = ?",
-                params![now, id],
-            )?;
+    /// Replace a note's content. The content is made durable (an atomic file
+    /// write, or a BLOB in the same transaction) before `updated_at` is
+    /// committed, so a crash leaves either the old or the new content with
+    /// consistent metadata.
+    ///
+    /// Checked repeating tasks are moved to their next occurrence first.
+    /// Returns whether any were, in which case the stored content has
+    /// changes `content` doesn't.
+    pub fn update_note_content(&self, id: &str, content: &[u8]) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
 
-            if rows == 0 {
-                return Err(StorageError::NoteNotFound(id.to_string()));
-            }
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM notes WHERE id = ?)",
+            [id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(StorageError::NoteNotFound(id.to_string()));
         }
 
-        // Save content to file
-        let content_path = self.notes_dir.join(format!("{}.yjs", id));
-        std::fs::write(&content_path, content)?;
+        let advanced = tasks::advance_recurring(content);
+        let content = advanced.as_deref().unwrap_or(content);
 
-        Ok(())
+        // Save content (the connection lock also serializes writers)
+        let tx = conn.unchecked_transaction()?;
+        self.content.read().unwrap().write(&tx, id, content)?;
+
+        // Update metadata in database
+        let now = chrono::Utc::now().timestamp_millis();
+        tx.execute(
+            "UPDATE notes SET updated_at = ?, content_length = ? WHERE id = ?",
+            params![now, content.len() as i64, id],
+        )?;
+        index_content(&tx, id, content)?;
+        tx.commit()?;
+
+        Ok(advanced.is_some())
     }
 
     pub fn delete_note(&self, id: &str) -> Result<()> {
@@ -276,17 +456,23 @@ impl Storage {
 
     pub fn permanently_delete_note(&self, id: &str) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-        let rows = conn.execute("DELETE FROM notes WHERE id = ?", params![id])?;
+        // Rows referencing the note go first, or the foreign keys fail
+        let tx = conn.unchecked_transaction()?;
+        tags::remove_note(&tx, id)?;
+        tasks::remove_note(&tx, id)?;
+        links::remove_note(&tx, id)?;
+        attachments::remove_note(&tx, id)?;
+        let rows = tx.execute("DELETE FROM notes WHERE id = ?", params![id])?;
 
         if rows == 0 {
             return Err(StorageError::NoteNotFound(id.to_string()));
         }
+        tx.commit()?;
 
-        // Delete content file
-        let content_path = self.notes_dir.join(format!("{}.yjs", id));
-        if content_path.exists() {
-            std::fs::remove_file(&content_path)?;
-        }
+        // Delete content and blobs after the note is gone; if this is
+        // interrupted the startup checks clean up what is left
+        self.content.read().unwrap().delete(&conn, id)?;
+        attachments::remove_unreferenced_blobs(&conn, &self.blobs_dir)?;
 
         Ok(())
     }
@@ -297,20 +483,63 @@ impl Storage {
         let now = chrono::Utc::now().timestamp_millis();
 
         let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO notes (id, title, starred, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
-            params![new_id, format!("{} (copy)", original.title), original.starred as i32, now, now],
-        )?;
+        let tx = conn.unchecked_transaction()?;
 
-        // Copy content file
+        // Copy content before the note row exists, so a crash leaves at
+        // worst orphaned content rather than a note with missing content
         if !original.content.is_empty() {
-            let content_path = self.notes_dir.join(format!("{}.yjs", new_id));
-            std::fs::write(&content_path, &original.content)?;
+            self.content.read().unwrap().write(&tx, &new_id, &original.content)?;
         }
 
+        tx.execute(
+            "INSERT INTO notes (id, title, starred, created_at, updated_at, content_length, folder_id) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                new_id,
+                format!("{} (copy)", original.title),
+                original.starred as i32,
+                now,
+                now,
+                original.content.len() as i64,
+                original.folder_id
+            ],
+        )?;
+        index_content(&tx, &new_id, &original.content)?;
+        attachments::copy_note(&tx, id, &new_id)?;
+        tx.commit()?;
+
         Ok(new_id)
     }
 
+    /// Add a note with the given content and timestamps (used by import)
+    pub fn insert_note(&self, note: &NewNote) -> Result<String> {
+        let id = Uuid::now_v7().to_string();
+
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+
+        // Content first, as in duplicate_note
+        if !note.content.is_empty() {
+            self.content.read().unwrap().write(&tx, &id, &note.content)?;
+        }
+
+        tx.execute(
+            "INSERT INTO notes (id, title, starred, created_at, updated_at, deleted_at, content_length) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                id,
+                note.title,
+                note.starred as i32,
+                note.created_at,
+                note.updated_at,
+                note.deleted_at,
+                note.content.len() as i64
+            ],
+        )?;
+        index_content(&tx, &id, &note.content)?;
+        tx.commit()?;
+
+        Ok(id)
+    }
+
     /// Ensure the Scratch Pad note exists (creates it if not present)
     pub fn ensure_scratch_pad(&self) -> Result<()> {
         const SCRATCH_PAD_ID: &str = "scratch-pad";
@@ -327,7 +556,7 @@ impl Storage {
         if !exists {
             let now = chrono::Utc::now().timestamp_millis();
             conn.execute(
-                "INSERT INTO notes (id, title, starred, created_at, updated_at) VALUES (?, ?, 0, ?, ?)",
+                "INSERT INTO notes (id, title, starred, created_at, updated_at, content_length) VALUES (?, ?, 0, ?, ?, 0)",
                 params![SCRATCH_PAD_ID, "Scratch Pad", now, now],
             )?;
         }
@@ -335,20 +564,39 @@ impl Storage {
         Ok(())
     }
 
+    /// Search notes using the sidebar query syntax (see `SearchQuery`)
     pub fn search_notes(&self, query: &str) -> Result<Vec<NoteMeta>> {
+        let query = SearchQuery::parse(query);
+        let (where_clause, params) = query.to_sql();
+
+        let sql = if query.has_text_match() {
+            format!(
+                r#"
+                SELECT n.id, n.title, n.starred, n.created_at, n.updated_at, n.deleted_at, n.folder_id
+                FROM notes n
+                JOIN notes_fts ON notes_fts.id = n.id
+                WHERE {}
+                ORDER BY rank
+                "#,
+                where_clause
+            )
+        } else {
+            format!(
+                r#"
+                SELECT n.id, n.title, n.starred, n.created_at, n.updated_at, n.deleted_at, n.folder_id
+                FROM notes n
+                WHERE {}
+                ORDER BY n.updated_at DESC
+                "#,
+                where_clause
+            )
+        };
+
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            r#"
-            SELECT n.id, n.title, n.starred, n.created_at, n.updated_at, n.deleted_at
-            FROM notes n
-            JOIN notes_fts fts ON n.id = fts.id
-            WHERE notes_fts MATCH ? AND n.deleted_at IS NULL
-            ORDER BY rank
-            "#,
-        )?;
+        let mut stmt = conn.prepare(&sql)?;
 
         let notes = stmt
-            .query_map([format!("title:{}", query)], |row| {
+            .query_map(params_from_iter(params), |row| {
                 Ok(NoteMeta {
                     id: row.get(0)?,
                     title: row.get(1)?,
@@ -356,6 +604,7 @@ impl Storage {
                     created_at: row.get(3)?,
                     updated_at: row.get(4)?,
                     deleted_at: row.get(5)?,
+                    folder_id: row.get(6)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -363,3 +612,46 @@ impl Storage {
         Ok(notes)
     }
 }
+
+/// Update what is derived from a note's content: hashtag tags, links to
+/// other notes and tasks. Content that can't be read keeps what it had.
+fn index_content(conn: &Connection, id: &str, content: &[u8]) -> Result<()> {
+    if let Ok(blocks) = document::from_yjs(content) {
+        tags::set_inline_tags(conn, id, &document::hashtags(&blocks))?;
+        links::set_links(conn, id, &document::links::targets(&blocks))?;
+    }
+    if let Ok(items) = document::tasks::read(content) {
+        tasks::set_tasks(conn, id, &items)?;
+    }
+    conn.execute("UPDATE notes SET tasks_indexed = 1, links_indexed = 1 WHERE id = ?", [id])?;
+    Ok(())
+}
+
+/// WAL lets readers proceed while content BLOBs are being written
+fn enable_wal(conn: &Connection) -> Result<()> {
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+    Ok(())
+}
+
+/// Write `data` to `path` via a temporary file that is fsynced and renamed
+/// over the target, so readers never see a partially written file.
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(TEMP_SUFFIX);
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::rename(&tmp_path, path)?;
+
+    // Persist the rename itself (directories can't be opened on Windows)
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        std::fs::File::open(dir)?.sync_all()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! Searches saved as smart folders.
+//!
+//! A saved search keeps the text of a query in the search box syntax (see
+//! `search`) rather than its results, so its notes are found again each
+//! time it is opened and follow edits to notes, tags and tasks.
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{NoteMeta, Result, Storage, StorageError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+}
+
+/// Trimmed name and query, neither of which may be empty
+fn saved_search_fields<'a>(name: &'a str, query: &'a str) -> Result<(&'a str, &'a str)> {
+    let (name, query) = (name.trim(), query.trim());
+    if name.is_empty() || query.is_empty() {
+        return Err(StorageError::InvalidSavedSearch);
+    }
+    Ok((name, query))
+}
+
+impl Storage {
+    /// All saved searches, by name
+    pub fn get_saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, query, created_at, updated_at FROM saved_searches ORDER BY name COLLATE NOCASE",
+        )?;
+        let searches = stmt
+            .query_map([], |row| {
+                Ok(SavedSearch {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    query: row.get(2)?,
+                    created_at: row.get(3)?,
+                    updated_at: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(searches)
+    }
+
+    pub fn create_saved_search(&self, name: &str, query: &str) -> Result<String> {
+        let (name, query) = saved_search_fields(name, query)?;
+        let id = Uuid::now_v7().to_string();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO saved_searches (id, name, query, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
+            params![id, name, query, now, now],
+        )?;
+
+        Ok(id)
+    }
+
+    /// Rename a saved search and change its query
+    pub fn update_saved_search(&self, id: &str, name: &str, query: &str) -> Result<()> {
+        let (name, query) = saved_search_fields(name, query)?;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let conn = self.conn.lock().unwrap();
+        let rows = conn.execute(
+            "UPDATE saved_searches SET name = ?, query = ?, updated_at = ? WHERE id = ?",
+            params![name, query, now, id],
+        )?;
+
+        if rows == 0 {
+            return Err(StorageError::SavedSearchNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub fn delete_saved_search(&self, id: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let rows = conn.execute("DELETE FROM saved_searches WHERE id = ?", [id])?;
+
+        if rows == 0 {
+            return Err(StorageError::SavedSearchNotFound(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// The notes a saved search finds now, the same as searching for its
+    /// query
+    pub fn run_saved_search(&self, id: &str) -> Result<Vec<NoteMeta>> {
+        let query: String = {
+            let conn = self.conn.lock().unwrap();
+            conn.query_row("SELECT query FROM saved_searches WHERE id = ?", [id], |row| row.get(0))
+                .optional()?
+                .ok_or_else(|| StorageError::SavedSearchNotFound(id.to_string()))?
+        };
+
+        self.search_notes(&query)
+    }
+}
@@ -0,0 +1,369 @@
+//! Parser for the sidebar search box.
+//!
+//! User input is never handed to FTS5 directly. It is split into terms and
+//! field filters, and each term is re-emitted as a quoted FTS5 string so
+//! quotes, hyphens, colons and keywords like `AND` are always literal.
+//!
+//! Supported syntax:
+//! - `word` (or `title:word`) matches titles with a word starting with `word`
+//! - `"some phrase"` matches the exact phrase
+//! - `-word` / `-"some phrase"` excludes matching notes
+//! - `starred:yes` / `starred:no` filters on the starred flag
+//! - `tag:name` (or `tag:#name`) matches notes with that tag, `-tag:name`
+//!   notes without it
+//! - `has:tasks` / `has:open-tasks` matches notes with (unchecked) tasks,
+//!   `-has:…` notes without
+//! - `created:` / `updated:` filter on dates in local time: a day
+//!   (`2024-05-01`, `today`, `yesterday`), a range (`2024-05-01..2024-05-31`,
+//!   either end may be left out), a comparison (`>2024-05-01`, `<=…`) or the
+//!   last days or weeks (`7d`, `2w`); negated with `-`
+//! - `in:trash` searches deleted notes, `in:all` searches everything
+
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+use rusqlite::types::Value;
+
+/// Which notes a search runs over, based on their trash state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchScope {
+    #[default]
+    Notes,
+    Trash,
+    All,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    text: String,
+    phrase: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateField {
+    Created,
+    Updated,
+}
+
+impl DateField {
+    fn column(self) -> &'static str {
+        match self {
+            DateField::Created => "n.created_at",
+            DateField::Updated => "n.updated_at",
+        }
+    }
+}
+
+/// Notes whose date falls in `from..until` (milliseconds, either end open)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DateFilter {
+    field: DateField,
+    from: Option<i64>,
+    until: Option<i64>,
+    negated: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    include: Vec<Term>,
+    exclude: Vec<Term>,
+    tags: Vec<String>,
+    excluded_tags: Vec<String>,
+    dates: Vec<DateFilter>,
+    pub starred: Option<bool>,
+    /// Whether notes must have any tasks, or any unchecked ones
+    pub has_tasks: Option<bool>,
+    pub has_open_tasks: Option<bool>,
+    pub scope: SearchScope,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut query = SearchQuery::default();
+        let mut chars = input.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            let Some(&first) = chars.peek() else { break };
+
+            let negated = first == '-';
+            if negated {
+                chars.next();
+            }
+
+            let (token, phrase) = if chars.next_if_eq(&'"').is_some() {
+                let mut text = String::new();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    text.push(c);
+                }
+                (text, true)
+            } else {
+                let mut text = String::new();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    text.push(c);
+                }
+                (text, false)
+            };
+
+            if !phrase && query.apply_filter(&token, negated) {
+                continue;
+            }
+
+            // Terms without any indexable characters (e.g. a lone "-") would
+            // produce an empty FTS5 phrase, so skip them.
+            if !token.chars().any(char::is_alphanumeric) {
+                continue;
+            }
+
+            let term = Term { text: token, phrase };
+            if negated {
+                query.exclude.push(term);
+            } else {
+                query.include.push(term);
+            }
+        }
+
+        query
+    }
+
+    /// Try to interpret `token` as a `field:value` filter. Returns false if it
+    /// isn't one, in which case the token is searched for as plain text.
+    fn apply_filter(&mut self, token: &str, negated: bool) -> bool {
+        let Some((field, value)) = token.split_once(':') else {
+            return false;
+        };
+
+        match field.to_ascii_lowercase().as_str() {
+            "title" if value.chars().any(char::is_alphanumeric) => {
+                let term = Term { text: value.to_string(), phrase: false };
+                if negated {
+                    self.exclude.push(term);
+                } else {
+                    self.include.push(term);
+                }
+                true
+            }
+            "starred" => {
+                let starred = match value.to_ascii_lowercase().as_str() {
+                    "" | "yes" | "true" | "1" => true,
+                    "no" | "false" | "0" => false,
+                    _ => return false,
+                };
+                self.starred = Some(starred != negated);
+                true
+            }
+            "tag" => {
+                let name = value.strip_prefix('#').unwrap_or(value);
+                if name.is_empty() {
+                    return false;
+                }
+                if negated {
+                    self.excluded_tags.push(name.to_string());
+                } else {
+                    self.tags.push(name.to_string());
+                }
+                true
+            }
+            "has" => {
+                match value.to_ascii_lowercase().as_str() {
+                    "tasks" | "task" => self.has_tasks = Some(!negated),
+                    "open-tasks" | "open-task" | "todo" => self.has_open_tasks = Some(!negated),
+                    _ => return false,
+                }
+                true
+            }
+            "created" | "updated" => {
+                let field = if field.eq_ignore_ascii_case("created") {
+                    DateField::Created
+                } else {
+                    DateField::Updated
+                };
+                let Some((from, until)) = date_range(value, Local::now().date_naive()) else {
+                    return false;
+                };
+                self.dates.push(DateFilter {
+                    field,
+                    from,
+                    until,
+                    negated,
+                });
+                true
+            }
+            "in" if !negated => {
+                self.scope = match value.to_ascii_lowercase().as_str() {
+                    "trash" => SearchScope::Trash,
+                    "all" => SearchScope::All,
+                    "notes" => SearchScope::Notes,
+                    _ => return false,
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// True if the query has terms that must match, in which case the
+    /// caller has to join `notes_fts` and can order by rank.
+    pub fn has_text_match(&self) -> bool {
+        !self.include.is_empty()
+    }
+
+    /// Build the WHERE clause (without the keyword) and its parameters for a
+    /// query against `notes n`, joined with `notes_fts` if `has_text_match`.
+    pub fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut clauses = Vec::new();
+        let mut params = Vec::new();
+
+        match self.scope {
+            SearchScope::Notes => clauses.push("n.deleted_at IS NULL".to_string()),
+            SearchScope::Trash => clauses.push("n.deleted_at IS NOT NULL".to_string()),
+            SearchScope::All => {}
+        }
+
+        if let Some(starred) = self.starred {
+            clauses.push("n.starred = ?".to_string());
+            params.push(Value::Integer(starred as i64));
+        }
+
+        for (has, checked) in [(self.has_tasks, None), (self.has_open_tasks, Some(false))] {
+            let Some(has) = has else { continue };
+            let tasks = match checked {
+                Some(_) => "SELECT note_id FROM tasks WHERE checked = 0",
+                None => "SELECT note_id FROM tasks",
+            };
+            let operator = if has { "IN" } else { "NOT IN" };
+            clauses.push(format!("n.id {} ({})", operator, tasks));
+        }
+
+        for date in &self.dates {
+            let column = date.field.column();
+            let mut range = Vec::new();
+            if let Some(from) = date.from {
+                range.push(format!("{} >= ?", column));
+                params.push(Value::Integer(from));
+            }
+            if let Some(until) = date.until {
+                range.push(format!("{} < ?", column));
+                params.push(Value::Integer(until));
+            }
+            if range.is_empty() {
+                continue;
+            }
+            let range = range.join(" AND ");
+            clauses.push(if date.negated { format!("NOT ({})", range) } else { range });
+        }
+
+        // Tag names compare without case (see the `tags` table)
+        for tag in &self.tags {
+            clauses.push(
+                "n.id IN (SELECT nt.note_id FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE t.name = ?)"
+                    .to_string(),
+            );
+            params.push(Value::Text(tag.clone()));
+        }
+
+        for tag in &self.excluded_tags {
+            clauses.push(
+                "n.id NOT IN (SELECT nt.note_id FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE t.name = ?)"
+                    .to_string(),
+            );
+            params.push(Value::Text(tag.clone()));
+        }
+
+        if let Some(expr) = fts_expr(&self.include, " ") {
+            clauses.push("notes_fts MATCH ?".to_string());
+            params.push(Value::Text(expr));
+        }
+
+        if let Some(expr) = fts_expr(&self.exclude, " OR ") {
+            clauses.push(
+                "n.id NOT IN (SELECT id FROM notes_fts WHERE notes_fts MATCH ?)".to_string(),
+            );
+            params.push(Value::Text(expr));
+        }
+
+        if clauses.is_empty() {
+            clauses.push("1".to_string());
+        }
+
+        (clauses.join(" AND "), params)
+    }
+}
+
+/// Longest relative range, well within what chrono can represent
+const MAX_DAYS: i64 = 100 * 366;
+
+/// Parse the value of a `created:` or `updated:` filter into a range of
+/// times in milliseconds, each end open if None
+fn date_range(value: &str, today: NaiveDate) -> Option<(Option<i64>, Option<i64>)> {
+    let value = value.to_ascii_lowercase();
+
+    if let Some((from, until)) = value.split_once("..") {
+        let from = if from.is_empty() { None } else { Some(day_start(parse_day(from, today)?)) };
+        let until = if until.is_empty() { None } else { Some(day_start(parse_day(until, today)?.succ_opt()?)) };
+        return Some((from, until));
+    }
+
+    for (operator, after) in [(">=", false), ("<=", true), (">", true), ("<", false)] {
+        let Some(day) = value.strip_prefix(operator) else { continue };
+        let day = parse_day(day, today)?;
+        let start = day_start(if after { day.succ_opt()? } else { day });
+        return Some(if operator.starts_with('>') { (Some(start), None) } else { (None, Some(start)) });
+    }
+
+    // The last days or weeks, counting back from now
+    let days = match (value.strip_suffix('d'), value.strip_suffix('w')) {
+        (Some(count), _) => count.parse::<i64>().ok(),
+        (_, Some(count)) => count.parse::<i64>().ok().map(|weeks| weeks * 7),
+        _ => None,
+    };
+    if let Some(days) = days {
+        let since = Local::now() - Duration::days(days.clamp(0, MAX_DAYS));
+        return Some((Some(since.timestamp_millis()), None));
+    }
+
+    let day = parse_day(&value, today)?;
+    Some((Some(day_start(day)), Some(day_start(day.succ_opt()?))))
+}
+
+fn parse_day(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match value {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        _ => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok(),
+    }
+}
+
+/// Milliseconds at the start of `day` in local time
+fn day_start(day: NaiveDate) -> i64 {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    // A day whose midnight is skipped by a clock change starts at the
+    // same moment it would in UTC, near enough for a search
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|time| time.timestamp_millis())
+        .unwrap_or_else(|| midnight.and_utc().timestamp_millis())
+}
+
+fn fts_expr(terms: &[Term], separator: &str) -> Option<String> {
+    if terms.is_empty() {
+        return None;
+    }
+
+    let parts: Vec<String> = terms
+        .iter()
+        .map(|term| {
+            let quoted = format!("\"{}\"", term.text.replace('"', "\"\""));
+            if term.phrase {
+                quoted
+            } else {
+                // Prefix match so results update while the word is being typed
+                format!("{}*", quoted)
+            }
+        })
+        .collect();
+
+    Some(parts.join(separator))
+}
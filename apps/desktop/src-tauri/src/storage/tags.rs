@@ -0,0 +1,243 @@
+//! Free-form tags on notes.
+//!
+//! Tag names are unique without regard to case, and a tag exists only while
+//! some note has it. Tags are added by hand or picked up from `#hashtags`
+//! when a note's content is saved. `note_tags.inline` tells the two apart:
+//! when a hashtag disappears from the text, only the tag it added goes.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{Result, Storage, StorageError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    /// Notes with the tag, not counting those in the trash
+    #[serde(rename = "noteCount")]
+    pub note_count: i64,
+}
+
+/// Trimmed, without a leading `#`; tags can't contain whitespace so they
+/// can be typed in `tag:` search filters
+fn tag_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    let name = name.strip_prefix('#').unwrap_or(name);
+    if name.is_empty() || name.chars().any(char::is_whitespace) {
+        return Err(StorageError::InvalidTagName(name.to_string()));
+    }
+    Ok(name)
+}
+
+/// The id of the tag with this name, creating the tag if needed
+fn tag_id(conn: &Connection, name: &str) -> Result<String> {
+    let existing = conn
+        .query_row("SELECT id FROM tags WHERE name = ?", [name], |row| row.get(0))
+        .optional()?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let id = Uuid::now_v7().to_string();
+    let now = chrono::Utc::now().timestamp_millis();
+    conn.execute(
+        "INSERT INTO tags (id, name, created_at) VALUES (?, ?, ?)",
+        params![id, name, now],
+    )?;
+    Ok(id)
+}
+
+fn remove_unused_tags(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM note_tags)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Make the note's hashtag tags match `names`, the hashtags now in its text.
+/// Tags added by hand are left alone.
+pub(super) fn set_inline_tags(conn: &Connection, note_id: &str, names: &[String]) -> Result<()> {
+    let mut current: Vec<(String, String)> = conn
+        .prepare(
+            "SELECT t.id, t.name FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE nt.note_id = ? AND nt.inline = 1",
+        )?
+        .query_map([note_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<_, _>>()?;
+
+    current.retain(|(_, name)| !names.iter().any(|n| n.to_lowercase() == name.to_lowercase()));
+    for (id, _) in &current {
+        conn.execute(
+            "DELETE FROM note_tags WHERE note_id = ? AND tag_id = ? AND inline = 1",
+            params![note_id, id],
+        )?;
+    }
+
+    for name in names {
+        let id = tag_id(conn, name)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO note_tags (note_id, tag_id, inline) VALUES (?, ?, 1)",
+            params![note_id, id],
+        )?;
+    }
+
+    if !current.is_empty() {
+        remove_unused_tags(conn)?;
+    }
+    Ok(())
+}
+
+/// Forget the tags of a note that is being deleted for good
+pub(super) fn remove_note(conn: &Connection, note_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM note_tags WHERE note_id = ?", [note_id])?;
+    remove_unused_tags(conn)
+}
+
+impl Storage {
+    /// All tags, by name
+    pub fn get_tags(&self) -> Result<Vec<Tag>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT t.id, t.name, COUNT(n.id)
+            FROM tags t
+            LEFT JOIN note_tags nt ON nt.tag_id = t.id
+            LEFT JOIN notes n ON n.id = nt.note_id AND n.deleted_at IS NULL
+            GROUP BY t.id
+            ORDER BY t.name COLLATE NOCASE
+            "#,
+        )?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    note_count: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(tags)
+    }
+
+    /// Names of a note's tags
+    pub fn get_note_tags(&self, note_id: &str) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT t.name FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+            WHERE nt.note_id = ?
+            ORDER BY t.name COLLATE NOCASE
+            "#,
+        )?;
+        let names = stmt
+            .query_map([note_id], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(names)
+    }
+
+    /// Tag a note by hand, creating the tag if it's new. Returns the tag's
+    /// name as stored, which keeps the spelling it was first given.
+    pub fn add_note_tag(&self, note_id: &str, name: &str) -> Result<String> {
+        let name = tag_name(name)?;
+        let conn = self.conn.lock().unwrap();
+
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM notes WHERE id = ?)",
+            [note_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(StorageError::NoteNotFound(note_id.to_string()));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        let id = tag_id(&tx, name)?;
+        // A hashtag that is also added by hand stays when the hashtag goes
+        tx.execute(
+            r#"
+            INSERT INTO note_tags (note_id, tag_id, inline) VALUES (?, ?, 0)
+            ON CONFLICT (note_id, tag_id) DO UPDATE SET inline = 0
+            "#,
+            params![note_id, id],
+        )?;
+        let stored: String = tx.query_row("SELECT name FROM tags WHERE id = ?", [&id], |row| row.get(0))?;
+        tx.commit()?;
+
+        Ok(stored)
+    }
+
+    /// Take a tag off a note. A hashtag still in the note's text adds it
+    /// again the next time the note is saved.
+    pub fn remove_note_tag(&self, note_id: &str, name: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "DELETE FROM note_tags WHERE note_id = ? AND tag_id IN (SELECT id FROM tags WHERE name = ?)",
+            params![note_id, name],
+        )?;
+        remove_unused_tags(&tx)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Rename a tag on every note that has it, merging it into the tag of
+    /// that name if there already is one. Hashtags in note text keep the old
+    /// name, so the renamed tag counts as added by hand from now on, and a
+    /// note that still says `#old` gets that tag back when it's next saved.
+    pub fn rename_tag(&self, id: &str, name: &str) -> Result<()> {
+        let name = tag_name(name)?;
+        let conn = self.conn.lock().unwrap();
+
+        let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM tags WHERE id = ?)", [id], |row| row.get(0))?;
+        if !exists {
+            return Err(StorageError::TagNotFound(id.to_string()));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("UPDATE note_tags SET inline = 0 WHERE tag_id = ?", [id])?;
+
+        let other: Option<String> = tx
+            .query_row("SELECT id FROM tags WHERE name = ? AND id != ?", params![name, id], |row| row.get(0))
+            .optional()?;
+        match other {
+            Some(other) => {
+                tx.execute(
+                    r#"
+                    INSERT INTO note_tags (note_id, tag_id, inline)
+                    SELECT note_id, ?, 0 FROM note_tags WHERE tag_id = ?
+                    ON CONFLICT (note_id, tag_id) DO UPDATE SET inline = 0
+                    "#,
+                    params![other, id],
+                )?;
+                tx.execute("DELETE FROM note_tags WHERE tag_id = ?", [id])?;
+                tx.execute("DELETE FROM tags WHERE id = ?", [id])?;
+            }
+            None => {
+                tx.execute("UPDATE tags SET name = ? WHERE id = ?", params![name, id])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Take a tag off every note. Hashtags in note text add it again the
+    /// next time those notes are saved.
+    pub fn delete_tag(&self, id: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM note_tags WHERE tag_id = ?", [id])?;
+        let rows = tx.execute("DELETE FROM tags WHERE id = ?", [id])?;
+        if rows == 0 {
+            return Err(StorageError::TagNotFound(id.to_string()));
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+}
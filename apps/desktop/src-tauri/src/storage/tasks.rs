@@ -0,0 +1,408 @@
+//! Task items extracted from note content.
+//!
+//! The `tasks` table mirrors the task items in each note's Yjs document and
+//! is rebuilt whenever the content is saved, so tasks can be listed across
+//! notes without opening them. A task's id is its note id and its path in
+//! the document (see `document::tasks`); toggling a task writes the change
+//! back into the document and re-indexes the note.
+//!
+//! Checked repeating tasks are moved to their next occurrence before the
+//! content is stored, wherever the check came from.
+//!
+//! Reminders fire once, when their time passes: the scheduler asks for the
+//! reminders due since it last checked, which is kept in `settings`.
+//!
+//! The Today, Upcoming and Recently completed views list tasks across notes
+//! by due date and completion time, in the device's local time zone. Since
+//! rows are rebuilt on every save, a checked task's completion time is
+//! carried over from its previous row.
+
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Params, Row};
+use serde::{Deserialize, Serialize};
+
+use super::{index_content, Result, Storage, StorageError};
+use crate::document::tasks::{self as document_tasks, TaskChange, TaskItem, DUE_DATE_FORMAT};
+
+/// Settings key of the time reminders were last checked
+const REMINDERS_CHECKED_KEY: &str = "reminders_checked_at";
+
+/// Days after today the Upcoming view covers
+const UPCOMING_DAYS: i64 = 7;
+
+/// Days back the Recently completed view covers, and its longest list
+const RECENTLY_COMPLETED_DAYS: i64 = 7;
+const RECENTLY_COMPLETED_LIMIT: i64 = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    #[serde(rename = "noteId")]
+    pub note_id: String,
+    #[serde(rename = "noteTitle")]
+    pub note_title: String,
+    /// Order of the task within its note
+    pub position: i64,
+    /// Child indexes from the top of the document, e.g. `2.0.1`
+    pub path: String,
+    /// Number of task items the task is nested in
+    pub depth: i64,
+    pub text: String,
+    pub checked: bool,
+    /// `YYYY-MM-DD`
+    #[serde(rename = "dueDate")]
+    pub due_date: Option<String>,
+    /// Reminder time in milliseconds since the epoch
+    #[serde(rename = "remindAt")]
+    pub remind_at: Option<i64>,
+    /// When the task was checked, if it is and that's known
+    #[serde(rename = "completedAt")]
+    pub completed_at: Option<i64>,
+}
+
+/// Completed and open tasks of a note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskCounts {
+    #[serde(rename = "noteId")]
+    pub note_id: String,
+    pub open: i64,
+    pub completed: i64,
+}
+
+const TASK_COLUMNS: &str =
+    "t.id, t.note_id, n.title, t.position, t.path, t.depth, t.text, t.checked, t.due_date, t.remind_at, t.completed_at";
+
+fn task_id(note_id: &str, path: &str) -> String {
+    format!("{}:{}", note_id, path)
+}
+
+fn read_task(row: &Row) -> rusqlite::Result<Task> {
+    Ok(Task {
+        id: row.get(0)?,
+        note_id: row.get(1)?,
+        note_title: row.get(2)?,
+        position: row.get(3)?,
+        path: row.get(4)?,
+        depth: row.get(5)?,
+        text: row.get(6)?,
+        checked: row.get::<_, i32>(7)? != 0,
+        due_date: row.get(8)?,
+        remind_at: row.get(9)?,
+        completed_at: row.get(10)?,
+    })
+}
+
+/// A note's task as indexed before its content changed
+struct PreviousTask {
+    path: String,
+    text: String,
+    checked: bool,
+    completed_at: Option<i64>,
+}
+
+/// When a checked task was completed: kept from its previous row, found by
+/// path or else by text in case the task moved, or now if it was open
+/// there. A task that wasn't indexed before has no known completion time.
+fn completed_at(previous: &[PreviousTask], path: &str, text: &str, now: i64) -> Option<i64> {
+    let task = previous
+        .iter()
+        .find(|task| task.path == path && task.text == text)
+        .or_else(|| previous.iter().find(|task| task.text == text))?;
+    if task.checked {
+        task.completed_at
+    } else {
+        Some(now)
+    }
+}
+
+fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+/// Replace the indexed tasks of a note with `items`, the task items now in
+/// its content
+pub(super) fn set_tasks(conn: &Connection, note_id: &str, items: &[TaskItem]) -> Result<()> {
+    let previous: Vec<PreviousTask> = conn
+        .prepare("SELECT path, text, checked, completed_at FROM tasks WHERE note_id = ?")?
+        .query_map([note_id], |row| {
+            Ok(PreviousTask {
+                path: row.get(0)?,
+                text: row.get(1)?,
+                checked: row.get::<_, i32>(2)? != 0,
+                completed_at: row.get(3)?,
+            })
+        })?
+        .collect::<std::result::Result<_, _>>()?;
+    conn.execute("DELETE FROM tasks WHERE note_id = ?", [note_id])?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut stmt = conn.prepare(
+        r#"
+        INSERT INTO tasks (id, note_id, position, path, depth, text, checked, due_date, remind_at, completed_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )?;
+    for (position, item) in items.iter().enumerate() {
+        let path = document_tasks::path_to_string(&item.path);
+        let completed_at = if item.checked {
+            completed_at(&previous, &path, &item.text, now)
+        } else {
+            None
+        };
+        stmt.execute(params![
+            task_id(note_id, &path),
+            note_id,
+            position as i64,
+            path,
+            item.depth,
+            item.text,
+            item.checked as i32,
+            item.due_date.map(|date| date.format(DUE_DATE_FORMAT).to_string()),
+            item.remind_at,
+            completed_at
+        ])?;
+    }
+    Ok(())
+}
+
+/// `content` with its checked repeating tasks moved to their next
+/// occurrence, or `None` if there are none. Dates are local to this device.
+pub(super) fn advance_recurring(content: &[u8]) -> Option<Vec<u8>> {
+    document_tasks::advance_recurring(content, today()).ok().flatten()
+}
+
+/// Forget the tasks of a note that is being deleted for good
+pub(super) fn remove_note(conn: &Connection, note_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM tasks WHERE note_id = ?", [note_id])?;
+    Ok(())
+}
+
+impl Storage {
+    /// Unchecked tasks of all notes outside the trash, most recently edited
+    /// notes first and in document order within a note
+    pub fn get_open_tasks(&self) -> Result<Vec<Task>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            r#"
+            SELECT {}
+            FROM tasks t JOIN notes n ON n.id = t.note_id
+            WHERE t.checked = 0 AND n.deleted_at IS NULL
+            ORDER BY n.updated_at DESC, t.note_id, t.position
+            "#,
+            TASK_COLUMNS
+        ))?;
+        let tasks = stmt
+            .query_map([], read_task)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(tasks)
+    }
+
+    /// All tasks of a note in document order
+    pub fn get_note_tasks(&self, note_id: &str) -> Result<Vec<Task>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            r#"
+            SELECT {}
+            FROM tasks t JOIN notes n ON n.id = t.note_id
+            WHERE t.note_id = ?
+            ORDER BY t.position
+            "#,
+            TASK_COLUMNS
+        ))?;
+        let tasks = stmt
+            .query_map([note_id], read_task)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(tasks)
+    }
+
+    /// Open tasks due today or overdue, earliest due first
+    pub fn get_today_tasks(&self) -> Result<Vec<Task>> {
+        let today = today().format(DUE_DATE_FORMAT).to_string();
+        self.select_tasks(
+            "t.checked = 0 AND t.due_date <= ? ORDER BY t.due_date, n.updated_at DESC, t.note_id, t.position",
+            [today],
+        )
+    }
+
+    /// Open tasks due in the days after today, earliest due first
+    pub fn get_upcoming_tasks(&self) -> Result<Vec<Task>> {
+        let today = today();
+        let last = today + Duration::days(UPCOMING_DAYS);
+        self.select_tasks(
+            "t.checked = 0 AND t.due_date > ? AND t.due_date <= ? ORDER BY t.due_date, n.updated_at DESC, t.note_id, t.position",
+            [today.format(DUE_DATE_FORMAT).to_string(), last.format(DUE_DATE_FORMAT).to_string()],
+        )
+    }
+
+    /// Tasks checked in the last few days, most recently checked first
+    pub fn get_recently_completed_tasks(&self) -> Result<Vec<Task>> {
+        let since = chrono::Utc::now().timestamp_millis() - Duration::days(RECENTLY_COMPLETED_DAYS).num_milliseconds();
+        self.select_tasks(
+            "t.checked != 0 AND t.completed_at >= ? ORDER BY t.completed_at DESC LIMIT ?",
+            params![since, RECENTLY_COMPLETED_LIMIT],
+        )
+    }
+
+    /// Tasks of notes outside the trash matching `filter`, which may also
+    /// order and limit them
+    fn select_tasks(&self, filter: &str, params: impl Params) -> Result<Vec<Task>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            r#"
+            SELECT {}
+            FROM tasks t JOIN notes n ON n.id = t.note_id
+            WHERE n.deleted_at IS NULL AND {}
+            "#,
+            TASK_COLUMNS, filter
+        ))?;
+        let tasks = stmt
+            .query_map(params, read_task)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(tasks)
+    }
+
+    /// Open and completed task counts of every note outside the trash that
+    /// has tasks
+    pub fn get_task_counts(&self) -> Result<Vec<TaskCounts>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT t.note_id, SUM(t.checked = 0), SUM(t.checked != 0)
+            FROM tasks t JOIN notes n ON n.id = t.note_id
+            WHERE n.deleted_at IS NULL
+            GROUP BY t.note_id
+            "#,
+        )?;
+        let counts = stmt
+            .query_map([], |row| {
+                Ok(TaskCounts {
+                    note_id: row.get(0)?,
+                    open: row.get(1)?,
+                    completed: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(counts)
+    }
+
+    /// Check an open task or uncheck a completed one
+    pub fn toggle_task(&self, id: &str) -> Result<Task> {
+        self.change_task(id, |checked| TaskChange::Check(!checked))
+    }
+
+    /// Check a task, e.g. from its reminder
+    pub fn complete_task(&self, id: &str) -> Result<Task> {
+        self.change_task(id, |_| TaskChange::Check(true))
+    }
+
+    /// Move a task's reminder to `until` (milliseconds since the epoch)
+    pub fn snooze_task(&self, id: &str, until: i64) -> Result<Task> {
+        self.change_task(id, |_| TaskChange::RemindAt(Some(until)))
+    }
+
+    /// Open tasks whose reminder time passed since the last call, up to
+    /// `now`. The first call only records the time, so reminders that were
+    /// already due before the scheduler ever ran don't all fire at once.
+    pub fn take_due_reminders(&self, now: i64) -> Result<Vec<Task>> {
+        let conn = self.conn.lock().unwrap();
+        let since: Option<i64> = conn
+            .query_row("SELECT value FROM settings WHERE key = ?", [REMINDERS_CHECKED_KEY], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()?
+            .and_then(|value| value.parse().ok());
+
+        let tasks = match since {
+            Some(since) => conn
+                .prepare(&format!(
+                    r#"
+                    SELECT {}
+                    FROM tasks t JOIN notes n ON n.id = t.note_id
+                    WHERE t.checked = 0 AND n.deleted_at IS NULL
+                      AND t.remind_at > ? AND t.remind_at <= ?
+                    ORDER BY t.remind_at
+                    "#,
+                    TASK_COLUMNS
+                ))?
+                .query_map(params![since, now], read_task)?
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![REMINDERS_CHECKED_KEY, now.to_string()],
+        )?;
+
+        Ok(tasks)
+    }
+
+    /// Change a task in its note's content, `change` picking the change
+    /// from whether the task is checked. Fails with `TaskNotFound` if the
+    /// note no longer has the task where it was indexed.
+    fn change_task(&self, id: &str, change: impl FnOnce(bool) -> TaskChange) -> Result<Task> {
+        let conn = self.conn.lock().unwrap();
+
+        let task: Option<(String, String, String, bool)> = conn
+            .query_row(
+                "SELECT note_id, path, text, checked FROM tasks WHERE id = ?",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get::<_, i32>(3)? != 0)),
+            )
+            .optional()?;
+        let Some((note_id, path, text, checked)) = task else {
+            return Err(StorageError::TaskNotFound(id.to_string()));
+        };
+        let not_found = || StorageError::TaskNotFound(id.to_string());
+        let path = document_tasks::parse_path(&path).ok_or_else(not_found)?;
+
+        let content = self.content.read().unwrap().read(&conn, &note_id)?.unwrap_or_default();
+        let content = document_tasks::update(&content, &path, &text, change(checked))?.ok_or_else(not_found)?;
+        let content = advance_recurring(&content).unwrap_or(content);
+
+        let tx = conn.unchecked_transaction()?;
+        self.content.read().unwrap().write(&tx, &note_id, &content)?;
+        let now = chrono::Utc::now().timestamp_millis();
+        tx.execute(
+            "UPDATE notes SET updated_at = ?, content_length = ? WHERE id = ?",
+            params![now, content.len() as i64, note_id],
+        )?;
+        index_content(&tx, &note_id, &content)?;
+        let task = tx
+            .query_row(
+                &format!("SELECT {} FROM tasks t JOIN notes n ON n.id = t.note_id WHERE t.id = ?", TASK_COLUMNS),
+                [id],
+                read_task,
+            )
+            .optional()?
+            .ok_or_else(not_found)?;
+        tx.commit()?;
+
+        Ok(task)
+    }
+
+    /// Index the tags, links and tasks of notes saved before they were
+    /// indexed, or whose index a migration cleared.
+    /// Returns the number of notes indexed.
+    pub fn index_pending_notes(&self) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let ids: Vec<String> = conn
+            .prepare("SELECT id FROM notes WHERE tasks_indexed = 0 OR links_indexed = 0")?
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+
+        let content = self.content.read().unwrap();
+        for id in &ids {
+            let data = content.read(&conn, id)?.unwrap_or_default();
+            let tx = conn.unchecked_transaction()?;
+            index_content(&tx, id, &data)?;
+            tx.commit()?;
+        }
+
+        Ok(ids.len())
+    }
+}
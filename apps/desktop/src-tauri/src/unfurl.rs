@@ -0,0 +1,217 @@
+//! Link previews: the title, description, site name and icon of a web page.
+//!
+//! Pages are read from their OpenGraph and Twitter card `<meta>` tags,
+//! falling back to `<title>`, `<meta name="description">` and the page's
+//! `<link rel="icon">`. Only the start of a page is downloaded; the tags
+//! previews are made from sit in its `<head>`. The icon is downloaded here
+//! too and kept as a `data:` URL, so showing a preview never makes the
+//! webview request an address the page chose.
+
+use base64::Engine;
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+
+use crate::fetch_guard::{self, mime_essence, FetchError, FetchPolicy};
+use crate::storage::LinkPreview;
+
+/// How long a cached preview is used before the page is fetched again: a
+/// week
+pub const CACHE_TTL_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+/// How much of a page is downloaded, in bytes
+const MAX_RESPONSE_SIZE: usize = 512 * 1024;
+
+/// Longest description kept, in characters
+const MAX_DESCRIPTION_LENGTH: usize = 300;
+
+/// Largest icon kept, in bytes
+const MAX_ICON_SIZE: usize = 64 * 1024;
+
+/// Types an icon may have
+const ICON_TYPES: &[&str] = &[
+    "image/png",
+    "image/x-icon",
+    "image/vnd.microsoft.icon",
+    "image/gif",
+    "image/jpeg",
+    "image/webp",
+    "image/svg+xml",
+];
+
+/// Download the start of the page at `url` and read a preview from it.
+/// Only HTML pages on public addresses are fetched (see `fetch_guard`).
+pub async fn unfurl(url: &str) -> Result<LinkPreview, FetchError> {
+    let policy = FetchPolicy {
+        max_size: MAX_RESPONSE_SIZE,
+        content_types: &["text/html", "application/xhtml+xml"],
+        ..FetchPolicy::default()
+    };
+    let page = fetch_guard::fetch(url, &policy).await?;
+
+    let html = decode(&page.body, page.content_type.as_deref());
+    // Relative icon URLs are resolved against where redirects ended up
+    let mut preview = parse(url, &page.url, &html);
+    preview.favicon_url = match preview.favicon_url.take() {
+        Some(icon) => inline_icon(&icon).await,
+        None => None,
+    };
+    Ok(preview)
+}
+
+/// The icon at `url` as a `data:` URL, fetched through the same guard as
+/// pages. An icon that can't be fetched, isn't an image or is too large is
+/// left out.
+async fn inline_icon(url: &str) -> Option<String> {
+    if let Some(data) = url.strip_prefix("data:") {
+        let media_type = mime_essence(data.split(',').next()?);
+        // Base64 takes 4 bytes for every 3
+        let small = url.len() <= MAX_ICON_SIZE / 3 * 4 + 64;
+        return (ICON_TYPES.contains(&media_type.as_str()) && small).then(|| url.to_string());
+    }
+
+    let policy = FetchPolicy {
+        max_size: MAX_ICON_SIZE,
+        content_types: ICON_TYPES,
+        ..FetchPolicy::default()
+    };
+    let icon = fetch_guard::fetch(url, &policy).await.ok()?;
+    if icon.truncated || icon.body.is_empty() {
+        return None;
+    }
+    let media_type = mime_essence(icon.content_type.as_deref()?);
+    let data = base64::engine::general_purpose::STANDARD.encode(&icon.body);
+    Some(format!("data:{};base64,{}", media_type, data))
+}
+
+/// Decode a page as the browser would: by its byte order mark, else the
+/// charset of its Content-Type, else a `<meta>` charset near its start,
+/// else as UTF-8. Invalid bytes become U+FFFD rather than failing.
+pub fn decode(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(charset_of)
+        .or_else(|| meta_charset(body))
+        .unwrap_or(UTF_8);
+    // `decode` lets a byte order mark override the encoding
+    encoding.decode(body).0.into_owned()
+}
+
+fn charset_of(content_type: &str) -> Option<&'static Encoding> {
+    let lower = content_type.to_ascii_lowercase();
+    let start = lower.find("charset=")? + "charset=".len();
+    let label: String = lower[start..]
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || "-_:.".contains(*c))
+        .collect();
+    Encoding::for_label(label.as_bytes())
+}
+
+/// The charset declared by `<meta charset>` or `<meta http-equiv>` in the
+/// first kilobyte, which is where browsers look for it
+fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(&body[..body.len().min(1024)]).to_ascii_lowercase();
+    let mut rest = head.as_str();
+    while let Some(start) = rest.find("<meta") {
+        let tag = &rest[start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        if let Some(encoding) = charset_of(tag) {
+            // Browsers read a page declaring UTF-16 this way as UTF-8
+            return Some(encoding.output_encoding());
+        }
+        rest = &rest[start + "<meta".len()..];
+    }
+    None
+}
+
+/// A preview of the page `html` fetched from `url`, which redirects took
+/// to `final_url`. The preview is keyed by the URL asked for.
+pub fn parse(url: &str, final_url: &Url, html: &str) -> LinkPreview {
+    let document = Html::parse_document(html);
+    // A <base href> changes what relative URLs on the page are relative to
+    let base = select_first(&document, "base[href]")
+        .and_then(|base| base.value().attr("href"))
+        .and_then(|href| final_url.join(href).ok())
+        .unwrap_or_else(|| final_url.clone());
+
+    let title = meta_content(&document, &["og:title", "twitter:title"]).or_else(|| {
+        select_first(&document, "title").map(|title| collapse_whitespace(&title.text().collect::<String>()))
+    });
+    let description = meta_content(&document, &["og:description", "twitter:description", "description"])
+        .map(|description| truncate(&description, MAX_DESCRIPTION_LENGTH));
+    let site_name = meta_content(&document, &["og:site_name", "application-name"]);
+
+    LinkPreview {
+        url: url.to_string(),
+        title: title.filter(|title| !title.is_empty()),
+        description,
+        site_name,
+        favicon_url: favicon(&document, &base),
+        fetched_at: chrono::Utc::now().timestamp_millis(),
+    }
+}
+
+fn select_first<'a>(document: &'a Html, selector: &str) -> Option<ElementRef<'a>> {
+    let selector = Selector::parse(selector).expect("valid selector");
+    document.select(&selector).next()
+}
+
+/// The content of the first `<meta>` named one of `names`, by priority.
+/// OpenGraph uses `property`, everything else `name`, but pages mix them up.
+fn meta_content(document: &Html, names: &[&str]) -> Option<String> {
+    let selector = Selector::parse("meta[content]").expect("valid selector");
+    names.iter().find_map(|name| {
+        document
+            .select(&selector)
+            .find(|meta| {
+                let element = meta.value();
+                [element.attr("property"), element.attr("name")]
+                    .into_iter()
+                    .flatten()
+                    .any(|key| key.trim().eq_ignore_ascii_case(name))
+            })
+            .and_then(|meta| meta.value().attr("content"))
+            .map(collapse_whitespace)
+            .filter(|content| !content.is_empty())
+    })
+}
+
+/// The page's icon, preferring a plain `icon` over Apple touch icons, or
+/// `/favicon.ico` if it doesn't name one
+fn favicon(document: &Html, base: &Url) -> Option<String> {
+    let selector = Selector::parse("link[rel][href]").expect("valid selector");
+    let icons: Vec<(bool, &str)> = document
+        .select(&selector)
+        .filter_map(|link| {
+            let element = link.value();
+            let rel = element.attr("rel")?.to_ascii_lowercase();
+            let kinds: Vec<&str> = rel.split_ascii_whitespace().collect();
+            let plain = kinds.contains(&"icon");
+            let touch = kinds.iter().any(|kind| kind.starts_with("apple-touch-icon"));
+            (plain || touch).then(|| (plain, element.attr("href").unwrap_or_default()))
+        })
+        .collect();
+
+    let href = icons
+        .iter()
+        .find(|(plain, _)| *plain)
+        .or_else(|| icons.first())
+        .map(|(_, href)| href.trim())
+        .filter(|href| !href.is_empty());
+    let icon = match href {
+        Some(href) => base.join(href).ok()?,
+        None => base.join("/favicon.ico").ok()?,
+    };
+    matches!(icon.scheme(), "http" | "https" | "data").then(|| icon.to_string())
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_string(),
+    }
+}
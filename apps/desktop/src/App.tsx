@@ -1,24 +1,58 @@
-import { Component, createSignal, onMount } from 'solid-js';
+import { Component, createSignal, onMount, onCleanup } from 'solid-js';
 import { Sidebar } from './components/Sidebar';
 import { Editor } from './components/Editor';
 import { TitleBar } from './components/TitleBar';
+import { UnlockScreen } from './components/UnlockScreen';
+import { ReminderToast } from './components/ReminderToast';
+import { TaskView } from './components/TaskView';
 import { notesStore, loadNotes } from './stores/notesStore';
+import { loadFolders } from './stores/foldersStore';
+import { loadTags } from './stores/tagsStore';
+import { loadSavedSearches } from './stores/savedSearchesStore';
+import { tasksStore, loadTasks, listenForReminders } from './stores/tasksStore';
 import { settingsStore, loadSettings } from './stores/settingsStore';
 import { useKeyboardShortcuts } from './hooks/useKeyboardShortcuts';
+import { invoke } from '@tauri-apps/api/core';
+
+interface EncryptionStatus {
+  encrypted: boolean;
+  unlocked: boolean;
+}
 
 export const App: Component = () => {
   const [isReady, setIsReady] = createSignal(false);
+  const [isLocked, setIsLocked] = createSignal(false);
 
   // Initialize keyboard shortcuts
   useKeyboardShortcuts();
 
+  // Reminders fire in the backend whether or not their note is open
+  let stopReminders: (() => void) | undefined;
+  onCleanup(() => stopReminders?.());
+
   onMount(async () => {
+    stopReminders = await listenForReminders();
+
     // Load settings and notes on startup
     await loadSettings();
-    await loadNotes();
+
+    // An encrypted library has no notes to load until it is unlocked
+    const status = await invoke<EncryptionStatus>('get_encryption_status');
+    if (status.encrypted && !status.unlocked) {
+      setIsLocked(true);
+      return;
+    }
+
+    await Promise.all([loadNotes(), loadFolders(), loadTags(), loadSavedSearches(), loadTasks()]);
     setIsReady(true);
   });
 
+  const handleUnlocked = async () => {
+    setIsLocked(false);
+    await Promise.all([loadNotes(), loadFolders(), loadTags(), loadSavedSearches(), loadTasks()]);
+    setIsReady(true);
+  };
+
   return (
     <div
       class="app"
@@ -28,18 +62,25 @@ export const App: Component = () => {
       }}
     >
       <TitleBar />
-      <div class="app-content">
-        <Sidebar />
-        <main class="editor-container">
-          {isReady() && notesStore.selectedNote ? (
-            <Editor noteId={notesStore.selectedNote.id} />
-          ) : (
-            <div class="empty-state">
-              <p>Select a note or create a new one</p>
-            </div>
-          )}
-        </main>
-      </div>
+      {isLocked() ? (
+        <UnlockScreen onUnlocked={handleUnlocked} />
+      ) : (
+        <div class="app-content">
+          <Sidebar />
+          <main class="editor-container">
+            {isReady() && tasksStore.view ? (
+              <TaskView view={tasksStore.view} />
+            ) : isReady() && notesStore.selectedNote ? (
+              <Editor noteId={notesStore.selectedNote.id} />
+            ) : (
+              <div class="empty-state">
+                <p>Select a note or create a new one</p>
+              </div>
+            )}
+          </main>
+        </div>
+      )}
+      <ReminderToast />
     </div>
   );
 };
@@ -1,7 +1,7 @@
 import { Component, createSignal, onMount, createEffect, onCleanup } from 'solid-js';
 import { invoke } from '@tauri-apps/api/core';
 import { notesStore } from '../stores/notesStore';
-import { settingsStore, setApiServerUrl } from '../stores/settingsStore';
+import { settingsStore, setApiServerUrl, setOfflineMode } from '../stores/settingsStore';
 import './AboutOverlay.css';
 
 interface AppInfo {
@@ -133,6 +133,19 @@ export const AboutOverlay: Component<AboutOverlayProps> = (props) => {
                 )}
               </td>
             </tr>
+            <tr>
+              <td class="about-info-label">Offline mode</td>
+              <td class="about-info-value">
+                <label class="about-offline-toggle">
+                  <input
+                    type="checkbox"
+                    checked={settingsStore.offlineMode}
+                    onChange={(e) => setOfflineMode(e.currentTarget.checked)}
+                  />
+                  <span>Don't fetch link previews</span>
+                </label>
+              </td>
+            </tr>
             <tr>
               <td class="about-info-label">Unsynced</td>
               <td class="about-info-value">0</td>
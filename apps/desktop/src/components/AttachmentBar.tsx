@@ -0,0 +1,67 @@
+import { Component, For } from 'solid-js';
+import { open, save } from '@tauri-apps/plugin-dialog';
+import type { Attachment } from '@pdtodo/types';
+import {
+  attachmentsStore,
+  addAttachmentFiles,
+  saveAttachment,
+  removeAttachment,
+} from '../stores/attachmentsStore';
+import './AttachmentBar.css';
+
+interface AttachmentBarProps {
+  noteId: string;
+}
+
+function formatSize(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
+/**
+ * Files attached to the open note. Clicking one saves a copy; images
+ * pasted into the editor are attached too.
+ */
+export const AttachmentBar: Component<AttachmentBarProps> = (props) => {
+  const attachFiles = async () => {
+    const selected = await open({ multiple: true, title: 'Attach Files' });
+    if (!selected) return;
+    const paths = Array.isArray(selected) ? selected : [selected];
+    if (paths.length > 0) {
+      await addAttachmentFiles(props.noteId, paths);
+    }
+  };
+
+  const saveCopy = async (attachment: Attachment) => {
+    const path = await save({ title: 'Save Attachment', defaultPath: attachment.fileName });
+    if (path) {
+      await saveAttachment(attachment.id, path);
+    }
+  };
+
+  return (
+    <div class="attachment-bar">
+      <For each={attachmentsStore.noteAttachments}>
+        {(attachment) => (
+          <span class="attachment-chip" title={attachment.contentType}>
+            <button class="attachment-chip-name" onClick={() => saveCopy(attachment)}>
+              {attachment.fileName}
+            </button>
+            <span class="attachment-chip-size">{formatSize(attachment.size)}</span>
+            <button
+              class="attachment-chip-remove"
+              onClick={() => removeAttachment(attachment.id)}
+              aria-label={`Remove attachment ${attachment.fileName}`}
+            >
+              ×
+            </button>
+          </span>
+        )}
+      </For>
+      <button class="attachment-add-btn" onClick={attachFiles}>
+        + File
+      </button>
+    </div>
+  );
+};
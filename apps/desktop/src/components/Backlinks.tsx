@@ -0,0 +1,73 @@
+import { Component, For, Show, createEffect, createSignal, on, onCleanup } from 'solid-js';
+import type { NoteMeta } from '@pdtodo/types';
+import { invoke } from '@tauri-apps/api/core';
+import { notesStore, selectNote } from '../stores/notesStore';
+import './Backlinks.css';
+
+/** Wait after notes change before looking links up again, e.g. while a title is typed */
+const RELOAD_DELAY_MS = 300;
+
+interface BacklinksProps {
+  noteId: string;
+}
+
+/**
+ * Notes linking to the open note with `[[Note Title]]`, below its content.
+ * Looked up again whenever a note's title or content changes.
+ */
+export const Backlinks: Component<BacklinksProps> = (props) => {
+  const [notes, setNotes] = createSignal<NoteMeta[]>([]);
+
+  const load = async (noteId: string) => {
+    try {
+      const backlinks = await invoke<NoteMeta[]>('get_backlinks', { noteId });
+      // Another note may have been opened meanwhile
+      if (noteId === props.noteId) {
+        setNotes(backlinks);
+      }
+    } catch (error) {
+      console.error('Failed to load backlinks:', error);
+      setNotes([]);
+    }
+  };
+
+  createEffect(on(() => props.noteId, (noteId) => {
+    setNotes([]);
+    load(noteId);
+  }));
+
+  // Links come and go with titles, content and trash state of notes
+  const linkInputs = () =>
+    notesStore.notes.map((n) => `${n.id}:${n.title}:${n.updatedAt}:${n.deletedAt}`).join();
+
+  createEffect(
+    on(
+      linkInputs,
+      () => {
+        const noteId = props.noteId;
+        const timeout = setTimeout(() => load(noteId), RELOAD_DELAY_MS);
+        onCleanup(() => clearTimeout(timeout));
+      },
+      { defer: true }
+    )
+  );
+
+  return (
+    <Show when={notes().length > 0}>
+      <div class="backlinks">
+        <h3 class="backlinks-title">Linked from</h3>
+        <ul class="backlinks-list">
+          <For each={notes()}>
+            {(note) => (
+              <li>
+                <button class="backlinks-item" onClick={() => selectNote(note.id)}>
+                  {note.title || 'Untitled'}
+                </button>
+              </li>
+            )}
+          </For>
+        </ul>
+      </div>
+    </Show>
+  );
+};
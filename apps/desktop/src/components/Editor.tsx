@@ -3,13 +3,35 @@ import { Editor as TipTapEditor } from '@tiptap/core';
 import type { Node as ProseMirrorNode } from '@tiptap/pm/model';
 import Collaboration from '@tiptap/extension-collaboration';
 import { getEditorExtensions, editorStyles } from '@pdtodo/editor';
-import { notesStore, updateNoteTitle, flushPendingTitleUpdate, updateNoteTimestamp, isScratchPad, SCRATCH_PAD_ID } from '../stores/notesStore';
+import {
+  notesStore,
+  updateNoteTitle,
+  flushPendingTitleUpdate,
+  updateNoteTimestamp,
+  openNoteLink,
+  isScratchPad,
+  SCRATCH_PAD_ID,
+} from '../stores/notesStore';
 import { registerEditorFocus, unregisterEditorFocus } from '../stores/focusStore';
+import { loadNoteTags, refreshTagsAfterSave } from '../stores/tagsStore';
+import { loadNoteAttachments, addAttachmentData } from '../stores/attachmentsStore';
+import { linkPreviewsStore, loadLinkPreview } from '../stores/linkPreviewsStore';
+import {
+  tasksStore,
+  loadTasks,
+  registerEditorContent,
+  unregisterEditorContent,
+  clearTaskReveal,
+} from '../stores/tasksStore';
 import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-shell';
 import * as Y from 'yjs';
 import { ySyncPluginKey } from 'y-prosemirror';
 import type { Note } from '@pdtodo/types';
+import { TagBar } from './TagBar';
+import { AttachmentBar } from './AttachmentBar';
+import { Backlinks } from './Backlinks';
+import { TaskDatesButton } from './TaskDatesButton';
 import './Editor.css';
 
 // Import extension types to augment ChainedCommands
@@ -24,6 +46,26 @@ interface EditorProps {
   noteId: string;
 }
 
+/**
+ * Position of the node at a task path (child indexes from the top of the
+ * document, as the backend indexes tasks), or null if there's none. Block
+ * nodes map one to one onto the Yjs elements the path was taken from.
+ */
+function positionOfPath(doc: ProseMirrorNode, path: string): number | null {
+  let node = doc;
+  let pos = -1;
+  for (const index of path.split('.').map(Number)) {
+    if (!Number.isInteger(index) || index >= node.childCount) return null;
+    // Step into the node, then past the children before the index
+    pos += 1;
+    for (let i = 0; i < index; i++) {
+      pos += node.child(i).nodeSize;
+    }
+    node = node.child(index);
+  }
+  return pos;
+}
+
 /**
  * Get ordinal suffix for a day number (1st, 2nd, 3rd, 4th, etc.)
  */
@@ -70,12 +112,16 @@ async function flushPendingContentSave(): Promise<void> {
     pendingContentSave = null;
     try {
       const content = Y.encodeStateAsUpdate(doc);
+      // Repeating tasks the backend moves on are merged by whoever flushed,
+      // or loaded with the note next time it opens
       await invoke('update_note_content', {
         noteId,
         content: Array.from(content),
       });
       // Update the timestamp in the store after successful save
       updateNoteTimestamp(noteId);
+      refreshTagsAfterSave(noteId);
+      loadTasks();
     } catch (error) {
       console.error('Failed to flush content save:', error);
     }
@@ -95,6 +141,8 @@ export const Editor: Component<EditorProps> = (props) => {
   const [title, setTitle] = createSignal('');
   const [isSaving, setIsSaving] = createSignal(false);
   const [isLoading, setIsLoading] = createSignal(false);
+  // Note whose content the editor shows, once loaded
+  const [loadedNoteId, setLoadedNoteId] = createSignal<string | null>(null);
   // Signal to trigger toolbar re-renders when editor state changes (selection, formatting)
   const [editorStateVersion, setEditorStateVersion] = createSignal(0);
 
@@ -128,9 +176,28 @@ export const Editor: Component<EditorProps> = (props) => {
     }
   };
 
+  // Merge content changed outside the editor (e.g. a toggled task) into the
+  // open note; Yjs merges it with any edits made meanwhile
+  const mergeStoredContent = async (noteId: string) => {
+    if (noteId !== props.noteId || !ydoc) return;
+    const doc = ydoc;
+    try {
+      const note = await invoke<Note>('get_note', { noteId });
+      if (note.content && note.content.length > 0 && doc === ydoc) {
+        const contentArray = note.content instanceof Uint8Array
+          ? note.content
+          : new Uint8Array(note.content as number[]);
+        Y.applyUpdate(doc, contentArray);
+      }
+    } catch (error) {
+      console.error('Failed to reload note content:', error);
+    }
+  };
+
   // Register with focus store on mount
   onMount(() => {
     registerEditorFocus(saveEditorSelection, restoreEditorSelection, focusAtStart);
+    registerEditorContent(flushPendingContentSave, mergeStoredContent);
   });
 
   // Link tooltip state
@@ -191,12 +258,18 @@ export const Editor: Component<EditorProps> = (props) => {
           setIsSaving(true);
           try {
             const content = Y.encodeStateAsUpdate(doc);
-            await invoke('update_note_content', {
+            const advanced = await invoke<boolean>('update_note_content', {
               noteId,
               content: Array.from(content),
             });
+            // Checked repeating tasks were moved to their next occurrence
+            if (advanced) {
+              await mergeStoredContent(noteId);
+            }
             // Update the timestamp in the store after successful save
             updateNoteTimestamp(noteId);
+            refreshTagsAfterSave(noteId);
+            loadTasks();
           } catch (error) {
             console.error('Failed to save note content:', error);
           } finally {
@@ -218,7 +291,7 @@ export const Editor: Component<EditorProps> = (props) => {
     }
 
     // Remove History extension from base extensions (Yjs handles undo/redo)
-    const baseExtensions = getEditorExtensions({ placeholder: 'Start writing...' })
+    const baseExtensions = getEditorExtensions({ placeholder: 'Start writing...', onOpenNoteLink: openNoteLink })
       .filter((ext: { name: string }) => ext.name !== 'history');
 
     const newEditor = new TipTapEditor({
@@ -258,6 +331,18 @@ export const Editor: Component<EditorProps> = (props) => {
           }
           return false;
         },
+        handlePaste: (_view, event) => {
+          // Pasted images become attachments; the note itself holds only text
+          const noteId = loadedNoteId();
+          const images = Array.from(event.clipboardData?.files ?? [])
+            .filter((file) => file.type.startsWith('image/'));
+          if (!noteId || isScratchPad(noteId) || images.length === 0) return false;
+          event.preventDefault();
+          for (const image of images) {
+            addAttachmentData(noteId, image);
+          }
+          return true;
+        },
         handleClick: (_view, _pos, event) => {
           // Handle link clicks - open in external browser
           const target = event.target as HTMLElement;
@@ -322,6 +407,7 @@ export const Editor: Component<EditorProps> = (props) => {
       const docRef = ydoc;
       updateHandler = () => saveContentDebounced(noteId, docRef);
       ydoc.on('update', updateHandler);
+      setLoadedNoteId(noteId);
     } catch (error) {
       console.error('Failed to load note:', error);
       ydoc = new Y.Doc();
@@ -348,9 +434,24 @@ export const Editor: Component<EditorProps> = (props) => {
       }
 
       await loadNote(noteId);
+      loadNoteTags(noteId);
+      loadNoteAttachments(noteId);
     }
   ));
 
+  // Scroll to a task opened from a smart view once its note is loaded
+  createEffect(() => {
+    const reveal = tasksStore.reveal;
+    const ed = editor();
+    if (!reveal || !ed || reveal.noteId !== loadedNoteId()) return;
+    clearTaskReveal();
+
+    const pos = positionOfPath(ed.state.doc, reveal.path);
+    if (pos === null || ed.state.doc.nodeAt(pos)?.type.name !== 'taskItem') return;
+    // Into the task's first paragraph
+    ed.chain().focus().setTextSelection(pos + 2).scrollIntoView().run();
+  });
+
   // Update title when note changes
   createEffect(() => {
     const note = notesStore.notes.find((n) => n.id === props.noteId);
@@ -396,6 +497,7 @@ export const Editor: Component<EditorProps> = (props) => {
         x,
         y,
       });
+      loadLinkPreview(link.href);
     } else {
       // Schedule hide with delay when not hovering a link
       scheduleTooltipHide();
@@ -446,6 +548,7 @@ export const Editor: Component<EditorProps> = (props) => {
 
     // Unregister focus callbacks
     unregisterEditorFocus();
+    unregisterEditorContent();
 
     // Flush any pending saves
     await flushPendingTitleUpdate();
@@ -480,6 +583,10 @@ export const Editor: Component<EditorProps> = (props) => {
               readOnly={isScratchPad(props.noteId)}
             />
             <span class="editor-timestamp">Last updated: {lastUpdated()}</span>
+            <Show when={!isScratchPad(props.noteId)}>
+              <TagBar noteId={props.noteId} />
+              <AttachmentBar noteId={props.noteId} />
+            </Show>
           </div>
           <div class="editor-status">
             {isLoading() && <span class="loading-indicator">Loading...</span>}
@@ -494,6 +601,9 @@ export const Editor: Component<EditorProps> = (props) => {
         onClick={handleEditorClick}
       >
         <div ref={editorRef} class="editor-content-wrapper" />
+        <Show when={!isScratchPad(props.noteId)}>
+          <Backlinks noteId={props.noteId} />
+        </Show>
       </div>
 
       {/* Link tooltip */}
@@ -514,22 +624,53 @@ export const Editor: Component<EditorProps> = (props) => {
             onClick={handleTooltipLinkClick}
             title="Open link"
           >
-            <svg
-              class="link-tooltip-icon"
-              width="14"
-              height="14"
-              viewBox="0 0 24 24"
-              fill="none"
-              stroke="currentColor"
-              stroke-width="2"
-              stroke-linecap="round"
-              stroke-linejoin="round"
+            <Show
+              when={linkPreviewsStore.preview(linkTooltip().url)?.faviconUrl}
+              fallback={
+                <svg
+                  class="link-tooltip-icon"
+                  width="14"
+                  height="14"
+                  viewBox="0 0 24 24"
+                  fill="none"
+                  stroke="currentColor"
+                  stroke-width="2"
+                  stroke-linecap="round"
+                  stroke-linejoin="round"
+                >
+                  <circle cx="12" cy="12" r="10" />
+                  <path d="M2 12h20" />
+                  <path d="M12 2a15.3 15.3 0 0 1 4 10 15.3 15.3 0 0 1-4 10 15.3 15.3 0 0 1-4-10 15.3 15.3 0 0 1 4-10z" />
+                </svg>
+              }
+            >
+              {(faviconUrl) => (
+                <img
+                  class="link-tooltip-icon link-tooltip-favicon"
+                  src={faviconUrl()}
+                  alt=""
+                  width="14"
+                  height="14"
+                  onError={(e) => (e.currentTarget.style.visibility = 'hidden')}
+                />
+              )}
+            </Show>
+            <Show
+              when={linkPreviewsStore.preview(linkTooltip().url)?.title}
+              fallback={<span class="link-tooltip-url">{linkTooltip().url}</span>}
             >
-              <circle cx="12" cy="12" r="10" />
-              <path d="M2 12h20" />
-              <path d="M12 2a15.3 15.3 0 0 1 4 10 15.3 15.3 0 0 1-4 10 15.3 15.3 0 0 1-4-10 15.3 15.3 0 0 1 4-10z" />
-            </svg>
-            <span class="link-tooltip-url">{linkTooltip().url}</span>
+              {(title) => (
+                <span class="link-tooltip-preview">
+                  <span class="link-tooltip-title">{title()}</span>
+                  <Show when={linkPreviewsStore.preview(linkTooltip().url)?.description}>
+                    {(description) => <span class="link-tooltip-description">{description()}</span>}
+                  </Show>
+                  <span class="link-tooltip-url">
+                    {linkPreviewsStore.preview(linkTooltip().url)?.siteName ?? linkTooltip().url}
+                  </span>
+                </span>
+              )}
+            </Show>
           </button>
         </div>
       </Show>
@@ -910,6 +1051,7 @@ const EditorToolbar: Component<EditorToolbarProps> = (props) => {
             />
           </svg>
         </button>
+        <TaskDatesButton editor={props.editor} editorStateVersion={props.editorStateVersion} />
       </div>
 
       <div class="toolbar-divider" />
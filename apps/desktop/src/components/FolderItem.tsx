@@ -0,0 +1,287 @@
+import { Component, For, Show, createMemo, createSignal } from 'solid-js';
+import type { Folder } from '@pdtodo/types';
+import {
+  notesStore,
+  createNote,
+  selectNote,
+  toggleNoteStarred,
+  deleteNote,
+  moveNoteToFolder,
+} from '../stores/notesStore';
+import {
+  foldersStore,
+  createFolder,
+  renameFolder,
+  moveFolder,
+  deleteFolder,
+  setFolderExpanded,
+  NOTE_DRAG_TYPE,
+  FOLDER_DRAG_TYPE,
+} from '../stores/foldersStore';
+import { NoteItem } from './NoteItem';
+import './FolderItem.css';
+
+/**
+ * Move whatever was dropped (a note or a folder) into `folderId`, or out of
+ * any folder with null. Returns false if the drop wasn't ours.
+ */
+export function handleFolderDrop(e: DragEvent, folderId: string | null): boolean {
+  const noteId = e.dataTransfer?.getData(NOTE_DRAG_TYPE);
+  if (noteId) {
+    moveNoteToFolder(noteId, folderId);
+    return true;
+  }
+  const draggedFolderId = e.dataTransfer?.getData(FOLDER_DRAG_TYPE);
+  if (draggedFolderId && draggedFolderId !== folderId) {
+    moveFolder(draggedFolderId, folderId);
+    return true;
+  }
+  return false;
+}
+
+export function isFolderDrag(e: DragEvent): boolean {
+  const types = e.dataTransfer?.types ?? [];
+  return types.includes(NOTE_DRAG_TYPE) || types.includes(FOLDER_DRAG_TYPE);
+}
+
+interface FolderNameInputProps {
+  initialValue?: string;
+  placeholder?: string;
+  onSubmit: (name: string) => void;
+  onCancel: () => void;
+}
+
+/**
+ * Inline input for naming a new folder or renaming one (or a tag)
+ */
+export const FolderNameInput: Component<FolderNameInputProps> = (props) => {
+  let submitted = false;
+
+  const submit = (value: string) => {
+    if (submitted) return;
+    submitted = true;
+    const name = value.trim();
+    if (name && name !== props.initialValue) {
+      props.onSubmit(name);
+    } else {
+      props.onCancel();
+    }
+  };
+
+  return (
+    <input
+      class="folder-name-input"
+      value={props.initialValue ?? ''}
+      placeholder={props.placeholder ?? 'Folder name'}
+      ref={(el) => setTimeout(() => el.select())}
+      onClick={(e) => e.stopPropagation()}
+      onKeyDown={(e) => {
+        e.stopPropagation();
+        if (e.key === 'Enter') {
+          submit(e.currentTarget.value);
+        } else if (e.key === 'Escape') {
+          submitted = true;
+          props.onCancel();
+        }
+      }}
+      onBlur={(e) => submit(e.currentTarget.value)}
+    />
+  );
+};
+
+interface FolderItemProps {
+  folder: Folder;
+  depth: number;
+  searchQuery?: string;
+}
+
+export const FolderItem: Component<FolderItemProps> = (props) => {
+  const [isRenaming, setIsRenaming] = createSignal(false);
+  const [isAddingSubfolder, setIsAddingSubfolder] = createSignal(false);
+  const [isDropTarget, setIsDropTarget] = createSignal(false);
+  const [showDeleteConfirm, setShowDeleteConfirm] = createSignal(false);
+
+  const isExpanded = () => foldersStore.isExpanded(props.folder.id);
+  const subfolders = createMemo(() => foldersStore.childrenOf(props.folder.id));
+  const notes = createMemo(() =>
+    notesStore.activeNotes
+      .filter((n) => n.folderId === props.folder.id)
+      .sort((a, b) => b.updatedAt - a.updatedAt)
+  );
+  const indent = () => ({ 'padding-left': `calc(var(--space-3) + ${props.depth * 12}px)` });
+
+  const handleNewNote = async (e: MouseEvent) => {
+    e.stopPropagation();
+    setFolderExpanded(props.folder.id, true);
+    await createNote(props.folder.id);
+  };
+
+  const handleNewSubfolder = (e: MouseEvent) => {
+    e.stopPropagation();
+    setFolderExpanded(props.folder.id, true);
+    setIsAddingSubfolder(true);
+  };
+
+  const handleDragStart = (e: DragEvent) => {
+    e.stopPropagation();
+    e.dataTransfer?.setData(FOLDER_DRAG_TYPE, props.folder.id);
+  };
+
+  const handleDragOver = (e: DragEvent) => {
+    if (!isFolderDrag(e)) return;
+    e.preventDefault();
+    e.stopPropagation();
+    setIsDropTarget(true);
+  };
+
+  const handleDrop = (e: DragEvent) => {
+    e.preventDefault();
+    e.stopPropagation();
+    setIsDropTarget(false);
+    if (handleFolderDrop(e, props.folder.id)) {
+      setFolderExpanded(props.folder.id, true);
+    }
+  };
+
+  const handleConfirmDelete = (e: MouseEvent) => {
+    e.stopPropagation();
+    setShowDeleteConfirm(false);
+    deleteFolder(props.folder.id);
+  };
+
+  const handleCancelDelete = (e: MouseEvent) => {
+    e.stopPropagation();
+    setShowDeleteConfirm(false);
+  };
+
+  return (
+    <>
+      <div
+        class="folder-item"
+        classList={{ 'is-drop-target': isDropTarget() }}
+        style={indent()}
+        draggable={!isRenaming()}
+        onClick={() => setFolderExpanded(props.folder.id, !isExpanded())}
+        onDblClick={() => setIsRenaming(true)}
+        onDragStart={handleDragStart}
+        onDragOver={handleDragOver}
+        onDragLeave={() => setIsDropTarget(false)}
+        onDrop={handleDrop}
+      >
+        <span class="folder-chevron" classList={{ 'is-expanded': isExpanded() }}>
+          <svg width="10" height="10" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+            <path stroke-linecap="round" stroke-linejoin="round" stroke-width="3" d="M9 5l7 7-7 7" />
+          </svg>
+        </span>
+        <svg class="folder-icon" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+          <path
+            stroke-linecap="round"
+            stroke-linejoin="round"
+            stroke-width="1.5"
+            d="M3 7a2 2 0 012-2h4l2 2h8a2 2 0 012 2v8a2 2 0 01-2 2H5a2 2 0 01-2-2V7z"
+          />
+        </svg>
+        <Show
+          when={isRenaming()}
+          fallback={<span class="folder-name">{props.folder.name}</span>}
+        >
+          <FolderNameInput
+            initialValue={props.folder.name}
+            onSubmit={(name) => {
+              setIsRenaming(false);
+              renameFolder(props.folder.id, name);
+            }}
+            onCancel={() => setIsRenaming(false)}
+          />
+        </Show>
+        <Show when={!isRenaming()}>
+          <div class="folder-actions">
+            <button class="action-btn" onClick={handleNewNote} aria-label="New note in folder">
+              <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+                <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M12 4v16m8-8H4" />
+              </svg>
+            </button>
+            <button class="action-btn" onClick={handleNewSubfolder} aria-label="New subfolder">
+              <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+                <path
+                  stroke-linecap="round"
+                  stroke-linejoin="round"
+                  stroke-width="2"
+                  d="M9 13h6m-3-3v6M3 7a2 2 0 012-2h4l2 2h8a2 2 0 012 2v8a2 2 0 01-2 2H5a2 2 0 01-2-2V7z"
+                />
+              </svg>
+            </button>
+            <button
+              class="action-btn delete-btn"
+              onClick={(e) => {
+                e.stopPropagation();
+                setShowDeleteConfirm(true);
+              }}
+              aria-label="Delete folder"
+            >
+              <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+                <path
+                  stroke-linecap="round"
+                  stroke-linejoin="round"
+                  stroke-width="2"
+                  d="M19 7l-.867 12.142A2 2 0 0116.138 21H7.862a2 2 0 01-1.995-1.858L5 7m5 4v6m4-6v6m1-10V4a1 1 0 00-1-1h-4a1 1 0 00-1 1v3M4 7h16"
+                />
+              </svg>
+            </button>
+          </div>
+        </Show>
+      </div>
+
+      <Show when={isExpanded()}>
+        <div class="folder-children">
+          <Show when={isAddingSubfolder()}>
+            <div class="folder-item" style={{ 'padding-left': `calc(var(--space-3) + ${(props.depth + 1) * 12}px)` }}>
+              <FolderNameInput
+                onSubmit={(name) => {
+                  setIsAddingSubfolder(false);
+                  createFolder(name, props.folder.id);
+                }}
+                onCancel={() => setIsAddingSubfolder(false)}
+              />
+            </div>
+          </Show>
+          <For each={subfolders()}>
+            {(folder) => <FolderItem folder={folder} depth={props.depth + 1} searchQuery={props.searchQuery} />}
+          </For>
+          <For each={notes()}>
+            {(note) => (
+              <div style={{ 'padding-left': `${(props.depth + 1) * 12}px` }}>
+                <NoteItem
+                  note={note}
+                  isSelected={note.id === notesStore.selectedNoteId}
+                  onSelect={() => selectNote(note.id)}
+                  onToggleStar={() => toggleNoteStarred(note.id)}
+                  onDelete={() => deleteNote(note.id)}
+                  searchQuery={props.searchQuery}
+                />
+              </div>
+            )}
+          </For>
+        </div>
+      </Show>
+
+      {/* Delete confirmation modal */}
+      <Show when={showDeleteConfirm()}>
+        <div class="delete-confirm-overlay" onClick={handleCancelDelete}>
+          <div class="delete-confirm-modal" onClick={(e) => e.stopPropagation()}>
+            <p>Delete folder "{props.folder.name}"?</p>
+            <p class="delete-confirm-subtitle">Its subfolders are deleted too, and its notes are moved to trash.</p>
+            <div class="delete-confirm-actions">
+              <button class="delete-confirm-btn cancel" onClick={handleCancelDelete}>
+                Cancel
+              </button>
+              <button class="delete-confirm-btn confirm" onClick={handleConfirmDelete}>
+                Delete
+              </button>
+            </div>
+          </div>
+        </div>
+      </Show>
+    </>
+  );
+};
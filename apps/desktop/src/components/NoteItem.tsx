@@ -1,5 +1,7 @@
 import { Component, Show, createSignal, For, createMemo } from 'solid-js';
 import type { NoteMeta } from '@pdtodo/types';
+import { NOTE_DRAG_TYPE } from '../stores/foldersStore';
+import { tasksStore } from '../stores/tasksStore';
 import './NoteItem.css';
 
 interface NoteItemProps {
@@ -59,6 +61,8 @@ export const NoteItem: Component<NoteItemProps> = (props) => {
     return getHighlightedParts(title, props.searchQuery || '');
   });
 
+  const taskCounts = () => tasksStore.counts[props.note.id];
+
   const formatDate = (timestamp: number) => {
     const date = new Date(timestamp);
     const now = new Date();
@@ -102,6 +106,8 @@ export const NoteItem: Component<NoteItemProps> = (props) => {
         class="note-item"
         classList={{ 'is-selected': props.isSelected, 'is-trash': props.isTrash }}
         onClick={props.onSelect}
+        draggable={!props.isTrash}
+        onDragStart={(e) => e.dataTransfer?.setData(NOTE_DRAG_TYPE, props.note.id)}
       >
         {/* Note icon */}
         <svg class="note-icon" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor">
@@ -121,6 +127,17 @@ export const NoteItem: Component<NoteItemProps> = (props) => {
             )}
           </For>
         </span>
+        <Show when={!props.isTrash && taskCounts()}>
+          {(counts) => (
+            <span
+              class="note-task-count"
+              classList={{ 'is-done': counts().open === 0 }}
+              title={`${counts().completed} of ${counts().open + counts().completed} tasks done`}
+            >
+              {counts().completed}/{counts().open + counts().completed}
+            </span>
+          )}
+        </Show>
         <Show when={!props.isTrash}>
           <div class="note-actions">
             <button
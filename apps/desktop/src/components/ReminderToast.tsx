@@ -0,0 +1,47 @@
+import { Component, For } from 'solid-js';
+import { tasksStore, completeTask, snoozeTask, dismissReminder } from '../stores/tasksStore';
+import { selectNote } from '../stores/notesStore';
+import './ReminderToast.css';
+
+/** Minutes a reminder is snoozed for */
+const SNOOZE_MINUTES = 10;
+
+/**
+ * Reminders that fired, with actions to snooze them or mark the task done
+ */
+export const ReminderToast: Component = () => {
+  return (
+    <div class="reminder-toasts">
+      <For each={tasksStore.reminders}>
+        {(task) => (
+          <div class="reminder-toast" role="alert">
+            <button
+              class="reminder-toast-body"
+              onClick={() => {
+                selectNote(task.noteId);
+                dismissReminder(task.id);
+              }}
+              title="Open note"
+            >
+              <span class="reminder-toast-title">{task.noteTitle || 'Untitled'}</span>
+              <span class="reminder-toast-text">{task.text}</span>
+            </button>
+            <div class="reminder-toast-actions">
+              <button onClick={() => snoozeTask(task.id, SNOOZE_MINUTES)}>Snooze {SNOOZE_MINUTES} min</button>
+              <button class="is-primary" onClick={() => completeTask(task.id)}>
+                Done
+              </button>
+              <button
+                class="reminder-toast-dismiss"
+                onClick={() => dismissReminder(task.id)}
+                aria-label="Dismiss reminder"
+              >
+                ×
+              </button>
+            </div>
+          </div>
+        )}
+      </For>
+    </div>
+  );
+};
@@ -0,0 +1,141 @@
+import { Component, For, Show, createEffect, createMemo, createSignal, on, onCleanup } from 'solid-js';
+import type { SavedSearch } from '@pdtodo/types';
+import { notesStore, selectNote, toggleNoteStarred, deleteNote, isScratchPad } from '../stores/notesStore';
+import {
+  savedSearchesStore,
+  selectSavedSearch,
+  updateSavedSearch,
+  deleteSavedSearch,
+  loadSearchNotes,
+} from '../stores/savedSearchesStore';
+import { tagsStore } from '../stores/tagsStore';
+import { tasksStore } from '../stores/tasksStore';
+import { FolderNameInput } from './FolderItem';
+import { NoteItem } from './NoteItem';
+import './SavedSearchItem.css';
+
+/** Wait after notes change before searching again, e.g. while a title is typed */
+const RERUN_DELAY_MS = 300;
+
+interface SavedSearchItemProps {
+  search: SavedSearch;
+}
+
+/**
+ * A smart folder in the sidebar. Clicking it lists the notes its search
+ * finds, which are looked up again whenever notes, tags or tasks change.
+ */
+export const SavedSearchItem: Component<SavedSearchItemProps> = (props) => {
+  const [editing, setEditing] = createSignal<'name' | 'query' | null>(null);
+
+  const isSelected = () => savedSearchesStore.selectedId === props.search.id;
+  const notes = createMemo(() => {
+    if (!isSelected()) return [];
+    const byId = new Map(notesStore.notes.map((n) => [n.id, n]));
+    return savedSearchesStore.noteIds.flatMap((id) => {
+      const note = byId.get(id);
+      return note && !isScratchPad(id) ? [note] : [];
+    });
+  });
+
+  // What the search finds depends on titles, stars, content and trash state
+  // of notes, and on their tags and tasks
+  const searchInputs = () =>
+    isSelected() && [
+      notesStore.notes.map((n) => `${n.id}:${n.title}:${n.starred}:${n.updatedAt}:${n.deletedAt}`).join(),
+      tagsStore.tags.map((t) => `${t.id}:${t.noteCount}`).join(),
+      Object.values(tasksStore.counts).map((c) => `${c.noteId}:${c.open}:${c.completed}`).join(),
+    ].join('|');
+
+  createEffect(
+    on(
+      searchInputs,
+      (inputs, previous) => {
+        // Picking the search runs it already
+        if (!inputs || !previous) return;
+        const timeout = setTimeout(loadSearchNotes, RERUN_DELAY_MS);
+        onCleanup(() => clearTimeout(timeout));
+      },
+      { defer: true }
+    )
+  );
+
+  const handleEditQuery = (e: MouseEvent) => {
+    e.stopPropagation();
+    setEditing('query');
+  };
+
+  const handleDelete = (e: MouseEvent) => {
+    e.stopPropagation();
+    deleteSavedSearch(props.search.id);
+  };
+
+  return (
+    <>
+      <div
+        class="saved-search-item"
+        classList={{ 'is-selected': isSelected() }}
+        title={props.search.query}
+        onClick={() => selectSavedSearch(isSelected() ? null : props.search.id)}
+        onDblClick={() => setEditing('name')}
+      >
+        <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" class="saved-search-icon">
+          <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z" />
+        </svg>
+        <Show
+          when={editing()}
+          fallback={<span class="saved-search-name">{props.search.name}</span>}
+        >
+          {(field) => (
+            <FolderNameInput
+              initialValue={field() === 'name' ? props.search.name : props.search.query}
+              placeholder={field() === 'name' ? 'Smart folder name' : 'Search query'}
+              onSubmit={(value) => {
+                setEditing(null);
+                updateSavedSearch(props.search.id, { [field()]: value });
+              }}
+              onCancel={() => setEditing(null)}
+            />
+          )}
+        </Show>
+        <Show when={!editing()}>
+          <Show when={isSelected()}>
+            <span class="saved-search-count">{notes().length}</span>
+          </Show>
+          <button class="action-btn" onClick={handleEditQuery} aria-label="Edit search query">
+            <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+              <path
+                stroke-linecap="round"
+                stroke-linejoin="round"
+                stroke-width="2"
+                d="M15.232 5.232l3.536 3.536M9 13l6.232-6.232a2.5 2.5 0 013.536 3.536L12.536 16.536 9 17l.464-3.536z"
+              />
+            </svg>
+          </button>
+          <button class="action-btn delete-btn" onClick={handleDelete} aria-label="Delete smart folder">
+            <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+              <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M6 18L18 6M6 6l12 12" />
+            </svg>
+          </button>
+        </Show>
+      </div>
+
+      <Show when={isSelected()}>
+        <For each={notes()}>
+          {(note) => (
+            <div style={{ 'padding-left': '12px' }}>
+              <NoteItem
+                note={note}
+                isSelected={note.id === notesStore.selectedNoteId}
+                onSelect={() => selectNote(note.id)}
+                onToggleStar={() => toggleNoteStarred(note.id)}
+                onDelete={() => deleteNote(note.id)}
+                isTrash={note.deletedAt !== null}
+              />
+            </div>
+          )}
+        </For>
+      </Show>
+    </>
+  );
+};
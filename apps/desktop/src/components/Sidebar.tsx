@@ -1,4 +1,4 @@
-import { Component, For, Show, createMemo } from 'solid-js';
+import { Component, For, Show, createMemo, createSignal } from 'solid-js';
 import {
   notesStore,
   createNote,
@@ -13,11 +13,28 @@ import {
   setAllNotesExpanded,
   setTrashExpanded,
 } from '../stores/settingsStore';
+import { foldersStore, createFolder } from '../stores/foldersStore';
+import { tagsStore } from '../stores/tagsStore';
+import { savedSearchesStore, createSavedSearch } from '../stores/savedSearchesStore';
+import { tasksStore, openTaskView, type TaskView } from '../stores/tasksStore';
 import { focusEditorStart } from '../stores/focusStore';
 import { NoteItem } from './NoteItem';
+import { FolderItem, FolderNameInput, handleFolderDrop, isFolderDrag } from './FolderItem';
+import { TagItem } from './TagItem';
+import { SavedSearchItem } from './SavedSearchItem';
 import { SearchInput } from './SearchInput';
 import './Sidebar.css';
 
+const TASK_VIEWS: { view: TaskView; title: string; icon: string }[] = [
+  { view: 'today', title: 'Today', icon: 'M12 8v4l3 3m6-3a9 9 0 11-18 0 9 9 0 0118 0z' },
+  {
+    view: 'upcoming',
+    title: 'Upcoming',
+    icon: 'M8 7V3m8 4V3m-9 8h10M5 21h14a2 2 0 002-2V7a2 2 0 00-2-2H5a2 2 0 00-2 2v12a2 2 0 002 2z',
+  },
+  { view: 'completed', title: 'Completed', icon: 'M9 12l2 2 4-4m6 2a9 9 0 11-18 0 9 9 0 0118 0z' },
+];
+
 export const Sidebar: Component = () => {
   const searchQuery = () => notesStore.searchQuery;
 
@@ -31,17 +48,23 @@ export const Sidebar: Component = () => {
     return starred.filter((n) => n.title.toLowerCase().includes(query));
   });
 
-  // Get non-starred active notes, filtered by search query
+  // Get non-starred active notes, filtered by search query. Notes in a
+  // folder are listed under it, unless searching.
   const activeNotes = createMemo(() => {
     const query = searchQuery().toLowerCase();
     // Exclude starred notes from "All Notes" section
     const nonStarred = notesStore.activeNotes.filter((n) => !n.starred);
     const filtered = query
       ? nonStarred.filter((n) => n.title.toLowerCase().includes(query))
-      : nonStarred;
+      : nonStarred.filter((n) => !n.folderId);
     return filtered.sort((a, b) => b.updatedAt - a.updatedAt);
   });
 
+  const topLevelFolders = createMemo(() => foldersStore.childrenOf(null));
+  const [isAddingFolder, setIsAddingFolder] = createSignal(false);
+  const [isSavingSearch, setIsSavingSearch] = createSignal(false);
+  const [isAllNotesDropTarget, setIsAllNotesDropTarget] = createSignal(false);
+
   // Combined matching notes for Enter key handling
   const allMatchingNotes = createMemo(() => {
     return [...starredNotes(), ...activeNotes()];
@@ -111,6 +134,65 @@ export const Sidebar: Component = () => {
           </section>
         </Show>
 
+        <section class="sidebar-section">
+          <div class="note-list">
+            <For each={TASK_VIEWS}>
+              {(item) => (
+                <div
+                  class="smart-view-item"
+                  classList={{ 'is-selected': tasksStore.view === item.view }}
+                  onClick={() => openTaskView(item.view)}
+                >
+                  <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" class="smart-view-icon">
+                    <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d={item.icon} />
+                  </svg>
+                  <span class="smart-view-title">{item.title}</span>
+                  <Show when={item.view === 'today' && tasksStore.todayCount > 0}>
+                    <span class="smart-view-count">{tasksStore.todayCount}</span>
+                  </Show>
+                </div>
+              )}
+            </For>
+          </div>
+        </section>
+
+        <Show when={savedSearchesStore.searches.length > 0 || searchQuery()}>
+          <section class="sidebar-section">
+            <div class="section-title">
+              <span>Smart Folders</span>
+              <Show when={searchQuery()}>
+                <button
+                  class="section-add-btn"
+                  onClick={() => setIsSavingSearch(true)}
+                  aria-label="Save search as smart folder"
+                  title="Save search as smart folder"
+                >
+                  +
+                </button>
+              </Show>
+            </div>
+            <div class="note-list">
+              <Show when={isSavingSearch()}>
+                <div class="saved-search-item">
+                  <FolderNameInput
+                    placeholder="Smart folder name"
+                    onSubmit={(name) => {
+                      const query = searchQuery();
+                      setIsSavingSearch(false);
+                      setSearchQuery('');
+                      createSavedSearch(name, query);
+                    }}
+                    onCancel={() => setIsSavingSearch(false)}
+                  />
+                </div>
+              </Show>
+              <For each={savedSearchesStore.searches}>
+                {(search) => <SavedSearchItem search={search} />}
+              </For>
+            </div>
+          </section>
+        </Show>
+
         <Show when={starredNotes().length > 0}>
           <section class="sidebar-section">
             <h3 class="section-title">Shortcuts</h3>
@@ -131,7 +213,64 @@ export const Sidebar: Component = () => {
           </section>
         </Show>
 
-        <section class="sidebar-section">
+        <Show when={!searchQuery()}>
+          <section class="sidebar-section">
+            <div class="section-title">
+              <span>Folders</span>
+              <button
+                class="section-add-btn"
+                onClick={() => setIsAddingFolder(true)}
+                aria-label="New folder"
+              >
+                +
+              </button>
+            </div>
+            <div class="note-list">
+              <Show when={isAddingFolder()}>
+                <div class="folder-item">
+                  <FolderNameInput
+                    onSubmit={(name) => {
+                      setIsAddingFolder(false);
+                      createFolder(name);
+                    }}
+                    onCancel={() => setIsAddingFolder(false)}
+                  />
+                </div>
+              </Show>
+              <For each={topLevelFolders()}>
+                {(folder) => <FolderItem folder={folder} depth={0} />}
+              </For>
+            </div>
+          </section>
+        </Show>
+
+        <Show when={!searchQuery() && tagsStore.tags.length > 0}>
+          <section class="sidebar-section">
+            <h3 class="section-title">Tags</h3>
+            <div class="note-list">
+              <For each={tagsStore.tags}>
+                {(tag) => <TagItem tag={tag} />}
+              </For>
+            </div>
+          </section>
+        </Show>
+
+        <section
+          class="sidebar-section"
+          classList={{ 'is-drop-target': isAllNotesDropTarget() }}
+          onDragOver={(e) => {
+            if (!isFolderDrag(e)) return;
+            e.preventDefault();
+            setIsAllNotesDropTarget(true);
+          }}
+          onDragLeave={() => setIsAllNotesDropTarget(false)}
+          onDrop={(e) => {
+            e.preventDefault();
+            setIsAllNotesDropTarget(false);
+            // Dropping here takes a note out of its folder, or a folder to the top level
+            handleFolderDrop(e, null);
+          }}
+        >
           <button
             class="section-title section-title-collapsible"
             onClick={() => setAllNotesExpanded(!settingsStore.allNotesExpanded)}
@@ -142,7 +281,7 @@ export const Sidebar: Component = () => {
           <Show when={settingsStore.allNotesExpanded}>
             <div class="note-list">
               <Show
-                when={activeNotes().length > 0}
+                when={activeNotes().length > 0 || notesStore.activeNotes.length > 0}
                 fallback={
                   <div class="empty-list">
                     <p>No notes yet</p>
@@ -0,0 +1,70 @@
+import { Component, For, Show, createSignal } from 'solid-js';
+import { tagsStore, addNoteTag, removeNoteTag } from '../stores/tagsStore';
+import './TagBar.css';
+
+interface TagBarProps {
+  noteId: string;
+}
+
+/**
+ * Tags of the open note, with an input to add one. Hashtags typed in the
+ * note show up here once the content is saved.
+ */
+export const TagBar: Component<TagBarProps> = (props) => {
+  const [isAdding, setIsAdding] = createSignal(false);
+
+  const submit = (input: HTMLInputElement) => {
+    const name = input.value.trim().replace(/^#/, '');
+    input.value = '';
+    if (name) {
+      addNoteTag(props.noteId, name);
+    }
+  };
+
+  return (
+    <div class="tag-bar">
+      <For each={tagsStore.noteTags}>
+        {(name) => (
+          <span class="tag-chip">
+            #{name}
+            <button
+              class="tag-chip-remove"
+              onClick={() => removeNoteTag(props.noteId, name)}
+              aria-label={`Remove tag ${name}`}
+            >
+              ×
+            </button>
+          </span>
+        )}
+      </For>
+      <Show
+        when={isAdding()}
+        fallback={
+          <button class="tag-add-btn" onClick={() => setIsAdding(true)}>
+            + Tag
+          </button>
+        }
+      >
+        <input
+          class="tag-input"
+          placeholder="Tag name"
+          ref={(el) => setTimeout(() => el.focus())}
+          onKeyDown={(e) => {
+            if (e.key === 'Enter') {
+              submit(e.currentTarget);
+            } else if (e.key === 'Escape') {
+              setIsAdding(false);
+            } else if (e.key === ' ') {
+              // Tags can't contain spaces
+              e.preventDefault();
+            }
+          }}
+          onBlur={(e) => {
+            submit(e.currentTarget);
+            setIsAdding(false);
+          }}
+        />
+      </Show>
+    </div>
+  );
+};
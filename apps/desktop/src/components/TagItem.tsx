@@ -0,0 +1,83 @@
+import { Component, For, Show, createMemo, createSignal } from 'solid-js';
+import type { Tag } from '@pdtodo/types';
+import { notesStore, selectNote, toggleNoteStarred, deleteNote } from '../stores/notesStore';
+import { tagsStore, selectTag, renameTag, deleteTag } from '../stores/tagsStore';
+import { FolderNameInput } from './FolderItem';
+import { NoteItem } from './NoteItem';
+import './TagItem.css';
+
+interface TagItemProps {
+  tag: Tag;
+}
+
+/**
+ * A tag in the sidebar. Clicking it lists the notes that have it.
+ */
+export const TagItem: Component<TagItemProps> = (props) => {
+  const [isRenaming, setIsRenaming] = createSignal(false);
+
+  const isSelected = () => tagsStore.selectedTag === props.tag.name;
+  const notes = createMemo(() => {
+    if (!isSelected()) return [];
+    const ids = new Set(tagsStore.taggedNoteIds);
+    return notesStore.activeNotes
+      .filter((n) => ids.has(n.id))
+      .sort((a, b) => b.updatedAt - a.updatedAt);
+  });
+
+  const handleDelete = (e: MouseEvent) => {
+    e.stopPropagation();
+    deleteTag(props.tag.id);
+  };
+
+  return (
+    <>
+      <div
+        class="tag-item"
+        classList={{ 'is-selected': isSelected() }}
+        onClick={() => selectTag(isSelected() ? null : props.tag.name)}
+        onDblClick={() => setIsRenaming(true)}
+      >
+        <span class="tag-hash">#</span>
+        <Show
+          when={isRenaming()}
+          fallback={<span class="tag-name">{props.tag.name}</span>}
+        >
+          <FolderNameInput
+            initialValue={props.tag.name}
+            placeholder="Tag name"
+            onSubmit={(name) => {
+              setIsRenaming(false);
+              renameTag(props.tag.id, name);
+            }}
+            onCancel={() => setIsRenaming(false)}
+          />
+        </Show>
+        <Show when={!isRenaming()}>
+          <span class="tag-count">{props.tag.noteCount}</span>
+          <button class="action-btn delete-btn" onClick={handleDelete} aria-label="Delete tag">
+            <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+              <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M6 18L18 6M6 6l12 12" />
+            </svg>
+          </button>
+        </Show>
+      </div>
+
+      <Show when={isSelected()}>
+        <For each={notes()}>
+          {(note) => (
+            <div style={{ 'padding-left': '12px' }}>
+              <NoteItem
+                note={note}
+                isSelected={note.id === notesStore.selectedNoteId}
+                onSelect={() => selectNote(note.id)}
+                onToggleStar={() => toggleNoteStarred(note.id)}
+                onDelete={() => deleteNote(note.id)}
+              />
+            </div>
+          )}
+        </For>
+      </Show>
+    </>
+  );
+};
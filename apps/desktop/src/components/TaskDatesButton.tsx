@@ -0,0 +1,201 @@
+import { Component, Accessor, For, Show, createSignal } from 'solid-js';
+import { Editor as TipTapEditor } from '@tiptap/core';
+import { taskItemAtCursor, type TaskDates } from '@pdtodo/editor';
+import './TaskDatesButton.css';
+
+interface TaskDatesButtonProps {
+  editor: Accessor<TipTapEditor | undefined>;
+  editorStateVersion: Accessor<number>;
+}
+
+type RepeatKind = 'none' | 'daily' | 'weekly' | 'monthly' | 'custom';
+
+/** RRULE weekday codes, Monday first */
+const WEEKDAYS = ['MO', 'TU', 'WE', 'TH', 'FR', 'SA', 'SU'];
+const WEEKDAY_LABELS = ['M', 'T', 'W', 'T', 'F', 'S', 'S'];
+
+/**
+ * Which of the simple choices a repeat rule is; anything else is edited as
+ * a custom rule
+ */
+function repeatKind(rule: string): RepeatKind {
+  if (!rule) return 'none';
+  if (rule === 'FREQ=DAILY') return 'daily';
+  if (/^FREQ=WEEKLY(;BYDAY=[A-Z]{2}(,[A-Z]{2})*)?$/.test(rule)) return 'weekly';
+  if (/^FREQ=MONTHLY(;BYMONTHDAY=-?\d+)?$/.test(rule)) return 'monthly';
+  return 'custom';
+}
+
+function weeklyDays(rule: string): string[] {
+  return rule.match(/BYDAY=([A-Z,]+)/)?.[1].split(',') ?? [];
+}
+
+/**
+ * The date a new repeat rule starts from: the due date, else today
+ */
+function startDate(dueDate: string): Date {
+  if (!dueDate) return new Date();
+  const [year, month, day] = dueDate.split('-').map(Number);
+  return new Date(year, month - 1, day);
+}
+
+/**
+ * Format a reminder time for a datetime-local input
+ */
+function toLocalInput(time: number): string {
+  const date = new Date(time - new Date(time).getTimezoneOffset() * 60000);
+  return date.toISOString().slice(0, 16);
+}
+
+/**
+ * Toolbar button to set the due date, reminder and repeat rule of the task
+ * at the cursor
+ */
+export const TaskDatesButton: Component<TaskDatesButtonProps> = (props) => {
+  const [isOpen, setIsOpen] = createSignal(false);
+
+  const currentTask = () => {
+    // Read the signal to create a reactive dependency
+    props.editorStateVersion();
+    const ed = props.editor();
+    return ed ? taskItemAtCursor(ed.state) : null;
+  };
+
+  const dueDate = () => (currentTask()?.node.attrs.dueDate as string | null) ?? '';
+  const remindAt = () => {
+    const time = currentTask()?.node.attrs.remindAt as number | null;
+    return time ? toLocalInput(time) : '';
+  };
+
+  const repeat = () => (currentTask()?.node.attrs.repeat as string | null) ?? '';
+
+  const setDates = (dates: Partial<TaskDates>) => {
+    props.editor()?.commands.setTaskDates(dates);
+  };
+
+  const setRepeatKind = (kind: RepeatKind) => {
+    const start = startDate(dueDate());
+    switch (kind) {
+      case 'none':
+        setDates({ repeat: null });
+        break;
+      case 'daily':
+        setDates({ repeat: 'FREQ=DAILY' });
+        break;
+      case 'weekly':
+        setDates({ repeat: `FREQ=WEEKLY;BYDAY=${WEEKDAYS[(start.getDay() + 6) % 7]}` });
+        break;
+      case 'monthly':
+        setDates({ repeat: `FREQ=MONTHLY;BYMONTHDAY=${start.getDate()}` });
+        break;
+      case 'custom':
+        setDates({ repeat: repeat() || 'FREQ=DAILY;INTERVAL=2' });
+        break;
+    }
+  };
+
+  const toggleWeekday = (code: string) => {
+    const days = weeklyDays(repeat());
+    const next = days.includes(code) ? days.filter((day) => day !== code) : [...days, code];
+    // Keep the days in week order, and at least one of them
+    const ordered = WEEKDAYS.filter((day) => next.includes(day));
+    if (ordered.length > 0) {
+      setDates({ repeat: `FREQ=WEEKLY;BYDAY=${ordered.join(',')}` });
+    }
+  };
+
+  return (
+    <div class="task-dates-menu">
+      <button
+        class="toolbar-btn"
+        classList={{ 'is-active': isOpen(), 'is-disabled': !currentTask() }}
+        disabled={!currentTask()}
+        onClick={() => setIsOpen(!isOpen())}
+        title="Due Date and Reminder"
+      >
+        <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+          <path
+            stroke-linecap="round"
+            stroke-linejoin="round"
+            stroke-width="2"
+            d="M8 7V3m8 4V3m-9 8h10M5 21h14a2 2 0 002-2V7a2 2 0 00-2-2H5a2 2 0 00-2 2v12a2 2 0 002 2z"
+          />
+        </svg>
+      </button>
+      <Show when={isOpen() && currentTask()}>
+        <div class="task-dates-popover">
+          <label class="task-dates-field">
+            <span>Due</span>
+            <input
+              type="date"
+              value={dueDate()}
+              onChange={(e) => setDates({ dueDate: e.currentTarget.value || null })}
+            />
+          </label>
+          <label class="task-dates-field">
+            <span>Remind</span>
+            <input
+              type="datetime-local"
+              value={remindAt()}
+              onChange={(e) => {
+                const value = e.currentTarget.value;
+                setDates({ remindAt: value ? new Date(value).getTime() : null });
+              }}
+            />
+          </label>
+          <label class="task-dates-field">
+            <span>Repeat</span>
+            <select
+              value={repeatKind(repeat())}
+              onChange={(e) => setRepeatKind(e.currentTarget.value as RepeatKind)}
+            >
+              <option value="none">Never</option>
+              <option value="daily">Daily</option>
+              <option value="weekly">Weekly</option>
+              <option value="monthly">Monthly</option>
+              <option value="custom">Custom rule</option>
+            </select>
+          </label>
+          <Show when={repeatKind(repeat()) === 'weekly'}>
+            <div class="task-dates-weekdays">
+              <For each={WEEKDAYS}>
+                {(code, index) => (
+                  <button
+                    classList={{ 'is-active': weeklyDays(repeat()).includes(code) }}
+                    onClick={() => toggleWeekday(code)}
+                    title={code}
+                  >
+                    {WEEKDAY_LABELS[index()]}
+                  </button>
+                )}
+              </For>
+            </div>
+          </Show>
+          <Show when={repeatKind(repeat()) === 'custom'}>
+            <input
+              class="task-dates-rule"
+              value={repeat()}
+              placeholder="FREQ=WEEKLY;INTERVAL=2;BYDAY=MO"
+              title="RRULE: FREQ, INTERVAL, BYDAY, BYMONTHDAY, COUNT and UNTIL"
+              onChange={(e) => setDates({ repeat: e.currentTarget.value.trim().toUpperCase() || null })}
+            />
+          </Show>
+          <div class="task-dates-actions">
+            <button
+              class="task-dates-clear"
+              onClick={() => {
+                setDates({ dueDate: null, remindAt: null, repeat: null });
+                setIsOpen(false);
+              }}
+            >
+              Clear
+            </button>
+            <button class="task-dates-done" onClick={() => setIsOpen(false)}>
+              Done
+            </button>
+          </div>
+        </div>
+      </Show>
+    </div>
+  );
+};
@@ -0,0 +1,94 @@
+import { Component, For, Show } from 'solid-js';
+import type { Task } from '@pdtodo/types';
+import { tasksStore, toggleTask, requestTaskReveal, type TaskView as TaskViewKind } from '../stores/tasksStore';
+import { selectNote } from '../stores/notesStore';
+import './TaskView.css';
+
+const TITLES: Record<TaskViewKind, string> = {
+  today: 'Today',
+  upcoming: 'Upcoming',
+  completed: 'Recently completed',
+};
+
+const EMPTY_MESSAGES: Record<TaskViewKind, string> = {
+  today: 'Nothing due today',
+  upcoming: 'Nothing due in the next 7 days',
+  completed: 'No tasks completed in the last 7 days',
+};
+
+function todayString(): string {
+  const now = new Date();
+  const month = String(now.getMonth() + 1).padStart(2, '0');
+  const day = String(now.getDate()).padStart(2, '0');
+  return `${now.getFullYear()}-${month}-${day}`;
+}
+
+/**
+ * "Overdue", "Today", or the weekday and date a task is due
+ */
+function dueLabel(dueDate: string): string {
+  const today = todayString();
+  if (dueDate < today) return 'Overdue';
+  if (dueDate === today) return 'Today';
+  const [year, month, day] = dueDate.split('-').map(Number);
+  return new Date(year, month - 1, day).toLocaleDateString([], { weekday: 'short', month: 'short', day: 'numeric' });
+}
+
+function completedLabel(completedAt: number): string {
+  return new Date(completedAt).toLocaleDateString([], { weekday: 'short', month: 'short', day: 'numeric' });
+}
+
+interface TaskViewProps {
+  view: TaskViewKind;
+}
+
+/**
+ * Tasks across notes by due date or completion, shown in place of a note.
+ * Clicking a task opens its note at the task.
+ */
+export const TaskView: Component<TaskViewProps> = (props) => {
+  const openTask = (task: Task) => {
+    requestTaskReveal(task);
+    selectNote(task.noteId);
+  };
+
+  return (
+    <div class="task-view">
+      <h1 class="task-view-title">{TITLES[props.view]}</h1>
+      <Show
+        when={tasksStore.viewTasks.length > 0}
+        fallback={<p class="task-view-empty">{EMPTY_MESSAGES[props.view]}</p>}
+      >
+        <ul class="task-view-list">
+          <For each={tasksStore.viewTasks}>
+            {(task) => (
+              <li class="task-view-item" classList={{ 'is-checked': task.checked }}>
+                <input
+                  type="checkbox"
+                  checked={task.checked}
+                  onChange={() => toggleTask(task.id)}
+                  aria-label={task.checked ? 'Uncheck task' : 'Check task'}
+                />
+                <button class="task-view-task" onClick={() => openTask(task)} title="Open in note">
+                  <span class="task-view-text">{task.text || 'Untitled task'}</span>
+                  <span class="task-view-note">{task.noteTitle || 'Untitled'}</span>
+                </button>
+                <Show when={!task.checked && task.dueDate}>
+                  <span
+                    class="task-view-date"
+                    classList={{ 'is-overdue': (task.dueDate ?? '') < todayString() }}
+                  >
+                    {dueLabel(task.dueDate!)}
+                  </span>
+                </Show>
+                <Show when={task.checked && task.completedAt}>
+                  <span class="task-view-date">{completedLabel(task.completedAt!)}</span>
+                </Show>
+              </li>
+            )}
+          </For>
+        </ul>
+      </Show>
+    </div>
+  );
+};
@@ -1,6 +1,8 @@
 import { Component, createSignal, Show } from 'solid-js';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { WebviewWindow } from '@tauri-apps/api/webviewWindow';
+import { open, save } from '@tauri-apps/plugin-dialog';
+import { notesStore, exportNote, exportAll, importNotes } from '../stores/notesStore';
 import { DropdownMenu, MenuItem } from './DropdownMenu';
 import { AboutOverlay } from './AboutOverlay';
 import { ShortcutsOverlay } from './ShortcutsOverlay';
@@ -55,6 +57,55 @@ export const TitleBar: Component = () => {
     }
   };
 
+  const handleExportNote = async () => {
+    const note = notesStore.selectedNote;
+    if (!note) return;
+    const name = note.title.replace(/[/\\:*?"<>|]/g, '-').trim() || 'Untitled';
+    const path = await save({
+      title: 'Export note',
+      defaultPath: `${name}.md`,
+      filters: [
+        { name: 'Markdown', extensions: ['md'] },
+        { name: 'HTML', extensions: ['html'] },
+        { name: 'PDF', extensions: ['pdf'] },
+      ],
+    });
+    if (path) {
+      await exportNote(note.id, path);
+    }
+  };
+
+  const handleExportAll = async () => {
+    const directory = await open({ directory: true, title: 'Export to folder' });
+    if (typeof directory === 'string') {
+      await exportAll(directory, 'markdown');
+    }
+  };
+
+  const handleImportFiles = async () => {
+    const files = await open({
+      multiple: true,
+      title: 'Import notes',
+      filters: [
+        { name: 'Notes', extensions: ['md', 'markdown', 'txt', 'json', 'enex', 'html', 'htm'] },
+        { name: 'Markdown and text', extensions: ['md', 'markdown', 'txt'] },
+        { name: 'Google Keep (Takeout JSON)', extensions: ['json'] },
+        { name: 'Evernote', extensions: ['enex'] },
+        { name: 'HTML', extensions: ['html', 'htm'] },
+      ],
+    });
+    if (files && files.length > 0) {
+      await importNotes(files);
+    }
+  };
+
+  const handleImportFolder = async () => {
+    const directory = await open({ directory: true, title: 'Import notes from folder' });
+    if (typeof directory === 'string') {
+      await importNotes([directory]);
+    }
+  };
+
   const menuItems: MenuItem[] = [
     {
       label: 'About',
@@ -85,6 +136,42 @@ export const TitleBar: Component = () => {
       ),
       onClick: openLogsWindow,
     },
+    {
+      label: 'Export Note',
+      icon: (
+        <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+          <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M4 16v2a2 2 0 002 2h12a2 2 0 002-2v-2M12 4v12M7 11l5 5 5-5" />
+        </svg>
+      ),
+      onClick: handleExportNote,
+    },
+    {
+      label: 'Export All Notes',
+      icon: (
+        <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+          <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M3 7a2 2 0 012-2h4l2 2h8a2 2 0 012 2v8a2 2 0 01-2 2H5a2 2 0 01-2-2V7zM12 10v6M9 13l3 3 3-3" />
+        </svg>
+      ),
+      onClick: handleExportAll,
+    },
+    {
+      label: 'Import Files',
+      icon: (
+        <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+          <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M4 16v2a2 2 0 002 2h12a2 2 0 002-2v-2M12 16V4M7 9l5-5 5 5" />
+        </svg>
+      ),
+      onClick: handleImportFiles,
+    },
+    {
+      label: 'Import Folder',
+      icon: (
+        <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor">
+          <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M3 7a2 2 0 012-2h4l2 2h8a2 2 0 012 2v8a2 2 0 01-2 2H5a2 2 0 01-2-2V7zM12 16v-6M9 13l3-3 3 3" />
+        </svg>
+      ),
+      onClick: handleImportFolder,
+    },
   ];
 
   return (
@@ -0,0 +1,51 @@
+import { Component, createSignal } from 'solid-js';
+import { invoke } from '@tauri-apps/api/core';
+import './UnlockScreen.css';
+
+interface UnlockScreenProps {
+  onUnlocked: () => void;
+}
+
+export const UnlockScreen: Component<UnlockScreenProps> = (props) => {
+  const [passphrase, setPassphrase] = createSignal('');
+  const [error, setError] = createSignal<string | null>(null);
+  const [isUnlocking, setIsUnlocking] = createSignal(false);
+
+  const handleSubmit = async (e: Event) => {
+    e.preventDefault();
+    if (!passphrase() || isUnlocking()) return;
+
+    setIsUnlocking(true);
+    setError(null);
+    try {
+      await invoke('unlock_library', { passphrase: passphrase() });
+      props.onUnlocked();
+    } catch (err) {
+      setError(String(err));
+      setPassphrase('');
+    } finally {
+      setIsUnlocking(false);
+    }
+  };
+
+  return (
+    <div class="unlock-screen">
+      <form class="unlock-form" onSubmit={handleSubmit}>
+        <h1 class="unlock-title">PDTodo</h1>
+        <p class="unlock-description">Your notes are encrypted. Enter your passphrase to unlock them.</p>
+        <input
+          type="password"
+          class="unlock-input"
+          value={passphrase()}
+          onInput={(e) => setPassphrase(e.currentTarget.value)}
+          placeholder="Passphrase"
+          autofocus
+        />
+        {error() && <p class="unlock-error">{error()}</p>}
+        <button type="submit" class="unlock-btn" disabled={!passphrase() || isUnlocking()}>
+          {isUnlocking() ? 'Unlocking...' : 'Unlock'}
+        </button>
+      </form>
+    </div>
+  );
+};
@@ -27,7 +27,7 @@ const shortcuts: ShortcutConfig[] = [
     key: 'n',
     ctrl: true,
     handler: () => {
-      createNote();
+      createNote(notesStore.selectedNote?.folderId ?? null);
       return true;
     },
     description: 'New note',
@@ -0,0 +1,97 @@
+import { createStore } from 'solid-js/store';
+import type { Attachment } from '@pdtodo/types';
+import { invoke } from '@tauri-apps/api/core';
+
+interface AttachmentsState {
+  /** Attachments of the note open in the editor, oldest first */
+  noteAttachments: Attachment[];
+  /** Note the noteAttachments belong to */
+  noteId: string | null;
+}
+
+const [attachmentsState, setAttachmentsState] = createStore<AttachmentsState>({
+  noteAttachments: [],
+  noteId: null,
+});
+
+export const attachmentsStore = {
+  get noteAttachments() {
+    return attachmentsState.noteAttachments;
+  },
+};
+
+/**
+ * Load the attachments of the note open in the editor
+ */
+export async function loadNoteAttachments(noteId: string): Promise<void> {
+  setAttachmentsState({ noteId, noteAttachments: [] });
+  try {
+    const attachments = await invoke<Attachment[]>('get_note_attachments', { noteId });
+    // Another note may have been opened meanwhile
+    if (attachmentsState.noteId === noteId) {
+      setAttachmentsState('noteAttachments', attachments);
+    }
+  } catch (error) {
+    console.error('Failed to load attachments:', error);
+  }
+}
+
+function addToOpenNote(noteId: string, attachments: Attachment[]): void {
+  if (attachmentsState.noteId === noteId) {
+    setAttachmentsState('noteAttachments', (current) => [...current, ...attachments]);
+  }
+}
+
+/**
+ * Attach files from disk to a note
+ */
+export async function addAttachmentFiles(noteId: string, paths: string[]): Promise<void> {
+  try {
+    const attachments = await invoke<Attachment[]>('add_attachment_files', { noteId, paths });
+    addToOpenNote(noteId, attachments);
+  } catch (error) {
+    console.error('Failed to attach files:', error);
+  }
+}
+
+/**
+ * Attach a file that only exists in memory, such as a pasted image
+ */
+export async function addAttachmentData(noteId: string, file: File): Promise<void> {
+  try {
+    const data = Array.from(new Uint8Array(await file.arrayBuffer()));
+    // Pasted images are all called "image.png"; keep them apart by time
+    const fileName = file.name && file.name !== 'image.png'
+      ? file.name
+      : `Pasted image ${new Date().toISOString().slice(0, 19).replace('T', ' ')}.png`;
+    const attachment = await invoke<Attachment>('add_attachment', {
+      noteId,
+      fileName,
+      contentType: file.type || null,
+      data,
+    });
+    addToOpenNote(noteId, [attachment]);
+  } catch (error) {
+    console.error('Failed to attach pasted file:', error);
+  }
+}
+
+/**
+ * Save a copy of an attachment to `path`
+ */
+export async function saveAttachment(attachmentId: string, path: string): Promise<void> {
+  try {
+    await invoke('save_attachment', { attachmentId, path });
+  } catch (error) {
+    console.error('Failed to save attachment:', error);
+  }
+}
+
+export async function removeAttachment(attachmentId: string): Promise<void> {
+  try {
+    await invoke('remove_attachment', { attachmentId });
+    setAttachmentsState('noteAttachments', (current) => current.filter((a) => a.id !== attachmentId));
+  } catch (error) {
+    console.error('Failed to remove attachment:', error);
+  }
+}
@@ -0,0 +1,133 @@
+import { createStore, produce } from 'solid-js/store';
+import type { Folder } from '@pdtodo/types';
+import { invoke } from '@tauri-apps/api/core';
+import { loadNotes } from './notesStore';
+
+/** Drag data types for notes and folders dropped onto folders */
+export const NOTE_DRAG_TYPE = 'application/x-pdtodo-note';
+export const FOLDER_DRAG_TYPE = 'application/x-pdtodo-folder';
+
+interface FoldersState {
+  folders: Folder[];
+  /** Folders whose contents are shown in the sidebar */
+  expanded: Record<string, boolean>;
+}
+
+const [foldersState, setFoldersState] = createStore<FoldersState>({
+  folders: [],
+  expanded: {},
+});
+
+export const foldersStore = {
+  get folders() {
+    return foldersState.folders;
+  },
+  /** Direct subfolders of a folder, or top-level folders for null */
+  childrenOf(parentId: string | null): Folder[] {
+    return foldersState.folders.filter((f) => f.parentId === parentId);
+  },
+  isExpanded(folderId: string): boolean {
+    return foldersState.expanded[folderId] ?? false;
+  },
+  /** Whether `folderId` is `ancestorId` or somewhere below it */
+  isWithin(folderId: string, ancestorId: string): boolean {
+    let current: string | null = folderId;
+    while (current) {
+      if (current === ancestorId) return true;
+      current = foldersState.folders.find((f) => f.id === current)?.parentId ?? null;
+    }
+    return false;
+  },
+};
+
+/**
+ * Load all folders from the backend
+ */
+export async function loadFolders(): Promise<void> {
+  try {
+    const folders = await invoke<Folder[]>('get_folders');
+    setFoldersState('folders', folders);
+  } catch (error) {
+    console.error('Failed to load folders:', error);
+  }
+}
+
+export function setFolderExpanded(folderId: string, expanded: boolean): void {
+  setFoldersState('expanded', folderId, expanded);
+}
+
+/**
+ * Create a folder, inside `parentId` if given
+ */
+export async function createFolder(name: string, parentId: string | null = null): Promise<string | null> {
+  try {
+    const folderId = await invoke<string>('create_folder', { name, parentId });
+    const now = Date.now();
+    setFoldersState(
+      produce((state) => {
+        state.folders.push({ id: folderId, name, parentId, createdAt: now, updatedAt: now });
+        state.folders.sort((a, b) => a.name.localeCompare(b.name, undefined, { sensitivity: 'base' }));
+        if (parentId) {
+          state.expanded[parentId] = true;
+        }
+      })
+    );
+    return folderId;
+  } catch (error) {
+    console.error('Failed to create folder:', error);
+    return null;
+  }
+}
+
+export async function renameFolder(folderId: string, name: string): Promise<void> {
+  try {
+    await invoke('rename_folder', { folderId, name });
+    setFoldersState(
+      produce((state) => {
+        const folder = state.folders.find((f) => f.id === folderId);
+        if (folder) {
+          folder.name = name;
+          folder.updatedAt = Date.now();
+        }
+        state.folders.sort((a, b) => a.name.localeCompare(b.name, undefined, { sensitivity: 'base' }));
+      })
+    );
+  } catch (error) {
+    console.error('Failed to rename folder:', error);
+  }
+}
+
+/**
+ * Move a folder under another one, or to the top level with null
+ */
+export async function moveFolder(folderId: string, parentId: string | null): Promise<void> {
+  // A folder can't go inside itself; the backend refuses too
+  if (parentId && foldersStore.isWithin(parentId, folderId)) return;
+
+  try {
+    await invoke('move_folder', { folderId, parentId });
+    setFoldersState(
+      produce((state) => {
+        const folder = state.folders.find((f) => f.id === folderId);
+        if (folder) {
+          folder.parentId = parentId;
+          folder.updatedAt = Date.now();
+        }
+      })
+    );
+  } catch (error) {
+    console.error('Failed to move folder:', error);
+  }
+}
+
+/**
+ * Delete a folder and its subfolders; the notes in them go to the trash
+ */
+export async function deleteFolder(folderId: string): Promise<void> {
+  try {
+    await invoke<number>('delete_folder', { folderId });
+    await Promise.all([loadFolders(), loadNotes()]);
+  } catch (error) {
+    console.error('Failed to delete folder:', error);
+  }
+}
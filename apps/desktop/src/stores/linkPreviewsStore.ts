@@ -0,0 +1,42 @@
+import { createStore } from 'solid-js/store';
+import type { LinkPreview } from '@pdtodo/types';
+import { invoke } from '@tauri-apps/api/core';
+import { settingsStore } from './settingsStore';
+
+interface LinkPreviewsState {
+  /** Previews by URL; null for pages that couldn't be previewed */
+  previews: Record<string, LinkPreview | null>;
+}
+
+const [linkPreviewsState, setLinkPreviewsState] = createStore<LinkPreviewsState>({
+  previews: {},
+});
+
+const loading = new Set<string>();
+
+export const linkPreviewsStore = {
+  preview(url: string): LinkPreview | null | undefined {
+    return linkPreviewsState.previews[url];
+  },
+};
+
+/**
+ * Fetch the preview of a linked page, once per URL while the app runs;
+ * the backend caches previews across restarts
+ */
+export async function loadLinkPreview(url: string): Promise<void> {
+  if (url in linkPreviewsState.previews || loading.has(url)) return;
+  loading.add(url);
+  try {
+    const preview = await invoke<LinkPreview>('unfurl_url', { url });
+    setLinkPreviewsState('previews', url, preview);
+  } catch {
+    // Unreachable pages and non-HTML links just show their URL. Offline,
+    // only cached previews come back; the rest are tried again once online.
+    if (!settingsStore.offlineMode) {
+      setLinkPreviewsState('previews', url, null);
+    }
+  } finally {
+    loading.delete(url);
+  }
+}
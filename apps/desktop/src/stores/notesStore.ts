@@ -2,6 +2,8 @@ import { createStore, produce } from 'solid-js/store';
 import type { NoteMeta } from '@pdtodo/types';
 import { invoke } from '@tauri-apps/api/core';
 import { settingsStore, setLastOpenedNoteId } from './settingsStore';
+import { loadTags } from './tagsStore';
+import { loadTasks, closeTaskView, saveEditorContent, mergeEditorContent } from './tasksStore';
 
 interface NotesState {
   notes: NoteMeta[];
@@ -41,13 +43,26 @@ export async function flushPendingTitleUpdate(): Promise<void> {
     const { noteId, title } = pendingTitleUpdate;
     pendingTitleUpdate = null;
     try {
-      await invoke('update_note_title', { noteId, title });
+      await saveNoteTitle(noteId, title);
     } catch (error) {
       console.error('Failed to flush title update:', error);
     }
   }
 }
 
+/**
+ * Save a note's title. The backend rewrites `[[links]]` to the note in
+ * other notes, so those are saved first and merged back afterwards.
+ */
+async function saveNoteTitle(noteId: string, title: string): Promise<void> {
+  await saveEditorContent();
+  const renamed = await invoke<string[]>('update_note_title', { noteId, title });
+  if (renamed.length > 0) {
+    renamed.forEach(updateNoteTimestamp);
+    await mergeEditorContent(renamed);
+  }
+}
+
 // Computed values
 export const notesStore = {
   get notes() {
@@ -124,12 +139,12 @@ export async function loadNotes(): Promise<void> {
  * Create a new note
  * Flushes pending updates and adds note directly to store (no loadNotes)
  */
-export async function createNote(): Promise<string | null> {
+export async function createNote(folderId: string | null = null): Promise<string | null> {
   // Flush any pending title updates first to avoid losing them
   await flushPendingTitleUpdate();
 
   try {
-    const noteId = await invoke<string>('create_note', { title: 'Untitled' });
+    const noteId = await invoke<string>('create_note', { title: 'Untitled', folderId });
     const now = Date.now();
 
     // Add new note to store directly instead of reloading all notes
@@ -143,6 +158,7 @@ export async function createNote(): Promise<string | null> {
           createdAt: now,
           updatedAt: now,
           deletedAt: null,
+          folderId,
         });
         state.selectedNoteId = noteId;
       })
@@ -176,10 +192,23 @@ export async function createNote(): Promise<string | null> {
  */
 export async function selectNote(noteId: string): Promise<void> {
   await flushPendingTitleUpdate();
+  closeTaskView();
   setNotesState('selectedNoteId', noteId);
   setLastOpenedNoteId(noteId);
 }
 
+/**
+ * Open the note a `[[Note Title]]` link points to, if there is one outside
+ * the trash. Titles are matched without case, as the backend does.
+ */
+export async function openNoteLink(title: string): Promise<void> {
+  const key = title.trim().toLowerCase();
+  const note = notesState.notes.find((n) => !n.deletedAt && n.title.trim().toLowerCase() === key);
+  if (note) {
+    await selectNote(note.id);
+  }
+}
+
 /**
  * Update note title with debounced persistence
  * Uses optimistic updates locally, debounces backend saves
@@ -208,7 +237,7 @@ export function updateNoteTitle(noteId: string, title: string): void {
     timeout: setTimeout(async () => {
       pendingTitleUpdate = null;
       try {
-        await invoke('update_note_title', { noteId, title });
+        await saveNoteTitle(noteId, title);
       } catch (error) {
         console.error('Failed to update note title:', error);
       }
@@ -276,6 +305,8 @@ export async function deleteNote(noteId: string): Promise<void> {
 
   try {
     await invoke('delete_note', { noteId });
+    loadTags();
+    loadTasks();
   } catch (error) {
     console.error('Failed to delete note:', error);
   }
@@ -296,6 +327,8 @@ export async function restoreNote(noteId: string): Promise<void> {
 
   try {
     await invoke('restore_note', { noteId });
+    loadTags();
+    loadTasks();
   } catch (error) {
     console.error('Failed to restore note:', error);
   }
@@ -316,11 +349,44 @@ export async function permanentlyDeleteNote(noteId: string): Promise<void> {
 
   try {
     await invoke('permanently_delete_note', { noteId });
+    loadTags();
   } catch (error) {
     console.error('Failed to permanently delete note:', error);
   }
 }
 
+/**
+ * Move a note into a folder, or out of any folder with null
+ */
+export async function moveNoteToFolder(noteId: string, folderId: string | null): Promise<void> {
+  const note = notesState.notes.find((n) => n.id === noteId);
+  if (!note || (note.folderId ?? null) === folderId) return;
+
+  const previousFolderId = note.folderId ?? null;
+  setNotesState(
+    produce((state) => {
+      const n = state.notes.find((n) => n.id === noteId);
+      if (n) {
+        n.folderId = folderId;
+      }
+    })
+  );
+
+  try {
+    await invoke('move_note', { noteId, folderId });
+  } catch (error) {
+    console.error('Failed to move note:', error);
+    setNotesState(
+      produce((state) => {
+        const n = state.notes.find((n) => n.id === noteId);
+        if (n) {
+          n.folderId = previousFolderId;
+        }
+      })
+    );
+  }
+}
+
 /**
  * Set search query
  */
@@ -343,6 +409,48 @@ export async function duplicateNote(noteId: string): Promise<string | null> {
   }
 }
 
+export type ExportFormat = 'markdown' | 'html' | 'pdf';
+
+/**
+ * Export a note to a file; the format follows the file's extension
+ */
+export async function exportNote(noteId: string, path: string): Promise<boolean> {
+  try {
+    await invoke('export_note', { noteId, path });
+    return true;
+  } catch (error) {
+    console.error('Failed to export note:', error);
+    return false;
+  }
+}
+
+/**
+ * Export all notes outside the trash as files in the given directory
+ */
+export async function exportAll(directory: string, format: ExportFormat): Promise<number> {
+  try {
+    const report = await invoke<{ exported: unknown[]; failed: unknown[] }>('export_all', { directory, format });
+    return report.exported.length;
+  } catch (error) {
+    console.error('Failed to export notes:', error);
+    return 0;
+  }
+}
+
+/**
+ * Import Markdown and text files (or folders of them) as new notes
+ */
+export async function importNotes(paths: string[]): Promise<number> {
+  try {
+    const report = await invoke<{ imported: unknown[]; failed: unknown[] }>('import_notes', { paths });
+    await loadNotes();
+    return report.imported.length;
+  } catch (error) {
+    console.error('Failed to import notes:', error);
+    return 0;
+  }
+}
+
 // Mock data for development without Tauri
 function getMockNotes(): NoteMeta[] {
   return [
@@ -0,0 +1,113 @@
+import { createStore, produce } from 'solid-js/store';
+import type { NoteMeta, SavedSearch } from '@pdtodo/types';
+import { invoke } from '@tauri-apps/api/core';
+
+interface SavedSearchesState {
+  searches: SavedSearch[];
+  /** Saved search picked in the sidebar to list its notes */
+  selectedId: string | null;
+  /** Notes the selected search finds, in its result order */
+  noteIds: string[];
+}
+
+const [savedSearchesState, setSavedSearchesState] = createStore<SavedSearchesState>({
+  searches: [],
+  selectedId: null,
+  noteIds: [],
+});
+
+export const savedSearchesStore = {
+  get searches() {
+    return savedSearchesState.searches;
+  },
+  get selectedId() {
+    return savedSearchesState.selectedId;
+  },
+  get noteIds() {
+    return savedSearchesState.noteIds;
+  },
+};
+
+/**
+ * Load all saved searches from the backend
+ */
+export async function loadSavedSearches(): Promise<void> {
+  try {
+    const searches = await invoke<SavedSearch[]>('get_saved_searches');
+    setSavedSearchesState('searches', searches);
+  } catch (error) {
+    console.error('Failed to load saved searches:', error);
+  }
+}
+
+/**
+ * Save a search query as a smart folder and pick it
+ */
+export async function createSavedSearch(name: string, query: string): Promise<string | null> {
+  try {
+    const searchId = await invoke<string>('create_saved_search', { name, query });
+    await loadSavedSearches();
+    await selectSavedSearch(searchId);
+    return searchId;
+  } catch (error) {
+    console.error('Failed to save search:', error);
+    return null;
+  }
+}
+
+/**
+ * Rename a saved search or change its query
+ */
+export async function updateSavedSearch(searchId: string, changes: { name?: string; query?: string }): Promise<void> {
+  const search = savedSearchesState.searches.find((s) => s.id === searchId);
+  if (!search) return;
+  const name = changes.name ?? search.name;
+  const query = changes.query ?? search.query;
+  try {
+    await invoke('update_saved_search', { searchId, name, query });
+    await Promise.all([loadSavedSearches(), loadSearchNotes()]);
+  } catch (error) {
+    console.error('Failed to update saved search:', error);
+  }
+}
+
+export async function deleteSavedSearch(searchId: string): Promise<void> {
+  try {
+    await invoke('delete_saved_search', { searchId });
+    setSavedSearchesState(
+      produce((state) => {
+        state.searches = state.searches.filter((s) => s.id !== searchId);
+        if (state.selectedId === searchId) {
+          state.selectedId = null;
+          state.noteIds = [];
+        }
+      })
+    );
+  } catch (error) {
+    console.error('Failed to delete saved search:', error);
+  }
+}
+
+/**
+ * Pick a saved search in the sidebar to list its notes, or clear it with null
+ */
+export async function selectSavedSearch(searchId: string | null): Promise<void> {
+  setSavedSearchesState({ selectedId: searchId, noteIds: [] });
+  await loadSearchNotes();
+}
+
+/**
+ * Run the selected saved search again, after notes changed
+ */
+export async function loadSearchNotes(): Promise<void> {
+  const searchId = savedSearchesState.selectedId;
+  if (!searchId) return;
+  try {
+    const notes = await invoke<NoteMeta[]>('run_saved_search', { searchId });
+    if (savedSearchesState.selectedId === searchId) {
+      setSavedSearchesState('noteIds', notes.map((n) => n.id));
+    }
+  } catch (error) {
+    console.error('Failed to run saved search:', error);
+  }
+}
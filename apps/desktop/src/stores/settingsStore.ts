@@ -1,16 +1,20 @@
 import { createStore } from 'solid-js/store';
+import { invoke } from '@tauri-apps/api/core';
 import type { UserSettings } from '@pdtodo/types';
 import { DEFAULT_USER_SETTINGS } from '@pdtodo/types';
 
 interface SettingsState extends UserSettings {
   isLoaded: boolean;
   apiServerUrl: string;
+  /** Kept by the backend, which enforces it */
+  offlineMode: boolean;
 }
 
 const [settingsState, setSettingsState] = createStore<SettingsState>({
   ...DEFAULT_USER_SETTINGS,
   isLoaded: false,
   apiServerUrl: '',
+  offlineMode: false,
 });
 
 export const settingsStore = {
@@ -38,6 +42,9 @@ export const settingsStore = {
   get lastOpenedNoteId() {
     return settingsState.lastOpenedNoteId;
   },
+  get offlineMode() {
+    return settingsState.offlineMode;
+  },
 };
 
 interface StoredSettings extends Partial<UserSettings> {
@@ -70,6 +77,12 @@ export async function loadSettings(): Promise<void> {
     console.error('Failed to load settings:', error);
     setSettingsState('isLoaded', true);
   }
+
+  try {
+    setSettingsState('offlineMode', await invoke<boolean>('get_offline_mode'));
+  } catch (error) {
+    console.error('Failed to load offline mode:', error);
+  }
 }
 
 /**
@@ -147,6 +160,18 @@ export function setApiServerUrl(url: string): void {
   saveSettings();
 }
 
+/**
+ * Turn offline mode on or off; while it's on, links aren't unfurled
+ */
+export async function setOfflineMode(offline: boolean): Promise<void> {
+  try {
+    await invoke('set_offline_mode', { offline });
+    setSettingsState('offlineMode', offline);
+  } catch (error) {
+    console.error('Failed to set offline mode:', error);
+  }
+}
+
 /**
  * Update All Notes section expanded state
  */
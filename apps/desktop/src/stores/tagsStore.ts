@@ -0,0 +1,168 @@
+import { createStore, produce } from 'solid-js/store';
+import type { NoteMeta, Tag } from '@pdtodo/types';
+import { invoke } from '@tauri-apps/api/core';
+
+interface TagsState {
+  tags: Tag[];
+  /** Tag names of the note open in the editor */
+  noteTags: string[];
+  /** Note the noteTags belong to */
+  noteId: string | null;
+  /** Tag picked in the sidebar to list its notes */
+  selectedTag: string | null;
+  /** Notes with the selected tag */
+  taggedNoteIds: string[];
+}
+
+const [tagsState, setTagsState] = createStore<TagsState>({
+  tags: [],
+  noteTags: [],
+  noteId: null,
+  selectedTag: null,
+  taggedNoteIds: [],
+});
+
+export const tagsStore = {
+  get tags() {
+    return tagsState.tags;
+  },
+  get noteTags() {
+    return tagsState.noteTags;
+  },
+  get selectedTag() {
+    return tagsState.selectedTag;
+  },
+  get taggedNoteIds() {
+    return tagsState.taggedNoteIds;
+  },
+};
+
+/**
+ * Load all tags from the backend
+ */
+export async function loadTags(): Promise<void> {
+  try {
+    const tags = await invoke<Tag[]>('get_tags');
+    setTagsState('tags', tags);
+  } catch (error) {
+    console.error('Failed to load tags:', error);
+  }
+}
+
+/**
+ * Load the tags of the note open in the editor
+ */
+export async function loadNoteTags(noteId: string): Promise<void> {
+  try {
+    const names = await invoke<string[]>('get_note_tags', { noteId });
+    setTagsState({ noteId, noteTags: names });
+  } catch (error) {
+    console.error('Failed to load note tags:', error);
+  }
+}
+
+/**
+ * Reload tags after a note's content was saved, since hashtags in the text
+ * add and remove tags
+ */
+export async function refreshTagsAfterSave(noteId: string): Promise<void> {
+  const loads = [loadTags(), loadTaggedNotes()];
+  if (tagsState.noteId === noteId) {
+    loads.push(loadNoteTags(noteId));
+  }
+  await Promise.all(loads);
+}
+
+export async function addNoteTag(noteId: string, name: string): Promise<void> {
+  try {
+    const stored = await invoke<string>('add_note_tag', { noteId, name });
+    if (tagsState.noteId === noteId) {
+      setTagsState(
+        produce((state) => {
+          if (!state.noteTags.some((t) => t.toLowerCase() === stored.toLowerCase())) {
+            state.noteTags.push(stored);
+            state.noteTags.sort((a, b) => a.localeCompare(b, undefined, { sensitivity: 'base' }));
+          }
+        })
+      );
+    }
+    await Promise.all([loadTags(), loadTaggedNotes()]);
+  } catch (error) {
+    console.error('Failed to add tag:', error);
+  }
+}
+
+export async function removeNoteTag(noteId: string, name: string): Promise<void> {
+  try {
+    await invoke('remove_note_tag', { noteId, name });
+    if (tagsState.noteId === noteId) {
+      setTagsState('noteTags', (tags) => tags.filter((t) => t !== name));
+    }
+    await Promise.all([loadTags(), loadTaggedNotes()]);
+  } catch (error) {
+    console.error('Failed to remove tag:', error);
+  }
+}
+
+/**
+ * Rename a tag on every note; renaming to an existing tag merges the two
+ */
+export async function renameTag(tagId: string, name: string): Promise<void> {
+  const previous = tagsState.tags.find((t) => t.id === tagId)?.name;
+  try {
+    await invoke('rename_tag', { tagId, name });
+    if (previous && tagsState.selectedTag === previous) {
+      setTagsState('selectedTag', name);
+    }
+    const loads = [loadTags(), loadTaggedNotes()];
+    if (tagsState.noteId) {
+      loads.push(loadNoteTags(tagsState.noteId));
+    }
+    await Promise.all(loads);
+  } catch (error) {
+    console.error('Failed to rename tag:', error);
+  }
+}
+
+/**
+ * Take a tag off every note
+ */
+export async function deleteTag(tagId: string): Promise<void> {
+  const name = tagsState.tags.find((t) => t.id === tagId)?.name;
+  try {
+    await invoke('delete_tag', { tagId });
+    setTagsState(
+      produce((state) => {
+        state.tags = state.tags.filter((t) => t.id !== tagId);
+        state.noteTags = state.noteTags.filter((t) => t !== name);
+        if (state.selectedTag === name) {
+          state.selectedTag = null;
+          state.taggedNoteIds = [];
+        }
+      })
+    );
+  } catch (error) {
+    console.error('Failed to delete tag:', error);
+  }
+}
+
+/**
+ * Pick a tag in the sidebar to list its notes, or clear it with null
+ */
+export async function selectTag(name: string | null): Promise<void> {
+  setTagsState({ selectedTag: name, taggedNoteIds: [] });
+  await loadTaggedNotes();
+}
+
+async function loadTaggedNotes(): Promise<void> {
+  const name = tagsState.selectedTag;
+  if (!name) return;
+  try {
+    const notes = await invoke<NoteMeta[]>('search_notes', { query: `tag:${name}` });
+    if (tagsState.selectedTag === name) {
+      setTagsState('taggedNoteIds', notes.map((n) => n.id));
+    }
+  } catch (error) {
+    console.error('Failed to load tagged notes:', error);
+  }
+}
@@ -0,0 +1,239 @@
+import { createStore } from 'solid-js/store';
+import type { Task, TaskCounts } from '@pdtodo/types';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+/** Event the backend emits when a task's reminder time passes */
+const REMINDER_EVENT = 'task-reminder';
+
+/** Smart views listing tasks across notes */
+export type TaskView = 'today' | 'upcoming' | 'completed';
+
+const VIEW_COMMANDS: Record<TaskView, string> = {
+  today: 'get_today_tasks',
+  upcoming: 'get_upcoming_tasks',
+  completed: 'get_recently_completed_tasks',
+};
+
+interface TasksState {
+  /** Unchecked tasks across all notes */
+  openTasks: Task[];
+  /** Open and completed task counts by note id */
+  counts: Record<string, TaskCounts>;
+  /** Reminders that fired and haven't been dismissed, oldest first */
+  reminders: Task[];
+  /** Number of open tasks due today or overdue */
+  todayCount: number;
+  /** The smart view shown instead of a note, and its tasks */
+  view: TaskView | null;
+  viewTasks: Task[];
+  /** A task to scroll to once its note is open in the editor */
+  reveal: { noteId: string; path: string } | null;
+}
+
+const [tasksState, setTasksState] = createStore<TasksState>({
+  openTasks: [],
+  counts: {},
+  reminders: [],
+  todayCount: 0,
+  view: null,
+  viewTasks: [],
+  reveal: null,
+});
+
+export const tasksStore = {
+  get openTasks() {
+    return tasksState.openTasks;
+  },
+  get counts() {
+    return tasksState.counts;
+  },
+  get reminders() {
+    return tasksState.reminders;
+  },
+  get todayCount() {
+    return tasksState.todayCount;
+  },
+  get view() {
+    return tasksState.view;
+  },
+  get viewTasks() {
+    return tasksState.viewTasks;
+  },
+  get reveal() {
+    return tasksState.reveal;
+  },
+};
+
+// Callbacks registered by the editor, so a task toggled outside it doesn't
+// get overwritten by the editor's next save
+let flushEditorContent: (() => Promise<void>) | null = null;
+let reloadEditorContent: ((noteId: string) => Promise<void>) | null = null;
+
+/**
+ * Register the editor's callbacks to save pending changes and to merge
+ * content changed in the backend into the open note
+ */
+export function registerEditorContent(
+  flush: () => Promise<void>,
+  reload: (noteId: string) => Promise<void>
+): void {
+  flushEditorContent = flush;
+  reloadEditorContent = reload;
+}
+
+export function unregisterEditorContent(): void {
+  flushEditorContent = null;
+  reloadEditorContent = null;
+}
+
+/**
+ * Save the editor's pending changes before the backend changes notes
+ * itself, e.g. when a rename rewrites links
+ */
+export async function saveEditorContent(): Promise<void> {
+  await flushEditorContent?.();
+}
+
+/**
+ * Merge notes changed in the backend into the editor, if one is open
+ */
+export async function mergeEditorContent(noteIds: string[]): Promise<void> {
+  for (const noteId of noteIds) {
+    await reloadEditorContent?.(noteId);
+  }
+}
+
+export async function loadOpenTasks(): Promise<void> {
+  try {
+    const tasks = await invoke<Task[]>('get_open_tasks');
+    setTasksState('openTasks', tasks);
+  } catch (error) {
+    console.error('Failed to load open tasks:', error);
+  }
+}
+
+export async function loadTaskCounts(): Promise<void> {
+  try {
+    const counts = await invoke<TaskCounts[]>('get_task_counts');
+    setTasksState('counts', Object.fromEntries(counts.map((c) => [c.noteId, c])));
+  } catch (error) {
+    console.error('Failed to load task counts:', error);
+  }
+}
+
+async function loadTodayCount(): Promise<void> {
+  try {
+    const tasks = await invoke<Task[]>('get_today_tasks');
+    setTasksState('todayCount', tasks.length);
+  } catch (error) {
+    console.error('Failed to load today\'s tasks:', error);
+  }
+}
+
+async function loadViewTasks(): Promise<void> {
+  const view = tasksState.view;
+  if (!view) return;
+  try {
+    const tasks = await invoke<Task[]>(VIEW_COMMANDS[view]);
+    // The view may have changed meanwhile
+    if (tasksState.view === view) {
+      setTasksState('viewTasks', tasks);
+    }
+  } catch (error) {
+    console.error(`Failed to load ${view} tasks:`, error);
+  }
+}
+
+/**
+ * Reload open tasks, task counts and the open smart view, after content
+ * was saved or notes moved in or out of the trash
+ */
+export async function loadTasks(): Promise<void> {
+  await Promise.all([loadOpenTasks(), loadTaskCounts(), loadTodayCount(), loadViewTasks()]);
+}
+
+/**
+ * Show a smart view in place of the selected note
+ */
+export async function openTaskView(view: TaskView): Promise<void> {
+  await flushEditorContent?.();
+  setTasksState({ view, viewTasks: [] });
+  await loadViewTasks();
+}
+
+export function closeTaskView(): void {
+  setTasksState({ view: null, viewTasks: [] });
+}
+
+/**
+ * Scroll to a task when its note is next shown in the editor
+ */
+export function requestTaskReveal(task: Task): void {
+  setTasksState('reveal', { noteId: task.noteId, path: task.path });
+}
+
+export function clearTaskReveal(): void {
+  setTasksState('reveal', null);
+}
+
+/**
+ * Change a task in the backend with `command`, keeping the open note in step
+ */
+async function changeTask(command: string, args: Record<string, unknown>): Promise<Task> {
+  await flushEditorContent?.();
+  const task = await invoke<Task>(command, args);
+  await reloadEditorContent?.(task.noteId);
+  await loadTasks();
+  return task;
+}
+
+/**
+ * Check or uncheck a task in its note's content
+ */
+export async function toggleTask(taskId: string): Promise<void> {
+  try {
+    await changeTask('toggle_task', { taskId });
+  } catch (error) {
+    console.error('Failed to toggle task:', error);
+  }
+}
+
+/**
+ * Show reminders fired by the backend until they are dismissed. Returns a
+ * function that stops listening.
+ */
+export async function listenForReminders(): Promise<() => void> {
+  return listen<Task>(REMINDER_EVENT, (event) => {
+    const task = event.payload;
+    setTasksState('reminders', (reminders) => [...reminders.filter((r) => r.id !== task.id), task]);
+  });
+}
+
+export function dismissReminder(taskId: string): void {
+  setTasksState('reminders', (reminders) => reminders.filter((r) => r.id !== taskId));
+}
+
+/**
+ * Check a task from its reminder
+ */
+export async function completeTask(taskId: string): Promise<void> {
+  try {
+    await changeTask('complete_task', { taskId });
+    dismissReminder(taskId);
+  } catch (error) {
+    console.error('Failed to complete task:', error);
+  }
+}
+
+/**
+ * Remind of a task again in `minutes`
+ */
+export async function snoozeTask(taskId: string, minutes: number): Promise<void> {
+  try {
+    await changeTask('snooze_task', { taskId, until: Date.now() + minutes * 60000 });
+    dismissReminder(taskId);
+  } catch (error) {
+    console.error('Failed to snooze task:', error);
+  }
+}
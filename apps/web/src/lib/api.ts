@@ -14,6 +14,8 @@ export interface AuthResponse {
   user: User;
 }
 
+export type NoteRole = 'owner' | 'editor' | 'viewer';
+
 export interface NoteMeta {
   id: string;
   title: string;
@@ -21,6 +23,9 @@ export interface NoteMeta {
   createdAt: number;
   updatedAt: number;
   deletedAt: number | null;
+  role: NoteRole;
+  workspaceId: string | null;
+  folderId: string | null;
 }
 
 export interface Note {
@@ -31,13 +36,156 @@ export interface Note {
   createdAt: number;
   updatedAt: number;
   stateVector: string | null;
+  role: NoteRole;
+  workspaceId: string | null;
+  folderId: string | null;
+}
+
+export interface Folder {
+  id: string;
+  name: string;
+  parentId: string | null;
+  workspaceId: string | null;
+  createdAt: number;
+  updatedAt: number;
+  deletedAt: number | null;
+}
+
+export interface Tag {
+  id: string;
+  name: string;
+  workspaceId: string | null;
+  /** Notes with the tag outside the trash; only in tag listings */
+  noteCount?: number;
+  createdAt: number;
+  updatedAt: number;
+  deletedAt: number | null;
+}
+
+/** Search query saved under a name, shown as a smart folder */
+export interface SavedSearch {
+  id: string;
+  name: string;
+  /** Query in the search syntax, e.g. `tag:work has:open-tasks updated:7d` */
+  query: string;
+  createdAt: number;
+  updatedAt: number;
+  deletedAt: number | null;
+}
+
+/** A file attached to a note; its content is the blob `hash` */
+export interface Attachment {
+  id: string;
+  noteId: string;
+  /** SHA-256 of the content, hex encoded */
+  hash: string;
+  fileName: string;
+  contentType: string;
+  /** Size in bytes */
+  size: number;
+  createdAt: number;
+  updatedAt: number;
+  deletedAt: number | null;
+}
+
+export interface SearchResult extends NoteMeta {
+  /** Matching excerpt, HTML-escaped with matches in <mark> */
+  snippet: string;
+  rank: number;
+}
+
+export interface SearchResponse {
+  results: SearchResult[];
+  total: number;
+  limit: number;
+  offset: number;
+}
+
+/** Task item in a note; the id is `<noteId>:<path>` */
+export interface Task {
+  id: string;
+  noteId: string;
+  noteTitle: string;
+  position: number;
+  path: string;
+  depth: number;
+  text: string;
+  checked: boolean;
+  /** `YYYY-MM-DD` */
+  dueDate: string | null;
+  /** Reminder time in milliseconds since the epoch */
+  remindAt: number | null;
+  /** ISO timestamp of when the task was checked, if known */
+  completedAt: string | null;
+}
+
+/** Smart views of tasks across notes */
+export type TaskView = 'today' | 'upcoming' | 'completed';
+
+export interface TaskCounts {
+  noteId: string;
+  open: number;
+  completed: number;
+}
+
+export interface NoteMember {
+  id: string;
+  userId: string | null;
+  email: string;
+  role: NoteRole;
+  pending: boolean;
+  createdAt: number;
+}
+
+export interface NoteMembers {
+  owner: { userId: string; email: string; name: string | null };
+  members: NoteMember[];
 }
 
+export interface ShareLink {
+  id: string;
+  token: string;
+  path: string;
+  hasPassword: boolean;
+  expiresAt: number | null;
+  createdAt: number;
+}
+
+export type WorkspaceRole = 'admin' | 'editor' | 'viewer';
+
+export interface Workspace {
+  id: string;
+  name: string;
+  role: WorkspaceRole;
+  createdAt: number;
+  updatedAt: number;
+}
+
+export interface WorkspaceMember {
+  id: string;
+  userId: string | null;
+  email: string;
+  role: WorkspaceRole;
+  pending: boolean;
+  createdAt: number;
+}
+
+/** `personal`, a workspace id, or undefined for every note */
+export type NoteScope = 'personal' | string | undefined;
+
 export interface NotesListResponse {
   notes: NoteMeta[];
   serverTime: number;
 }
 
+/** Today's date in the browser's time zone, as `YYYY-MM-DD` */
+function localDate(): string {
+  const now = new Date();
+  const month = String(now.getMonth() + 1).padStart(2, '0');
+  const day = String(now.getDate()).padStart(2, '0');
+  return `${now.getFullYear()}-${month}-${day}`;
+}
+
 class ApiClient {
   private accessToken: string | null = null;
   private refreshToken: string | null = null;
@@ -185,10 +333,11 @@ class ApiClient {
   }
 
   // Notes methods
-  async listNotes(includeDeleted = false, since?: number): Promise<NotesListResponse> {
+  async listNotes(includeDeleted = false, since?: number, workspace?: NoteScope): Promise<NotesListResponse> {
     const params = new URLSearchParams();
     if (includeDeleted) params.set('includeDeleted', 'true');
     if (since) params.set('since', since.toString());
+    if (workspace) params.set('workspace', workspace);
     const query = params.toString();
     return this.request(`/notes${query ? `?${query}` : ''}`);
   }
@@ -197,10 +346,17 @@ class ApiClient {
     return this.request(`/notes/${id}`);
   }
 
-  async createNote(id: string, title: string, content: string, starred = false): Promise<{ id: string; createdAt: number }> {
+  async createNote(
+    id: string,
+    title: string,
+    content: string,
+    starred = false,
+    workspaceId?: string,
+    folderId?: string
+  ): Promise<{ id: string; createdAt: number }> {
     return this.request('/notes', {
       method: 'POST',
-      body: JSON.stringify({ id, title, content, starred }),
+      body: JSON.stringify({ id, title, content, starred, workspaceId, folderId }),
     });
   }
 
@@ -226,6 +382,281 @@ class ApiClient {
     await this.request(`/notes/${id}/permanent`, { method: 'DELETE' });
   }
 
+  // Note member methods
+  async listMembers(noteId: string): Promise<NoteMembers> {
+    return this.request(`/notes/${noteId}/members`);
+  }
+
+  async inviteMember(noteId: string, email: string, role: 'editor' | 'viewer'): Promise<NoteMember> {
+    return this.request(`/notes/${noteId}/members`, {
+      method: 'POST',
+      body: JSON.stringify({ email, role }),
+    });
+  }
+
+  async removeMember(noteId: string, memberId: string): Promise<void> {
+    await this.request(`/notes/${noteId}/members/${memberId}`, { method: 'DELETE' });
+  }
+
+  /**
+   * URL of the live sync WebSocket. Browsers can't set headers on
+   * WebSockets, so the access token goes in the query.
+   */
+  async liveSyncUrl(): Promise<string> {
+    const token = await this.ensureValidToken();
+    return `${API_BASE.replace(/^http/, 'ws')}/sync/live?token=${encodeURIComponent(token)}`;
+  }
+
+  // Folder methods
+  async listFolders(workspace?: NoteScope): Promise<{ folders: Folder[] }> {
+    const params = new URLSearchParams();
+    if (workspace) params.set('workspace', workspace);
+    return this.request(`/folders?${params}`);
+  }
+
+  async createFolder(id: string, name: string, parentId: string | null, workspaceId?: string): Promise<Folder> {
+    return this.request('/folders', {
+      method: 'POST',
+      body: JSON.stringify({ id, name, parentId, workspaceId }),
+    });
+  }
+
+  /** Rename a folder and move it; a null parentId moves it to the top level */
+  async updateFolder(id: string, name: string, parentId: string | null): Promise<Folder> {
+    return this.request(`/folders/${id}`, {
+      method: 'PUT',
+      body: JSON.stringify({ name, parentId }),
+    });
+  }
+
+  /** Delete a folder and its subfolders; returns the notes moved to trash */
+  async deleteFolder(id: string): Promise<{ trashedNotes: string[] }> {
+    return this.request(`/folders/${id}`, { method: 'DELETE' });
+  }
+
+  async setNoteFolder(noteId: string, folderId: string | null): Promise<Note> {
+    return this.request(`/notes/${noteId}/folder`, {
+      method: 'PUT',
+      body: JSON.stringify({ folderId }),
+    });
+  }
+
+  // Tag methods
+  async listTags(workspace?: NoteScope): Promise<{ tags: Tag[] }> {
+    const params = new URLSearchParams();
+    if (workspace) params.set('workspace', workspace);
+    return this.request(`/tags?${params}`);
+  }
+
+  async listNoteTags(noteId: string): Promise<{ tags: Tag[] }> {
+    return this.request(`/notes/${noteId}/tags`);
+  }
+
+  /** Tag a note, creating the tag if it's new */
+  async addNoteTag(noteId: string, name: string): Promise<Tag> {
+    return this.request(`/notes/${noteId}/tags`, {
+      method: 'POST',
+      body: JSON.stringify({ name }),
+    });
+  }
+
+  async removeNoteTag(noteId: string, tagId: string): Promise<void> {
+    await this.request(`/notes/${noteId}/tags/${tagId}`, { method: 'DELETE' });
+  }
+
+  /** Rename a tag; renaming to another tag's name merges them and returns that tag */
+  async renameTag(id: string, name: string): Promise<Tag> {
+    return this.request(`/tags/${id}`, {
+      method: 'PATCH',
+      body: JSON.stringify({ name }),
+    });
+  }
+
+  async deleteTag(id: string): Promise<void> {
+    await this.request(`/tags/${id}`, { method: 'DELETE' });
+  }
+
+  // Attachment methods
+  async listNoteAttachments(noteId: string): Promise<{ attachments: Attachment[] }> {
+    return this.request(`/notes/${noteId}/attachments`);
+  }
+
+  /** Attach a file to a note, uploading its content unless the server already has it */
+  async attachFile(noteId: string, file: File): Promise<Attachment> {
+    const data = await file.arrayBuffer();
+    const digest = new Uint8Array(await crypto.subtle.digest('SHA-256', data));
+    const hash = Array.from(digest, (byte) => byte.toString(16).padStart(2, '0')).join('');
+
+    const { missing } = await this.missingBlobs([hash]);
+    if (missing.length > 0) {
+      await this.uploadBlob(hash, data);
+    }
+    return this.request(`/notes/${noteId}/attachments`, {
+      method: 'POST',
+      body: JSON.stringify({
+        id: crypto.randomUUID(),
+        hash,
+        fileName: file.name,
+        contentType: file.type || null,
+      }),
+    });
+  }
+
+  /** Which of the given blobs the server doesn't have yet */
+  async missingBlobs(hashes: string[]): Promise<{ missing: string[] }> {
+    return this.request('/blobs/missing', {
+      method: 'POST',
+      body: JSON.stringify({ hashes }),
+    });
+  }
+
+  async uploadBlob(hash: string, data: ArrayBuffer): Promise<{ hash: string; size: number }> {
+    return this.request(`/blobs/${hash}`, {
+      method: 'PUT',
+      headers: { 'Content-Type': 'application/octet-stream' },
+      body: data,
+    });
+  }
+
+  async downloadAttachment(noteId: string, attachmentId: string): Promise<Blob> {
+    const token = await this.ensureValidToken();
+    const response = await fetch(`${API_BASE}/notes/${noteId}/attachments/${attachmentId}`, {
+      headers: { Authorization: `Bearer ${token}` },
+    });
+    if (!response.ok) {
+      const error = await response.text();
+      throw new Error(error || `HTTP ${response.status}`);
+    }
+    return response.blob();
+  }
+
+  async removeAttachment(noteId: string, attachmentId: string): Promise<void> {
+    await this.request(`/notes/${noteId}/attachments/${attachmentId}`, { method: 'DELETE' });
+  }
+
+  // Saved search methods
+  async listSavedSearches(): Promise<{ savedSearches: SavedSearch[] }> {
+    return this.request('/saved-searches');
+  }
+
+  async createSavedSearch(id: string, name: string, query: string): Promise<SavedSearch> {
+    return this.request('/saved-searches', {
+      method: 'POST',
+      body: JSON.stringify({ id, name, query }),
+    });
+  }
+
+  async updateSavedSearch(id: string, name: string, query: string): Promise<SavedSearch> {
+    return this.request(`/saved-searches/${id}`, {
+      method: 'PUT',
+      body: JSON.stringify({ name, query }),
+    });
+  }
+
+  async deleteSavedSearch(id: string): Promise<void> {
+    await this.request(`/saved-searches/${id}`, { method: 'DELETE' });
+  }
+
+  /** The notes a saved search finds now */
+  async runSavedSearch(id: string, limit?: number, offset?: number): Promise<SearchResponse> {
+    const params = new URLSearchParams();
+    if (limit) params.set('limit', limit.toString());
+    if (offset) params.set('offset', offset.toString());
+    return this.request(`/saved-searches/${id}/notes?${params}`);
+  }
+
+  // Task methods
+  /** Unchecked tasks across notes */
+  async listOpenTasks(workspace?: NoteScope): Promise<{ tasks: Task[] }> {
+    const params = new URLSearchParams();
+    if (workspace) params.set('workspace', workspace);
+    return this.request(`/tasks?${params}`);
+  }
+
+  /**
+   * Tasks due today or overdue, due in the next week, or checked in the
+   * last week. Due dates are compared with the browser's local date.
+   */
+  async listTaskView(view: TaskView, workspace?: NoteScope): Promise<{ tasks: Task[] }> {
+    const params = new URLSearchParams();
+    if (workspace) params.set('workspace', workspace);
+    if (view !== 'completed') params.set('today', localDate());
+    return this.request(`/tasks/${view}?${params}`);
+  }
+
+  async listTaskCounts(workspace?: NoteScope): Promise<{ counts: TaskCounts[] }> {
+    const params = new URLSearchParams();
+    if (workspace) params.set('workspace', workspace);
+    return this.request(`/tasks/counts?${params}`);
+  }
+
+  async listNoteTasks(noteId: string): Promise<{ tasks: Task[] }> {
+    return this.request(`/notes/${noteId}/tasks`);
+  }
+
+  /** Check or uncheck a task in its note's content */
+  async toggleTask(id: string): Promise<Task> {
+    return this.request(`/tasks/${encodeURIComponent(id)}/toggle`, { method: 'POST' });
+  }
+
+  // Workspace methods
+  async listWorkspaces(): Promise<{ workspaces: Workspace[] }> {
+    return this.request('/workspaces');
+  }
+
+  async createWorkspace(name: string): Promise<Workspace> {
+    return this.request('/workspaces', {
+      method: 'POST',
+      body: JSON.stringify({ name }),
+    });
+  }
+
+  async getWorkspace(id: string): Promise<Workspace & { members: WorkspaceMember[] }> {
+    return this.request(`/workspaces/${id}`);
+  }
+
+  async renameWorkspace(id: string, name: string): Promise<Workspace> {
+    return this.request(`/workspaces/${id}`, {
+      method: 'PATCH',
+      body: JSON.stringify({ name }),
+    });
+  }
+
+  async deleteWorkspace(id: string): Promise<void> {
+    await this.request(`/workspaces/${id}`, { method: 'DELETE' });
+  }
+
+  async addWorkspaceMember(id: string, email: string, role: WorkspaceRole): Promise<WorkspaceMember> {
+    return this.request(`/workspaces/${id}/members`, {
+      method: 'POST',
+      body: JSON.stringify({ email, role }),
+    });
+  }
+
+  async removeWorkspaceMember(id: string, memberId: string): Promise<void> {
+    await this.request(`/workspaces/${id}/members/${memberId}`, { method: 'DELETE' });
+  }
+
+  // Share link methods
+  async createShareLink(noteId: string, options: { expiresAt?: number; password?: string } = {}): Promise<ShareLink> {
+    return this.request(`/notes/${noteId}/share`, {
+      method: 'POST',
+      body: JSON.stringify(options),
+    });
+  }
+
+  async listShareLinks(noteId: string): Promise<{ links: ShareLink[] }> {
+    return this.request(`/notes/${noteId}/share`);
+  }
+
+  async revokeShareLink(noteId: string, linkId: string): Promise<void> {
+    await this.request(`/notes/${noteId}/share/${linkId}`, { method: 'DELETE' });
+  }
+
+  shareUrl(link: ShareLink): string {
+    return `${API_BASE}${link.path}`;
+  }
+
   // Sync methods
   async pushUpdates(updates: { noteId: string; update: string; timestamp: number }[]): Promise<{
     processed: string[];
@@ -238,15 +669,32 @@ class ApiClient {
     });
   }
 
-  async pullUpdates(stateVectors: Record<string, string>, since: number): Promise<{
+  async pullUpdates(stateVectors: Record<string, string>, since: number, workspace?: NoteScope): Promise<{
     updates: Record<string, string[]>;
-    newNotes: { id: string; title: string; content: string; starred: boolean; createdAt: number }[];
+    newNotes: {
+      id: string;
+      title: string;
+      content: string;
+      starred: boolean;
+      createdAt: number;
+      role: NoteRole;
+      workspaceId: string | null;
+      folderId: string | null;
+      tagIds: string[];
+    }[];
     deletedNotes: string[];
+    revokedNotes: string[];
+    folders: Folder[];
+    noteFolders: Record<string, string | null>;
+    tags: Tag[];
+    noteTags: Record<string, string[]>;
+    savedSearches: SavedSearch[];
+    attachments: Attachment[];
     serverTime: number;
   }> {
     return this.request('/sync/pull', {
       method: 'POST',
-      body: JSON.stringify({ stateVectors, since }),
+      body: JSON.stringify({ stateVectors, since, workspace }),
     });
   }
 }
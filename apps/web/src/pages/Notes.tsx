@@ -4,6 +4,7 @@ import { Editor } from '@tiptap/core';
 import { getEditorExtensions, editorStyles } from '@pdtodo/editor';
 import { notesStore } from '../stores/notes';
 import { authStore } from '../stores/auth';
+import { api } from '../lib/api';
 
 export const Notes: Component = () => {
   const navigate = useNavigate();
@@ -21,9 +22,9 @@ export const Notes: Component = () => {
     styleEl.textContent = editorStyles;
     document.head.appendChild(styleEl);
 
-    // Fetch notes
+    // Fetch workspaces and notes
     try {
-      await notesStore.fetchNotes();
+      await Promise.all([notesStore.fetchWorkspaces(), notesStore.fetchNotes()]);
     } catch (e) {
       console.error('Failed to fetch notes:', e);
     }
@@ -77,6 +78,9 @@ export const Notes: Component = () => {
         // If not JSON, try to set as text
         editor.commands.clearContent();
       }
+
+      // Notes shared read-only can't be edited
+      editor.setEditable(note.role !== 'viewer');
     }
   });
 
@@ -96,6 +100,37 @@ export const Notes: Component = () => {
     }
   };
 
+  const handleSwitchWorkspace = async (value: string) => {
+    try {
+      if (value === 'new') {
+        const name = prompt('Workspace name');
+        if (name?.trim()) {
+          await notesStore.createWorkspace(name.trim());
+        }
+        return;
+      }
+      await notesStore.switchWorkspace(value || null);
+    } catch (e) {
+      console.error('Failed to switch workspace:', e);
+    }
+  };
+
+  const handleAddWorkspaceMember = async () => {
+    const workspaceId = notesStore.currentWorkspace();
+    if (!workspaceId) return;
+    const email = prompt('Email address to add to this workspace');
+    if (!email) return;
+    const role = confirm('Allow them to edit notes? (Cancel for read-only)') ? 'editor' : 'viewer';
+    try {
+      await api.addWorkspaceMember(workspaceId, email, role);
+    } catch (err) {
+      console.error('Failed to add workspace member:', err);
+    }
+  };
+
+  const isWorkspaceAdmin = () =>
+    notesStore.workspaces().find((w) => w.id === notesStore.currentWorkspace())?.role === 'admin';
+
   const handleSelectNote = async (id: string) => {
     try {
       await notesStore.selectNote(id);
@@ -115,6 +150,34 @@ export const Notes: Component = () => {
     }
   };
 
+  const handleShareNote = async () => {
+    const note = notesStore.currentNote();
+    if (!note) return;
+    const password = prompt('Password for the link (leave empty for none)');
+    if (password === null) return;
+    try {
+      const link = await api.createShareLink(note.id, password ? { password } : {});
+      const url = api.shareUrl(link);
+      await navigator.clipboard.writeText(url).catch(() => {});
+      prompt('Anyone with this link can read the note (copied to clipboard):', url);
+    } catch (err) {
+      console.error('Failed to share note:', err);
+    }
+  };
+
+  const handleInviteMember = async () => {
+    const note = notesStore.currentNote();
+    if (!note) return;
+    const email = prompt('Email address to share this note with');
+    if (!email) return;
+    const canEdit = confirm(`Allow ${email} to edit the note? Cancel to share it read-only.`);
+    try {
+      await api.inviteMember(note.id, email, canEdit ? 'editor' : 'viewer');
+    } catch (err) {
+      console.error('Failed to share note:', err);
+    }
+  };
+
   const handleToggleStar = async (e: Event, id: string) => {
     e.stopPropagation();
     try {
@@ -183,6 +246,33 @@ export const Notes: Component = () => {
             </svg>
             New Note
           </button>
+          <div class="workspace-switcher">
+            <select
+              value={notesStore.currentWorkspace() ?? ''}
+              onChange={(e) => {
+                const value = e.currentTarget.value;
+                // Keep showing the current space until a new one exists
+                e.currentTarget.value = notesStore.currentWorkspace() ?? '';
+                handleSwitchWorkspace(value);
+              }}
+            >
+              <option value="">Personal</option>
+              <For each={notesStore.workspaces()}>
+                {(workspace) => <option value={workspace.id}>{workspace.name}</option>}
+              </For>
+              <option value="new">New workspace...</option>
+            </select>
+            <Show when={isWorkspaceAdmin()}>
+              <button class="icon-btn" onClick={handleAddWorkspaceMember} title="Add people to this workspace">
+                <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+                  <path d="M16 21v-2a4 4 0 0 0-4-4H5a4 4 0 0 0-4 4v2" />
+                  <circle cx="8.5" cy="7" r="4" />
+                  <line x1="20" y1="8" x2="20" y2="14" />
+                  <line x1="23" y1="11" x2="17" y2="11" />
+                </svg>
+              </button>
+            </Show>
+          </div>
         </div>
 
         <div class="sidebar-search">
@@ -226,27 +316,30 @@ export const Notes: Component = () => {
                           {formatDate(note.updatedAt)}
                         </div>
                       </div>
-                      <div class="note-item-actions">
-                        <button
-                          class={`icon-btn star-btn ${note.starred ? 'starred' : ''}`}
-                          onClick={(e) => handleToggleStar(e, note.id)}
-                          title={note.starred ? 'Unstar' : 'Star'}
-                        >
-                          <svg width="14" height="14" viewBox="0 0 24 24" fill={note.starred ? 'currentColor' : 'none'} stroke="currentColor" stroke-width="2">
-                            <polygon points="12 2 15.09 8.26 22 9.27 17 14.14 18.18 21.02 12 17.77 5.82 21.02 7 14.14 2 9.27 8.91 8.26 12 2" />
-                          </svg>
-                        </button>
-                        <button
-                          class="icon-btn delete-btn"
-                          onClick={(e) => handleDeleteNote(e, note.id)}
-                          title="Delete"
-                        >
-                          <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
-                            <polyline points="3 6 5 6 21 6" />
-                            <path d="M19 6v14a2 2 0 0 1-2 2H7a2 2 0 0 1-2-2V6m3 0V4a2 2 0 0 1 2-2h4a2 2 0 0 1 2 2v2" />
-                          </svg>
-                        </button>
-                      </div>
+                      {/* Starring and trash belong to the owner's library */}
+                      <Show when={note.role === 'owner'}>
+                        <div class="note-item-actions">
+                          <button
+                            class={`icon-btn star-btn ${note.starred ? 'starred' : ''}`}
+                            onClick={(e) => handleToggleStar(e, note.id)}
+                            title={note.starred ? 'Unstar' : 'Star'}
+                          >
+                            <svg width="14" height="14" viewBox="0 0 24 24" fill={note.starred ? 'currentColor' : 'none'} stroke="currentColor" stroke-width="2">
+                              <polygon points="12 2 15.09 8.26 22 9.27 17 14.14 18.18 21.02 12 17.77 5.82 21.02 7 14.14 2 9.27 8.91 8.26 12 2" />
+                            </svg>
+                          </button>
+                          <button
+                            class="icon-btn delete-btn"
+                            onClick={(e) => handleDeleteNote(e, note.id)}
+                            title="Delete"
+                          >
+                            <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+                              <polyline points="3 6 5 6 21 6" />
+                              <path d="M19 6v14a2 2 0 0 1-2 2H7a2 2 0 0 1-2-2V6m3 0V4a2 2 0 0 1 2-2h4a2 2 0 0 1 2 2v2" />
+                            </svg>
+                          </button>
+                        </div>
+                      </Show>
                     </li>
                   )}
                 </For>
@@ -295,6 +388,24 @@ export const Notes: Component = () => {
                 <Show when={isSaving()}>
                   <span class="saving-indicator">Saving...</span>
                 </Show>
+                <Show when={notesStore.currentNote()?.role === 'owner'}>
+                  <button class="icon-btn" onClick={handleInviteMember} title="Share with people">
+                    <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+                      <path d="M16 21v-2a4 4 0 0 0-4-4H6a4 4 0 0 0-4 4v2" />
+                      <circle cx="9" cy="7" r="4" />
+                      <path d="M19 8v6M22 11h-6" />
+                    </svg>
+                  </button>
+                  <button class="icon-btn" onClick={handleShareNote} title="Share link">
+                    <svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+                      <path d="M10 13a5 5 0 0 0 7.54.54l3-3a5 5 0 0 0-7.07-7.07l-1.72 1.71" />
+                      <path d="M14 11a5 5 0 0 0-7.54-.54l-3 3a5 5 0 0 0 7.07 7.07l1.71-1.71" />
+                    </svg>
+                  </button>
+                </Show>
+                <Show when={notesStore.currentNote()?.role === 'viewer'}>
+                  <span class="saving-indicator">Read-only</span>
+                </Show>
               </div>
             </div>
             <div class="editor-container" ref={editorRef} />
@@ -1,5 +1,5 @@
 import { createSignal, createRoot } from 'solid-js';
-import { api, NoteMeta, Note } from '../lib/api';
+import { api, NoteMeta, Note, Workspace } from '../lib/api';
 
 function createNotesStore() {
   const [notes, setNotes] = createSignal<NoteMeta[]>([]);
@@ -7,12 +7,15 @@ function createNotesStore() {
   const [isLoading, setIsLoading] = createSignal(false);
   const [error, setError] = createSignal<string | null>(null);
   const [lastSyncTime, setLastSyncTime] = createSignal<number>(0);
+  const [workspaces, setWorkspaces] = createSignal<Workspace[]>([]);
+  // The workspace whose notes are shown; null for the personal space
+  const [currentWorkspace, setCurrentWorkspace] = createSignal<string | null>(null);
 
   const fetchNotes = async (includeDeleted = false) => {
     setIsLoading(true);
     setError(null);
     try {
-      const response = await api.listNotes(includeDeleted);
+      const response = await api.listNotes(includeDeleted, undefined, currentWorkspace() ?? 'personal');
       setNotes(response.notes);
       setLastSyncTime(response.serverTime);
       return response.notes;
@@ -25,6 +28,38 @@ function createNotesStore() {
     }
   };
 
+  const fetchWorkspaces = async () => {
+    try {
+      const response = await api.listWorkspaces();
+      setWorkspaces(response.workspaces);
+      return response.workspaces;
+    } catch (e) {
+      const message = e instanceof Error ? e.message : 'Failed to fetch workspaces';
+      setError(message);
+      throw e;
+    }
+  };
+
+  const switchWorkspace = async (id: string | null) => {
+    setCurrentWorkspace(id);
+    setCurrentNote(null);
+    await fetchNotes();
+  };
+
+  const createWorkspace = async (name: string) => {
+    setError(null);
+    try {
+      const workspace = await api.createWorkspace(name);
+      setWorkspaces((prev) => [...prev, workspace].sort((a, b) => a.name.localeCompare(b.name)));
+      await switchWorkspace(workspace.id);
+      return workspace;
+    } catch (e) {
+      const message = e instanceof Error ? e.message : 'Failed to create workspace';
+      setError(message);
+      throw e;
+    }
+  };
+
   const selectNote = async (id: string) => {
     setIsLoading(true);
     setError(null);
@@ -47,9 +82,11 @@ function createNotesStore() {
       const id = crypto.randomUUID();
       // Encode content as base64 if not already
       const encodedContent = btoa(content || '');
-      const result = await api.createNote(id, title, encodedContent);
+      const workspaceId = currentWorkspace() ?? undefined;
+      const result = await api.createNote(id, title, encodedContent, false, workspaceId);
+      const note = await api.getNote(result.id);
 
-      // Add to local list
+      // Add to local list; in a workspace the role comes from the workspace
       const newNoteMeta: NoteMeta = {
         id: result.id,
         title,
@@ -57,11 +94,13 @@ function createNotesStore() {
         createdAt: result.createdAt,
         updatedAt: result.createdAt,
         deletedAt: null,
+        role: note.role,
+        workspaceId: note.workspaceId,
+        folderId: note.folderId,
       };
       setNotes((prev) => [newNoteMeta, ...prev]);
 
       // Select the new note
-      const note = await api.getNote(result.id);
       setCurrentNote(note);
 
       return note;
@@ -141,7 +180,12 @@ function createNotesStore() {
     isLoading,
     error,
     lastSyncTime,
+    workspaces,
+    currentWorkspace,
     fetchNotes,
+    fetchWorkspaces,
+    switchWorkspace,
+    createWorkspace,
     selectNote,
     createNote,
     updateNote,
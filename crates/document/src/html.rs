@@ -0,0 +1,115 @@
+//! HTML rendering of notes, as a standalone page.
+
+use super::{Block, Inline, ListItem};
+
+/// Styles for rendered notes, following the editor's look (see FEATURES.md)
+const STYLE: &str = r#"
+body { margin: 0; background: #fff; color: #1f2937; font: 16px/1.6 -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, Helvetica, Arial, sans-serif; }
+.note { max-width: 720px; margin: 0 auto; padding: 48px 24px; }
+.note-title { font-size: 2rem; line-height: 1.25; margin: 0 0 1.5rem; }
+h1 { font-size: 1.75rem; } h2 { font-size: 1.375rem; } h3 { font-size: 1.125rem; }
+h1, h2, h3 { line-height: 1.3; margin: 1.5rem 0 0.75rem; }
+p { margin: 0 0 0.75rem; }
+li p { margin: 0; }
+a { color: #2563eb; }
+ul, ol { margin: 0 0 0.75rem; padding-left: 1.5rem; }
+li ul, li ol { margin: 0; }
+ul[data-type="taskList"] { list-style: none; padding-left: 0; }
+li ul[data-type="taskList"] { margin-top: 8px; padding-left: 1.5rem; }
+li[data-checked] { display: flex; gap: 8px; align-items: flex-start; margin: 3px 0; min-height: 22px; }
+li[data-checked] > input { appearance: none; -webkit-appearance: none; flex: none; width: 16px; height: 16px; margin: 4px 0 0; border: 1.5px solid #6b7280; border-radius: 4px; }
+li[data-checked="true"] > input { border-color: #22c55e; background: #22c55e url("data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 16 16'%3E%3Cpath d='M3.5 8.5l3 3 6-7' fill='none' stroke='white' stroke-width='2'/%3E%3C/svg%3E") center / 12px no-repeat; }
+li[data-checked] > div { flex: 1; min-width: 0; }
+li[data-checked="true"] > div > p { color: #9ca3af; text-decoration: line-through; }
+@media print { .note { padding: 0; } }
+"#;
+
+/// A standalone HTML page for a note, with its styles inlined
+pub fn render(title: &str, blocks: &[Block]) -> String {
+    let title = escape(title);
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<article class=\"note\">\n\
+         <h1 class=\"note-title\">{title}</h1>\n{}</article>\n</body>\n</html>\n",
+        render_blocks(blocks),
+    )
+}
+
+fn render_blocks(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        match block {
+            Block::Paragraph(content) if content.iter().all(|inline| inline.text.is_empty()) => {
+                // Keep the spacing of empty lines
+                out.push_str("<p><br></p>\n");
+            }
+            Block::Paragraph(content) => out.push_str(&format!("<p>{}</p>\n", render_inlines(content))),
+            Block::Heading { level, content } => {
+                out.push_str(&format!("<h{level}>{}</h{level}>\n", render_inlines(content)));
+            }
+            Block::BulletList(items) => out.push_str(&format!("<ul>\n{}</ul>\n", render_items(items))),
+            Block::OrderedList { start, items } => {
+                let start = if *start == 1 { String::new() } else { format!(" start=\"{}\"", start) };
+                out.push_str(&format!("<ol{}>\n{}</ol>\n", start, render_items(items)));
+            }
+            Block::TaskList(items) => {
+                out.push_str(&format!("<ul data-type=\"taskList\">\n{}</ul>\n", render_items(items)));
+            }
+        }
+    }
+    out
+}
+
+/// Task items use the same markup as the editor, so exports import back
+fn render_items(items: &[ListItem]) -> String {
+    items
+        .iter()
+        .map(|item| match item.checked {
+            Some(checked) => format!(
+                "<li data-checked=\"{}\"><input type=\"checkbox\" disabled{}><div>\n{}</div></li>\n",
+                checked,
+                if checked { " checked" } else { "" },
+                render_blocks(&item.blocks),
+            ),
+            None => format!("<li>\n{}</li>\n", render_blocks(&item.blocks)),
+        })
+        .collect()
+}
+
+fn render_inlines(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        let mut html = escape(&inline.text).replace('\n', "<br>");
+        if inline.marks.underline {
+            html = format!("<u>{}</u>", html);
+        }
+        if inline.marks.bold {
+            html = format!("<strong>{}</strong>", html);
+        }
+        if let Some(href) = inline.marks.link.as_deref().filter(|href| is_safe_link(href)) {
+            html = format!("<a href=\"{}\">{}</a>", escape(href), html);
+        }
+        out.push_str(&html);
+    }
+    out
+}
+
+/// Rendered notes may be shared, so links can't run script
+fn is_safe_link(href: &str) -> bool {
+    let href = href.trim().to_ascii_lowercase();
+    match href.split_once(':') {
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => {
+            matches!(scheme, "http" | "https" | "mailto" | "tel")
+        }
+        // No scheme: a relative link
+        _ => true,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
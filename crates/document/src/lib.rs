@@ -0,0 +1,193 @@
+//! Note content as a tree of blocks, and its rendering as Markdown, HTML
+//! and PDF.
+//!
+//! Notes are stored as Yjs documents holding the TipTap editor's
+//! ProseMirror structure in the "content" XmlFragment. Both the desktop app
+//! and the API read notes through this crate, so a note exports the same
+//! wherever it is rendered.
+
+pub mod html;
+pub mod markdown;
+pub mod pdf;
+
+use thiserror::Error;
+use yrs::types::text::YChange;
+use yrs::types::Attrs;
+use yrs::updates::decoder::Decode;
+use yrs::{Any, Doc, Out, ReadTxn, Text, Transact, Update, Xml, XmlElementRef, XmlFragment, XmlOut, XmlTextRef};
+
+/// Name of the XmlFragment the editor binds to (see desktop Editor.tsx)
+pub const CONTENT_FRAGMENT: &str = "content";
+
+#[derive(Error, Debug)]
+pub enum DocumentError {
+    #[error("Invalid Yjs update: {0}")]
+    Decode(#[from] yrs::encoding::read::Error),
+    #[error("Failed to apply Yjs update: {0}")]
+    Apply(#[from] yrs::error::UpdateError),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Paragraph(Vec<Inline>),
+    Heading { level: u8, content: Vec<Inline> },
+    BulletList(Vec<ListItem>),
+    OrderedList { start: u32, items: Vec<ListItem> },
+    TaskList(Vec<ListItem>),
+}
+
+/// An item of any list; `checked` is only set for task items
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ListItem {
+    pub checked: Option<bool>,
+    pub blocks: Vec<Block>,
+}
+
+/// A run of text sharing the same marks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inline {
+    pub text: String,
+    pub marks: Marks,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Marks {
+    pub bold: bool,
+    pub underline: bool,
+    pub link: Option<String>,
+}
+
+/// Read the blocks of a stored note (full state encoded as a v1 update)
+pub fn from_yjs(content: &[u8]) -> Result<Vec<Block>, DocumentError> {
+    let doc = load(content)?;
+    let fragment = doc.get_or_insert_xml_fragment(CONTENT_FRAGMENT);
+    let txn = doc.transact();
+    Ok(read_blocks(&txn, &fragment))
+}
+
+/// Load a stored note (full state encoded as a v1 update)
+pub fn load(content: &[u8]) -> Result<Doc, DocumentError> {
+    let doc = Doc::new();
+    // Notes that were never edited have no content at all
+    if !content.is_empty() {
+        let update = Update::decode_v1(content)?;
+        doc.transact_mut().apply_update(update)?;
+    }
+    Ok(doc)
+}
+
+fn read_blocks<T: ReadTxn, F: XmlFragment>(txn: &T, parent: &F) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    for i in 0..parent.len(txn) {
+        match parent.get(txn, i) {
+            Some(XmlOut::Element(element)) => read_element(txn, &element, &mut blocks),
+            Some(XmlOut::Text(text)) => {
+                let content = read_text(txn, &text);
+                if !content.is_empty() {
+                    blocks.push(Block::Paragraph(content));
+                }
+            }
+            Some(XmlOut::Fragment(fragment)) => blocks.extend(read_blocks(txn, &fragment)),
+            None => {}
+        }
+    }
+    blocks
+}
+
+fn read_element<T: ReadTxn>(txn: &T, element: &XmlElementRef, blocks: &mut Vec<Block>) {
+    match element.tag().as_ref() {
+        "paragraph" => blocks.push(Block::Paragraph(read_inlines(txn, element))),
+        "heading" => blocks.push(Block::Heading {
+            level: attr_number(txn, element, "level").map_or(1, |level| level.clamp(1.0, 6.0) as u8),
+            content: read_inlines(txn, element),
+        }),
+        "bulletList" => blocks.push(Block::BulletList(read_items(txn, element))),
+        "orderedList" => blocks.push(Block::OrderedList {
+            start: attr_number(txn, element, "start").map_or(1, |start| start.max(0.0) as u32),
+            items: read_items(txn, element),
+        }),
+        "taskList" => blocks.push(Block::TaskList(read_items(txn, element))),
+        // Anything else (including list items outside a list) keeps its content
+        _ => blocks.extend(read_blocks(txn, element)),
+    }
+}
+
+fn read_items<T: ReadTxn>(txn: &T, list: &XmlElementRef) -> Vec<ListItem> {
+    let mut items = Vec::new();
+    for i in 0..list.len(txn) {
+        if let Some(XmlOut::Element(item)) = list.get(txn, i) {
+            let checked = (item.tag().as_ref() == "taskItem").then(|| attr_bool(txn, &item, "checked"));
+            items.push(ListItem {
+                checked,
+                blocks: read_blocks(txn, &item),
+            });
+        }
+    }
+    items
+}
+
+/// Text content of a textblock (paragraph or heading)
+pub fn read_inlines<T: ReadTxn>(txn: &T, element: &XmlElementRef) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    for i in 0..element.len(txn) {
+        match element.get(txn, i) {
+            Some(XmlOut::Text(text)) => inlines.extend(read_text(txn, &text)),
+            Some(XmlOut::Element(child)) => inlines.extend(read_inlines(txn, &child)),
+            _ => {}
+        }
+    }
+    inlines
+}
+
+/// Text runs with their marks. y-prosemirror stores each mark as a
+/// formatting attribute named after the mark, holding the mark's attrs.
+fn read_text<T: ReadTxn>(txn: &T, text: &XmlTextRef) -> Vec<Inline> {
+    text.diff(txn, YChange::identity)
+        .into_iter()
+        .filter_map(|chunk| match chunk.insert {
+            Out::Any(Any::String(s)) => Some(Inline {
+                text: s.to_string(),
+                marks: read_marks(chunk.attributes.as_deref()),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn read_marks(attrs: Option<&Attrs>) -> Marks {
+    let Some(attrs) = attrs else {
+        return Marks::default();
+    };
+    let has = |name: &str| matches!(attrs.get(name), Some(value) if *value != Any::Null);
+
+    Marks {
+        bold: has("bold"),
+        underline: has("underline"),
+        link: match attrs.get("link") {
+            Some(Any::Map(link)) => match link.get("href") {
+                Some(Any::String(href)) => Some(href.to_string()),
+                _ => None,
+            },
+            _ => None,
+        },
+    }
+}
+
+/// A boolean node attribute; y-prosemirror may store it as a string
+pub fn attr_bool<T: ReadTxn>(txn: &T, element: &XmlElementRef, name: &str) -> bool {
+    match element.get_attribute(txn, name) {
+        Some(Out::Any(Any::Bool(value))) => value,
+        Some(Out::Any(Any::String(value))) => value.as_ref() == "true",
+        _ => false,
+    }
+}
+
+/// A numeric node attribute, however it was stored
+pub fn attr_number<T: ReadTxn>(txn: &T, element: &XmlElementRef, name: &str) -> Option<f64> {
+    match element.get_attribute(txn, name) {
+        Some(Out::Any(Any::Number(value))) => Some(value),
+        Some(Out::Any(Any::BigInt(value))) => Some(value as f64),
+        Some(Out::Any(Any::String(value))) => value.parse().ok(),
+        _ => None,
+    }
+}
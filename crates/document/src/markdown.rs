@@ -0,0 +1,153 @@
+//! Markdown (CommonMark with GFM task lists) rendering of note blocks.
+
+use super::{Block, Inline, ListItem};
+
+/// Render blocks as Markdown, ending with a newline
+pub fn to_markdown(blocks: &[Block]) -> String {
+    let mut out = render_blocks(blocks, "\n\n");
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+fn render_blocks(blocks: &[Block], separator: &str) -> String {
+    let mut rendered: Vec<(bool, String)> = Vec::new();
+    for block in blocks {
+        // Empty paragraphs are only spacing in the editor
+        if matches!(block, Block::Paragraph(content) if content.iter().all(|i| i.text.trim().is_empty())) {
+            continue;
+        }
+        rendered.push((matches!(block, Block::Paragraph(_)), render_block(block)));
+    }
+
+    let mut out = String::new();
+    for (i, (is_paragraph, text)) in rendered.iter().enumerate() {
+        if i > 0 {
+            // Consecutive paragraphs would merge into one without a blank line
+            if *is_paragraph && rendered[i - 1].0 {
+                out.push_str("\n\n");
+            } else {
+                out.push_str(separator);
+            }
+        }
+        out.push_str(text);
+    }
+    out
+}
+
+fn render_block(block: &Block) -> String {
+    match block {
+        Block::Paragraph(content) => escape_line_start(&render_inlines(content)),
+        Block::Heading { level, content } => {
+            format!("{} {}", "#".repeat(*level as usize), render_inlines(content))
+        }
+        Block::BulletList(items) => render_list(items, |_| "- ".to_string()),
+        Block::OrderedList { start, items } => render_list(items, |i| format!("{}. ", *start as usize + i)),
+        Block::TaskList(items) => render_list(items, |_| "- ".to_string()),
+    }
+}
+
+fn render_list(items: &[ListItem], marker: impl Fn(usize) -> String) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = marker(i);
+            let checkbox = match item.checked {
+                Some(true) => "[x] ",
+                Some(false) => "[ ] ",
+                None => "",
+            };
+            // Continuation lines and nested lists line up with the item text
+            let indent = " ".repeat(marker.len());
+            let body = render_blocks(&item.blocks, "\n");
+
+            let mut out = format!("{}{}", marker, checkbox);
+            for (n, line) in body.lines().enumerate() {
+                if n > 0 {
+                    out.push('\n');
+                    if !line.is_empty() {
+                        out.push_str(&indent);
+                    }
+                }
+                out.push_str(line);
+            }
+            out.trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_inlines(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        let text = escape(&inline.text);
+
+        // Emphasis can't open or close on whitespace, so keep it outside
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            out.push_str(&text);
+            continue;
+        }
+        let start = text.len() - text.trim_start().len();
+        let end = start + trimmed.len();
+
+        let mut core = trimmed.to_string();
+        if inline.marks.underline {
+            core = format!("<u>{}</u>", core);
+        }
+        if inline.marks.bold {
+            core = format!("**{}**", core);
+        }
+        if let Some(href) = &inline.marks.link {
+            core = format!("[{}]({})", core, link_destination(href));
+        }
+
+        out.push_str(&text[..start]);
+        out.push_str(&core);
+        out.push_str(&text[end..]);
+    }
+    // Hard breaks inside a block
+    out.replace('\n', "\\\n")
+}
+
+/// Backslash-escape characters that would otherwise start inline syntax
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '~' => out.push('\\'),
+            // Only escape what could be read as an entity reference
+            '&' if chars.peek().is_some_and(|next| next.is_ascii_alphanumeric() || *next == '#') => {
+                out.push('\\')
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Keep paragraph text from being read as a heading, quote or list
+fn escape_line_start(text: &str) -> String {
+    if text.starts_with(['#', '>', '-', '+', '=']) {
+        return format!("\\{}", text);
+    }
+
+    let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 && text[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &text[..digits], &text[digits..]);
+    }
+
+    text.to_string()
+}
+
+fn link_destination(href: &str) -> String {
+    if href.contains([' ', '(', ')', '<', '>']) {
+        format!("<{}>", href.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        href.to_string()
+    }
+}
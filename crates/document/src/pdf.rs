@@ -0,0 +1,620 @@
+//! PDF rendering of notes, without a browser.
+//!
+//! Text is set in DejaVu Sans, embedded in the crate and subset into each
+//! document, so PDFs look the same in every reader. It covers Latin, Greek,
+//! Cyrillic and most symbols; characters it has no glyph for (CJK scripts,
+//! for one) show as its missing-glyph box. Every character keeps its
+//! Unicode value either way, so text copied or searched in the PDF is the
+//! note's text.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use pdf_writer::types::{ActionType, AnnotationType, CidFontType, FontFlags, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use ttf_parser::{Face, GlyphId};
+
+use super::{Block, Inline, ListItem, Marks};
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+/// Indent of each list level
+const INDENT: f32 = 20.0;
+const BODY_SIZE: f32 = 11.0;
+const LINE_HEIGHT: f32 = 1.45;
+
+const TEXT: [f32; 3] = [0.12, 0.16, 0.22];
+const MUTED: [f32; 3] = [0.61, 0.64, 0.69];
+const LINK: [f32; 3] = [0.15, 0.39, 0.92];
+const CHECKBOX: [f32; 3] = [0.42, 0.45, 0.50];
+const CHECKED: [f32; 3] = [0.13, 0.77, 0.37];
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+/// DejaVu Sans 2.37 (see fonts/LICENSE)
+const REGULAR_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans-Bold.ttf");
+
+const SYSTEM_INFO: SystemInfo = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0,
+};
+
+/// Render a note as a PDF document
+pub fn render(title: &str, blocks: &[Block]) -> Vec<u8> {
+    let mut layout = Layout::new();
+    let title = [Inline {
+        text: title.to_string(),
+        marks: Marks {
+            bold: true,
+            ..Default::default()
+        },
+    }];
+    layout.textblock(&title, 22.0, 0.0, false);
+    layout.y -= 12.0;
+    layout.blocks(blocks, 0.0);
+    layout.finish(&title[0].text)
+}
+
+struct Page {
+    content: Content,
+    /// Link areas and their targets
+    links: Vec<(Rect, String)>,
+}
+
+/// A run of text on one line, set in one style
+struct Span {
+    text: String,
+    marks: Marks,
+    x: f32,
+    width: f32,
+}
+
+struct Layout {
+    pages: Vec<Page>,
+    /// Top of the free space on the current page
+    y: f32,
+    fonts: Fonts,
+}
+
+impl Layout {
+    fn new() -> Self {
+        let mut layout = Self {
+            pages: Vec::new(),
+            y: 0.0,
+            fonts: Fonts {
+                regular: Font::new("DejaVuSans", REGULAR_FONT),
+                bold: Font::new("DejaVuSans-Bold", BOLD_FONT),
+            },
+        };
+        layout.new_page();
+        layout
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Page {
+            content: Content::new(),
+            links: Vec::new(),
+        });
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.pages.last_mut().expect("layout always has a page")
+    }
+
+    /// Start a new page unless `height` fits on this one
+    fn ensure(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+    }
+
+    fn blocks(&mut self, blocks: &[Block], indent: f32) {
+        for block in blocks {
+            match block {
+                Block::Paragraph(content) => {
+                    self.textblock(content, BODY_SIZE, indent, false);
+                    self.y -= if indent > 0.0 { 2.0 } else { 6.0 };
+                }
+                Block::Heading { level, content } => {
+                    let size = match level {
+                        1 => 18.0,
+                        2 => 15.0,
+                        _ => 13.0,
+                    };
+                    self.y -= 8.0;
+                    let bold: Vec<Inline> = content
+                        .iter()
+                        .map(|inline| Inline {
+                            text: inline.text.clone(),
+                            marks: Marks {
+                                bold: true,
+                                ..inline.marks.clone()
+                            },
+                        })
+                        .collect();
+                    self.textblock(&bold, size, indent, false);
+                    self.y -= 4.0;
+                }
+                Block::BulletList(items) => self.list(items, indent, |_| Marker::Bullet),
+                Block::OrderedList { start, items } => {
+                    self.list(items, indent, |i| Marker::Number(*start as usize + i))
+                }
+                Block::TaskList(items) => self.list(items, indent, |_| Marker::Checkbox),
+            }
+        }
+    }
+
+    fn list(&mut self, items: &[ListItem], indent: f32, marker: impl Fn(usize) -> Marker) {
+        for (i, item) in items.iter().enumerate() {
+            // The marker sits on the first line of the item's text
+            self.ensure(BODY_SIZE * LINE_HEIGHT);
+            let baseline = self.y - BODY_SIZE;
+            let checked = item.checked == Some(true);
+            match marker(i) {
+                Marker::Bullet => self.text(MARGIN + indent + 6.0, baseline, "\u{2022}", BODY_SIZE, false, TEXT),
+                Marker::Number(n) => {
+                    let label = format!("{}.", n);
+                    let x = MARGIN + indent + INDENT - 5.0 - self.fonts.get(false).width(&label, BODY_SIZE);
+                    self.text(x, baseline, &label, BODY_SIZE, false, TEXT);
+                }
+                Marker::Checkbox => self.checkbox(indent + 2.0, baseline - 1.0, checked),
+            }
+
+            let content = indent + INDENT;
+            match item.blocks.split_first() {
+                // Completed tasks are struck through, but not their subtasks
+                Some((Block::Paragraph(first), rest)) => {
+                    self.textblock(first, BODY_SIZE, content, checked);
+                    self.y -= 2.0;
+                    self.blocks(rest, content);
+                }
+                _ => {
+                    self.y -= BODY_SIZE * LINE_HEIGHT;
+                    self.blocks(&item.blocks, content);
+                }
+            }
+        }
+        if indent == 0.0 {
+            self.y -= 6.0;
+        }
+    }
+
+    /// Wrap and draw a paragraph or heading
+    fn textblock(&mut self, inlines: &[Inline], size: f32, indent: f32, done: bool) {
+        let x = MARGIN + indent;
+        let lines = wrap(&self.fonts, inlines, size, PAGE_WIDTH - MARGIN - x);
+
+        for line in lines {
+            let height = size * LINE_HEIGHT;
+            self.ensure(height);
+            let baseline = self.y - size;
+
+            for span in line {
+                let color = if span.marks.link.is_some() {
+                    LINK
+                } else if done {
+                    MUTED
+                } else {
+                    TEXT
+                };
+                let x = x + span.x;
+                self.text(x, baseline, &span.text, size, span.marks.bold, color);
+
+                if span.marks.underline || span.marks.link.is_some() {
+                    self.rule(x, baseline - size * 0.12, span.width, size, color);
+                }
+                if done {
+                    self.rule(x, baseline + size * 0.3, span.width, size, color);
+                }
+                if let Some(href) = span.marks.link {
+                    let area = Rect::new(x, baseline - size * 0.25, x + span.width, baseline + size * 0.85);
+                    self.page().links.push((area, href));
+                }
+            }
+            self.y -= height;
+        }
+    }
+
+    fn text(&mut self, x: f32, baseline: f32, text: &str, size: f32, bold: bool, color: [f32; 3]) {
+        let encoded = self.fonts.get_mut(bold).encode(text);
+        let content = &mut self.page().content;
+        content.begin_text();
+        content.set_font(if bold { BOLD } else { REGULAR }, size);
+        content.set_fill_rgb(color[0], color[1], color[2]);
+        content.next_line(x, baseline);
+        content.show(Str(&encoded));
+        content.end_text();
+    }
+
+    /// A horizontal line, for underlines and strike-through
+    fn rule(&mut self, x: f32, y: f32, width: f32, size: f32, color: [f32; 3]) {
+        let content = &mut self.page().content;
+        content.set_stroke_rgb(color[0], color[1], color[2]);
+        content.set_line_width(size * 0.06);
+        content.move_to(x, y);
+        content.line_to(x + width, y);
+        content.stroke();
+    }
+
+    fn checkbox(&mut self, x: f32, y: f32, checked: bool) {
+        let x = MARGIN + x;
+        let size = 9.0;
+        let content = &mut self.page().content;
+        content.set_line_width(1.0);
+        if checked {
+            content.set_fill_rgb(CHECKED[0], CHECKED[1], CHECKED[2]);
+            content.set_stroke_rgb(CHECKED[0], CHECKED[1], CHECKED[2]);
+            content.rect(x, y, size, size);
+            content.fill_nonzero_and_stroke();
+
+            content.set_stroke_rgb(1.0, 1.0, 1.0);
+            content.set_line_width(1.4);
+            content.move_to(x + 2.0, y + 4.6);
+            content.line_to(x + 3.8, y + 2.6);
+            content.line_to(x + 7.2, y + 6.8);
+            content.stroke();
+        } else {
+            content.set_stroke_rgb(CHECKBOX[0], CHECKBOX[1], CHECKBOX[2]);
+            content.rect(x, y, size, size);
+            content.stroke();
+        }
+    }
+
+    fn finish(self, title: &str) -> Vec<u8> {
+        let mut pdf = Pdf::new();
+        let mut next = 1;
+        let mut alloc = || {
+            next += 1;
+            Ref::new(next - 1)
+        };
+
+        let catalog_id = alloc();
+        let page_tree_id = alloc();
+        let info_id = alloc();
+
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        let regular_id = self.fonts.regular.write(&mut pdf, &mut alloc);
+        let bold_id = self.fonts.bold.write(&mut pdf, &mut alloc);
+        pdf.document_info(info_id).title(TextStr(title));
+
+        let mut page_ids = Vec::new();
+        for page in self.pages {
+            let page_id = alloc();
+            let content_id = alloc();
+            page_ids.push(page_id);
+
+            let mut writer = pdf.page(page_id);
+            writer
+                .media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                .parent(page_tree_id)
+                .contents(content_id);
+            writer.resources().fonts().pair(REGULAR, regular_id).pair(BOLD, bold_id);
+            if !page.links.is_empty() {
+                let mut annotations = writer.annotations();
+                for (area, href) in &page.links {
+                    let mut annotation = annotations.push();
+                    annotation.subtype(AnnotationType::Link).rect(*area).border(0.0, 0.0, 0.0, None);
+                    annotation.action().action_type(ActionType::Uri).uri(Str(href.as_bytes()));
+                }
+            }
+            writer.finish();
+
+            pdf.stream(content_id, &page.content.finish());
+        }
+
+        let count = page_ids.len() as i32;
+        pdf.pages(page_tree_id).kids(page_ids).count(count);
+        pdf.finish()
+    }
+}
+
+enum Marker {
+    Bullet,
+    Number(usize),
+    Checkbox,
+}
+
+/// Break text into lines no wider than `max_width`. Words are only split
+/// when a single word is wider than a line.
+fn wrap(fonts: &Fonts, inlines: &[Inline], size: f32, max_width: f32) -> Vec<Vec<Span>> {
+    let mut lines: Vec<Vec<Span>> = vec![Vec::new()];
+    let mut x = 0.0;
+    let mut space: Option<Marks> = None;
+
+    for token in tokens(inlines) {
+        let word = match token {
+            Token::Break => {
+                lines.push(Vec::new());
+                x = 0.0;
+                space = None;
+                continue;
+            }
+            Token::Space(marks) => {
+                space.get_or_insert(marks);
+                continue;
+            }
+            Token::Word(word) => word,
+        };
+
+        let width: f32 = word.iter().map(|(text, marks)| fonts.get(marks.bold).width(text, size)).sum();
+        // Spaces keep the style of the text they were typed in
+        let space_width = match space.take() {
+            Some(marks) if x > 0.0 => {
+                let space_width = fonts.get(marks.bold).width(" ", size);
+                if x + space_width + width <= max_width {
+                    push_span(lines.last_mut().unwrap(), " ", &marks, x, space_width);
+                }
+                space_width
+            }
+            _ => 0.0,
+        };
+        if x > 0.0 && x + space_width + width > max_width {
+            lines.push(Vec::new());
+            x = 0.0;
+        } else {
+            x += space_width;
+        }
+
+        for (text, marks) in word {
+            let font = fonts.get(marks.bold);
+            for piece in split_to_fit(font, &text, size, max_width) {
+                let width = font.width(&piece, size);
+                if x > 0.0 && x + width > max_width {
+                    lines.push(Vec::new());
+                    x = 0.0;
+                }
+                push_span(lines.last_mut().unwrap(), &piece, &marks, x, width);
+                x += width;
+            }
+        }
+    }
+
+    lines
+}
+
+enum Token {
+    /// A run of non-space characters, which may span several marks
+    Word(Vec<(String, Marks)>),
+    Space(Marks),
+    Break,
+}
+
+fn tokens(inlines: &[Inline]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word: Vec<(String, Marks)> = Vec::new();
+
+    for inline in inlines {
+        let mut part = String::new();
+        for c in inline.text.chars() {
+            if !c.is_whitespace() {
+                part.push(c);
+                continue;
+            }
+            if !part.is_empty() {
+                word.push((std::mem::take(&mut part), inline.marks.clone()));
+            }
+            if !word.is_empty() {
+                tokens.push(Token::Word(std::mem::take(&mut word)));
+            }
+            tokens.push(if c == '\n' {
+                Token::Break
+            } else {
+                Token::Space(inline.marks.clone())
+            });
+        }
+        if !part.is_empty() {
+            word.push((part, inline.marks.clone()));
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    }
+    tokens
+}
+
+/// Split text that is wider than a whole line into pieces that fit
+fn split_to_fit(font: &Font, text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut width = 0.0;
+    for c in text.chars() {
+        let char_width = font.advance(c) * size / 1000.0;
+        if width + char_width > max_width && width > 0.0 {
+            pieces.push(String::new());
+            width = 0.0;
+        }
+        pieces.last_mut().unwrap().push(c);
+        width += char_width;
+    }
+    pieces
+}
+
+/// Add text to a line, extending the last span if it has the same style
+fn push_span(line: &mut Vec<Span>, text: &str, marks: &Marks, x: f32, width: f32) {
+    match line.last_mut() {
+        Some(last) if last.marks == *marks && (last.x + last.width - x).abs() < 0.01 => {
+            last.text.push_str(text);
+            last.width += width;
+        }
+        _ => line.push(Span {
+            text: text.to_string(),
+            marks: marks.clone(),
+            x,
+            width,
+        }),
+    }
+}
+
+struct Fonts {
+    regular: Font,
+    bold: Font,
+}
+
+impl Fonts {
+    fn get(&self, bold: bool) -> &Font {
+        if bold {
+            &self.bold
+        } else {
+            &self.regular
+        }
+    }
+
+    fn get_mut(&mut self, bold: bool) -> &mut Font {
+        if bold {
+            &mut self.bold
+        } else {
+            &mut self.regular
+        }
+    }
+}
+
+/// An embedded TrueType font and the characters a document sets in it.
+/// Text is shown by CID, one per character in order of first use, and the
+/// font maps each CID to its glyph and back to the character.
+struct Font {
+    name: &'static str,
+    data: &'static [u8],
+    face: Face<'static>,
+    /// The characters used; the CID of each is its index plus one, since
+    /// CID 0 is the missing glyph
+    chars: Vec<char>,
+    cids: HashMap<char, u16>,
+}
+
+impl Font {
+    fn new(name: &'static str, data: &'static [u8]) -> Self {
+        Self {
+            name,
+            data,
+            face: Face::parse(data, 0).expect("embedded font is valid"),
+            chars: Vec::new(),
+            cids: HashMap::new(),
+        }
+    }
+
+    /// The glyph for a character, or 0 (the missing glyph) if the font has none
+    fn glyph(&self, c: char) -> GlyphId {
+        self.face.glyph_index(c).unwrap_or(GlyphId(0))
+    }
+
+    /// Advance width in thousandths of the font size
+    fn advance(&self, c: char) -> f32 {
+        let advance = self.face.glyph_hor_advance(self.glyph(normalize(c))).unwrap_or(0);
+        advance as f32 * 1000.0 / self.face.units_per_em() as f32
+    }
+
+    fn width(&self, text: &str, size: f32) -> f32 {
+        text.chars().map(|c| self.advance(c)).sum::<f32>() * size / 1000.0
+    }
+
+    /// Encode text as two-byte CIDs (Identity-H), adding new characters
+    fn encode(&mut self, text: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(text.len() * 2);
+        for c in text.chars().map(normalize) {
+            let cid = *self.cids.entry(c).or_insert_with(|| {
+                self.chars.push(c);
+                self.chars.len() as u16
+            });
+            out.extend_from_slice(&cid.to_be_bytes());
+        }
+        out
+    }
+
+    /// Write the font with a subset of its glyphs, returning the font's id
+    fn write(&self, pdf: &mut Pdf, alloc: &mut impl FnMut() -> Ref) -> Ref {
+        let type0_id = alloc();
+        let cid_id = alloc();
+        let descriptor_id = alloc();
+        let cid_to_gid_id = alloc();
+        let to_unicode_id = alloc();
+        let file_id = alloc();
+
+        let glyphs: Vec<u16> = self.chars.iter().map(|&c| self.glyph(c).0).collect();
+        // Subset fonts are named with a tag unique to the subset
+        let mut hasher = DefaultHasher::new();
+        glyphs.hash(&mut hasher);
+        let hash = Hasher::finish(&hasher);
+        let tag: String = (0..6).map(|i| (b'A' + (hash >> (i * 5)) as u8 % 26) as char).collect();
+        let base_font = format!("{}+{}", tag, self.name);
+        let base_font = Name(base_font.as_bytes());
+
+        pdf.type0_font(type0_id)
+            .base_font(base_font)
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid_id)
+            .to_unicode(to_unicode_id);
+
+        let mut cid_font = pdf.cid_font(cid_id);
+        cid_font
+            .subtype(CidFontType::Type2)
+            .base_font(base_font)
+            .system_info(SYSTEM_INFO)
+            .font_descriptor(descriptor_id)
+            .cid_to_gid_map_stream(cid_to_gid_id);
+        if !self.chars.is_empty() {
+            cid_font.widths().consecutive(1, self.chars.iter().map(|&c| self.advance(c)));
+        }
+        cid_font.finish();
+
+        let face = &self.face;
+        let scale = |value: i16| value as f32 * 1000.0 / face.units_per_em() as f32;
+        let bbox = face.global_bounding_box();
+        pdf.font_descriptor(descriptor_id)
+            .name(base_font)
+            .flags(FontFlags::SYMBOLIC)
+            .bbox(Rect::new(scale(bbox.x_min), scale(bbox.y_min), scale(bbox.x_max), scale(bbox.y_max)))
+            .italic_angle(0.0)
+            .ascent(scale(face.ascender()))
+            .descent(scale(face.descender()))
+            .cap_height(scale(
+                face.capital_height()
+                    .or_else(|| face.glyph_bounding_box(self.glyph('H')).map(|bbox| bbox.y_max))
+                    .unwrap_or(face.ascender()),
+            ))
+            // Estimated from the weight, as the font doesn't say
+            .stem_v(10.0 + 0.244 * (face.weight().to_number() as f32 - 50.0))
+            .font_file2(file_id);
+
+        let mut cid_to_gid = vec![0, 0];
+        for glyph in &glyphs {
+            cid_to_gid.extend_from_slice(&glyph.to_be_bytes());
+        }
+        pdf.stream(cid_to_gid_id, &cid_to_gid);
+
+        let mut to_unicode = UnicodeCmap::new(Name(b"Custom"), SYSTEM_INFO);
+        for (i, &c) in self.chars.iter().enumerate() {
+            to_unicode.pair(i as u16 + 1, c);
+        }
+        pdf.cmap(to_unicode_id, &to_unicode.finish());
+
+        // Subsetting keeps glyph ids, so the CID to glyph map holds. The
+        // glyphs left out are emptied, which compresses to next to nothing.
+        let mut used = glyphs;
+        used.push(0);
+        used.sort_unstable();
+        used.dedup();
+        let file = subsetter::subset(self.data, 0, subsetter::Profile::pdf(&used)).unwrap_or_else(|_| self.data.to_vec());
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&file, 6);
+        pdf.stream(file_id, &compressed)
+            .filter(Filter::FlateDecode)
+            .pair(Name(b"Length1"), file.len() as i32);
+
+        type0_id
+    }
+}
+
+/// Whitespace other than line breaks (which never reach the page) is set
+/// as a space
+fn normalize(c: char) -> char {
+    if c.is_whitespace() {
+        ' '
+    } else {
+        c
+    }
+}
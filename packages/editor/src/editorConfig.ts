@@ -13,11 +13,16 @@ import History from '@tiptap/extension-history';
 import Placeholder from '@tiptap/extension-placeholder';
 import Link from '@tiptap/extension-link';
 import { TaskItemExtended } from './extensions/TaskItemExtended';
+import { NoteLink } from './extensions/NoteLink';
 
 /**
  * Get all extensions for the TipTap editor
  */
-export function getEditorExtensions(options?: { placeholder?: string }) {
+export function getEditorExtensions(options?: {
+  placeholder?: string;
+  /** Open the note a `[[Note Title]]` link points to */
+  onOpenNoteLink?: (title: string) => void;
+}) {
   return [
     Document,
     Paragraph,
@@ -50,6 +55,9 @@ export function getEditorExtensions(options?: { placeholder?: string }) {
         target: null,
       },
     }),
+    NoteLink.configure({
+      onOpen: options?.onOpenNoteLink ?? null,
+    }),
     History,
     Placeholder.configure({
       placeholder: options?.placeholder ?? 'Start typing...',
@@ -203,6 +211,27 @@ export const editorStyles = `
     color: var(--text-muted);
   }
 
+  /* Due date and reminder of a task */
+  .ProseMirror ul[data-type="taskList"] li > .task-dates {
+    flex-shrink: 0;
+    margin-top: 2px;
+    padding: 0 6px;
+    border-radius: 4px;
+    background: var(--bg-tertiary);
+    color: var(--text-secondary);
+    font-size: 12px;
+    line-height: 18px;
+    user-select: none;
+  }
+
+  .ProseMirror ul[data-type="taskList"] li > .task-dates.is-overdue {
+    color: var(--accent-danger);
+  }
+
+  .ProseMirror ul[data-type="taskList"] li[data-checked="true"] > .task-dates {
+    color: var(--text-muted);
+  }
+
   /* Nested task lists */
   .ProseMirror ul[data-type="taskList"] ul[data-type="taskList"] {
     margin-left: 0;
@@ -224,4 +253,14 @@ export const editorStyles = `
   .ProseMirror u {
     text-decoration: underline;
   }
+
+  /* Links to other notes */
+  .ProseMirror .note-link {
+    color: var(--accent-primary);
+    cursor: pointer;
+  }
+
+  .ProseMirror .note-link:hover {
+    text-decoration: underline;
+  }
 `;
@@ -0,0 +1,80 @@
+import { Extension } from '@tiptap/core';
+import type { Node as ProseMirrorNode } from '@tiptap/pm/model';
+import { Plugin, PluginKey } from '@tiptap/pm/state';
+import { Decoration, DecorationSet } from '@tiptap/pm/view';
+
+/**
+ * A link to another note by its title, e.g. `[[Deploy runbook]]`. Links
+ * stay plain text in the document; the desktop backend indexes them and
+ * rewrites them when the linked note is renamed. A title can't span lines
+ * or contain brackets, and surrounding whitespace doesn't count.
+ */
+const NOTE_LINK = /\[\[([^[\]\r\n]+)\]\]/g;
+
+export interface NoteLinkOptions {
+  /** Called with the title of a link that was Ctrl/Cmd+clicked */
+  onOpen: ((title: string) => void) | null;
+}
+
+const noteLinkKey = new PluginKey<DecorationSet>('noteLink');
+
+function decorations(doc: ProseMirrorNode): DecorationSet {
+  const found: Decoration[] = [];
+  doc.descendants((node, pos) => {
+    if (!node.isTextblock) return true;
+    // One character per inline leaf, so offsets match document positions
+    const text = node.textBetween(0, node.content.size, undefined, '\ufffc');
+    for (const match of text.matchAll(NOTE_LINK)) {
+      const title = match[1].trim();
+      if (!title || match.index === undefined) continue;
+      const from = pos + 1 + match.index;
+      found.push(
+        Decoration.inline(from, from + match[0].length, {
+          class: 'note-link',
+          'data-note-title': title,
+        })
+      );
+    }
+    return false;
+  });
+  return DecorationSet.create(doc, found);
+}
+
+/**
+ * Highlights `[[Note Title]]` links and opens them on Ctrl/Cmd+click
+ */
+export const NoteLink = Extension.create<NoteLinkOptions>({
+  name: 'noteLink',
+
+  addOptions() {
+    return {
+      onOpen: null,
+    };
+  },
+
+  addProseMirrorPlugins() {
+    const options = this.options;
+    return [
+      new Plugin<DecorationSet>({
+        key: noteLinkKey,
+        state: {
+          init: (_, state) => decorations(state.doc),
+          apply: (tr, set) => (tr.docChanged ? decorations(tr.doc) : set),
+        },
+        props: {
+          decorations: (state) => noteLinkKey.getState(state),
+          handleClick: (_view, _pos, event) => {
+            // A plain click places the cursor, so the link text can be edited
+            if (!(event.ctrlKey || event.metaKey)) return false;
+            const link = (event.target as HTMLElement).closest<HTMLElement>('.note-link');
+            const title = link?.dataset.noteTitle;
+            if (!title || !options.onOpen) return false;
+            event.preventDefault();
+            options.onOpen(title);
+            return true;
+          },
+        },
+      }),
+    ];
+  },
+});
@@ -1,9 +1,74 @@
 import TaskItem from '@tiptap/extension-task-item';
-import { TextSelection } from '@tiptap/pm/state';
+import type { Node as ProseMirrorNode } from '@tiptap/pm/model';
+import { EditorState, TextSelection } from '@tiptap/pm/state';
 
 /**
- * Extended TaskItem with strikethrough styling for completed tasks
- * and proper Enter key behavior (creates new task item instead of paragraph)
+ * Due date (`YYYY-MM-DD`) and reminder time (milliseconds since the epoch)
+ * of a task item; the desktop backend indexes both and fires reminders.
+ * `repeat` is a recurrence rule (an RRULE subset such as
+ * `FREQ=WEEKLY;BYDAY=MO,TH`): the backend moves a checked repeating task to
+ * its next occurrence.
+ */
+export interface TaskDates {
+  dueDate: string | null;
+  remindAt: number | null;
+  repeat: string | null;
+}
+
+declare module '@tiptap/core' {
+  interface Commands<ReturnType> {
+    taskDates: {
+      /** Set the due date, reminder or repeat rule of the task item at the cursor */
+      setTaskDates: (dates: Partial<TaskDates>) => ReturnType;
+    };
+  }
+}
+
+/**
+ * The innermost task item around the cursor, so nested tasks get their own
+ * dates rather than their parent's
+ */
+export function taskItemAtCursor(state: EditorState): { node: ProseMirrorNode; pos: number } | null {
+  const { $head } = state.selection;
+  for (let depth = $head.depth; depth > 0; depth--) {
+    const node = $head.node(depth);
+    if (node.type.name === 'taskItem') {
+      return { node, pos: $head.before(depth) };
+    }
+  }
+  return null;
+}
+
+function todayString(): string {
+  const now = new Date();
+  const month = String(now.getMonth() + 1).padStart(2, '0');
+  const day = String(now.getDate()).padStart(2, '0');
+  return `${now.getFullYear()}-${month}-${day}`;
+}
+
+/**
+ * Short label for a task's dates, e.g. "Mar 4 · 🔔 09:30 · ↻"
+ */
+function datesLabel(attrs: Record<string, unknown>): string {
+  const parts: string[] = [];
+  if (typeof attrs.dueDate === 'string') {
+    const [year, month, day] = attrs.dueDate.split('-').map(Number);
+    parts.push(new Date(year, month - 1, day).toLocaleDateString([], { month: 'short', day: 'numeric' }));
+  }
+  if (typeof attrs.remindAt === 'number') {
+    const time = new Date(attrs.remindAt).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' });
+    parts.push(`🔔 ${time}`);
+  }
+  if (typeof attrs.repeat === 'string' && attrs.repeat) {
+    parts.push('↻');
+  }
+  return parts.join(' · ');
+}
+
+/**
+ * Extended TaskItem with strikethrough styling for completed tasks,
+ * proper Enter key behavior (creates new task item instead of paragraph)
+ * and optional due dates and reminders
  */
 export const TaskItemExtended = TaskItem.extend({
   addAttributes() {
@@ -16,6 +81,76 @@ export const TaskItemExtended = TaskItem.extend({
           'data-checked': attributes.checked,
         }),
       },
+      dueDate: {
+        default: null,
+        parseHTML: (element) => element.getAttribute('data-due-date'),
+        renderHTML: (attributes) => (attributes.dueDate ? { 'data-due-date': attributes.dueDate } : {}),
+      },
+      remindAt: {
+        default: null,
+        parseHTML: (element) => {
+          const value = element.getAttribute('data-remind-at');
+          return value ? Number(value) : null;
+        },
+        renderHTML: (attributes) => (attributes.remindAt ? { 'data-remind-at': attributes.remindAt } : {}),
+      },
+      repeat: {
+        default: null,
+        parseHTML: (element) => element.getAttribute('data-repeat'),
+        renderHTML: (attributes) => (attributes.repeat ? { 'data-repeat': attributes.repeat } : {}),
+      },
+    };
+  },
+
+  addCommands() {
+    return {
+      ...this.parent?.(),
+      setTaskDates:
+        (dates: Partial<TaskDates>) =>
+        ({ state, tr, dispatch }) => {
+          const item = taskItemAtCursor(state);
+          if (!item) return false;
+          if (dispatch) {
+            tr.setNodeMarkup(item.pos, undefined, { ...item.node.attrs, ...dates });
+          }
+          return true;
+        },
+    };
+  },
+
+  // The default node view with a label for the task's due date and reminder
+  addNodeView() {
+    const parentView = this.parent?.();
+    if (!parentView) return null;
+
+    return (props) => {
+      const view = parentView(props);
+      const dom = view.dom as HTMLElement;
+      const badge = document.createElement('span');
+      badge.className = 'task-dates';
+      badge.contentEditable = 'false';
+
+      const render = (node: ProseMirrorNode) => {
+        const label = datesLabel(node.attrs);
+        badge.textContent = label;
+        badge.hidden = !label;
+        badge.title = typeof node.attrs.repeat === 'string' && node.attrs.repeat ? `Repeats: ${node.attrs.repeat}` : '';
+        badge.classList.toggle(
+          'is-overdue',
+          !node.attrs.checked && typeof node.attrs.dueDate === 'string' && node.attrs.dueDate < todayString()
+        );
+      };
+      render(props.node);
+      dom.append(badge);
+
+      return {
+        ...view,
+        update: (node, decorations, innerDecorations) => {
+          const updated = view.update ? view.update(node, decorations, innerDecorations) : false;
+          if (updated) render(node);
+          return updated;
+        },
+      };
     };
   },
 
@@ -1 +1,4 @@
-export { TaskItemExtended } from './TaskItemExtended';
+export { TaskItemExtended, taskItemAtCursor } from './TaskItemExtended';
+export type { TaskDates } from './TaskItemExtended';
+export { NoteLink } from './NoteLink';
+export type { NoteLinkOptions } from './NoteLink';
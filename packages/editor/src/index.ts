@@ -1,5 +1,6 @@
 export { getEditorExtensions, editorStyles } from './editorConfig';
-export { TaskItemExtended } from './extensions';
+export { TaskItemExtended, taskItemAtCursor, NoteLink } from './extensions';
+export type { TaskDates, NoteLinkOptions } from './extensions';
 
 // Re-export useful types from TipTap
 export type { Editor } from '@tiptap/core';
@@ -0,0 +1,119 @@
+/**
+ * End-to-end encryption of synced notes.
+ *
+ * Each account has one AES-GCM note key that encrypts Yjs updates, snapshots
+ * and titles before they leave the client. The server only ever sees it
+ * wrapped: every device holds an RSA-OAEP key pair, and a device that has
+ * the note key wraps it with the public key of each newly registered device
+ * (see the /user/devices API).
+ */
+
+const NOTE_KEY_ALGORITHM: AesKeyGenParams = { name: 'AES-GCM', length: 256 };
+
+const DEVICE_KEY_ALGORITHM: RsaHashedKeyGenParams = {
+  name: 'RSA-OAEP',
+  modulusLength: 3072,
+  publicExponent: new Uint8Array([1, 0, 1]),
+  hash: 'SHA-256',
+};
+
+const IV_LENGTH = 12;
+
+const textEncoder = new TextEncoder();
+const textDecoder = new TextDecoder();
+
+/**
+ * Create the account's note key (done once, by the first device)
+ */
+export async function generateNoteKey(): Promise<CryptoKey> {
+  // Extractable so it can be wrapped for other devices
+  return crypto.subtle.generateKey(NOTE_KEY_ALGORITHM, true, ['encrypt', 'decrypt']);
+}
+
+/**
+ * Create this device's key pair. The private key can't be exported.
+ */
+export async function generateDeviceKeyPair(): Promise<CryptoKeyPair> {
+  return crypto.subtle.generateKey(DEVICE_KEY_ALGORITHM, false, ['wrapKey', 'unwrapKey']);
+}
+
+/**
+ * Export a device public key for registration with the server
+ */
+export async function exportPublicKey(publicKey: CryptoKey): Promise<Uint8Array> {
+  return new Uint8Array(await crypto.subtle.exportKey('spki', publicKey));
+}
+
+/**
+ * Import another device's public key as returned by the server
+ */
+export async function importPublicKey(spki: Uint8Array): Promise<CryptoKey> {
+  return crypto.subtle.importKey('spki', spki, DEVICE_KEY_ALGORITHM, false, ['wrapKey']);
+}
+
+/**
+ * Wrap the note key for a device
+ */
+export async function wrapNoteKey(noteKey: CryptoKey, devicePublicKey: CryptoKey): Promise<Uint8Array> {
+  return new Uint8Array(await crypto.subtle.wrapKey('raw', noteKey, devicePublicKey, { name: 'RSA-OAEP' }));
+}
+
+/**
+ * Unwrap the note key with this device's private key
+ */
+export async function unwrapNoteKey(wrappedKey: Uint8Array, devicePrivateKey: CryptoKey): Promise<CryptoKey> {
+  return crypto.subtle.unwrapKey(
+    'raw',
+    wrappedKey,
+    devicePrivateKey,
+    { name: 'RSA-OAEP' },
+    NOTE_KEY_ALGORITHM,
+    true,
+    ['encrypt', 'decrypt']
+  );
+}
+
+/**
+ * Encrypt data belonging to a note. The note ID is authenticated with it,
+ * so the server can't move ciphertext between notes unnoticed.
+ * Output is the IV followed by the ciphertext.
+ */
+export async function encryptBytes(noteKey: CryptoKey, noteId: string, data: Uint8Array): Promise<Uint8Array> {
+  const iv = crypto.getRandomValues(new Uint8Array(IV_LENGTH));
+  const ciphertext = await crypto.subtle.encrypt(
+    { name: 'AES-GCM', iv, additionalData: textEncoder.encode(noteId) },
+    noteKey,
+    data
+  );
+
+  const result = new Uint8Array(IV_LENGTH + ciphertext.byteLength);
+  result.set(iv);
+  result.set(new Uint8Array(ciphertext), IV_LENGTH);
+  return result;
+}
+
+/**
+ * Decrypt data produced by encryptBytes for the same note
+ */
+export async function decryptBytes(noteKey: CryptoKey, noteId: string, data: Uint8Array): Promise<Uint8Array> {
+  const plaintext = await crypto.subtle.decrypt(
+    { name: 'AES-GCM', iv: data.subarray(0, IV_LENGTH), additionalData: textEncoder.encode(noteId) },
+    noteKey,
+    data.subarray(IV_LENGTH)
+  );
+  return new Uint8Array(plaintext);
+}
+
+/**
+ * Encrypt a note title
+ */
+export async function encryptTitle(noteKey: CryptoKey, noteId: string, title: string): Promise<Uint8Array> {
+  return encryptBytes(noteKey, noteId, textEncoder.encode(title));
+}
+
+/**
+ * Decrypt a note title
+ */
+export async function decryptTitle(noteKey: CryptoKey, noteId: string, data: Uint8Array): Promise<string> {
+  return textDecoder.decode(await decryptBytes(noteKey, noteId, data));
+}
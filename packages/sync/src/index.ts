@@ -1,3 +1,4 @@
+export * from './crypto';
 export * from './document';
 export * from './provider';
 export * from './storage';
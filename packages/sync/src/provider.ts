@@ -1,5 +1,6 @@
 import * as Y from 'yjs';
 import { applyUpdate, getStateVector, getMissingUpdates } from './document';
+import { encryptBytes, decryptBytes } from './crypto';
 
 export interface SyncProviderOptions {
   /** WebSocket URL for real-time sync */
@@ -8,6 +9,11 @@ export interface SyncProviderOptions {
   apiUrl?: string;
   /** Auth token for API requests */
   authToken?: string;
+  /**
+   * Note key for end-to-end encrypted accounts. When set, updates are
+   * encrypted before they are pushed and decrypted after they are pulled.
+   */
+  encryptionKey?: CryptoKey;
   /** Callback when sync status changes */
   onSyncStatusChange?: (status: SyncStatus) => void;
   /** Callback when an error occurs */
@@ -105,7 +111,7 @@ export class SyncProvider {
       this.ws.onmessage = (event) => {
         try {
           const message = JSON.parse(event.data);
-          this.handleMessage(message);
+          this.handleMessage(message).catch((error) => this.options.onError?.(error as Error));
         } catch (error) {
           this.options.onError?.(error as Error);
         }
@@ -151,19 +157,20 @@ export class SyncProvider {
 
     this.setStatus('syncing');
 
-    const updates: Array<{ noteId: string; update: string; timestamp: number }> = [];
+    const updates: Array<{ noteId: string; update: string; timestamp: number; encrypted: boolean }> = [];
 
-    for (const [noteId, pending] of this.pendingUpdates.entries()) {
-      for (const p of pending) {
-        updates.push({
-          noteId: p.noteId,
-          update: this.encodeBase64(p.update),
-          timestamp: p.timestamp,
-        });
+    try {
+      for (const [noteId, pending] of this.pendingUpdates.entries()) {
+        for (const p of pending) {
+          updates.push({
+            noteId: p.noteId,
+            update: this.encodeBase64(await this.encryptUpdate(noteId, p.update)),
+            timestamp: p.timestamp,
+            encrypted: this.options.encryptionKey !== undefined,
+          });
+        }
       }
-    }
 
-    try {
       const response = await fetch(`${this.options.apiUrl}/sync/push`, {
         method: 'POST',
         headers: {
@@ -220,7 +227,7 @@ export class SyncProvider {
           const doc = this.documents.get(noteId);
           if (doc) {
             for (const update of updates as string[]) {
-              applyUpdate(doc, this.decodeBase64(update));
+              applyUpdate(doc, await this.decryptUpdate(noteId, this.decodeBase64(update)));
             }
           }
         }
@@ -238,7 +245,7 @@ export class SyncProvider {
   /**
    * Handle incoming WebSocket messages
    */
-  private handleMessage(message: unknown): void {
+  private async handleMessage(message: unknown): Promise<void> {
     const msg = message as { type: string; noteId?: string; update?: string };
 
     switch (msg.type) {
@@ -246,7 +253,7 @@ export class SyncProvider {
         if (msg.noteId && msg.update) {
           const doc = this.documents.get(msg.noteId);
           if (doc) {
-            applyUpdate(doc, this.decodeBase64(msg.update));
+            applyUpdate(doc, await this.decryptUpdate(msg.noteId, this.decodeBase64(msg.update)));
           }
         }
         break;
@@ -294,6 +301,22 @@ export class SyncProvider {
     return this.status;
   }
 
+  /**
+   * Encrypt an outgoing update if the account uses end-to-end encryption
+   */
+  private async encryptUpdate(noteId: string, update: Uint8Array): Promise<Uint8Array> {
+    const key = this.options.encryptionKey;
+    return key ? encryptBytes(key, noteId, update) : update;
+  }
+
+  /**
+   * Decrypt an incoming update if the account uses end-to-end encryption
+   */
+  private async decryptUpdate(noteId: string, update: Uint8Array): Promise<Uint8Array> {
+    const key = this.options.encryptionKey;
+    return key ? decryptBytes(key, noteId, update) : update;
+  }
+
   /**
    * Encode Uint8Array to base64
    */
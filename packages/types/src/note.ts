@@ -32,6 +32,116 @@ export interface NoteMeta {
   createdAt: number;
   updatedAt: number;
   deletedAt: number | null;
+  /** End-to-end encrypted; the title is in encryptedTitle (synced notes only) */
+  encrypted?: boolean;
+  encryptedTitle?: string;
+  /** Folder the note is in; null (or missing) outside any folder */
+  folderId?: string | null;
+}
+
+/**
+ * Folder for organizing notes; folders nest through parentId
+ */
+export interface Folder {
+  id: string;
+  name: string;
+  /** null for top-level folders */
+  parentId: string | null;
+  createdAt: number;
+  updatedAt: number;
+  /** Set on folders deleted since the last sync (synced folders only) */
+  deletedAt?: number | null;
+  /** Workspace the folder belongs to (synced folders only) */
+  workspaceId?: string | null;
+}
+
+/**
+ * Search query saved under a name, shown in the sidebar as a smart folder
+ */
+export interface SavedSearch {
+  id: string;
+  name: string;
+  /** Query in the search box syntax */
+  query: string;
+  createdAt: number;
+  updatedAt: number;
+  /** Set on saved searches deleted since the last sync (synced ones only) */
+  deletedAt?: number | null;
+}
+
+/**
+ * Free-form tag; names are unique without regard to case
+ */
+export interface Tag {
+  id: string;
+  name: string;
+  /** Notes with the tag, not counting those in the trash (desktop only) */
+  noteCount?: number;
+  /** Set on tags deleted since the last sync (synced tags only) */
+  deletedAt?: number | null;
+  /** Workspace the tag belongs to (synced tags only) */
+  workspaceId?: string | null;
+}
+
+/**
+ * Task item in a note's content; the id is the note id and the task's path
+ * in the document, e.g. `<noteId>:2.0.1`
+ */
+export interface Task {
+  id: string;
+  noteId: string;
+  noteTitle: string;
+  /** Order of the task within its note */
+  position: number;
+  /** Child indexes from the top of the document, dot-separated */
+  path: string;
+  /** Number of task items the task is nested in */
+  depth: number;
+  text: string;
+  checked: boolean;
+  /** Due date as `YYYY-MM-DD` */
+  dueDate?: string | null;
+  /** Reminder time in milliseconds since the epoch */
+  remindAt?: number | null;
+  /** When the task was checked, if it is and that's known */
+  completedAt?: number | null;
+}
+
+/**
+ * Completed and open tasks of a note
+ */
+export interface TaskCounts {
+  noteId: string;
+  open: number;
+  completed: number;
+}
+
+/**
+ * A file attached to a note. Files are stored once per content hash, so
+ * attachments with the same content share their data.
+ */
+export interface Attachment {
+  id: string;
+  noteId: string;
+  /** SHA-256 of the content, hex encoded */
+  hash: string;
+  fileName: string;
+  contentType: string;
+  /** Size in bytes */
+  size: number;
+  createdAt: number;
+}
+
+/**
+ * Preview of a linked web page, read from its OpenGraph/Twitter tags
+ */
+export interface LinkPreview {
+  url: string;
+  title: string | null;
+  description: string | null;
+  siteName: string | null;
+  faviconUrl: string | null;
+  fetchedAt: number;
 }
 
 /**
@@ -1,3 +1,5 @@
+import type { Folder, Tag } from './note';
+
 /**
  * Sync update structure
  */
@@ -22,6 +24,9 @@ export interface SyncPushRequest {
     noteId: string;
     update: string; // Base64 encoded
     timestamp: number;
+    /** Update is end-to-end encrypted; the server relays it without merging */
+    encrypted?: boolean;
+    encryptedTitle?: string; // Base64 encoded
   }>;
 }
 
@@ -53,8 +58,21 @@ export interface SyncPullResponse {
     content: string; // Base64 encoded
     starred: boolean;
     createdAt: number;
+    /** Content is an encrypted snapshot and title is empty */
+    encrypted: boolean;
+    encryptedTitle?: string; // Base64 encoded
+    folderId?: string | null;
+    tagIds?: string[];
   }>;
   deletedNotes: string[];
+  /** Folders changed since the last pull; deleted ones have deletedAt set */
+  folders?: Folder[];
+  /** noteId -> folder id, for changed notes the client already has */
+  noteFolders?: Record<string, string | null>;
+  /** Tags changed since the last pull; deleted ones have deletedAt set */
+  tags?: Tag[];
+  /** noteId -> tag ids, for changed notes the client already has */
+  noteTags?: Record<string, string[]>;
   serverTime: number;
 }
 
@@ -8,6 +8,8 @@ export interface User {
   picture?: string;
   createdAt: number;
   settings: UserSettings;
+  /** Notes are end-to-end encrypted before syncing */
+  e2eeEnabled: boolean;
 }
 
 /**